@@ -1,173 +1,14 @@
 // Demonstrates issuing an arbitrary DNS query via `send_dnsrec` and
-// printing the parsed response using the strongly-typed record view API
-// (`DnsRr::as_typed`, `TypedRr`, the per-type wrappers like `ARecord`,
-// `MxRecord`, …).
+// printing each record in the parsed response in zone-file presentation
+// format, as `dig` does.
 //
 // Requires c-ares >= 1.28.
 
 #[cfg(cares1_28)]
 mod inner {
-    use c_ares::{DnsCls, DnsRecord, DnsRecordType, DnsRr, DnsSection, TypedRr};
+    use c_ares::{DnsCls, DnsRecord, DnsRecordType, DnsSection};
     use c_ares_resolver::Resolver;
 
-    fn print_rr(rr: &DnsRr) {
-        println!(
-            "    {} {} {} TTL={}",
-            rr.name(),
-            rr.rr_type(),
-            rr.dns_class(),
-            rr.ttl(),
-        );
-        match rr.as_typed() {
-            TypedRr::A(a) => {
-                println!("      A {}", a.addr());
-            }
-            TypedRr::Aaaa(aaaa) => {
-                println!("      AAAA {}", aaaa.addr());
-            }
-            TypedRr::Cname(cname) => {
-                println!("      CNAME {}", cname.cname());
-            }
-            TypedRr::Ns(ns) => {
-                println!("      NS {}", ns.nsdname());
-            }
-            TypedRr::Ptr(ptr) => {
-                println!("      PTR {}", ptr.dname());
-            }
-            TypedRr::Mx(mx) => {
-                println!(
-                    "      MX preference={} exchange={}",
-                    mx.preference(),
-                    mx.exchange(),
-                );
-            }
-            TypedRr::Txt(txt) => {
-                for entry in txt.entries() {
-                    let text = std::str::from_utf8(entry).unwrap_or("<not utf-8>");
-                    println!("      TXT {text}");
-                }
-            }
-            TypedRr::Soa(soa) => {
-                println!(
-                    "      SOA mname={} rname={} serial={} refresh={} retry={} expire={} minimum={}",
-                    soa.mname(),
-                    soa.rname(),
-                    soa.serial(),
-                    soa.refresh(),
-                    soa.retry(),
-                    soa.expire(),
-                    soa.minimum(),
-                );
-            }
-            TypedRr::Srv(srv) => {
-                println!(
-                    "      SRV priority={} weight={} port={} target={}",
-                    srv.priority(),
-                    srv.weight(),
-                    srv.port(),
-                    srv.target(),
-                );
-            }
-            TypedRr::Naptr(n) => {
-                println!(
-                    "      NAPTR order={} preference={} flags={:?} services={:?} regexp={:?} replacement={:?}",
-                    n.order(),
-                    n.preference(),
-                    n.flags(),
-                    n.services(),
-                    n.regexp(),
-                    n.replacement(),
-                );
-            }
-            TypedRr::Hinfo(h) => {
-                println!("      HINFO cpu={:?} os={:?}", h.cpu(), h.os());
-            }
-            TypedRr::Caa(caa) => {
-                let value = std::str::from_utf8(caa.value()).unwrap_or("<not utf-8>");
-                println!(
-                    "      CAA flags={} critical={} tag={} value={}",
-                    caa.flags(),
-                    caa.is_critical(),
-                    caa.tag(),
-                    value,
-                );
-            }
-            TypedRr::Tlsa(t) => {
-                println!(
-                    "      TLSA cert_usage={} selector={} matching_type={} data_len={}",
-                    t.cert_usage(),
-                    t.selector(),
-                    t.matching_type(),
-                    t.data().len(),
-                );
-            }
-            TypedRr::Sig(s) => {
-                println!(
-                    "      SIG type_covered={} algorithm={} labels={} key_tag={} signers_name={:?}",
-                    s.type_covered(),
-                    s.algorithm(),
-                    s.labels(),
-                    s.key_tag(),
-                    s.signers_name(),
-                );
-            }
-            TypedRr::Uri(u) => {
-                println!(
-                    "      URI priority={} weight={} target={:?}",
-                    u.priority(),
-                    u.weight(),
-                    u.target(),
-                );
-            }
-            TypedRr::Svcb(s) => {
-                println!(
-                    "      SVCB priority={} target={:?} param_count={}",
-                    s.priority(),
-                    s.target(),
-                    s.param_count(),
-                );
-                for (key, value) in s.params() {
-                    let parsed = value
-                        .map(|v| v.to_string())
-                        .unwrap_or_else(|e| format!("<error: {e}>"));
-                    println!("        param {key}: {parsed}");
-                }
-            }
-            TypedRr::Https(h) => {
-                println!(
-                    "      HTTPS priority={} target={:?} param_count={}",
-                    h.priority(),
-                    h.target(),
-                    h.param_count(),
-                );
-                for (key, value) in h.params() {
-                    let parsed = value
-                        .map(|v| v.to_string())
-                        .unwrap_or_else(|e| format!("<error: {e}>"));
-                    println!("        param {key}: {parsed}");
-                }
-            }
-            TypedRr::Opt(o) => {
-                println!(
-                    "      OPT udp_size={} version={} flags={:#06x} option_count={}",
-                    o.udp_size(),
-                    o.version(),
-                    o.flags(),
-                    o.option_count(),
-                );
-            }
-            TypedRr::RawRr(r) => {
-                println!(
-                    "      RAW_RR raw_type={} data_len={}",
-                    r.raw_type(),
-                    r.data().len(),
-                );
-            }
-            TypedRr::Any(_) => {}
-            _ => {}
-        }
-    }
-
     fn print_section(record: &DnsRecord, section: DnsSection, label: &str) {
         let count = record.rr_count(section);
         if count == 0 {
@@ -175,7 +16,7 @@ mod inner {
         }
         println!("  {label} ({count}):");
         for rr in record.rrs(section) {
-            print_rr(rr);
+            println!("    {rr}");
         }
     }
 
@@ -5,9 +5,12 @@ use crate::error::Error;
 use crate::host::HostResults;
 use crate::nameinfo::NameInfoResult;
 use crate::resolver::{Options, Resolver};
+use crate::stats::ResolverMetrics;
 
 #[cfg(cares1_29)]
 use c_ares::ServerStateFlags;
+#[cfg(cares1_34)]
+use c_ares::TraceEvent;
 
 use std::sync::mpsc;
 
@@ -76,6 +79,14 @@ impl BlockingResolver {
         Ok(self)
     }
 
+    /// Returns a snapshot of the options that the underlying channel was configured with.
+    ///
+    /// c-ares does not report the values that it read from the system configuration: see
+    /// [`c_ares::EffectiveOptions`].
+    pub fn effective_options(&self) -> c_ares::Result<c_ares::EffectiveOptions> {
+        self.inner.effective_options()
+    }
+
     /// Set the list of servers to contact, instead of the servers specified in resolv.conf or the
     /// local named.
     ///
@@ -99,6 +110,22 @@ impl BlockingResolver {
         self.inner.servers()
     }
 
+    /// Set the list of servers to contact, from structured [`c_ares::ServerConfig`]s.
+    #[cfg(cares1_24)]
+    pub fn set_servers_config<'a, I>(&self, servers: I) -> c_ares::Result<&Self>
+    where
+        I: IntoIterator<Item = &'a c_ares::ServerConfig>,
+    {
+        self.inner.set_servers_config(servers)?;
+        Ok(self)
+    }
+
+    /// Retrieves the list of configured servers, as structured [`c_ares::ServerConfig`]s.
+    #[cfg(cares1_24)]
+    pub fn servers_config(&self) -> c_ares::Result<Vec<c_ares::ServerConfig>> {
+        self.inner.servers_config()
+    }
+
     /// Set the local IPv4 address from which to make queries.
     pub fn set_local_ipv4(&self, ipv4: Ipv4Addr) -> &Self {
         self.inner.set_local_ipv4(ipv4);
@@ -132,6 +159,32 @@ impl BlockingResolver {
         Ok(self)
     }
 
+    /// Set a callback function to be invoked after a socket has been created and connected.
+    ///
+    /// `callback(socket, socket_type)` is called with the new socket and its type.  Returning an
+    /// error causes c-ares to abandon the connection.
+    pub fn set_socket_created_callback<F>(&self, callback: F) -> &Self
+    where
+        F: Fn(c_ares::Socket, c_ares::SocketType) -> c_ares::Result<()> + Send + Sync + 'static,
+    {
+        self.inner.set_socket_created_callback(callback);
+        self
+    }
+
+    /// Set a callback function to be invoked after a socket has been created, but before it is
+    /// connected.
+    ///
+    /// This is the place to apply socket options - for example `SO_MARK`, `IP_TOS` or
+    /// `SO_BINDTODEVICE`.  `callback(socket, socket_type)` is called with the new socket and its
+    /// type.  Returning an error causes c-ares to abandon the connection.
+    pub fn set_socket_configure_callback<F>(&self, callback: F) -> &Self
+    where
+        F: Fn(c_ares::Socket, c_ares::SocketType) -> c_ares::Result<()> + Send + Sync + 'static,
+    {
+        self.inner.set_socket_configure_callback(callback);
+        self
+    }
+
     /// Set a callback function to be invoked whenever a query on the channel completes.
     ///
     /// `callback(server, success, flags)` will be called when a query completes.
@@ -148,6 +201,21 @@ impl BlockingResolver {
         self
     }
 
+    /// Set a callback function to be invoked for every DNS message that the resolver sends to, or
+    /// receives from, a server.
+    ///
+    /// Connections that the resolver opened before tracing began are never traced, so prefer
+    /// [`Options::set_trace_callback()`](crate::Options::set_trace_callback), or else call this
+    /// before making any queries.  See [`c_ares::Channel::set_trace_callback()`] for details.
+    #[cfg(cares1_34)]
+    pub fn set_trace_callback<F>(&self, callback: F) -> c_ares::Result<&Self>
+    where
+        F: Fn(&TraceEvent<'_>) + Send + Sync + 'static,
+    {
+        self.inner.set_trace_callback(callback)?;
+        Ok(self)
+    }
+
     /// Look up the A records associated with `name`.
     pub fn query_a(&self, name: &str) -> c_ares::Result<c_ares::AResults> {
         blockify!(self.inner, query_a, name)
@@ -343,6 +411,19 @@ impl BlockingResolver {
         rx.recv().unwrap()
     }
 
+    /// Send a DNS query that is already in wire format, returning the raw response.
+    ///
+    /// This method is provided for callers - such as proxies - that already hold queries as bytes.
+    /// The query still benefits from the channel's retry and server failover behaviour.
+    /// The response carries the query ID from `query`: see [`c_ares::Channel::send_raw()`].
+    pub fn send_raw(&self, query: &[u8]) -> c_ares::Result<Vec<u8>> {
+        let (tx, rx) = mpsc::sync_channel(1);
+        self.inner.send_raw(query, move |result| {
+            let _ = tx.send(result.map(std::borrow::ToOwned::to_owned));
+        });
+        rx.recv().unwrap()
+    }
+
     /// Send a DNS query using a pre-built [`c_ares::DnsRecord`].
     ///
     /// # Examples
@@ -443,6 +524,21 @@ impl BlockingResolver {
     pub fn queue_active_queries(&self) -> usize {
         self.inner.queue_active_queries()
     }
+
+    /// Whether the resolver is driven by the `c-ares` built-in event thread, rather than by an
+    /// event loop of its own.
+    pub fn uses_event_thread(&self) -> bool {
+        self.inner.uses_event_thread()
+    }
+
+    /// Returns a snapshot of the resolver's metrics: the queries that it has made, how they
+    /// completed and how long they took, and how each server has fared.
+    ///
+    /// The snapshot is empty unless the resolver was created with
+    /// [`Options::set_metrics()`](crate::Options::set_metrics).
+    pub fn metrics(&self) -> ResolverMetrics {
+        self.inner.metrics()
+    }
 }
 
 #[cfg(test)]
@@ -537,6 +633,27 @@ mod tests {
         assert!(!servers.is_empty());
     }
 
+    #[test]
+    fn blocking_resolver_effective_options() {
+        let mut options = Options::new();
+        options.set_tries(3);
+        let resolver = BlockingResolver::with_options(options).unwrap();
+        let effective = resolver.effective_options().unwrap();
+        assert_eq!(effective.tries(), Some(3));
+    }
+
+    #[test]
+    #[cfg(cares1_24)]
+    fn blocking_resolver_servers_config() {
+        let resolver = BlockingResolver::new().unwrap();
+        let config: c_ares::ServerConfig = "dns://8.8.8.8:5353".parse().unwrap();
+        let result = resolver.set_servers_config([&config]).unwrap();
+        assert!(std::ptr::eq(result, &raw const resolver));
+        let servers = resolver.servers_config().unwrap();
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].udp_port(), Some(5353));
+    }
+
     #[test]
     #[cfg(cares1_27)]
     fn blocking_resolver_queue_active_queries() {
@@ -544,6 +661,14 @@ mod tests {
         assert_eq!(resolver.queue_active_queries(), 0);
     }
 
+    #[test]
+    fn blocking_resolver_metrics_start_empty() {
+        let resolver = BlockingResolver::new().unwrap();
+        let metrics = resolver.metrics();
+        assert_eq!(metrics.query_count(), 0);
+        assert_eq!(metrics.active_queries(), 0);
+    }
+
     #[test]
     #[cfg(cares1_27)]
     fn blocking_resolver_queue_wait_empty() {
@@ -552,6 +677,15 @@ mod tests {
         assert!(result.is_ok() || result == Err(c_ares::Error::ENOTIMP));
     }
 
+    #[test]
+    fn blocking_resolver_set_socket_callbacks() {
+        let resolver = BlockingResolver::new().unwrap();
+        let result = resolver
+            .set_socket_created_callback(|_socket, _socket_type| Ok(()))
+            .set_socket_configure_callback(|_socket, _socket_type| Ok(()));
+        assert!(std::ptr::eq(result, &raw const resolver));
+    }
+
     #[test]
     #[cfg(cares1_29)]
     fn blocking_resolver_set_server_state_callback() {
@@ -560,6 +694,21 @@ mod tests {
         assert!(std::ptr::eq(result, &raw const resolver));
     }
 
+    #[test]
+    #[cfg(cares1_34)]
+    fn blocking_resolver_set_trace_callback() {
+        let resolver = BlockingResolver::new().unwrap();
+        let result = resolver.set_trace_callback(|_event| {}).unwrap();
+        assert!(std::ptr::eq(result, &raw const resolver));
+    }
+
+    #[test]
+    fn blocking_resolver_send_raw_malformed() {
+        let resolver = BlockingResolver::new().unwrap();
+        let result = resolver.send_raw(&[0x12, 0x34, 0x01]);
+        assert!(result.is_err());
+    }
+
     #[test]
     fn debug_blocking_resolver() {
         let resolver = BlockingResolver::new().unwrap();
@@ -0,0 +1,40 @@
+use std::sync::Mutex;
+
+// A resolver's channel.  When c-ares is thread-safe, the channel is shared between threads without
+// a lock; otherwise, it is protected by a mutex.
+pub(crate) enum ChannelCell {
+    #[cfg(cares1_23)]
+    Shared(c_ares::SharedChannel),
+    Locked(Mutex<c_ares::Channel>),
+}
+
+impl ChannelCell {
+    pub(crate) fn new(channel: c_ares::Channel) -> Self {
+        #[cfg(cares1_23)]
+        let channel = match c_ares::SharedChannel::new(channel) {
+            Ok(channel) => return Self::Shared(channel),
+            Err(channel) => channel,
+        };
+        Self::Locked(Mutex::new(channel))
+    }
+}
+
+// Evaluate `body` with `channel` bound to the channel in `cell`, taking the lock if there is one.
+//
+// The `SharedChannel` and `Channel` methods share names and arguments, but not always return
+// types: so `body` is expanded once for each, and must evaluate to the same type in both.
+macro_rules! with_channel {
+    ($cell:expr, |$channel:ident| $body:expr) => {{
+        let cell: &$crate::channelcell::ChannelCell = &$cell;
+        match cell {
+            #[cfg(cares1_23)]
+            $crate::channelcell::ChannelCell::Shared($channel) => $body,
+            $crate::channelcell::ChannelCell::Locked(mutex) => {
+                let $channel = &mut *mutex.lock().unwrap();
+                $body
+            }
+        }
+    }};
+}
+
+pub(crate) use with_channel;
@@ -3,6 +3,7 @@ use std::net::IpAddr;
 
 /// An owned version of `c_ares::HostResults`.
 #[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct HostResults {
     hostname: String,
     addresses: Vec<IpAddr>,
@@ -178,4 +179,19 @@ mod tests {
         assert!(display.contains("example.com"));
         assert!(display.contains("127.0.0.1"));
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn host_results_serialize() {
+        let results = HostResults {
+            hostname: "example.com".to_string(),
+            addresses: vec!["127.0.0.1".parse().unwrap()],
+            aliases: vec!["www.example.com".to_string()],
+        };
+        let json = serde_json::to_string(&results).unwrap();
+        assert_eq!(
+            json,
+            r#"{"hostname":"example.com","addresses":["127.0.0.1"],"aliases":["www.example.com"]}"#
+        );
+    }
 }
@@ -11,6 +11,14 @@
 //! - The `BlockingResolver` isn't asynchronous at all - as the name suggests, it blocks until the
 //!   lookup completes.
 //!
+//! By default, the resolvers run their own event loop on a background thread.  Applications that
+//! already have an event loop can instead implement the `Reactor` trait, and create a `Resolver`
+//! with `Resolver::with_reactor()`.
+//!
+//! With the `tokio` feature there is also a `TokioResolver`.  This offers the same interface as
+//! the `FutureResolver`, but is driven by the tokio runtime rather than by a thread of its own.  It
+//! is available on unix, with `c-ares` 1.34 or later.
+//!
 //! On all resolvers:
 //!
 //! - methods like `query_xxx` correspond to the `c-ares` function `ares_query`, which "initiates
@@ -43,16 +51,27 @@
 #![deny(missing_docs)]
 
 mod blockingresolver;
+mod channelcell;
 mod error;
 mod eventloop;
 mod futureresolver;
 mod host;
 mod nameinfo;
+mod reactor;
 mod resolver;
+mod stats;
+#[cfg(all(feature = "tokio", unix, cares1_34))]
+mod tokioloop;
+#[cfg(all(feature = "tokio", unix, cares1_34))]
+mod tokioresolver;
 
 pub use crate::blockingresolver::BlockingResolver;
 pub use crate::error::Error;
 pub use crate::futureresolver::{CAresFuture, FutureResolver};
 pub use crate::host::HostResults;
 pub use crate::nameinfo::NameInfoResult;
+pub use crate::reactor::{Reactor, ReactorHandle, SocketEvent};
 pub use crate::resolver::{Options, Resolver};
+pub use crate::stats::{LatencyHistogram, QueryMethod, ResolverMetrics, ServerCounts};
+#[cfg(all(feature = "tokio", unix, cares1_34))]
+pub use crate::tokioresolver::TokioResolver;
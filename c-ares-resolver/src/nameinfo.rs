@@ -2,6 +2,7 @@ use std::fmt;
 
 /// An owned version of `c_ares::NameInfoResult`.
 #[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct NameInfoResult {
     node: Option<String>,
     service: Option<String>,
@@ -163,4 +164,15 @@ mod tests {
         let display = format!("{result}");
         assert!(display.contains("<None>"));
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn name_info_result_serialize() {
+        let result = NameInfoResult {
+            node: Some("example.com".to_string()),
+            service: None,
+        };
+        let json = serde_json::to_string(&result).unwrap();
+        assert_eq!(json, r#"{"node":"example.com","service":null}"#);
+    }
 }
@@ -0,0 +1,274 @@
+use std::fmt;
+#[cfg(cares1_34)]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+#[cfg(cares1_34)]
+use c_ares::{FdEventFlags, FdEvents, ProcessFlags};
+
+use crate::channelcell::{ChannelCell, with_channel};
+
+// The longest that a reactor is asked to wait before calling back into the resolver, even if
+// c-ares has no timeout pending.  This bounds the delay in noticing timeouts for queries that were
+// sent without any change to the sockets of interest.
+const MAX_WAIT: Duration = Duration::from_millis(500);
+
+/// An event loop that can drive a [`Resolver`](crate::Resolver).
+///
+/// By default, each `Resolver` runs its own event loop on a background thread.  Applications that
+/// already have an event loop - GUI toolkits, `mio`, `calloop` and so on - can instead implement
+/// this trait, and pass the result to
+/// [`Resolver::with_reactor()`](crate::Resolver::with_reactor).
+///
+/// The resolver tells the reactor which sockets it is interested in, and when it next needs to be
+/// called; the reactor reports back through the [`ReactorHandle`] that it is given by
+/// [`attach()`](Self::attach).
+///
+/// The methods on this trait may be called from any thread, and may be called while the resolver
+/// is busy: implementations should record what they are asked and return, and must not call back
+/// into the `ReactorHandle` from within them.
+pub trait Reactor: Send + Sync + 'static {
+    /// Called once, when the resolver has been created.
+    ///
+    /// The reactor should keep the `handle`, and use it to report events on sockets and the
+    /// expiry of timers.
+    fn attach(&self, handle: ReactorHandle);
+
+    /// Set the events that the resolver is interested in on `socket`, replacing any interest
+    /// previously set.
+    ///
+    /// When both `readable` and `writable` are false, the reactor should stop watching the socket.
+    /// The resolver is about to close it.
+    fn set_interest(&self, socket: c_ares::Socket, readable: bool, writable: bool);
+
+    /// Arrange for [`ReactorHandle::process()`] to be called after `timeout`, replacing any timer
+    /// previously set.
+    fn set_timer(&self, timeout: Duration);
+
+    /// Arrange for [`ReactorHandle::process()`] to be called as soon as possible.
+    fn wake(&self);
+
+    /// Called when the resolver is dropped.
+    ///
+    /// The default implementation does nothing.
+    fn detach(&self) {}
+}
+
+impl<R: Reactor + ?Sized> Reactor for Arc<R> {
+    fn attach(&self, handle: ReactorHandle) {
+        (**self).attach(handle);
+    }
+
+    fn set_interest(&self, socket: c_ares::Socket, readable: bool, writable: bool) {
+        (**self).set_interest(socket, readable, writable);
+    }
+
+    fn set_timer(&self, timeout: Duration) {
+        (**self).set_timer(timeout);
+    }
+
+    fn wake(&self) {
+        (**self).wake();
+    }
+
+    fn detach(&self) {
+        (**self).detach();
+    }
+}
+
+/// Events on a socket, as reported by a [`Reactor`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SocketEvent {
+    socket: c_ares::Socket,
+    readable: bool,
+    writable: bool,
+}
+
+impl SocketEvent {
+    /// Returns a new `SocketEvent`, indicating whether `socket` is readable and whether it is
+    /// writable.
+    pub fn new(socket: c_ares::Socket, readable: bool, writable: bool) -> Self {
+        Self {
+            socket,
+            readable,
+            writable,
+        }
+    }
+
+    /// The socket on which the events occurred.
+    pub fn socket(&self) -> c_ares::Socket {
+        self.socket
+    }
+
+    /// Whether the socket is readable.
+    pub fn readable(&self) -> bool {
+        self.readable
+    }
+
+    /// Whether the socket is writable.
+    pub fn writable(&self) -> bool {
+        self.writable
+    }
+}
+
+/// The means by which a [`Reactor`] reports events to the resolver that it is driving.
+///
+/// The handle does not keep the resolver alive: once the resolver has been dropped, `process()`
+/// does nothing.
+#[derive(Clone)]
+pub struct ReactorHandle {
+    ares_channel: Weak<ChannelCell>,
+    reactor: Weak<dyn Reactor>,
+
+    #[cfg(cares1_34)]
+    pending_write: Arc<AtomicBool>,
+}
+
+impl fmt::Debug for ReactorHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReactorHandle").finish_non_exhaustive()
+    }
+}
+
+impl ReactorHandle {
+    // Create a handle for the given channel and reactor.  This also sets up the channel's pending
+    // write callback, so that queued writes wake the reactor.
+    pub(crate) fn new(ares_channel: &Arc<ChannelCell>, reactor: &Arc<dyn Reactor>) -> Self {
+        #[cfg(cares1_34)]
+        let pending_write = {
+            let pending_write = Arc::new(AtomicBool::new(false));
+            let flag = Arc::clone(&pending_write);
+            let reactor = Arc::clone(reactor);
+            let pending_write_callback = move || {
+                flag.store(true, Ordering::Release);
+                reactor.wake();
+            };
+            with_channel!(ares_channel, |channel| {
+                channel.set_pending_write_callback(pending_write_callback);
+            });
+            pending_write
+        };
+
+        Self {
+            ares_channel: Arc::downgrade(ares_channel),
+            reactor: Arc::downgrade(reactor),
+            #[cfg(cares1_34)]
+            pending_write,
+        }
+    }
+
+    /// Have the resolver handle `events`, along with any timeouts that have expired and any
+    /// writes that it has queued.
+    ///
+    /// Reactors should call this when sockets of interest are ready, when the timer expires, and
+    /// when woken.  `events` may be empty.
+    ///
+    /// Before returning, this sets the reactor's timer.
+    pub fn process(&self, events: &[SocketEvent]) {
+        let Some(ares_channel) = self.ares_channel.upgrade() else {
+            return;
+        };
+        #[cfg(cares1_34)]
+        let fd_events = fd_events(events);
+        let timeout = with_channel!(ares_channel, |channel| {
+            // Process any pending write, and then any events.
+            #[cfg(cares1_34)]
+            {
+                if self.pending_write.swap(false, Ordering::AcqRel) {
+                    #[cfg(feature = "tracing")]
+                    tracing::trace!("processing pending write");
+                    channel.process_pending_write();
+                }
+                let _ = channel.process_fds(&fd_events, ProcessFlags::empty());
+            }
+
+            // Process any events.
+            #[cfg(not(cares1_34))]
+            {
+                for event in events {
+                    let rfd = event.readable.then_some(event.socket);
+                    let wfd = event.writable.then_some(event.socket);
+                    channel.process_fd(rfd, wfd);
+                }
+
+                if events.is_empty() {
+                    // No events.  Have c-ares process any timeouts.
+                    channel.process_fd(None, None);
+                }
+            }
+
+            channel.timeout(Some(MAX_WAIT)).unwrap_or(MAX_WAIT)
+        });
+        self.set_timer(timeout);
+    }
+
+    // Set the reactor's timer according to the channel's next timeout.
+    pub(crate) fn schedule(&self) {
+        let Some(ares_channel) = self.ares_channel.upgrade() else {
+            return;
+        };
+        let timeout = with_channel!(ares_channel, |channel| channel.timeout(Some(MAX_WAIT)))
+            .unwrap_or(MAX_WAIT);
+        self.set_timer(timeout);
+    }
+
+    fn set_timer(&self, timeout: Duration) {
+        if let Some(reactor) = self.reactor.upgrade() {
+            reactor.set_timer(timeout);
+        }
+    }
+}
+
+// Detaches the reactor when dropped.
+pub(crate) struct ReactorGuard(pub(crate) Arc<dyn Reactor>);
+
+impl Drop for ReactorGuard {
+    fn drop(&mut self) {
+        self.0.detach();
+    }
+}
+
+// The events reported by a reactor, as c-ares wants them.
+#[cfg(cares1_34)]
+fn fd_events(events: &[SocketEvent]) -> Vec<FdEvents> {
+    events
+        .iter()
+        .map(|event| {
+            let mut event_flags = FdEventFlags::empty();
+            if event.readable {
+                event_flags.insert(FdEventFlags::READ);
+            }
+            if event.writable {
+                event_flags.insert(FdEventFlags::WRITE);
+            }
+            FdEvents::new(event.socket, event_flags)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+
+    #[test]
+    fn reactor_handle_is_send() {
+        assert_send::<ReactorHandle>();
+    }
+
+    #[test]
+    fn reactor_handle_is_sync() {
+        assert_sync::<ReactorHandle>();
+    }
+
+    #[test]
+    fn socket_event_accessors() {
+        let event = SocketEvent::new(3, true, false);
+        assert_eq!(event.socket(), 3);
+        assert!(event.readable());
+        assert!(!event.writable());
+    }
+}
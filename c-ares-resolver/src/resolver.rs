@@ -1,17 +1,70 @@
 use std::fmt;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
+use crate::channelcell::{ChannelCell, with_channel};
 use crate::error::Error;
-use crate::eventloop::{EventLoop, EventLoopStopper};
-
+use crate::eventloop::EventLoop;
+use crate::reactor::{Reactor, ReactorGuard, ReactorHandle};
+use crate::stats::{QueryMethod, Recorder, ResolverMetrics};
+#[cfg(all(feature = "tokio", unix, cares1_34))]
+use crate::tokioloop::TokioEventLoop;
+
+#[cfg(cares1_34)]
+use c_ares::TraceEvent;
 #[cfg(cares1_29)]
 use c_ares::{ServerFailoverOptions, ServerStateFlags};
+use c_ares_sys::ares_dns_rec_type_t as RecType;
+
+// Wrap a query's handler so that its completion is recorded in the resolver's metrics, if it is
+// collecting them.
+macro_rules! tracked {
+    ($resolver:expr, $method:ident, $record_type:expr, $handler:expr) => {{
+        let completion = $resolver
+            .metrics
+            .as_ref()
+            .map(|metrics| metrics.start(QueryMethod::$method, $record_type));
+        let handler = $handler;
+        move |result| {
+            if let Some(completion) = completion {
+                completion.finish(&result);
+            }
+            handler(result)
+        }
+    }};
+}
+
+// The event loop that should drive a resolver.
+#[derive(Clone, Copy)]
+enum Driver {
+    // Our own `EventLoop`.
+    EventLoop,
+
+    // The c-ares built-in event thread, with the given backend - if c-ares is thread-safe.
+    // Otherwise, our own `EventLoop`.
+    #[cfg(cares1_26)]
+    EventThread(c_ares::EventSys),
+}
+
+impl Default for Driver {
+    #[cfg(cares1_26)]
+    fn default() -> Self {
+        Self::EventThread(c_ares::EventSys::Default)
+    }
+
+    #[cfg(not(cares1_26))]
+    fn default() -> Self {
+        Self::EventLoop
+    }
+}
 
 /// Used to configure the behaviour of the resolver.
 #[derive(Default)]
 pub struct Options {
     inner: c_ares::Options,
+    #[cfg_attr(not(cares1_26), allow(dead_code))]
+    driver: Driver,
+    metrics: bool,
 }
 
 impl fmt::Debug for Options {
@@ -195,18 +248,78 @@ impl Options {
             .set_server_failover_options(server_failover_options);
         self
     }
+
+    /// Set a callback function to be invoked for every DNS message that the resolver sends to, or
+    /// receives from, a server.
+    ///
+    /// See [`c_ares::Channel::set_trace_callback()`] for details.
+    #[cfg(cares1_34)]
+    pub fn set_trace_callback<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&TraceEvent<'_>) + Send + Sync + 'static,
+    {
+        self.inner.set_trace_callback(callback);
+        self
+    }
+
+    /// Set the socket functions that the resolver uses in place of the operating system's own.
+    ///
+    /// See [`c_ares::SocketFunctions`] for details.  The resolver waits for its sockets to become
+    /// readable or writable, so unless it is driven by a [`Reactor`] that knows better, the
+    /// sockets that these functions hand out must be real file descriptors.
+    #[cfg(cares1_34)]
+    pub fn set_socket_functions<S>(&mut self, socket_functions: S) -> &mut Self
+    where
+        S: c_ares::SocketFunctions,
+    {
+        self.inner.set_socket_functions(socket_functions);
+        self
+    }
+
+    /// Drive the resolver with the `c-ares` built-in event thread, using the given I/O backend.
+    ///
+    /// If `c-ares` was not built with thread safety, the resolver falls back to running its own
+    /// event loop.  Use `uses_event_thread()` on the resolver to find out which was chosen.
+    ///
+    /// This is the default, with [`c_ares::EventSys::Default`].
+    #[cfg(cares1_26)]
+    pub fn set_event_thread(&mut self, evsys: c_ares::EventSys) -> &mut Self {
+        self.driver = Driver::EventThread(evsys);
+        self
+    }
+
+    /// Drive the resolver with its own event loop, on a background thread, even if the `c-ares`
+    /// built-in event thread is available.
+    pub fn set_event_loop(&mut self) -> &mut Self {
+        self.driver = Driver::EventLoop;
+        self
+    }
+
+    /// Collect metrics, as returned by `metrics()` on the resolver - and, with the `metrics`
+    /// feature, published through the `metrics` crate.
+    ///
+    /// By default no metrics are collected.
+    pub fn set_metrics(&mut self) -> &mut Self {
+        self.metrics = true;
+        self
+    }
 }
 
 /// An asynchronous DNS resolver, which returns results via callbacks.
 ///
+/// When `c-ares` was built with thread safety, queries go straight to it without taking a lock,
+/// so that a resolver can be shared between many threads without contention.
+///
 /// Note that dropping the resolver will cause all outstanding requests to fail with result
 /// `c_ares::Error::EDESTRUCTION`.
 pub struct Resolver {
-    ares_channel: Arc<Mutex<c_ares::Channel>>,
+    ares_channel: Arc<ChannelCell>,
+    metrics: Option<Arc<Recorder>>,
 
-    // Present when we run our own event loop; `None` when c-ares manages its built-in event
-    // thread (the thread stops automatically when the Channel is destroyed).
-    _event_loop_stopper: Option<EventLoopStopper>,
+    // Present when the channel is driven by a reactor or a tokio task, and stops it when dropped;
+    // `None` when c-ares manages its built-in event thread (the thread stops automatically when
+    // the Channel is destroyed).
+    event_loop_stopper: Option<Box<dyn Send + Sync>>,
 }
 
 impl fmt::Debug for Resolver {
@@ -223,39 +336,108 @@ impl Resolver {
     }
 
     /// Create a new `Resolver`, with the given `Options`.
-    pub fn with_options(options: Options) -> Result<Self, Error> {
-        let mut inner = options.inner;
-
-        // Use the c-ares built-in event thread if available, else our custom event loop.
+    ///
+    /// The resolver is driven by the `c-ares` built-in event thread if that is available, and not
+    /// ruled out by the `options`; else by an event loop of its own.
+    #[cfg_attr(not(cares1_26), allow(unused_mut))]
+    pub fn with_options(mut options: Options) -> Result<Self, Error> {
         #[cfg(cares1_26)]
-        let event_loop = if c_ares::thread_safety() {
-            inner.set_event_thread(c_ares::EventSys::Default);
-            None
-        } else {
-            Some(EventLoop::new(&mut inner)?)
-        };
-        #[cfg(not(cares1_26))]
-        let event_loop = Some(EventLoop::new(&mut inner)?);
-
-        // Create the channel.
-        let ares_channel = Arc::new(Mutex::new(c_ares::Channel::with_options(inner)?));
+        if let Driver::EventThread(evsys) = options.driver
+            && c_ares::thread_safety()
+        {
+            options.inner.set_event_thread(evsys);
+            let (ares_channel, metrics) = Self::create_channel(options.inner, options.metrics)?;
+            return Ok(Self {
+                ares_channel,
+                metrics,
+                event_loop_stopper: None,
+            });
+        }
+
+        Self::with_reactor(options, EventLoop::new()?)
+    }
+
+    /// Create a new `Resolver`, with the given `Options`, driven by the given [`Reactor`].
+    ///
+    /// No background thread is started: the resolver makes progress only as the reactor reports
+    /// events to it.
+    pub fn with_reactor<R: Reactor>(options: Options, reactor: R) -> Result<Self, Error> {
+        let Options {
+            mut inner, metrics, ..
+        } = options;
+        let reactor: Arc<dyn Reactor> = Arc::new(reactor);
+
+        // Pass on c-ares's interest in sockets to the reactor.
+        {
+            let reactor = Arc::clone(&reactor);
+            let sock_callback = move |socket: c_ares::Socket, readable: bool, writable: bool| {
+                reactor.set_interest(socket, readable, writable);
+            };
+            inner.set_socket_state_callback(sock_callback);
+        }
+
+        let (ares_channel, metrics) = Self::create_channel(inner, metrics)?;
+
+        // Set the reactor's first timer, and hand it the means to drive the channel.
+        let handle = ReactorHandle::new(&ares_channel, &reactor);
+        handle.schedule();
+        reactor.attach(handle);
 
-        // Start the custom event loop if we're using one.
-        let stopper = event_loop.map(|el| el.run(Arc::clone(&ares_channel)));
+        Ok(Self {
+            ares_channel,
+            metrics,
+            event_loop_stopper: Some(Box::new(ReactorGuard(reactor))),
+        })
+    }
 
+    // Create a new `Resolver` whose channel is driven by a task on the current tokio runtime.
+    #[cfg(all(feature = "tokio", unix, cares1_34))]
+    pub(crate) fn with_tokio(options: Options) -> Result<Self, Error> {
+        let mut inner = options.inner;
+        let event_loop = TokioEventLoop::new(&mut inner)?;
+        let (ares_channel, metrics) = Self::create_channel(inner, options.metrics)?;
+        let stopper = event_loop.run(Arc::clone(&ares_channel));
         Ok(Self {
             ares_channel,
-            _event_loop_stopper: stopper,
+            metrics,
+            event_loop_stopper: Some(Box::new(stopper)),
         })
     }
 
+    // Create the channel, and - if metrics are wanted - a recorder for them, fed by a server
+    // state callback.
+    #[allow(clippy::type_complexity)]
+    fn create_channel(
+        options: c_ares::Options,
+        metrics: bool,
+    ) -> Result<(Arc<ChannelCell>, Option<Arc<Recorder>>), Error> {
+        let metrics = metrics.then(Arc::<Recorder>::default);
+        #[cfg_attr(not(cares1_29), allow(unused_mut))]
+        let mut channel = c_ares::Channel::with_options(options)?;
+        #[cfg(cares1_29)]
+        if let Some(metrics) = &metrics {
+            channel.set_server_state_callback(record_server_state(metrics));
+        }
+        Ok((Arc::new(ChannelCell::new(channel)), metrics))
+    }
+
     /// Reinitialize a channel from system configuration.
     #[cfg(cares1_22)]
     pub fn reinit(&self) -> c_ares::Result<&Self> {
-        self.ares_channel.lock().unwrap().reinit()?;
+        with_channel!(self.ares_channel, |channel| {
+            channel.reinit()?;
+        });
         Ok(self)
     }
 
+    /// Returns a snapshot of the options that the underlying channel was configured with.
+    ///
+    /// c-ares does not report the values that it read from the system configuration: see
+    /// [`c_ares::EffectiveOptions`].
+    pub fn effective_options(&self) -> c_ares::Result<c_ares::EffectiveOptions> {
+        with_channel!(self.ares_channel, |channel| channel.effective_options())
+    }
+
     /// Set the list of servers to contact, instead of the servers specified in resolv.conf or the
     /// local named.
     ///
@@ -266,7 +448,9 @@ impl Resolver {
         I: IntoIterator<Item = S>,
         S: AsRef<str>,
     {
-        self.ares_channel.lock().unwrap().set_servers(servers)?;
+        with_channel!(self.ares_channel, |channel| {
+            channel.set_servers(servers)?;
+        });
         Ok(self)
     }
 
@@ -276,24 +460,48 @@ impl Resolver {
     /// accepts.
     #[cfg(cares1_24)]
     pub fn servers(&self) -> Vec<String> {
-        self.ares_channel.lock().unwrap().servers()
+        with_channel!(self.ares_channel, |channel| channel.servers())
+    }
+
+    /// Set the list of servers to contact, from structured [`c_ares::ServerConfig`]s.
+    #[cfg(cares1_24)]
+    pub fn set_servers_config<'a, I>(&self, servers: I) -> c_ares::Result<&Self>
+    where
+        I: IntoIterator<Item = &'a c_ares::ServerConfig>,
+    {
+        with_channel!(self.ares_channel, |channel| {
+            channel.set_servers_config(servers)?;
+        });
+        Ok(self)
+    }
+
+    /// Retrieves the list of configured servers, as structured [`c_ares::ServerConfig`]s.
+    #[cfg(cares1_24)]
+    pub fn servers_config(&self) -> c_ares::Result<Vec<c_ares::ServerConfig>> {
+        with_channel!(self.ares_channel, |channel| channel.servers_config())
     }
 
     /// Set the local IPv4 address from which to make queries.
     pub fn set_local_ipv4(&self, ipv4: Ipv4Addr) -> &Self {
-        self.ares_channel.lock().unwrap().set_local_ipv4(ipv4);
+        with_channel!(self.ares_channel, |channel| {
+            channel.set_local_ipv4(ipv4);
+        });
         self
     }
 
     /// Set the local IPv6 address from which to make queries.
     pub fn set_local_ipv6(&self, ipv6: Ipv6Addr) -> &Self {
-        self.ares_channel.lock().unwrap().set_local_ipv6(ipv6);
+        with_channel!(self.ares_channel, |channel| {
+            channel.set_local_ipv6(ipv6);
+        });
         self
     }
 
     /// Set the local device from which to make queries.
     pub fn set_local_device(&self, device: &str) -> c_ares::Result<&Self> {
-        self.ares_channel.lock().unwrap().set_local_device(device)?;
+        with_channel!(self.ares_channel, |channel| {
+            channel.set_local_device(device)?;
+        });
         Ok(self)
     }
 
@@ -308,10 +516,42 @@ impl Resolver {
         I: IntoIterator<Item = S>,
         S: AsRef<str>,
     {
-        self.ares_channel.lock().unwrap().set_sortlist(sortlist)?;
+        with_channel!(self.ares_channel, |channel| {
+            channel.set_sortlist(sortlist)?;
+        });
         Ok(self)
     }
 
+    /// Set a callback function to be invoked after a socket has been created and connected.
+    ///
+    /// `callback(socket, socket_type)` is called with the new socket and its type.  Returning an
+    /// error causes c-ares to abandon the connection.
+    pub fn set_socket_created_callback<F>(&self, callback: F) -> &Self
+    where
+        F: Fn(c_ares::Socket, c_ares::SocketType) -> c_ares::Result<()> + Send + Sync + 'static,
+    {
+        with_channel!(self.ares_channel, |channel| {
+            channel.set_socket_created_callback(callback);
+        });
+        self
+    }
+
+    /// Set a callback function to be invoked after a socket has been created, but before it is
+    /// connected.
+    ///
+    /// This is the place to apply socket options - for example `SO_MARK`, `IP_TOS` or
+    /// `SO_BINDTODEVICE`.  `callback(socket, socket_type)` is called with the new socket and its
+    /// type.  Returning an error causes c-ares to abandon the connection.
+    pub fn set_socket_configure_callback<F>(&self, callback: F) -> &Self
+    where
+        F: Fn(c_ares::Socket, c_ares::SocketType) -> c_ares::Result<()> + Send + Sync + 'static,
+    {
+        with_channel!(self.ares_channel, |channel| {
+            channel.set_socket_configure_callback(callback);
+        });
+        self
+    }
+
     /// Set a callback function to be invoked whenever a query on the channel completes.
     ///
     /// `callback(server, success, flags)` will be called when a query completes.
@@ -324,13 +564,35 @@ impl Resolver {
     where
         F: Fn(&str, bool, ServerStateFlags) + Send + Sync + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .set_server_state_callback(callback);
+        let record = self.metrics.as_ref().map(record_server_state);
+        with_channel!(self.ares_channel, |channel| {
+            channel.set_server_state_callback(move |server, success, flags| {
+                if let Some(record) = &record {
+                    record(server, success, flags);
+                }
+                callback(server, success, flags);
+            });
+        });
         self
     }
 
+    /// Set a callback function to be invoked for every DNS message that the resolver sends to, or
+    /// receives from, a server.
+    ///
+    /// Connections that the resolver opened before tracing began are never traced, so prefer
+    /// [`Options::set_trace_callback()`](crate::Options::set_trace_callback), or else call this
+    /// before making any queries.  See [`c_ares::Channel::set_trace_callback()`] for details.
+    #[cfg(cares1_34)]
+    pub fn set_trace_callback<F>(&self, callback: F) -> c_ares::Result<&Self>
+    where
+        F: Fn(&TraceEvent<'_>) + Send + Sync + 'static,
+    {
+        with_channel!(self.ares_channel, |channel| {
+            channel.set_trace_callback(callback)?;
+        });
+        Ok(self)
+    }
+
     /// Look up the A records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
@@ -338,7 +600,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::AResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_a(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.query_a(
+                name,
+                tracked!(self, Query, Some(RecType::ARES_REC_TYPE_A), handler),
+            );
+        });
     }
 
     /// Search for the A records associated with `name`.
@@ -348,7 +615,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::AResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_a(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.search_a(
+                name,
+                tracked!(self, Search, Some(RecType::ARES_REC_TYPE_A), handler),
+            );
+        });
     }
 
     /// Look up the AAAA records associated with `name`.
@@ -358,7 +630,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::AAAAResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_aaaa(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.query_aaaa(
+                name,
+                tracked!(self, Query, Some(RecType::ARES_REC_TYPE_AAAA), handler),
+            );
+        });
     }
 
     /// Search for the AAAA records associated with `name`.
@@ -368,7 +645,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::AAAAResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_aaaa(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.search_aaaa(
+                name,
+                tracked!(self, Search, Some(RecType::ARES_REC_TYPE_AAAA), handler),
+            );
+        });
     }
 
     /// Look up the CAA records associated with `name`.
@@ -378,7 +660,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::CAAResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_caa(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.query_caa(
+                name,
+                tracked!(self, Query, Some(RecType::ARES_REC_TYPE_CAA), handler),
+            );
+        });
     }
 
     /// Search for the CAA records associated with `name`.
@@ -388,7 +675,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::CAAResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_caa(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.search_caa(
+                name,
+                tracked!(self, Search, Some(RecType::ARES_REC_TYPE_CAA), handler),
+            );
+        });
     }
 
     /// Look up the CNAME records associated with `name`.
@@ -398,7 +690,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::CNameResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_cname(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.query_cname(
+                name,
+                tracked!(self, Query, Some(RecType::ARES_REC_TYPE_CNAME), handler),
+            );
+        });
     }
 
     /// Search for the CNAME records associated with `name`.
@@ -408,10 +705,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::CNameResults>) + Send + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .search_cname(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.search_cname(
+                name,
+                tracked!(self, Search, Some(RecType::ARES_REC_TYPE_CNAME), handler),
+            );
+        });
     }
 
     /// Look up the MX records associated with `name`.
@@ -421,7 +720,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::MXResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_mx(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.query_mx(
+                name,
+                tracked!(self, Query, Some(RecType::ARES_REC_TYPE_MX), handler),
+            );
+        });
     }
 
     /// Search for the MX records associated with `name`.
@@ -431,7 +735,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::MXResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_mx(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.search_mx(
+                name,
+                tracked!(self, Search, Some(RecType::ARES_REC_TYPE_MX), handler),
+            );
+        });
     }
 
     /// Look up the NAPTR records associated with `name`.
@@ -441,7 +750,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::NAPTRResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_naptr(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.query_naptr(
+                name,
+                tracked!(self, Query, Some(RecType::ARES_REC_TYPE_NAPTR), handler),
+            );
+        });
     }
 
     /// Search for the NAPTR records associated with `name`.
@@ -451,10 +765,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::NAPTRResults>) + Send + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .search_naptr(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.search_naptr(
+                name,
+                tracked!(self, Search, Some(RecType::ARES_REC_TYPE_NAPTR), handler),
+            );
+        });
     }
 
     /// Look up the NS records associated with `name`.
@@ -464,7 +780,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::NSResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_ns(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.query_ns(
+                name,
+                tracked!(self, Query, Some(RecType::ARES_REC_TYPE_NS), handler),
+            );
+        });
     }
 
     /// Search for the NS records associated with `name`.
@@ -474,7 +795,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::NSResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_ns(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.search_ns(
+                name,
+                tracked!(self, Search, Some(RecType::ARES_REC_TYPE_NS), handler),
+            );
+        });
     }
 
     /// Look up the PTR records associated with `name`.
@@ -484,7 +810,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::PTRResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_ptr(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.query_ptr(
+                name,
+                tracked!(self, Query, Some(RecType::ARES_REC_TYPE_PTR), handler),
+            );
+        });
     }
 
     /// Search for the PTR records associated with `name`.
@@ -494,7 +825,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::PTRResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_ptr(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.search_ptr(
+                name,
+                tracked!(self, Search, Some(RecType::ARES_REC_TYPE_PTR), handler),
+            );
+        });
     }
 
     /// Look up the SOA record associated with `name`.
@@ -504,7 +840,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::SOAResult>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_soa(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.query_soa(
+                name,
+                tracked!(self, Query, Some(RecType::ARES_REC_TYPE_SOA), handler),
+            );
+        });
     }
 
     /// Search for the SOA record associated with `name`.
@@ -514,7 +855,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::SOAResult>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_soa(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.search_soa(
+                name,
+                tracked!(self, Search, Some(RecType::ARES_REC_TYPE_SOA), handler),
+            );
+        });
     }
 
     /// Look up the SRV records associated with `name`.
@@ -524,7 +870,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::SRVResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_srv(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.query_srv(
+                name,
+                tracked!(self, Query, Some(RecType::ARES_REC_TYPE_SRV), handler),
+            );
+        });
     }
 
     /// Search for the SRV records associated with `name`.
@@ -534,7 +885,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::SRVResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_srv(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.search_srv(
+                name,
+                tracked!(self, Search, Some(RecType::ARES_REC_TYPE_SRV), handler),
+            );
+        });
     }
 
     /// Look up the TXT records associated with `name`.
@@ -544,7 +900,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::TXTResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_txt(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.query_txt(
+                name,
+                tracked!(self, Query, Some(RecType::ARES_REC_TYPE_TXT), handler),
+            );
+        });
     }
 
     /// Search for the TXT records associated with `name`.
@@ -554,7 +915,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::TXTResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_txt(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.search_txt(
+                name,
+                tracked!(self, Search, Some(RecType::ARES_REC_TYPE_TXT), handler),
+            );
+        });
     }
 
     /// Look up the URI records associated with `name`.
@@ -564,7 +930,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::URIResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_uri(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.query_uri(
+                name,
+                tracked!(self, Query, Some(RecType::ARES_REC_TYPE_URI), handler),
+            );
+        });
     }
 
     /// Search for the URI records associated with `name`.
@@ -574,7 +945,12 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::URIResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_uri(name, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.search_uri(
+                name,
+                tracked!(self, Search, Some(RecType::ARES_REC_TYPE_URI), handler),
+            );
+        });
     }
 
     /// Perform a host query by address.
@@ -584,10 +960,9 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<&c_ares::HostResults>) + Send + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .get_host_by_address(address, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.get_host_by_address(address, tracked!(self, HostByAddress, None, handler));
+        });
     }
 
     /// Perform a host query by name.
@@ -597,10 +972,9 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<&c_ares::HostResults>) + Send + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .get_host_by_name(name, family, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.get_host_by_name(name, family, tracked!(self, HostByName, None, handler));
+        });
     }
 
     /// Address-to-nodename translation in protocol-independent manner.
@@ -610,10 +984,9 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::NameInfoResult>) + Send + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .get_name_info(address, flags, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.get_name_info(address, flags, tracked!(self, NameInfo, None, handler));
+        });
     }
 
     /// Initiate a host query by name and service.
@@ -628,10 +1001,14 @@ impl Resolver {
     ) where
         F: FnOnce(c_ares::Result<c_ares::AddrInfoResults>) + Send + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .get_addrinfo(name, service, hints, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.get_addrinfo(
+                name,
+                service,
+                hints,
+                tracked!(self, AddrInfo, None, handler),
+            );
+        });
     }
 
     /// Initiate a single-question DNS query for `name`.  The class and type of the query are per
@@ -646,10 +1023,14 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<&[u8]>) + Send + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .query(name, dns_class, query_type, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.query(
+                name,
+                dns_class,
+                query_type,
+                tracked!(self, Query, Some(RecType(query_type.into())), handler),
+            );
+        });
     }
 
     /// Initiate a series of single-question DNS queries for `name`.  The class and type of the
@@ -664,10 +1045,30 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<&[u8]>) + Send + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .search(name, dns_class, query_type, handler);
+        with_channel!(self.ares_channel, |channel| {
+            channel.search(
+                name,
+                dns_class,
+                query_type,
+                tracked!(self, Search, Some(RecType(query_type.into())), handler),
+            );
+        });
+    }
+
+    /// Send a DNS query that is already in wire format.
+    ///
+    /// On completion, `handler` is called with the raw response.
+    ///
+    /// This method is provided for callers - such as proxies - that already hold queries as bytes.
+    /// The query still benefits from the channel's retry and server failover behaviour.
+    /// The response carries the query ID from `query`: see [`c_ares::Channel::send_raw()`].
+    pub fn send_raw<F>(&self, query: &[u8], handler: F)
+    where
+        F: FnOnce(c_ares::Result<&[u8]>) + Send + 'static,
+    {
+        with_channel!(self.ares_channel, |channel| {
+            channel.send_raw(query, tracked!(self, Raw, None, handler));
+        });
     }
 
     /// Send a DNS query using a pre-built [`c_ares::DnsRecord`].
@@ -678,10 +1079,11 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<&c_ares::DnsRecord>) + Send + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .send_dnsrec(dnsrec, handler)
+        with_channel!(self.ares_channel, |channel| channel.send_dnsrec(
+            dnsrec,
+            tracked!(self, DnsRec, dnsrec_type(dnsrec), handler)
+        ))
+        .map(|handle| handle.qid().unwrap_or_default())
     }
 
     /// Initiate a DNS query for `name` with the given class and type, receiving a parsed
@@ -697,10 +1099,14 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<&c_ares::DnsRecord>) + Send + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .query_dnsrec(name, dns_class, query_type, handler)
+        let record_type = Some(RecType::from(query_type));
+        with_channel!(self.ares_channel, |channel| channel.query_dnsrec(
+            name,
+            dns_class,
+            query_type,
+            tracked!(self, DnsRec, record_type, handler),
+        ))
+        .map(|handle| handle.qid().unwrap_or_default())
     }
 
     /// Initiate a series of DNS queries using a pre-built [`c_ares::DnsRecord`], receiving a
@@ -710,10 +1116,11 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<&c_ares::DnsRecord>) + Send + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .search_dnsrec(dnsrec, handler)
+        with_channel!(self.ares_channel, |channel| channel.search_dnsrec(
+            dnsrec,
+            tracked!(self, DnsRec, dnsrec_type(dnsrec), handler)
+        ))
+        .map(|_| ())
     }
 
     /// Block until notified that there are no longer any queries in queue, or the specified
@@ -722,34 +1129,64 @@ impl Resolver {
     /// Pass `None` to wait indefinitely.
     #[cfg(cares1_27)]
     pub fn queue_wait_empty(&self, timeout: Option<std::time::Duration>) -> c_ares::Result<()> {
-        // Call ares_queue_wait_empty without holding the channel Mutex.
-        //
-        // This function blocks until all queries complete. If we held the
-        // Mutex, the event loop thread would be unable to call process_fd,
-        // causing a deadlock. This is safe because ares_queue_wait_empty is
-        // only functional when c-ares has thread safety (otherwise it returns
-        // ENOTIMP), and thread-safe c-ares has internal locking.
-        let raw = self.ares_channel.lock().unwrap().as_raw();
-        let timeout_ms: core::ffi::c_int = match timeout {
-            None => -1,
-            Some(d) => d.as_millis().try_into().unwrap_or(core::ffi::c_int::MAX),
-        };
-        let rc = unsafe { c_ares_sys::ares_queue_wait_empty(raw, timeout_ms) };
-        c_ares::Error::try_from(rc).map_or(Ok(()), Err)
+        match &*self.ares_channel {
+            ChannelCell::Shared(channel) => channel.queue_wait_empty(timeout),
+
+            // Without thread safety, c-ares cannot wait for the queue to empty.
+            ChannelCell::Locked(_) => Err(c_ares::Error::ENOTIMP),
+        }
     }
 
     /// Retrieve the total number of active queries pending answers from servers.
     #[cfg(cares1_27)]
     pub fn queue_active_queries(&self) -> usize {
-        self.ares_channel.lock().unwrap().queue_active_queries()
+        with_channel!(self.ares_channel, |channel| channel.queue_active_queries())
+    }
+
+    /// Whether the resolver is driven by the `c-ares` built-in event thread, rather than by an
+    /// event loop of its own or by a [`Reactor`].
+    pub fn uses_event_thread(&self) -> bool {
+        self.event_loop_stopper.is_none()
+    }
+
+    /// Returns a snapshot of the resolver's metrics: the queries that it has made, how they
+    /// completed and how long they took, and how each server has fared.
+    ///
+    /// The snapshot is empty unless the resolver was created with
+    /// [`Options::set_metrics()`].
+    pub fn metrics(&self) -> ResolverMetrics {
+        let Some(metrics) = &self.metrics else {
+            return ResolverMetrics::default();
+        };
+        #[cfg(cares1_27)]
+        let active_queries = Some(self.queue_active_queries());
+        #[cfg(not(cares1_27))]
+        let active_queries = None;
+        metrics.snapshot(active_queries)
     }
 
     /// Cancel all requests made on this `Resolver`.
     pub fn cancel(&self) {
-        self.ares_channel.lock().unwrap().cancel();
+        with_channel!(self.ares_channel, |channel| {
+            channel.cancel();
+        });
     }
 }
 
+// A server state callback that records what it is told in `metrics`.
+#[cfg(cares1_29)]
+fn record_server_state(metrics: &Arc<Recorder>) -> impl Fn(&str, bool, ServerStateFlags) + use<> {
+    let metrics = Arc::clone(metrics);
+    move |server, success, _flags| metrics.server_state(server, success)
+}
+
+// The type of the first question in `dnsrec`.
+#[cfg(cares1_28)]
+fn dnsrec_type(dnsrec: &c_ares::DnsRecord) -> Option<RecType> {
+    let (_, record_type, _) = dnsrec.query_get(0).ok()?;
+    Some(record_type.into())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -933,6 +1370,38 @@ mod tests {
         assert!(resolver.is_ok());
     }
 
+    #[test]
+    fn resolver_with_event_loop() {
+        let mut options = Options::new();
+        options.set_event_loop();
+        let resolver = Resolver::with_options(options).unwrap();
+        assert!(!resolver.uses_event_thread());
+    }
+
+    #[test]
+    #[cfg(cares1_26)]
+    fn resolver_with_event_thread() {
+        let mut options = Options::new();
+        options.set_event_thread(c_ares::EventSys::Default);
+        let resolver = Resolver::with_options(options).unwrap();
+        assert_eq!(resolver.uses_event_thread(), c_ares::thread_safety());
+    }
+
+    #[test]
+    #[cfg(cares1_26)]
+    fn resolver_uses_event_thread_by_default() {
+        let resolver = Resolver::new().unwrap();
+        assert_eq!(resolver.uses_event_thread(), c_ares::thread_safety());
+    }
+
+    #[test]
+    #[cfg(cares1_23)]
+    fn resolver_shares_channel_when_thread_safe() {
+        let resolver = Resolver::new().unwrap();
+        let shared = matches!(*resolver.ares_channel, ChannelCell::Shared(_));
+        assert_eq!(shared, c_ares::thread_safety());
+    }
+
     #[test]
     fn resolver_with_custom_options() {
         let mut options = Options::new();
@@ -1016,6 +1485,36 @@ mod tests {
         assert!(!servers.is_empty());
     }
 
+    #[test]
+    fn resolver_effective_options() {
+        let mut options = Options::new();
+        options.set_tries(3);
+        let resolver = Resolver::with_options(options).unwrap();
+        let effective = resolver.effective_options().unwrap();
+        assert_eq!(effective.tries(), Some(3));
+    }
+
+    #[test]
+    #[cfg(cares1_24)]
+    fn resolver_servers_config() {
+        let resolver = Resolver::new().unwrap();
+        let config: c_ares::ServerConfig = "dns://8.8.8.8:5353".parse().unwrap();
+        let result = resolver.set_servers_config([&config]).unwrap();
+        assert!(std::ptr::eq(result, &raw const resolver));
+        let servers = resolver.servers_config().unwrap();
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].udp_port(), Some(5353));
+    }
+
+    #[test]
+    fn resolver_set_socket_callbacks() {
+        let resolver = Resolver::new().unwrap();
+        let result = resolver
+            .set_socket_created_callback(|_socket, _socket_type| Ok(()))
+            .set_socket_configure_callback(|_socket, _socket_type| Ok(()));
+        assert!(std::ptr::eq(result, &raw const resolver));
+    }
+
     #[test]
     #[cfg(cares1_29)]
     fn resolver_set_server_state_callback() {
@@ -1024,6 +1523,14 @@ mod tests {
         assert!(std::ptr::eq(result, &raw const resolver));
     }
 
+    #[test]
+    #[cfg(cares1_34)]
+    fn resolver_set_trace_callback() {
+        let resolver = Resolver::new().unwrap();
+        let result = resolver.set_trace_callback(|_event| {}).unwrap();
+        assert!(std::ptr::eq(result, &raw const resolver));
+    }
+
     #[test]
     #[cfg(cares1_29)]
     fn options_set_server_failover_options() {
@@ -1040,6 +1547,28 @@ mod tests {
         assert_eq!(resolver.queue_active_queries(), 0);
     }
 
+    #[test]
+    fn resolver_metrics_record_queries() {
+        let mut options = Options::new();
+        options.set_metrics();
+        let resolver = Resolver::with_options(options).unwrap();
+        resolver.query_a("bad\0name", |result| {
+            assert_eq!(result.unwrap_err(), c_ares::Error::EBADNAME);
+        });
+        let metrics = resolver.metrics();
+        let queries: Vec<_> = metrics.queries().collect();
+        assert_eq!(queries, [(QueryMethod::Query, Some("A"), 1)]);
+        assert_eq!(metrics.error_count(c_ares::Error::EBADNAME), 1);
+        assert_eq!(metrics.latency().count(), 1);
+    }
+
+    #[test]
+    fn resolver_metrics_off_by_default() {
+        let resolver = Resolver::new().unwrap();
+        resolver.query_a("bad\0name", |_| {});
+        assert_eq!(resolver.metrics().query_count(), 0);
+    }
+
     #[test]
     #[cfg(cares1_27)]
     fn resolver_queue_wait_empty() {
@@ -1088,6 +1617,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn resolver_send_raw_malformed() {
+        let resolver = Resolver::new().unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        resolver.send_raw(&[0x12, 0x34, 0x01], move |result| {
+            tx.send(result.is_err()).unwrap();
+        });
+        assert!(rx.recv().unwrap());
+    }
+
     #[test]
     fn debug_resolver_options() {
         let options = Options::new();
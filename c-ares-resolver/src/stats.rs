@@ -0,0 +1,500 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use c_ares_sys::ares_dns_rec_type_t;
+
+/// How a query was made.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+pub enum QueryMethod {
+    /// A single-question query: `query()` and the `query_xxx()` methods.
+    Query,
+
+    /// A search, applying the configured search domains: `search()` and the `search_xxx()`
+    /// methods.
+    Search,
+
+    /// A query that was already in wire format: `send_raw()`.
+    Raw,
+
+    /// A query made with a `DnsRecord`: `send_dnsrec()`, `query_dnsrec()` and `search_dnsrec()`.
+    DnsRec,
+
+    /// `get_host_by_name()`.
+    HostByName,
+
+    /// `get_host_by_address()`.
+    HostByAddress,
+
+    /// `get_addrinfo()`.
+    AddrInfo,
+
+    /// `get_name_info()`.
+    NameInfo,
+}
+
+impl QueryMethod {
+    fn as_str(self) -> &'static str {
+        match self {
+            QueryMethod::Query => "query",
+            QueryMethod::Search => "search",
+            QueryMethod::Raw => "raw",
+            QueryMethod::DnsRec => "dnsrec",
+            QueryMethod::HostByName => "host_by_name",
+            QueryMethod::HostByAddress => "host_by_address",
+            QueryMethod::AddrInfo => "addrinfo",
+            QueryMethod::NameInfo => "nameinfo",
+        }
+    }
+}
+
+impl fmt::Display for QueryMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// The methods, in the order in which they are declared - so that `method as usize` indexes them.
+const METHODS: [QueryMethod; 8] = [
+    QueryMethod::Query,
+    QueryMethod::Search,
+    QueryMethod::Raw,
+    QueryMethod::DnsRec,
+    QueryMethod::HostByName,
+    QueryMethod::HostByAddress,
+    QueryMethod::AddrInfo,
+    QueryMethod::NameInfo,
+];
+
+// The record types that have counters of their own.  Queries for other types are counted under a
+// lock.
+const RECORD_TYPES: [ares_dns_rec_type_t; 19] = [
+    ares_dns_rec_type_t::ARES_REC_TYPE_A,
+    ares_dns_rec_type_t::ARES_REC_TYPE_NS,
+    ares_dns_rec_type_t::ARES_REC_TYPE_CNAME,
+    ares_dns_rec_type_t::ARES_REC_TYPE_SOA,
+    ares_dns_rec_type_t::ARES_REC_TYPE_PTR,
+    ares_dns_rec_type_t::ARES_REC_TYPE_HINFO,
+    ares_dns_rec_type_t::ARES_REC_TYPE_MX,
+    ares_dns_rec_type_t::ARES_REC_TYPE_TXT,
+    ares_dns_rec_type_t::ARES_REC_TYPE_SIG,
+    ares_dns_rec_type_t::ARES_REC_TYPE_AAAA,
+    ares_dns_rec_type_t::ARES_REC_TYPE_SRV,
+    ares_dns_rec_type_t::ARES_REC_TYPE_NAPTR,
+    ares_dns_rec_type_t::ARES_REC_TYPE_OPT,
+    ares_dns_rec_type_t::ARES_REC_TYPE_TLSA,
+    ares_dns_rec_type_t::ARES_REC_TYPE_SVCB,
+    ares_dns_rec_type_t::ARES_REC_TYPE_HTTPS,
+    ares_dns_rec_type_t::ARES_REC_TYPE_ANY,
+    ares_dns_rec_type_t::ARES_REC_TYPE_URI,
+    ares_dns_rec_type_t::ARES_REC_TYPE_CAA,
+];
+
+// Errors are counted by their codes, which run up to that of `Error::UNKNOWN`.
+const ERROR_CODES: usize = c_ares::Error::UNKNOWN as usize + 1;
+
+// The name of a record type, for labelling queries.
+#[cfg(cares1_28)]
+fn record_type_name(record_type: ares_dns_rec_type_t) -> String {
+    c_ares::DnsRecordType::from(record_type).to_string()
+}
+
+// Before c-ares 1.28 there is no way to look up the name of a record type, so use the generic
+// name from RFC 3597.
+#[cfg(not(cares1_28))]
+fn record_type_name(record_type: ares_dns_rec_type_t) -> String {
+    format!("TYPE{}", record_type.0)
+}
+
+/// The number of successes and failures that the server-state callback has reported for a server.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub struct ServerCounts {
+    successes: u64,
+    failures: u64,
+}
+
+impl ServerCounts {
+    /// The number of queries that the server answered successfully.
+    pub fn successes(&self) -> u64 {
+        self.successes
+    }
+
+    /// The number of queries that failed at the server.
+    pub fn failures(&self) -> u64 {
+        self.failures
+    }
+}
+
+// Upper bounds of the latency histogram buckets.  A final bucket counts everything slower.
+const LATENCY_BOUNDS: [Duration; 13] = [
+    Duration::from_millis(1),
+    Duration::from_millis(2),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(20),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(200),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(2),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+];
+
+/// A histogram of the time taken for queries to complete.
+#[derive(Clone, Debug, Default)]
+pub struct LatencyHistogram {
+    counts: [u64; LATENCY_BOUNDS.len() + 1],
+    sum: Duration,
+}
+
+impl LatencyHistogram {
+    /// The buckets of the histogram, fastest first.
+    ///
+    /// Each bucket is given as its upper bound and the number of queries that took longer than
+    /// the previous bucket's bound, but no longer than this one.  The last bucket has no upper
+    /// bound.
+    pub fn buckets(&self) -> impl Iterator<Item = (Option<Duration>, u64)> + '_ {
+        LATENCY_BOUNDS
+            .iter()
+            .copied()
+            .map(Some)
+            .chain([None])
+            .zip(self.counts.iter().copied())
+    }
+
+    /// The number of queries in the histogram.
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// The total time taken by the queries in the histogram.
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+}
+
+/// A snapshot of the activity of a resolver, as returned by [`Resolver::metrics()`].
+///
+/// [`Resolver::metrics()`]: crate::Resolver::metrics
+#[derive(Clone, Debug, Default)]
+pub struct ResolverMetrics {
+    queries: BTreeMap<(QueryMethod, Option<String>), u64>,
+    successes: u64,
+    errors: BTreeMap<c_ares::Error, u64>,
+    latency: LatencyHistogram,
+    servers: HashMap<String, ServerCounts>,
+    active_queries: usize,
+}
+
+impl ResolverMetrics {
+    /// The number of queries made, by method and record type.
+    ///
+    /// The record type is absent for methods that don't have one, such as `get_host_by_name()`,
+    /// and for raw queries.  With c-ares versions before 1.28, which cannot name record types,
+    /// types are given in the generic form of RFC 3597: `TYPE1` for A, and so on.
+    pub fn queries(&self) -> impl Iterator<Item = (QueryMethod, Option<&str>, u64)> + '_ {
+        self.queries
+            .iter()
+            .map(|((method, record_type), count)| (*method, record_type.as_deref(), *count))
+    }
+
+    /// The total number of queries made.
+    pub fn query_count(&self) -> u64 {
+        self.queries.values().sum()
+    }
+
+    /// The number of queries that completed successfully.
+    pub fn successes(&self) -> u64 {
+        self.successes
+    }
+
+    /// The number of queries that completed with each error.
+    pub fn errors(&self) -> impl Iterator<Item = (c_ares::Error, u64)> + '_ {
+        self.errors.iter().map(|(error, count)| (*error, *count))
+    }
+
+    /// The number of queries that completed with `error`.
+    pub fn error_count(&self, error: c_ares::Error) -> u64 {
+        self.errors.get(&error).copied().unwrap_or_default()
+    }
+
+    /// The time taken for queries to complete, successfully or otherwise.
+    pub fn latency(&self) -> &LatencyHistogram {
+        &self.latency
+    }
+
+    /// Success and failure counts for each server that has been used, as reported to the
+    /// server-state callback.
+    pub fn servers(&self) -> impl Iterator<Item = (&str, ServerCounts)> + '_ {
+        self.servers
+            .iter()
+            .map(|(server, counts)| (server.as_str(), *counts))
+    }
+
+    /// The number of queries that were waiting for answers when the snapshot was taken.
+    ///
+    /// With c-ares 1.27 or later this is as reported by `queue_active_queries()`.
+    pub fn active_queries(&self) -> usize {
+        self.active_queries
+    }
+}
+
+// Collects the metrics for a resolver.  Queries are counted without allocating or locking, unless
+// they are for a record type that has no counter of its own.
+#[derive(Default)]
+pub(crate) struct Recorder {
+    // By method, then by record type as in `RECORD_TYPES`; the final count is of queries that have
+    // no record type.
+    queries: [[AtomicU64; RECORD_TYPES.len() + 1]; METHODS.len()],
+    other_queries: Mutex<BTreeMap<(QueryMethod, u32), u64>>,
+    successes: AtomicU64,
+    errors: [AtomicU64; ERROR_CODES],
+    latency: [AtomicU64; LATENCY_BOUNDS.len() + 1],
+    latency_nanos: AtomicU64,
+    active_queries: AtomicUsize,
+    servers: Mutex<HashMap<String, ServerCounts>>,
+}
+
+impl Recorder {
+    // Record that a query is being made.  The query's handler should pass its result to the
+    // returned `Completion`.
+    pub(crate) fn start(
+        self: &Arc<Self>,
+        method: QueryMethod,
+        record_type: Option<ares_dns_rec_type_t>,
+    ) -> Completion {
+        #[cfg(feature = "metrics")]
+        {
+            let record_type = record_type.map(record_type_name).unwrap_or_default();
+            ::metrics::counter!(
+                "c_ares_resolver_queries_total",
+                "method" => method.as_str(),
+                "type" => record_type
+            )
+            .increment(1);
+            ::metrics::gauge!("c_ares_resolver_queries_in_flight").increment(1.0);
+        }
+        let column = match record_type {
+            None => Some(RECORD_TYPES.len()),
+            Some(record_type) => RECORD_TYPES.iter().position(|known| *known == record_type),
+        };
+        if let Some(column) = column {
+            self.queries[method as usize][column].fetch_add(1, Ordering::Relaxed);
+        } else if let Some(record_type) = record_type {
+            let mut other_queries = self.other_queries.lock().unwrap();
+            *other_queries.entry((method, record_type.0)).or_default() += 1;
+        }
+        self.active_queries.fetch_add(1, Ordering::Relaxed);
+        Completion {
+            recorder: Arc::clone(self),
+            start: Instant::now(),
+        }
+    }
+
+    // Record a report from the server-state callback.
+    pub(crate) fn server_state(&self, server: &str, success: bool) {
+        #[cfg(feature = "metrics")]
+        ::metrics::counter!(
+            "c_ares_resolver_server_responses_total",
+            "server" => server.to_owned(),
+            "success" => if success { "true" } else { "false" }
+        )
+        .increment(1);
+        let mut servers = self.servers.lock().unwrap();
+        let counts = match servers.get_mut(server) {
+            Some(counts) => counts,
+            None => servers.entry(server.to_owned()).or_default(),
+        };
+        if success {
+            counts.successes += 1;
+        } else {
+            counts.failures += 1;
+        }
+    }
+
+    fn record_latency(&self, elapsed: Duration) {
+        let bucket = LATENCY_BOUNDS.partition_point(|bound| *bound < elapsed);
+        self.latency[bucket].fetch_add(1, Ordering::Relaxed);
+        let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        self.latency_nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    // Take a snapshot, preferring c-ares's own count of active queries where it has one.
+    pub(crate) fn snapshot(&self, active_queries: Option<usize>) -> ResolverMetrics {
+        let mut queries = BTreeMap::new();
+        for (method, counts) in METHODS.iter().zip(&self.queries) {
+            let record_types = RECORD_TYPES.iter().copied().map(Some).chain([None]);
+            for (record_type, count) in record_types.zip(counts) {
+                let count = count.load(Ordering::Relaxed);
+                if count > 0 {
+                    queries.insert((*method, record_type.map(record_type_name)), count);
+                }
+            }
+        }
+        for (&(method, record_type), &count) in &*self.other_queries.lock().unwrap() {
+            let record_type = record_type_name(ares_dns_rec_type_t(record_type));
+            queries.insert((method, Some(record_type)), count);
+        }
+        let errors = self
+            .errors
+            .iter()
+            .zip(0..)
+            .filter_map(|(count, code)| {
+                let count = count.load(Ordering::Relaxed);
+                (count > 0).then(|| (c_ares::Error::from(code), count))
+            })
+            .collect();
+        let latency = LatencyHistogram {
+            counts: self
+                .latency
+                .each_ref()
+                .map(|count| count.load(Ordering::Relaxed)),
+            sum: Duration::from_nanos(self.latency_nanos.load(Ordering::Relaxed)),
+        };
+        ResolverMetrics {
+            queries,
+            successes: self.successes.load(Ordering::Relaxed),
+            errors,
+            latency,
+            servers: self.servers.lock().unwrap().clone(),
+            active_queries: active_queries
+                .unwrap_or_else(|| self.active_queries.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+// Records the outcome of a single query.
+pub(crate) struct Completion {
+    recorder: Arc<Recorder>,
+    start: Instant,
+}
+
+impl Completion {
+    pub(crate) fn finish<T>(self, result: &c_ares::Result<T>) {
+        let elapsed = self.start.elapsed();
+        #[cfg(feature = "metrics")]
+        {
+            let outcome = match result {
+                Ok(_) => "success".to_owned(),
+                Err(error) => format!("{error:?}"),
+            };
+            ::metrics::counter!("c_ares_resolver_completions_total", "result" => outcome)
+                .increment(1);
+            ::metrics::histogram!("c_ares_resolver_query_duration_seconds")
+                .record(elapsed.as_secs_f64());
+            ::metrics::gauge!("c_ares_resolver_queries_in_flight").decrement(1.0);
+        }
+        let recorder = &self.recorder;
+        match result {
+            Ok(_) => recorder.successes.fetch_add(1, Ordering::Relaxed),
+            Err(error) => recorder.errors[*error as usize].fetch_add(1, Ordering::Relaxed),
+        };
+        recorder.active_queries.fetch_sub(1, Ordering::Relaxed);
+        recorder.record_latency(elapsed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn methods_are_in_declaration_order() {
+        for (index, method) in METHODS.iter().enumerate() {
+            assert_eq!(*method as usize, index);
+        }
+    }
+
+    #[test]
+    fn counts_uncommon_record_types() {
+        let recorder = Arc::new(Recorder::default());
+        let uncommon = ares_dns_rec_type_t(99);
+        for record_type in [ares_dns_rec_type_t::ARES_REC_TYPE_AAAA, uncommon, uncommon] {
+            recorder
+                .start(QueryMethod::Query, Some(record_type))
+                .finish(&Ok(()));
+        }
+        let metrics = recorder.snapshot(None);
+        let queries: Vec<_> = metrics.queries().collect();
+        #[cfg(cares1_28)]
+        let aaaa = "AAAA";
+        #[cfg(not(cares1_28))]
+        let aaaa = "TYPE28";
+        assert_eq!(
+            queries,
+            [
+                (QueryMethod::Query, Some(aaaa), 1),
+                (QueryMethod::Query, Some("TYPE99"), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn latency_buckets() {
+        let recorder = Recorder::default();
+        recorder.record_latency(Duration::from_micros(500));
+        recorder.record_latency(Duration::from_millis(1));
+        recorder.record_latency(Duration::from_millis(30));
+        recorder.record_latency(Duration::from_secs(60));
+        let histogram = recorder.snapshot(None).latency;
+        let buckets: Vec<_> = histogram
+            .buckets()
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        assert_eq!(
+            buckets,
+            [
+                (Some(Duration::from_millis(1)), 2),
+                (Some(Duration::from_millis(50)), 1),
+                (None, 1),
+            ]
+        );
+        assert_eq!(histogram.count(), 4);
+        assert_eq!(histogram.sum(), Duration::from_micros(60_031_500));
+    }
+
+    #[test]
+    fn records_queries_and_completions() {
+        let recorder = Arc::new(Recorder::default());
+        let a = ares_dns_rec_type_t::ARES_REC_TYPE_A;
+        let first = recorder.start(QueryMethod::Query, Some(a));
+        let second = recorder.start(QueryMethod::Query, Some(a));
+        let third = recorder.start(QueryMethod::HostByName, None);
+        first.finish(&Ok(()));
+        second.finish::<()>(&Err(c_ares::Error::ENOTFOUND));
+        third.finish::<()>(&Err(c_ares::Error::ENOTFOUND));
+        recorder.server_state("192.0.2.1:53", true);
+        recorder.server_state("192.0.2.1:53", false);
+        recorder.server_state("192.0.2.1:53", true);
+
+        let metrics = recorder.snapshot(None);
+        let queries: Vec<_> = metrics.queries().collect();
+        assert_eq!(
+            queries,
+            [
+                (QueryMethod::Query, Some(record_type_name(a).as_str()), 2),
+                (QueryMethod::HostByName, None, 1),
+            ]
+        );
+        assert_eq!(metrics.query_count(), 3);
+        assert_eq!(metrics.successes(), 1);
+        assert_eq!(metrics.error_count(c_ares::Error::ENOTFOUND), 2);
+        assert_eq!(metrics.error_count(c_ares::Error::ETIMEOUT), 0);
+        assert_eq!(metrics.latency().count(), 3);
+        let servers: Vec<_> = metrics.servers().collect();
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].0, "192.0.2.1:53");
+        assert_eq!(servers[0].1.successes(), 2);
+        assert_eq!(servers[0].1.failures(), 1);
+        assert_eq!(metrics.active_queries(), 0);
+        let mx = Some(ares_dns_rec_type_t::ARES_REC_TYPE_MX);
+        let pending = recorder.start(QueryMethod::Search, mx);
+        assert_eq!(recorder.snapshot(None).active_queries(), 1);
+        assert_eq!(recorder.snapshot(Some(7)).active_queries(), 7);
+        pending.finish::<()>(&Err(c_ares::Error::ECANCELLED));
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::future::{Future, poll_fn};
+use std::io;
+use std::os::fd::{AsRawFd, RawFd};
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use c_ares::{FdEventFlags, FdEvents, ProcessFlags};
+use tokio::io::unix::AsyncFd;
+use tokio::runtime::Handle;
+use tokio::sync::Notify;
+use tokio::task::AbortHandle;
+
+use crate::channelcell::{ChannelCell, with_channel};
+use crate::error::Error;
+
+// A socket owned by c-ares.  Unlike the standard library's socket types, dropping this does not
+// close the socket: that remains c-ares's job.
+struct RawSocket(c_ares::Socket);
+
+impl AsRawFd for RawSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+// A socket registered with the tokio reactor, and the events that c-ares is interested in.
+struct Registration {
+    fd: AsyncFd<RawSocket>,
+    readable: bool,
+    writable: bool,
+}
+
+// State shared between the socket state callback, the pending write callback, and the task that
+// drives the channel.
+struct Shared {
+    registrations: Mutex<HashMap<c_ares::Socket, Registration>>,
+    pending_write: AtomicBool,
+    notify: Notify,
+}
+
+impl Shared {
+    // Collect events on all sockets that c-ares is interested in, and that tokio reports ready.
+    //
+    // Readiness is cleared as it is reported.  This is safe because c-ares reads until the socket
+    // would block (or, on TCP, until a short read) and only asks for write events after a write
+    // would have blocked: so tokio always sees a fresh edge before c-ares next needs an event.
+    fn ready_events(&self, cx: &mut Context<'_>) -> Vec<FdEvents> {
+        let registrations = self.registrations.lock().unwrap();
+        let mut events = Vec::new();
+        for (&socket, registration) in registrations.iter() {
+            let mut flags = FdEventFlags::empty();
+            if registration.readable
+                && let Poll::Ready(ready) = registration.fd.poll_read_ready(cx)
+            {
+                if let Ok(mut guard) = ready {
+                    guard.clear_ready();
+                }
+                flags.insert(FdEventFlags::READ);
+            }
+            if registration.writable
+                && let Poll::Ready(ready) = registration.fd.poll_write_ready(cx)
+            {
+                if let Ok(mut guard) = ready {
+                    guard.clear_ready();
+                }
+                flags.insert(FdEventFlags::WRITE);
+            }
+            if !flags.is_empty() {
+                events.push(FdEvents::new(socket, flags));
+            }
+        }
+        events
+    }
+}
+
+// Object returned when the TokioEventLoop is run.  When this is dropped, the task driving the
+// channel is aborted.
+pub struct TokioEventLoopStopper {
+    task: AbortHandle,
+}
+
+impl Drop for TokioEventLoopStopper {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+// The TokioEventLoop registers c-ares's sockets with the tokio reactor, and drives the channel
+// from a task on the runtime that was current when it was created.
+//
+// As with the `EventLoop`, construction is two-phase: `new()` prepares options (sets the socket
+// state callback), then `run()` accepts the already-created channel and spawns the task.
+pub struct TokioEventLoop {
+    handle: Handle,
+    shared: Arc<Shared>,
+}
+
+impl TokioEventLoop {
+    // Create a new event loop, setting up the socket state callback on `options`.
+    //
+    // Fails if called outside the context of a tokio runtime.
+    pub fn new(options: &mut c_ares::Options) -> Result<Self, Error> {
+        let handle = Handle::try_current().map_err(io::Error::other)?;
+        let shared = Arc::new(Shared {
+            registrations: Mutex::new(HashMap::new()),
+            pending_write: AtomicBool::new(false),
+            notify: Notify::new(),
+        });
+
+        // Whenever c-ares tells us that it cares about a socket, we'll update its registration
+        // with the reactor and wake the task.
+        //
+        // c-ares tells us that it has lost interest in a socket before closing it, so sockets are
+        // always deregistered while they are still open.
+        {
+            let handle = handle.clone();
+            let shared = Arc::clone(&shared);
+            let sock_callback = move |socket: c_ares::Socket, readable: bool, writable: bool| {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(socket, readable, writable, "socket state");
+                let mut registrations = shared.registrations.lock().unwrap();
+                if !readable && !writable {
+                    registrations.remove(&socket);
+                } else if let Some(registration) = registrations.get_mut(&socket) {
+                    registration.readable = readable;
+                    registration.writable = writable;
+                } else {
+                    let _guard = handle.enter();
+                    match AsyncFd::new(RawSocket(socket)) {
+                        Ok(fd) => {
+                            let registration = Registration {
+                                fd,
+                                readable,
+                                writable,
+                            };
+                            registrations.insert(socket, registration);
+                        }
+
+                        // The runtime may be shutting down, or custom socket functions may have
+                        // handed out something that isn't a file descriptor.  Panicking here would
+                        // abort the process: instead leave the socket unwatched, so that its
+                        // queries time out.
+                        #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+                        Err(err) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!(socket, %err, "failed to register socket with tokio");
+                        }
+                    }
+                }
+                drop(registrations);
+                shared.notify.notify_one();
+            };
+            options.set_socket_state_callback(sock_callback);
+        }
+
+        Ok(Self { handle, shared })
+    }
+
+    // Run the event loop with the given channel.
+    pub fn run(self, ares_channel: Arc<ChannelCell>) -> TokioEventLoopStopper {
+        // Set up the pending-write optimization.
+        {
+            let shared = Arc::clone(&self.shared);
+            let pending_write_callback = move || {
+                shared.pending_write.store(true, Ordering::Release);
+                shared.notify.notify_one();
+            };
+            with_channel!(ares_channel, |channel| {
+                channel.set_pending_write_callback(pending_write_callback);
+            });
+        }
+
+        let task = self.handle.spawn(drive(self.shared, ares_channel));
+        TokioEventLoopStopper {
+            task: task.abort_handle(),
+        }
+    }
+}
+
+// Event loop task - waits for events, and handles them.
+async fn drive(shared: Arc<Shared>, ares_channel: Arc<ChannelCell>) {
+    const MAX_WAIT: Duration = Duration::from_millis(500);
+    #[cfg(feature = "tracing")]
+    tracing::debug!("tokio event loop started");
+
+    loop {
+        // Ask c-ares how long until the next timeout fires.
+        let timeout = with_channel!(ares_channel, |channel| channel.timeout(Some(MAX_WAIT)))
+            .unwrap_or(MAX_WAIT);
+
+        // Wait until a socket is ready, c-ares changes its interests or has a write pending, or
+        // the timeout expires.
+        let mut notified = pin!(shared.notify.notified());
+        let mut sleep = pin!(tokio::time::sleep(timeout));
+        let events = poll_fn(|cx| {
+            let events = shared.ready_events(cx);
+            let woken = notified.as_mut().poll(cx).is_ready() || sleep.as_mut().poll(cx).is_ready();
+            if events.is_empty() && !woken {
+                Poll::Pending
+            } else {
+                Poll::Ready(events)
+            }
+        })
+        .await;
+        #[cfg(feature = "tracing")]
+        tracing::trace!(events = events.len(), ?timeout, "tokio event loop woke");
+
+        with_channel!(ares_channel, |channel| {
+            // Process any pending write.
+            if shared.pending_write.swap(false, Ordering::AcqRel) {
+                #[cfg(feature = "tracing")]
+                tracing::trace!("processing pending write");
+                channel.process_pending_write();
+            }
+
+            // Process any events, and any timeouts.
+            let _ = channel.process_fds(&events, ProcessFlags::empty());
+        });
+    }
+}
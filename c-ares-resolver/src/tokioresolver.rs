@@ -0,0 +1,477 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use c_ares::{ServerStateFlags, TraceEvent};
+
+use crate::error::Error;
+use crate::futureresolver::{CAresFuture, FutureResolver};
+use crate::host::HostResults;
+use crate::nameinfo::NameInfoResult;
+use crate::resolver::{Options, Resolver};
+use crate::stats::ResolverMetrics;
+
+/// An asynchronous DNS resolver, driven by the tokio runtime.
+///
+/// This offers the same interface as the `FutureResolver`, but rather than starting an event
+/// thread of its own it registers `c-ares`'s sockets with the tokio reactor and handles them -
+/// along with `c-ares`'s timeouts - from a task.
+///
+/// The `TokioResolver` must be created from within a tokio runtime, and that runtime must have
+/// both IO and time enabled.  The returned futures can be awaited from anywhere, but queries only
+/// make progress while the runtime is running.
+pub struct TokioResolver {
+    inner: FutureResolver,
+}
+
+impl fmt::Debug for TokioResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokioResolver").finish_non_exhaustive()
+    }
+}
+
+impl TokioResolver {
+    /// Create a new `TokioResolver`, using default `Options`.
+    ///
+    /// Fails if not called from within a tokio runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn example() {
+    /// let resolver = c_ares_resolver::TokioResolver::new().unwrap();
+    /// let result = resolver.query_a("example.com").await;
+    /// # }
+    /// ```
+    pub fn new() -> Result<Self, Error> {
+        let options = Options::default();
+        Self::with_options(options)
+    }
+
+    /// Create a new `TokioResolver`, with the given `Options`.
+    ///
+    /// The resolver is always driven by the tokio runtime, so any choice of event loop in
+    /// `options` is ignored.  Fails if not called from within a tokio runtime.
+    pub fn with_options(options: Options) -> Result<Self, Error> {
+        let inner = Resolver::with_tokio(options)?;
+        let resolver = Self {
+            inner: FutureResolver::from_resolver(inner),
+        };
+        Ok(resolver)
+    }
+
+    /// Reinitialize a channel from system configuration.
+    pub fn reinit(&self) -> c_ares::Result<&Self> {
+        self.inner.reinit()?;
+        Ok(self)
+    }
+
+    /// Returns a snapshot of the options that the underlying channel was configured with.
+    ///
+    /// c-ares does not report the values that it read from the system configuration: see
+    /// [`c_ares::EffectiveOptions`].
+    pub fn effective_options(&self) -> c_ares::Result<c_ares::EffectiveOptions> {
+        self.inner.effective_options()
+    }
+
+    /// Set the list of servers to contact, instead of the servers specified in resolv.conf or the
+    /// local named.
+    ///
+    /// String format is `host[:port]`.  IPv6 addresses with ports require square brackets eg
+    /// `[2001:4860:4860::8888]:53`.
+    pub fn set_servers<I, S>(&self, servers: I) -> c_ares::Result<&Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.inner.set_servers(servers)?;
+        Ok(self)
+    }
+
+    /// Retrieves the list of configured servers.
+    ///
+    /// Each entry is in `host[:port]` format, matching what [`set_servers`](Self::set_servers)
+    /// accepts.
+    pub fn servers(&self) -> Vec<String> {
+        self.inner.servers()
+    }
+
+    /// Set the list of servers to contact, from structured [`c_ares::ServerConfig`]s.
+    pub fn set_servers_config<'a, I>(&self, servers: I) -> c_ares::Result<&Self>
+    where
+        I: IntoIterator<Item = &'a c_ares::ServerConfig>,
+    {
+        self.inner.set_servers_config(servers)?;
+        Ok(self)
+    }
+
+    /// Retrieves the list of configured servers, as structured [`c_ares::ServerConfig`]s.
+    pub fn servers_config(&self) -> c_ares::Result<Vec<c_ares::ServerConfig>> {
+        self.inner.servers_config()
+    }
+
+    /// Set the local IPv4 address from which to make queries.
+    pub fn set_local_ipv4(&self, ipv4: Ipv4Addr) -> &Self {
+        self.inner.set_local_ipv4(ipv4);
+        self
+    }
+
+    /// Set the local IPv6 address from which to make queries.
+    pub fn set_local_ipv6(&self, ipv6: Ipv6Addr) -> &Self {
+        self.inner.set_local_ipv6(ipv6);
+        self
+    }
+
+    /// Set the local device from which to make queries.
+    pub fn set_local_device(&self, device: &str) -> c_ares::Result<&Self> {
+        self.inner.set_local_device(device)?;
+        Ok(self)
+    }
+
+    /// Initializes an address sortlist configuration, so that addresses returned by
+    /// `get_host_by_name()` are sorted according to the sortlist.
+    ///
+    /// Each element of the sortlist holds an IP-address/netmask pair. The netmask is optional but
+    /// follows the address after a slash if present. For example: "130.155.160.0/255.255.240.0",
+    /// or "130.155.0.0".
+    pub fn set_sortlist<I, S>(&self, sortlist: I) -> c_ares::Result<&Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.inner.set_sortlist(sortlist)?;
+        Ok(self)
+    }
+
+    /// Set a callback function to be invoked after a socket has been created and connected.
+    ///
+    /// `callback(socket, socket_type)` is called with the new socket and its type.  Returning an
+    /// error causes c-ares to abandon the connection.
+    pub fn set_socket_created_callback<F>(&self, callback: F) -> &Self
+    where
+        F: Fn(c_ares::Socket, c_ares::SocketType) -> c_ares::Result<()> + Send + Sync + 'static,
+    {
+        self.inner.set_socket_created_callback(callback);
+        self
+    }
+
+    /// Set a callback function to be invoked after a socket has been created, but before it is
+    /// connected.
+    ///
+    /// This is the place to apply socket options - for example `SO_MARK`, `IP_TOS` or
+    /// `SO_BINDTODEVICE`.  `callback(socket, socket_type)` is called with the new socket and its
+    /// type.  Returning an error causes c-ares to abandon the connection.
+    pub fn set_socket_configure_callback<F>(&self, callback: F) -> &Self
+    where
+        F: Fn(c_ares::Socket, c_ares::SocketType) -> c_ares::Result<()> + Send + Sync + 'static,
+    {
+        self.inner.set_socket_configure_callback(callback);
+        self
+    }
+
+    /// Set a callback function to be invoked whenever a query on the channel completes.
+    ///
+    /// `callback(server, success, flags)` will be called when a query completes.
+    ///
+    /// - `server` indicates the DNS server that was used for the query.
+    /// - `success` indicates whether the query succeeded or not.
+    /// - `flags` is a bitmask of flags describing various aspects of the query.
+    pub fn set_server_state_callback<F>(&self, callback: F) -> &Self
+    where
+        F: Fn(&str, bool, ServerStateFlags) + Send + Sync + 'static,
+    {
+        self.inner.set_server_state_callback(callback);
+        self
+    }
+
+    /// Set a callback function to be invoked for every DNS message that the resolver sends to, or
+    /// receives from, a server.
+    ///
+    /// Connections that the resolver opened before tracing began are never traced, so prefer
+    /// [`Options::set_trace_callback()`](crate::Options::set_trace_callback), or else call this
+    /// before making any queries.  See [`c_ares::Channel::set_trace_callback()`] for details.
+    pub fn set_trace_callback<F>(&self, callback: F) -> c_ares::Result<&Self>
+    where
+        F: Fn(&TraceEvent<'_>) + Send + Sync + 'static,
+    {
+        self.inner.set_trace_callback(callback)?;
+        Ok(self)
+    }
+
+    /// Look up the A records associated with `name`.
+    pub fn query_a(&self, name: &str) -> CAresFuture<c_ares::AResults> {
+        self.inner.query_a(name)
+    }
+
+    /// Search for the A records associated with `name`.
+    pub fn search_a(&self, name: &str) -> CAresFuture<c_ares::AResults> {
+        self.inner.search_a(name)
+    }
+
+    /// Look up the AAAA records associated with `name`.
+    pub fn query_aaaa(&self, name: &str) -> CAresFuture<c_ares::AAAAResults> {
+        self.inner.query_aaaa(name)
+    }
+
+    /// Search for the AAAA records associated with `name`.
+    pub fn search_aaaa(&self, name: &str) -> CAresFuture<c_ares::AAAAResults> {
+        self.inner.search_aaaa(name)
+    }
+
+    /// Look up the CAA records associated with `name`.
+    pub fn query_caa(&self, name: &str) -> CAresFuture<c_ares::CAAResults> {
+        self.inner.query_caa(name)
+    }
+
+    /// Search for the CAA records associated with `name`.
+    pub fn search_caa(&self, name: &str) -> CAresFuture<c_ares::CAAResults> {
+        self.inner.search_caa(name)
+    }
+
+    /// Look up the CNAME records associated with `name`.
+    pub fn query_cname(&self, name: &str) -> CAresFuture<c_ares::CNameResults> {
+        self.inner.query_cname(name)
+    }
+
+    /// Search for the CNAME records associated with `name`.
+    pub fn search_cname(&self, name: &str) -> CAresFuture<c_ares::CNameResults> {
+        self.inner.search_cname(name)
+    }
+
+    /// Look up the MX records associated with `name`.
+    pub fn query_mx(&self, name: &str) -> CAresFuture<c_ares::MXResults> {
+        self.inner.query_mx(name)
+    }
+
+    /// Search for the MX records associated with `name`.
+    pub fn search_mx(&self, name: &str) -> CAresFuture<c_ares::MXResults> {
+        self.inner.search_mx(name)
+    }
+
+    /// Look up the NAPTR records associated with `name`.
+    pub fn query_naptr(&self, name: &str) -> CAresFuture<c_ares::NAPTRResults> {
+        self.inner.query_naptr(name)
+    }
+
+    /// Search for the NAPTR records associated with `name`.
+    pub fn search_naptr(&self, name: &str) -> CAresFuture<c_ares::NAPTRResults> {
+        self.inner.search_naptr(name)
+    }
+
+    /// Look up the NS records associated with `name`.
+    pub fn query_ns(&self, name: &str) -> CAresFuture<c_ares::NSResults> {
+        self.inner.query_ns(name)
+    }
+
+    /// Search for the NS records associated with `name`.
+    pub fn search_ns(&self, name: &str) -> CAresFuture<c_ares::NSResults> {
+        self.inner.search_ns(name)
+    }
+
+    /// Look up the PTR records associated with `name`.
+    pub fn query_ptr(&self, name: &str) -> CAresFuture<c_ares::PTRResults> {
+        self.inner.query_ptr(name)
+    }
+
+    /// Search for the PTR records associated with `name`.
+    pub fn search_ptr(&self, name: &str) -> CAresFuture<c_ares::PTRResults> {
+        self.inner.search_ptr(name)
+    }
+
+    /// Look up the SOA records associated with `name`.
+    pub fn query_soa(&self, name: &str) -> CAresFuture<c_ares::SOAResult> {
+        self.inner.query_soa(name)
+    }
+
+    /// Search for the SOA records associated with `name`.
+    pub fn search_soa(&self, name: &str) -> CAresFuture<c_ares::SOAResult> {
+        self.inner.search_soa(name)
+    }
+
+    /// Look up the SRV records associated with `name`.
+    pub fn query_srv(&self, name: &str) -> CAresFuture<c_ares::SRVResults> {
+        self.inner.query_srv(name)
+    }
+
+    /// Search for the SRV records associated with `name`.
+    pub fn search_srv(&self, name: &str) -> CAresFuture<c_ares::SRVResults> {
+        self.inner.search_srv(name)
+    }
+
+    /// Look up the TXT records associated with `name`.
+    pub fn query_txt(&self, name: &str) -> CAresFuture<c_ares::TXTResults> {
+        self.inner.query_txt(name)
+    }
+
+    /// Search for the TXT records associated with `name`.
+    pub fn search_txt(&self, name: &str) -> CAresFuture<c_ares::TXTResults> {
+        self.inner.search_txt(name)
+    }
+
+    /// Look up the URI records associated with `name`.
+    pub fn query_uri(&self, name: &str) -> CAresFuture<c_ares::URIResults> {
+        self.inner.query_uri(name)
+    }
+
+    /// Search for the URI records associated with `name`.
+    pub fn search_uri(&self, name: &str) -> CAresFuture<c_ares::URIResults> {
+        self.inner.search_uri(name)
+    }
+
+    /// Perform a host query by address.
+    pub fn get_host_by_address(&self, address: &IpAddr) -> CAresFuture<HostResults> {
+        self.inner.get_host_by_address(address)
+    }
+
+    /// Perform a host query by name.
+    pub fn get_host_by_name(
+        &self,
+        name: &str,
+        family: c_ares::AddressFamily,
+    ) -> CAresFuture<HostResults> {
+        self.inner.get_host_by_name(name, family)
+    }
+
+    /// Address-to-nodename translation in protocol-independent manner.
+    pub fn get_name_info(
+        &self,
+        address: &SocketAddr,
+        flags: c_ares::NIFlags,
+    ) -> CAresFuture<NameInfoResult> {
+        self.inner.get_name_info(address, flags)
+    }
+
+    /// Initiate a host query by name and service.
+    pub fn get_addrinfo(
+        &self,
+        name: &str,
+        service: Option<&str>,
+        hints: &c_ares::AddrInfoHints,
+    ) -> CAresFuture<c_ares::AddrInfoResults> {
+        self.inner.get_addrinfo(name, service, hints)
+    }
+
+    /// Initiate a single-question DNS query for `name`.  The class and type of the query are per
+    /// the provided parameters, taking values as defined in `arpa/nameser.h`.
+    ///
+    /// This method is provided so that users can query DNS types for which `c-ares` does not
+    /// provide a parser; or in case a third-party parser is preferred.  Usually, if a suitable
+    /// `query_xxx()` is available, that should be used.
+    pub fn query(&self, name: &str, dns_class: u16, query_type: u16) -> CAresFuture<Vec<u8>> {
+        self.inner.query(name, dns_class, query_type)
+    }
+
+    /// Initiate a series of single-question DNS queries for `name`.  The class and type of the
+    /// query are per the provided parameters, taking values as defined in `arpa/nameser.h`.
+    ///
+    /// This method is provided so that users can search DNS types for which `c-ares` does not
+    /// provide a parser; or in case a third-party parser is preferred.  Usually, if a suitable
+    /// `search_xxx()` is available, that should be used.
+    pub fn search(&self, name: &str, dns_class: u16, query_type: u16) -> CAresFuture<Vec<u8>> {
+        self.inner.search(name, dns_class, query_type)
+    }
+
+    /// Send a DNS query that is already in wire format, returning the raw response.
+    ///
+    /// This method is provided for callers - such as proxies - that already hold queries as bytes.
+    /// The query still benefits from the channel's retry and server failover behaviour.
+    /// The response carries the query ID from `query`: see [`c_ares::Channel::send_raw()`].
+    pub fn send_raw(&self, query: &[u8]) -> CAresFuture<Vec<u8>> {
+        self.inner.send_raw(query)
+    }
+
+    /// Send a DNS query using a pre-built [`c_ares::DnsRecord`].
+    ///
+    /// Returns a tuple of `(query_id, future)`.
+    pub fn send_dnsrec(
+        &self,
+        dnsrec: &c_ares::DnsRecord,
+    ) -> c_ares::Result<CAresFuture<c_ares::DnsRecord>> {
+        self.inner.send_dnsrec(dnsrec)
+    }
+
+    /// Initiate a DNS query for `name` with the given class and type, receiving a parsed
+    /// [`c_ares::DnsRecord`].
+    pub fn query_dnsrec(
+        &self,
+        name: &str,
+        dns_class: c_ares::DnsCls,
+        query_type: c_ares::DnsRecordType,
+    ) -> c_ares::Result<CAresFuture<c_ares::DnsRecord>> {
+        self.inner.query_dnsrec(name, dns_class, query_type)
+    }
+
+    /// Initiate a series of DNS queries using a pre-built [`c_ares::DnsRecord`], receiving a
+    /// parsed [`c_ares::DnsRecord`].
+    pub fn search_dnsrec(
+        &self,
+        dnsrec: &c_ares::DnsRecord,
+    ) -> c_ares::Result<CAresFuture<c_ares::DnsRecord>> {
+        self.inner.search_dnsrec(dnsrec)
+    }
+
+    /// Block until notified that there are no longer any queries in queue, or the specified
+    /// timeout has expired.
+    ///
+    /// Pass `None` to wait indefinitely.
+    ///
+    /// Queries are driven by a task on the tokio runtime, so this must not be called from that
+    /// runtime's threads: use [`tokio::task::spawn_blocking`] or similar.
+    pub fn queue_wait_empty(&self, timeout: Option<std::time::Duration>) -> c_ares::Result<()> {
+        self.inner.queue_wait_empty(timeout)
+    }
+
+    /// Retrieve the total number of active queries pending answers from servers.
+    pub fn queue_active_queries(&self) -> usize {
+        self.inner.queue_active_queries()
+    }
+
+    /// Returns a snapshot of the resolver's metrics: the queries that it has made, how they
+    /// completed and how long they took, and how each server has fared.
+    ///
+    /// The snapshot is empty unless the resolver was created with
+    /// [`Options::set_metrics()`](crate::Options::set_metrics).
+    pub fn metrics(&self) -> ResolverMetrics {
+        self.inner.metrics()
+    }
+
+    /// Cancel all requests made on this `TokioResolver`.
+    pub fn cancel(&self) {
+        self.inner.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+
+    #[test]
+    fn tokio_resolver_is_send() {
+        assert_send::<TokioResolver>();
+    }
+
+    #[test]
+    fn tokio_resolver_is_sync() {
+        assert_sync::<TokioResolver>();
+    }
+
+    #[test]
+    fn tokio_resolver_requires_runtime() {
+        let result = TokioResolver::new();
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+
+    #[tokio::test]
+    async fn tokio_resolver_new() {
+        let resolver = TokioResolver::new();
+        assert!(resolver.is_ok());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn tokio_resolver_new_current_thread() {
+        let resolver = TokioResolver::new();
+        assert!(resolver.is_ok());
+    }
+}
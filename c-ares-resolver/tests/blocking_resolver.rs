@@ -3,13 +3,22 @@
 mod common;
 
 use c_ares_resolver::BlockingResolver;
-use common::test_options;
+use common::{RAW_QUERY, assert_raw_response, server, test_options};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
+// A resolver that queries the test server.
+fn resolver() -> BlockingResolver {
+    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    resolver
+        .set_servers([server().as_str()])
+        .expect("Failed to set servers");
+    resolver
+}
+
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_host_by_address() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let addr: IpAddr = "8.8.8.8".parse().unwrap();
     let result = resolver.get_host_by_address(&addr);
     assert!(result.is_ok(), "Failed to get host by address");
@@ -17,9 +26,9 @@ fn get_host_by_address() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_host_by_name() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.get_host_by_name("google.com", c_ares::AddressFamily::INET);
     assert!(result.is_ok(), "Failed to get host by name");
     let host = result.unwrap();
@@ -27,9 +36,9 @@ fn get_host_by_name() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_name_info() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let addr: SocketAddr = "8.8.8.8:53".parse().unwrap();
     let result = resolver.get_name_info(&addr, c_ares::NIFlags::empty());
     assert!(result.is_ok(), "Failed to get name info");
@@ -38,9 +47,38 @@ fn get_name_info() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
+fn query_a_with_event_loop() {
+    let mut options = test_options();
+    options.set_event_loop();
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    resolver
+        .set_servers([server().as_str()])
+        .expect("Failed to set servers");
+    assert!(!resolver.uses_event_thread());
+    let result = resolver.query_a("google.com");
+    assert!(result.is_ok(), "Failed to query A record");
+}
+
+#[test]
+#[cfg(all(target_os = "linux", cares1_26))]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
+fn query_a_with_epoll_event_thread() {
+    let mut options = test_options();
+    options.set_event_thread(c_ares::EventSys::Epoll);
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    resolver
+        .set_servers([server().as_str()])
+        .expect("Failed to set servers");
+    assert_eq!(resolver.uses_event_thread(), c_ares::thread_safety());
+    let result = resolver.query_a("google.com");
+    assert!(result.is_ok(), "Failed to query A record");
+}
+
+#[test]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_a() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.query_a("google.com");
     assert!(result.is_ok(), "Failed to query A record");
     let records = result.unwrap();
@@ -51,72 +89,72 @@ fn query_a() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_aaaa() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.query_aaaa("google.com");
     assert!(result.is_ok(), "Failed to query AAAA record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_caa() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.query_caa("google.com");
     assert!(result.is_ok(), "Failed to query CAA record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_cname() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.query_cname("www.github.com");
     assert!(result.is_ok(), "Failed to query CNAME record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_mx() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.query_mx("google.com");
     assert!(result.is_ok(), "Failed to query MX record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_naptr() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.query_naptr("sip2sip.info");
     assert!(result.is_ok(), "Failed to query NAPTR record");
 }
 
 #[test]
 fn query_nonexistent_domain() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.query_a("this.domain.definitely.does.not.exist.invalid");
     assert!(result.is_err());
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_ns() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.query_ns("google.com");
     assert!(result.is_ok(), "Failed to query NS record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_ptr() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.query_ptr("8.8.8.8.in-addr.arpa");
     assert!(result.is_ok(), "Failed to query PTR record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_raw() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     // Query type 1 = A record, class 1 = IN
     let result = resolver.query("google.com", 1, 1);
     assert!(result.is_ok(), "Failed raw query");
@@ -125,148 +163,148 @@ fn query_raw() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_soa() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.query_soa("google.com");
     assert!(result.is_ok(), "Failed to query SOA record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_srv() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.query_srv("_imaps._tcp.gmail.com");
     assert!(result.is_ok(), "Failed to query SRV record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_txt() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.query_txt("google.com");
     assert!(result.is_ok(), "Failed to query TXT record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_uri() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.query_uri("_kerberos.fedoraproject.org");
     assert!(result.is_ok(), "Failed to query URI record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_a() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.search_a("google.com");
     assert!(result.is_ok(), "Failed to search A record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_aaaa() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.search_aaaa("google.com");
     assert!(result.is_ok(), "Failed to search AAAA record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_caa() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.search_caa("google.com");
     assert!(result.is_ok(), "Failed to search CAA record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_cname() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.search_cname("www.github.com");
     assert!(result.is_ok(), "Failed to search CNAME record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_mx() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.search_mx("google.com");
     assert!(result.is_ok(), "Failed to search MX record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_naptr() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.search_naptr("sip2sip.info");
     assert!(result.is_ok(), "Failed to search NAPTR record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_ns() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.search_ns("google.com");
     assert!(result.is_ok(), "Failed to search NS record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_ptr() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.search_ptr("8.8.8.8.in-addr.arpa");
     assert!(result.is_ok(), "Failed to search PTR record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_raw() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.search("google.com", 1, 1);
     assert!(result.is_ok(), "Failed raw search");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_soa() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.search_soa("google.com");
     assert!(result.is_ok(), "Failed to search SOA record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_srv() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.search_srv("_imaps._tcp.gmail.com");
     assert!(result.is_ok(), "Failed to search SRV record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_txt() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.search_txt("google.com");
     assert!(result.is_ok(), "Failed to search TXT record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_uri() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.search_uri("_kerberos.fedoraproject.org");
     assert!(result.is_ok(), "Failed to search URI record");
 }
 
 #[cfg(cares1_28)]
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_dnsrec() {
     use c_ares::{DnsRcode, DnsSection};
 
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.query_dnsrec("google.com", c_ares::DnsCls::IN, c_ares::DnsRecordType::A);
     assert!(result.is_ok(), "Failed to query dnsrec");
     let record = result.unwrap();
@@ -276,7 +314,7 @@ fn query_dnsrec() {
 
 #[cfg(cares1_28)]
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_dnsrec() {
     use c_ares::{DnsCls, DnsFlags, DnsOpcode, DnsRcode, DnsRecord, DnsRecordType, DnsSection};
 
@@ -284,7 +322,7 @@ fn search_dnsrec() {
     dnsrec
         .query_add("google.com", DnsRecordType::A, DnsCls::IN)
         .unwrap();
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.search_dnsrec(&dnsrec);
     assert!(result.is_ok(), "Failed to search dnsrec");
     let record = result.unwrap();
@@ -294,7 +332,7 @@ fn search_dnsrec() {
 
 #[cfg(cares1_28)]
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn send_dnsrec() {
     use c_ares::{DnsCls, DnsFlags, DnsOpcode, DnsRcode, DnsRecord, DnsRecordType, DnsSection};
 
@@ -302,7 +340,7 @@ fn send_dnsrec() {
     dnsrec
         .query_add("google.com", DnsRecordType::A, DnsCls::IN)
         .unwrap();
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let result = resolver.send_dnsrec(&dnsrec);
     assert!(result.is_ok(), "Failed to send dnsrec");
     let record = result.unwrap();
@@ -310,6 +348,14 @@ fn send_dnsrec() {
     assert!(record.rr_count(DnsSection::Answer) > 0);
 }
 
+#[test]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
+fn send_raw() {
+    let resolver = resolver();
+    let response = resolver.send_raw(RAW_QUERY).expect("Query failed");
+    assert_raw_response(&response);
+}
+
 mod resolver_configuration {
     use super::*;
 
@@ -343,12 +389,9 @@ mod resolver_configuration {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn concurrent_queries() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
-    resolver
-        .set_servers(["8.8.8.8"])
-        .expect("Failed to set servers");
+    let resolver = resolver();
 
     // Multiple concurrent queries exercise the eventloop socket
     // modify and re-assert interest paths.
@@ -365,9 +408,9 @@ fn concurrent_queries() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_addrinfo() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let hints = c_ares::AddrInfoHints {
         family: Some(c_ares::AddressFamily::INET),
         ..c_ares::AddrInfoHints::default()
@@ -379,9 +422,9 @@ fn get_addrinfo() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_addrinfo_with_service() {
-    let resolver = BlockingResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let hints = c_ares::AddrInfoHints {
         family: Some(c_ares::AddressFamily::INET),
         ..c_ares::AddrInfoHints::default()
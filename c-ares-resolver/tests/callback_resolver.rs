@@ -3,11 +3,20 @@
 mod common;
 
 use c_ares_resolver::Resolver;
-use common::test_options;
+use common::{RAW_QUERY, assert_raw_response, server, test_options};
 use std::net::{IpAddr, SocketAddr};
 use std::sync::{Arc, Condvar, Mutex};
 use std::time::Duration;
 
+// A resolver that queries the test server.
+fn resolver() -> Resolver {
+    let resolver = Resolver::with_options(test_options()).unwrap();
+    resolver
+        .set_servers([server().as_str()])
+        .expect("Failed to set servers");
+    resolver
+}
+
 // Helper to wait for a callback with timeout
 fn wait_for_completion(pair: &Arc<(Mutex<bool>, Condvar)>, timeout: Duration) -> bool {
     let (lock, cvar) = pair.as_ref();
@@ -19,9 +28,9 @@ fn wait_for_completion(pair: &Arc<(Mutex<bool>, Condvar)>, timeout: Duration) ->
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn cancel_queries() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -44,9 +53,9 @@ fn cancel_queries() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_host_by_address() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
     let addr: IpAddr = "8.8.8.8".parse().unwrap();
@@ -66,9 +75,9 @@ fn get_host_by_address() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_host_by_name() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -87,9 +96,9 @@ fn get_host_by_name() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_name_info() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
     let addr: SocketAddr = "8.8.8.8:53".parse().unwrap();
@@ -110,9 +119,9 @@ fn get_name_info() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_a() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -131,9 +140,9 @@ fn query_a() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_aaaa() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -151,9 +160,9 @@ fn query_aaaa() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_caa() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -171,9 +180,9 @@ fn query_caa() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_cname() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -191,9 +200,9 @@ fn query_cname() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_mx() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -211,9 +220,9 @@ fn query_mx() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_naptr() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -231,9 +240,9 @@ fn query_naptr() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_ns() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -252,7 +261,7 @@ fn query_ns() {
 
 #[test]
 fn query_nonexistent_domain() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -273,9 +282,9 @@ fn query_nonexistent_domain() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_ptr() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -293,9 +302,9 @@ fn query_ptr() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_raw() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -314,9 +323,9 @@ fn query_raw() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_soa() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -334,9 +343,9 @@ fn query_soa() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_txt() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -354,9 +363,9 @@ fn query_txt() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_srv() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -374,9 +383,9 @@ fn query_srv() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_uri() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -395,11 +404,11 @@ fn query_uri() {
 
 #[test]
 #[cfg(cares1_29)]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_with_server_state_callback() {
     use std::sync::atomic::{AtomicBool, Ordering};
 
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let callback_called = Arc::new(AtomicBool::new(false));
     let callback_called_clone = Arc::clone(&callback_called);
 
@@ -427,9 +436,9 @@ fn query_with_server_state_callback() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_a() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -447,9 +456,9 @@ fn search_a() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_aaaa() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -467,9 +476,9 @@ fn search_aaaa() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_caa() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -487,9 +496,9 @@ fn search_caa() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_cname() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -507,9 +516,9 @@ fn search_cname() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_mx() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -527,9 +536,9 @@ fn search_mx() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_naptr() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -547,9 +556,9 @@ fn search_naptr() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_ns() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -567,9 +576,9 @@ fn search_ns() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_ptr() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -587,9 +596,9 @@ fn search_ptr() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_raw() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -607,9 +616,9 @@ fn search_raw() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_soa() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -627,9 +636,9 @@ fn search_soa() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_srv() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -647,9 +656,9 @@ fn search_srv() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_txt() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -667,9 +676,9 @@ fn search_txt() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_uri() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -688,9 +697,9 @@ fn search_uri() {
 
 #[cfg(cares1_28)]
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_dnsrec() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -718,7 +727,7 @@ fn query_dnsrec() {
 
 #[cfg(cares1_28)]
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_dnsrec() {
     use c_ares::{DnsCls, DnsFlags, DnsOpcode, DnsRcode, DnsRecord, DnsRecordType, DnsSection};
 
@@ -727,7 +736,7 @@ fn search_dnsrec() {
         .query_add("google.com", DnsRecordType::A, DnsCls::IN)
         .unwrap();
 
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -750,7 +759,7 @@ fn search_dnsrec() {
 
 #[cfg(cares1_28)]
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn send_dnsrec() {
     use c_ares::{DnsCls, DnsFlags, DnsOpcode, DnsRcode, DnsRecord, DnsRecordType, DnsSection};
 
@@ -759,7 +768,7 @@ fn send_dnsrec() {
         .query_add("google.com", DnsRecordType::A, DnsCls::IN)
         .unwrap();
 
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -781,9 +790,9 @@ fn send_dnsrec() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_addrinfo() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -807,9 +816,9 @@ fn get_addrinfo() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_addrinfo_with_service() {
-    let resolver = Resolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair_clone = Arc::clone(&pair);
 
@@ -836,3 +845,46 @@ fn get_addrinfo_with_service() {
         "Callback was not called"
     );
 }
+
+#[test]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
+fn queries_from_many_threads() {
+    let resolver = Arc::new(resolver());
+    let (tx, rx) = std::sync::mpsc::channel();
+    let threads: Vec<_> = (0..8)
+        .map(|_| {
+            let resolver = Arc::clone(&resolver);
+            let tx = tx.clone();
+            std::thread::spawn(move || {
+                resolver.query_a("google.com", move |result| {
+                    tx.send(result.is_ok()).unwrap();
+                });
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    for _ in 0..8 {
+        let ok = rx
+            .recv_timeout(Duration::from_secs(3))
+            .expect("Callback was not called");
+        assert!(ok);
+    }
+}
+
+#[test]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
+fn send_raw() {
+    let resolver = resolver();
+    let (tx, rx) = std::sync::mpsc::channel();
+    resolver.send_raw(RAW_QUERY, move |result| {
+        tx.send(result.map(<[u8]>::to_vec)).unwrap();
+    });
+    let response = rx
+        .recv_timeout(Duration::from_secs(3))
+        .expect("Callback was not called")
+        .expect("Query failed");
+    assert_raw_response(&response);
+}
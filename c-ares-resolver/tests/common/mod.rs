@@ -7,3 +7,36 @@ pub fn test_options() -> Options {
     options.set_timeout(Duration::from_secs(5)).set_tries(2);
     options
 }
+
+/// The DNS server that the tests should use: a local server answering from
+/// fixture records, or `8.8.8.8` if c-ares is too old to run that server.
+#[cfg(cares1_28)]
+pub fn server() -> String {
+    use c_ares_test_server::TestServer;
+    use std::sync::OnceLock;
+
+    static SERVER: OnceLock<TestServer> = OnceLock::new();
+    SERVER
+        .get_or_init(|| TestServer::with_fixtures().expect("Failed to start test server"))
+        .addr()
+        .to_string()
+}
+
+/// The DNS server that the tests should use: a local server answering from
+/// fixture records, or `8.8.8.8` if c-ares is too old to run that server.
+#[cfg(not(cares1_28))]
+pub fn server() -> String {
+    "8.8.8.8".to_owned()
+}
+
+/// A query for google.com's A records, with ID 0x1234 and recursion desired.
+pub const RAW_QUERY: &[u8] = b"\x12\x34\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00\
+    \x06google\x03com\x00\x00\x01\x00\x01";
+
+/// Check that `response` answers [`RAW_QUERY`]: with its ID, and without error.
+pub fn assert_raw_response(response: &[u8]) {
+    assert_eq!(response[..2], RAW_QUERY[..2], "Response has the wrong ID");
+    assert_eq!(response[2] & 0x80, 0x80, "Response is not a response");
+    assert_eq!(response[3] & 0x0f, 0, "Response has an error rcode");
+    assert_ne!(response[6..8], [0, 0], "Response has no answers");
+}
@@ -3,14 +3,23 @@
 mod common;
 
 use c_ares_resolver::FutureResolver;
-use common::test_options;
+use common::{RAW_QUERY, assert_raw_response, server, test_options};
 use futures_executor::block_on;
 use std::net::{IpAddr, SocketAddr};
 
+// A resolver that queries the test server.
+fn resolver() -> FutureResolver {
+    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    resolver
+        .set_servers([server().as_str()])
+        .expect("Failed to set servers");
+    resolver
+}
+
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn cancel_queries() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.query_a("example.com");
     resolver.cancel();
     let result = block_on(future);
@@ -22,9 +31,9 @@ fn cancel_queries() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_host_by_address() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let addr: IpAddr = "8.8.8.8".parse().unwrap();
     let future = resolver.get_host_by_address(&addr);
     let result = block_on(future);
@@ -33,9 +42,9 @@ fn get_host_by_address() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_host_by_name() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.get_host_by_name("google.com", c_ares::AddressFamily::INET);
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to get host by name");
@@ -44,9 +53,9 @@ fn get_host_by_name() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_name_info() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let addr: SocketAddr = "8.8.8.8:53".parse().unwrap();
     let future = resolver.get_name_info(&addr, c_ares::NIFlags::empty());
     let result = block_on(future);
@@ -56,9 +65,23 @@ fn get_name_info() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
+fn query_a_with_event_loop() {
+    let mut options = test_options();
+    options.set_event_loop();
+    let resolver = FutureResolver::with_options(options).unwrap();
+    resolver
+        .set_servers([server().as_str()])
+        .expect("Failed to set servers");
+    assert!(!resolver.uses_event_thread());
+    let result = block_on(resolver.query_a("google.com"));
+    assert!(result.is_ok(), "Failed to query A record");
+}
+
+#[test]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_a() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.query_a("google.com");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to query A record");
@@ -70,45 +93,45 @@ fn query_a() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_aaaa() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.query_aaaa("google.com");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to query AAAA record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_caa() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.query_caa("google.com");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to query CAA record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_cname() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.query_cname("www.github.com");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to query CNAME record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_mx() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.query_mx("google.com");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to query MX record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_naptr() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.query_naptr("sip2sip.info");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to query NAPTR record");
@@ -116,187 +139,187 @@ fn query_naptr() {
 
 #[test]
 fn query_nonexistent_domain() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.query_a("this.domain.definitely.does.not.exist.invalid");
     let result = block_on(future);
     assert!(result.is_err());
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_ns() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.query_ns("google.com");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to query NS record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_ptr() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.query_ptr("8.8.8.8.in-addr.arpa");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to query PTR record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_raw() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.query("google.com", 1, 1);
     let result = block_on(future);
     assert!(result.is_ok(), "Failed raw query");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_soa() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.query_soa("google.com");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to query SOA record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_srv() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.query_srv("_imaps._tcp.gmail.com");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to query SRV record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_txt() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.query_txt("google.com");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to query TXT record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_uri() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.query_uri("_kerberos.fedoraproject.org");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to query URI record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_a() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.search_a("google.com");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to search A record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_aaaa() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.search_aaaa("google.com");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to search AAAA record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_caa() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.search_caa("google.com");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to search CAA record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_cname() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.search_cname("www.github.com");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to search CNAME record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_mx() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.search_mx("google.com");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to search MX record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_naptr() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.search_naptr("sip2sip.info");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to search NAPTR record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_ns() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.search_ns("google.com");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to search NS record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_ptr() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.search_ptr("8.8.8.8.in-addr.arpa");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to search PTR record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_raw() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.search("google.com", 1, 1);
     let result = block_on(future);
     assert!(result.is_ok(), "Failed raw search");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_soa() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.search_soa("google.com");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to search SOA record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_srv() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.search_srv("_imaps._tcp.gmail.com");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to search SRV record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_txt() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.search_txt("google.com");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to search TXT record");
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_uri() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.search_uri("_kerberos.fedoraproject.org");
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to search URI record");
@@ -304,11 +327,11 @@ fn search_uri() {
 
 #[cfg(cares1_28)]
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_dnsrec() {
     use c_ares::{DnsRcode, DnsSection};
 
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver
         .query_dnsrec("google.com", c_ares::DnsCls::IN, c_ares::DnsRecordType::A)
         .unwrap();
@@ -321,7 +344,7 @@ fn query_dnsrec() {
 
 #[cfg(cares1_28)]
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_dnsrec() {
     use c_ares::{DnsCls, DnsFlags, DnsOpcode, DnsRcode, DnsRecord, DnsRecordType, DnsSection};
 
@@ -329,7 +352,7 @@ fn search_dnsrec() {
     dnsrec
         .query_add("google.com", DnsRecordType::A, DnsCls::IN)
         .unwrap();
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.search_dnsrec(&dnsrec).unwrap();
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to search dnsrec");
@@ -340,7 +363,7 @@ fn search_dnsrec() {
 
 #[cfg(cares1_28)]
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn send_dnsrec() {
     use c_ares::{DnsCls, DnsFlags, DnsOpcode, DnsRcode, DnsRecord, DnsRecordType, DnsSection};
 
@@ -348,7 +371,7 @@ fn send_dnsrec() {
     dnsrec
         .query_add("google.com", DnsRecordType::A, DnsCls::IN)
         .unwrap();
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let future = resolver.send_dnsrec(&dnsrec).unwrap();
     let result = block_on(future);
     assert!(result.is_ok(), "Failed to send dnsrec");
@@ -358,9 +381,9 @@ fn send_dnsrec() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_addrinfo() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let hints = c_ares::AddrInfoHints {
         family: Some(c_ares::AddressFamily::INET),
         ..c_ares::AddrInfoHints::default()
@@ -373,9 +396,9 @@ fn get_addrinfo() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_addrinfo_with_service() {
-    let resolver = FutureResolver::with_options(test_options()).unwrap();
+    let resolver = resolver();
     let hints = c_ares::AddrInfoHints {
         family: Some(c_ares::AddressFamily::INET),
         ..c_ares::AddrInfoHints::default()
@@ -391,3 +414,189 @@ fn get_addrinfo_with_service() {
         }
     }
 }
+
+#[test]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
+fn send_raw() {
+    let resolver = resolver();
+    let response = block_on(resolver.send_raw(RAW_QUERY)).expect("Query failed");
+    assert_raw_response(&response);
+}
+
+#[cfg(all(unix, cares1_34))]
+#[test]
+fn record_and_replay() {
+    use c_ares_test_server::{Recorder, Recording, ReplaySockets};
+
+    // Record answers from the test server, as we might from real-world servers...
+    let recorder = Recorder::new();
+    let resolver = resolver();
+    resolver
+        .set_trace_callback(recorder.trace_callback())
+        .expect("Failed to set trace callback");
+    let recorded_a = block_on(resolver.query_a("google.com")).unwrap();
+    let recorded_mx = block_on(resolver.query_mx("google.com")).unwrap();
+
+    let path = std::env::temp_dir().join(format!("c-ares-recording-{}.txt", std::process::id()));
+    recorder.recording().unwrap().save(&path).unwrap();
+    let recording = Recording::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(recording.len(), 2);
+
+    // ... and get the same results when they are replayed, without any network.
+    let mut options = test_options();
+    options.set_socket_functions(ReplaySockets::new(&recording).unwrap());
+    let resolver = FutureResolver::with_options(options).unwrap();
+    resolver
+        .set_servers(["192.0.2.53"])
+        .expect("Failed to set servers");
+    let replayed_a = block_on(resolver.query_a("google.com")).unwrap();
+    let replayed_mx = block_on(resolver.query_mx("google.com")).unwrap();
+    assert_eq!(replayed_a.to_string(), recorded_a.to_string());
+    assert_eq!(replayed_mx.to_string(), recorded_mx.to_string());
+
+    // Anything that wasn't recorded is refused.
+    let result = block_on(resolver.query_txt("google.com"));
+    assert_eq!(result.err(), Some(c_ares::Error::EREFUSED));
+}
+
+#[cfg(all(feature = "tracing", cares1_28))]
+#[test]
+fn query_spans() {
+    use spans::SpanCapture;
+
+    let capture = SpanCapture::default();
+    tracing::subscriber::with_default(capture.clone(), || {
+        let resolver = resolver();
+        let caller = tracing::info_span!("caller");
+        let found = caller.in_scope(|| resolver.query_mx("google.com"));
+        let missing = caller.in_scope(|| resolver.query_a("missing.example.com"));
+        assert!(block_on(found).is_ok());
+        assert_eq!(block_on(missing).err(), Some(c_ares::Error::ENOTFOUND));
+    });
+
+    // One span per query, each a child of the span that was current where the query was made.
+    let caller = capture.find("caller").unwrap();
+    let queries = capture.spans_named("dns_query");
+    assert_eq!(queries.len(), 2);
+    assert!(queries.iter().all(|query| query.parent == Some(caller)));
+
+    let found = &queries[0].fields;
+    assert_eq!(found["method"], "query");
+    assert_eq!(found["name"], "google.com");
+    assert_eq!(found["query_type"], "15");
+    assert_eq!(found["rcode"], "0");
+    assert!(!found.contains_key("error"));
+
+    let missing = &queries[1].fields;
+    assert_eq!(missing["method"], "query");
+    assert_eq!(missing["name"], "missing.example.com");
+    assert_eq!(missing["query_type"], "1");
+    assert_eq!(missing["rcode"], "3");
+    assert_eq!(missing["error"], "ENOTFOUND");
+}
+
+// A subscriber that remembers every span, with its parent and the fields recorded on it.
+#[cfg(all(feature = "tracing", cares1_28))]
+mod spans {
+    use std::collections::HashMap;
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+    use std::thread::{self, ThreadId};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    #[derive(Clone, Debug, Default)]
+    pub struct CapturedSpan {
+        pub name: &'static str,
+        pub parent: Option<u64>,
+        pub fields: HashMap<&'static str, String>,
+    }
+
+    #[derive(Clone, Default)]
+    pub struct SpanCapture {
+        // Indexed by span id, less one.
+        spans: Arc<Mutex<Vec<CapturedSpan>>>,
+        entered: Arc<Mutex<HashMap<ThreadId, Vec<u64>>>>,
+    }
+
+    impl SpanCapture {
+        // The id of the first span called `name`.
+        pub fn find(&self, name: &str) -> Option<u64> {
+            let spans = self.spans.lock().unwrap();
+            let index = spans.iter().position(|span| span.name == name)?;
+            Some(index as u64 + 1)
+        }
+
+        pub fn spans_named(&self, name: &str) -> Vec<CapturedSpan> {
+            let spans = self.spans.lock().unwrap();
+            spans
+                .iter()
+                .filter(|span| span.name == name)
+                .cloned()
+                .collect()
+        }
+    }
+
+    struct FieldRecorder<'a>(&'a mut HashMap<&'static str, String>);
+
+    impl Visit for FieldRecorder<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name(), value.to_owned());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0.insert(field.name(), format!("{value:?}"));
+        }
+    }
+
+    impl Subscriber for SpanCapture {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+            let parent = if attributes.is_contextual() {
+                let entered = self.entered.lock().unwrap();
+                entered
+                    .get(&thread::current().id())
+                    .and_then(|stack| stack.last().copied())
+            } else {
+                attributes.parent().map(Id::into_u64)
+            };
+            let mut span = CapturedSpan {
+                name: attributes.metadata().name(),
+                parent,
+                fields: HashMap::new(),
+            };
+            attributes.record(&mut FieldRecorder(&mut span.fields));
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(span);
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            let index = usize::try_from(span.into_u64() - 1).unwrap();
+            values.record(&mut FieldRecorder(&mut spans[index].fields));
+        }
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, _event: &Event<'_>) {}
+
+        fn enter(&self, span: &Id) {
+            let mut entered = self.entered.lock().unwrap();
+            let stack = entered.entry(thread::current().id()).or_default();
+            stack.push(span.into_u64());
+        }
+
+        fn exit(&self, _span: &Id) {
+            let mut entered = self.entered.lock().unwrap();
+            if let Some(stack) = entered.get_mut(&thread::current().id()) {
+                stack.pop();
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Integration tests for driving a resolver from a user-supplied reactor.
+
+#[allow(dead_code)]
+mod common;
+
+use c_ares_resolver::{Reactor, ReactorHandle, Resolver, SocketEvent};
+use common::{server, test_options};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+// A reactor whose loop is run by the test itself, on the test thread.
+#[derive(Default)]
+struct TestReactor {
+    interests: Mutex<HashMap<c_ares::Socket, (bool, bool)>>,
+    timeout: Mutex<Option<Duration>>,
+    handle: OnceLock<ReactorHandle>,
+    wakes: AtomicUsize,
+    detached: AtomicBool,
+}
+
+impl Reactor for TestReactor {
+    fn attach(&self, handle: ReactorHandle) {
+        self.handle.set(handle).unwrap();
+    }
+
+    fn set_interest(&self, socket: c_ares::Socket, readable: bool, writable: bool) {
+        let mut interests = self.interests.lock().unwrap();
+        if readable || writable {
+            interests.insert(socket, (readable, writable));
+        } else {
+            interests.remove(&socket);
+        }
+    }
+
+    fn set_timer(&self, timeout: Duration) {
+        *self.timeout.lock().unwrap() = Some(timeout);
+    }
+
+    fn wake(&self) {
+        self.wakes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn detach(&self) {
+        self.detached.store(true, Ordering::Relaxed);
+    }
+}
+
+impl TestReactor {
+    // Run one iteration of the loop: wait for the sockets of interest, up to the timer, and
+    // report what happened.
+    #[cfg(unix)]
+    fn turn(&self, poller: &polling::Poller) {
+        let interests = self.interests.lock().unwrap().clone();
+        for (&socket, &(readable, writable)) in &interests {
+            let key = usize::try_from(socket).unwrap();
+            unsafe {
+                poller
+                    .add(socket, polling::Event::new(key, readable, writable))
+                    .unwrap();
+            }
+        }
+
+        let timeout = self.timeout.lock().unwrap().unwrap();
+        let mut events = polling::Events::new();
+        poller.wait(&mut events, Some(timeout)).unwrap();
+        let events: Vec<_> = events
+            .iter()
+            .map(|event| {
+                let socket = c_ares::Socket::try_from(event.key).unwrap();
+                SocketEvent::new(socket, event.readable, event.writable)
+            })
+            .collect();
+
+        for &socket in interests.keys() {
+            let source = unsafe { std::os::fd::BorrowedFd::borrow_raw(socket) };
+            poller.delete(source).unwrap();
+        }
+
+        self.handle.get().unwrap().process(&events);
+    }
+}
+
+fn resolver(reactor: &Arc<TestReactor>) -> Resolver {
+    let resolver = Resolver::with_reactor(test_options(), Arc::clone(reactor)).unwrap();
+    resolver
+        .set_servers([server().as_str()])
+        .expect("Failed to set servers");
+    resolver
+}
+
+#[test]
+fn attaches_and_sets_timer() {
+    let reactor = Arc::new(TestReactor::default());
+    let _resolver = resolver(&reactor);
+    assert!(reactor.handle.get().is_some());
+    assert!(reactor.timeout.lock().unwrap().is_some());
+}
+
+#[test]
+fn detaches_on_drop() {
+    let reactor = Arc::new(TestReactor::default());
+    let resolver = resolver(&reactor);
+    assert!(!reactor.detached.load(Ordering::Relaxed));
+    drop(resolver);
+    assert!(reactor.detached.load(Ordering::Relaxed));
+
+    // Once the resolver has gone, the handle does nothing.
+    reactor.handle.get().unwrap().process(&[]);
+}
+
+#[test]
+#[cfg(unix)]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
+fn query_a() {
+    let reactor = Arc::new(TestReactor::default());
+    let resolver = resolver(&reactor);
+    let (tx, rx) = mpsc::channel();
+    resolver.query_a("google.com", move |result| {
+        tx.send(result.map(|results| results.iter().count()))
+            .unwrap();
+    });
+
+    let poller = polling::Poller::new().unwrap();
+    let result = loop {
+        if let Ok(result) = rx.try_recv() {
+            break result;
+        }
+        reactor.turn(&poller);
+    };
+    assert!(result.unwrap() > 0, "Expected at least one A record");
+    assert!(reactor.interests.lock().unwrap().len() <= 1);
+}
+
+// c-ares only defers writes on TCP connections, so that several queries can go in one write.
+#[test]
+#[cfg(all(unix, cares1_34))]
+fn pending_write_wakes_reactor() {
+    let reactor = Arc::new(TestReactor::default());
+    let mut options = test_options();
+    options.set_flags(c_ares::Flags::USEVC);
+    let resolver = Resolver::with_reactor(options, Arc::clone(&reactor)).unwrap();
+    resolver
+        .set_servers([server().as_str()])
+        .expect("Failed to set servers");
+    resolver.query_a("google.com", |_| {});
+    assert!(reactor.wakes.load(Ordering::Relaxed) > 0);
+}
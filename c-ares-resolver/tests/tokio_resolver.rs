@@ -0,0 +1,231 @@
+//! Integration tests for the tokio resolver.
+
+#![cfg(all(feature = "tokio", unix, cares1_34))]
+
+mod common;
+
+use c_ares_resolver::TokioResolver;
+use common::{RAW_QUERY, assert_raw_response, server, test_options};
+use std::net::IpAddr;
+
+// A resolver that queries the test server.
+fn resolver() -> TokioResolver {
+    let resolver = TokioResolver::with_options(test_options()).unwrap();
+    resolver
+        .set_servers([server().as_str()])
+        .expect("Failed to set servers");
+    resolver
+}
+
+#[tokio::test]
+async fn query_a() {
+    let resolver = resolver();
+    let result = resolver.query_a("google.com").await;
+    assert!(result.is_ok(), "Failed to query A record");
+    let records = result.unwrap();
+    assert!(
+        records.into_iter().next().is_some(),
+        "Expected at least one A record"
+    );
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn query_a_current_thread() {
+    let resolver = resolver();
+    let result = resolver.query_a("google.com").await;
+    assert!(result.is_ok(), "Failed to query A record");
+}
+
+#[tokio::test]
+async fn query_mx() {
+    let resolver = resolver();
+    let result = resolver.query_mx("google.com").await;
+    assert!(result.is_ok(), "Failed to query MX record");
+    assert!(result.unwrap().into_iter().next().is_some());
+}
+
+#[tokio::test]
+async fn get_host_by_name() {
+    let resolver = resolver();
+    let result = resolver
+        .get_host_by_name("google.com", c_ares::AddressFamily::INET)
+        .await;
+    assert!(result.is_ok(), "Failed to get host by name");
+    assert!(!result.unwrap().hostname().is_empty());
+}
+
+#[tokio::test]
+async fn get_host_by_address() {
+    let resolver = resolver();
+    let addr: IpAddr = "8.8.8.8".parse().unwrap();
+    let result = resolver.get_host_by_address(&addr).await;
+    assert!(result.is_ok(), "Failed to get host by address");
+}
+
+#[tokio::test]
+async fn concurrent_queries() {
+    let resolver = resolver();
+    let (a, aaaa, txt) = tokio::join!(
+        resolver.query_a("google.com"),
+        resolver.query_aaaa("google.com"),
+        resolver.query_txt("google.com"),
+    );
+    assert!(a.is_ok(), "Failed to query A record");
+    assert!(aaaa.is_ok(), "Failed to query AAAA record");
+    assert!(txt.is_ok(), "Failed to query TXT record");
+}
+
+#[tokio::test]
+async fn sequential_queries() {
+    let resolver = resolver();
+    for _ in 0..3 {
+        let result = resolver.query_a("google.com").await;
+        assert!(result.is_ok(), "Failed to query A record");
+    }
+}
+
+#[tokio::test]
+async fn future_outlives_resolver() {
+    let resolver = resolver();
+    let future = resolver.query_a("google.com");
+    drop(resolver);
+    assert!(future.await.is_ok(), "Failed to query A record");
+}
+
+#[tokio::test]
+async fn query_from_spawned_task() {
+    let resolver = std::sync::Arc::new(resolver());
+    let task = {
+        let resolver = std::sync::Arc::clone(&resolver);
+        tokio::spawn(async move { resolver.query_a("google.com").await })
+    };
+    let result = task.await.unwrap();
+    assert!(result.is_ok(), "Failed to query A record");
+}
+
+#[tokio::test]
+async fn send_raw() {
+    let resolver = resolver();
+    let response = resolver.send_raw(RAW_QUERY).await.expect("Query failed");
+    assert_raw_response(&response);
+}
+
+#[tokio::test]
+async fn cancel_queries() {
+    let resolver = resolver();
+    let future = resolver.query_a("example.com");
+    resolver.cancel();
+    let result = future.await;
+    assert!(
+        result.is_ok() || result.as_ref().err() == Some(&c_ares::Error::ECANCELLED),
+        "Expected success or ECANCELLED, got error: {:?}",
+        result.err()
+    );
+}
+
+// Socket functions that hand out sockets which aren't file descriptors, and never get an answer.
+struct UnwatchableSockets;
+
+impl c_ares::SocketFunctions for UnwatchableSockets {
+    fn open(
+        &self,
+        _family: c_ares::AddressFamily,
+        _socket_type: c_ares::SocketType,
+    ) -> std::io::Result<c_ares::Socket> {
+        Ok(c_ares::Socket::MAX - 1)
+    }
+
+    fn close(&self, _socket: c_ares::Socket) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn connect(
+        &self,
+        _socket: c_ares::Socket,
+        _addr: &std::net::SocketAddr,
+        _flags: c_ares::SocketConnectFlags,
+    ) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn recvfrom(
+        &self,
+        _socket: c_ares::Socket,
+        _buf: &mut [u8],
+    ) -> std::io::Result<(usize, Option<std::net::SocketAddr>)> {
+        Err(std::io::ErrorKind::WouldBlock.into())
+    }
+
+    fn sendto(
+        &self,
+        _socket: c_ares::Socket,
+        buf: &[u8],
+        _addr: Option<&std::net::SocketAddr>,
+    ) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn getsockname(&self, _socket: c_ares::Socket) -> std::io::Result<std::net::SocketAddr> {
+        Ok(([127, 0, 0, 1], 1024).into())
+    }
+
+    fn bind(
+        &self,
+        _socket: c_ares::Socket,
+        _addr: &std::net::SocketAddr,
+        _flags: c_ares::SocketBindFlags,
+    ) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn unregistrable_socket_times_out() {
+    let timeout = std::time::Duration::from_millis(50);
+    let mut options = test_options();
+    options
+        .set_timeout(timeout)
+        .set_max_timeout(timeout)
+        .set_tries(1)
+        .set_socket_functions(UnwatchableSockets);
+    let resolver = TokioResolver::with_options(options).unwrap();
+    resolver
+        .set_servers(["192.0.2.53"])
+        .expect("Failed to set servers");
+    let result = resolver.query_a("google.com").await;
+    assert_eq!(result.err(), Some(c_ares::Error::ETIMEOUT));
+}
+
+#[tokio::test]
+async fn large_tcp_response() {
+    use c_ares::{DnsFlags, DnsOpcode, DnsRcode, DnsRecord, DnsSection};
+    use c_ares_test_server::TestServer;
+
+    // Enough TXT records that the response comes close to the 64KiB limit on a DNS message, so
+    // that c-ares must read it in several pieces.
+    let text = "x".repeat(250);
+    let zone: String = (0..240)
+        .map(|idx| format!("big.example. 300 IN TXT \"{idx:03}{text}\"\n"))
+        .collect();
+    let mut fixtures =
+        DnsRecord::new(0, DnsFlags::empty(), DnsOpcode::Query, DnsRcode::NoError).unwrap();
+    fixtures
+        .add_zone_str(DnsSection::Answer, &zone, ".")
+        .unwrap();
+    let server = TestServer::start(&fixtures).unwrap();
+
+    let mut options = test_options();
+    options.set_flags(c_ares::Flags::USEVC);
+    let resolver = TokioResolver::with_options(options).unwrap();
+    resolver
+        .set_servers([server.addr().to_string().as_str()])
+        .expect("Failed to set servers");
+    let result = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        resolver.query_txt("big.example"),
+    )
+    .await
+    .expect("Timed out reading a large response");
+    let records = result.expect("Failed to query TXT records");
+    assert_eq!(records.iter().count(), 240);
+}
@@ -0,0 +1,15 @@
+use std::env;
+
+fn main() {
+    println!("cargo::rustc-check-cfg=cfg(cares1_28)");
+    println!("cargo::rustc-check-cfg=cfg(cares1_34)");
+    if let Ok(version) = env::var("DEP_CARES_VERSION_NUMBER") {
+        let version = u64::from_str_radix(&version, 16).unwrap();
+        if version >= 0x1_1c_00 {
+            println!("cargo:rustc-cfg=cares1_28");
+        }
+        if version >= 0x1_22_00 {
+            println!("cargo:rustc-cfg=cares1_34");
+        }
+    }
+}
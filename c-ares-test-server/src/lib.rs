@@ -0,0 +1,542 @@
+//! An authoritative DNS server for tests.
+//!
+//! A [`TestServer`] listens for UDP and TCP on a port of `127.0.0.1`, and
+//! answers queries from the resource records in a [`DnsRecord`].  Point a
+//! channel at it with `set_servers()` to run tests without a network:
+//!
+//! ```no_run
+//! use c_ares_test_server::TestServer;
+//!
+//! let server = TestServer::with_fixtures().unwrap();
+//! let mut channel = c_ares::Channel::new().unwrap();
+//! channel.set_servers([server.addr().to_string().as_str()]).unwrap();
+//! ```
+//!
+//! Answers follow CNAMEs within the fixtures, and come with glue for name
+//! servers.  Names that the fixtures know nothing about get `NXDOMAIN`; other
+//! negative answers carry the SOA record of the enclosing zone.
+//!
+//! Alternatively, a [`Recorder`] captures the responses that a channel gets
+//! from real servers, and [`TestServer::replay()`], [`ReplaySockets`] or
+//! [`Simulation::replay()`] answers from that [`Recording`] ever after.
+//!
+//! [`Fault`]s can be injected into the responses for particular names, or for
+//! all of them.
+//!
+//! For tests of timeouts and retries, a [`Simulation`] carries a channel's
+//! queries to simulated servers without any sockets, and follows the retry
+//! schedule on a virtual clock.
+#![cfg(cares1_28)]
+
+#[cfg(cares1_34)]
+mod record;
+mod replay;
+#[cfg(cares1_34)]
+mod sim;
+#[cfg(all(unix, cares1_34))]
+mod sockets;
+mod zone;
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use c_ares::{
+    DnsFlags, DnsOpcode, DnsParseFlags, DnsRcode, DnsRecord, DnsRecordType, DnsSection, SocketType,
+};
+
+#[cfg(cares1_34)]
+pub use crate::record::Recorder;
+pub use crate::replay::Recording;
+#[cfg(cares1_34)]
+pub use crate::sim::{Attempt, Simulation};
+#[cfg(all(unix, cares1_34))]
+pub use crate::sockets::ReplaySockets;
+
+use crate::replay::Replay;
+use crate::zone::{Zone, canonical, empty_response};
+
+// The largest response that we send over UDP: we don't look at EDNS, so stick to the RFC 1035
+// limit and truncate anything bigger.
+const MAX_UDP_SIZE: usize = 512;
+
+// The records that the integration tests expect to find.
+const FIXTURES: &str = include_str!("fixtures.zone");
+
+/// Returns the records that the integration tests look up.
+///
+/// These cover names such as `google.com`, `www.github.com` and
+/// `8.8.8.8.in-addr.arpa`, with records of every type that the tests query.
+pub fn fixtures() -> DnsRecord {
+    let mut fixtures = DnsRecord::new(0, DnsFlags::empty(), DnsOpcode::Query, DnsRcode::NoError)
+        .expect("Failed to create DnsRecord");
+    fixtures
+        .add_zone_str(DnsSection::Answer, FIXTURES, ".")
+        .expect("Failed to parse fixtures");
+    fixtures
+}
+
+/// A fault to inject into the server's responses.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Fault {
+    /// Send UDP responses with the TC bit set and no records, so that the
+    /// client retries over TCP.  TCP responses are unaffected.
+    Truncate,
+
+    /// Wait this long before responding.
+    Delay(Duration),
+
+    /// Respond with `SERVFAIL`.
+    ServFail,
+
+    /// Respond with `REFUSED`.
+    Refused,
+
+    /// Don't respond at all.
+    Drop,
+}
+
+/// A query received by a [`TestServer`].
+#[derive(Clone, Debug)]
+pub struct Request {
+    name: String,
+    record_type: DnsRecordType,
+    transport: SocketType,
+}
+
+impl Request {
+    /// The name that was queried.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The record type that was queried.
+    pub fn record_type(&self) -> DnsRecordType {
+        self.record_type
+    }
+
+    /// The transport that carried the query: `Datagram` for UDP, `Stream` for
+    /// TCP.
+    pub fn transport(&self) -> SocketType {
+        self.transport
+    }
+}
+
+/// An authoritative DNS server, answering from fixture records or from a
+/// recording.
+///
+/// The server runs on background threads, which stop when it is dropped.
+pub struct TestServer {
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+struct Shared {
+    answers: Answers,
+    faults: Mutex<HashMap<Option<String>, Fault>>,
+    requests: Mutex<Vec<Request>>,
+    connections: Mutex<Vec<TcpStream>>,
+    shutdown: AtomicBool,
+}
+
+impl TestServer {
+    /// Start a server that answers from the records in `fixtures`.
+    ///
+    /// Records are taken from every section of `fixtures`.
+    pub fn start(fixtures: &DnsRecord) -> io::Result<Self> {
+        Self::serve(Answers::Zone(Zone::new(fixtures)))
+    }
+
+    /// Start a server that answers from [`fixtures()`].
+    pub fn with_fixtures() -> io::Result<Self> {
+        Self::start(&fixtures())
+    }
+
+    /// Start a server that replays the responses in `recording`.
+    ///
+    /// Each query gets the first response recorded for its question, with the
+    /// query's id.  Queries with no recorded response are refused.
+    pub fn replay(recording: &Recording) -> io::Result<Self> {
+        let replay = Replay::new(recording).map_err(io::Error::other)?;
+        Self::serve(Answers::Replay(replay))
+    }
+
+    fn serve(answers: Answers) -> io::Result<Self> {
+        let (udp, tcp) = bind()?;
+        let addr = udp.local_addr()?;
+        let shared = Arc::new(Shared {
+            answers,
+            faults: Mutex::new(HashMap::new()),
+            requests: Mutex::new(Vec::new()),
+            connections: Mutex::new(Vec::new()),
+            shutdown: AtomicBool::new(false),
+        });
+
+        let udp_shared = Arc::clone(&shared);
+        let tcp_shared = Arc::clone(&shared);
+        let threads = vec![
+            thread::Builder::new()
+                .name("test-server-udp".to_owned())
+                .spawn(move || udp_shared.serve_udp(&udp))?,
+            thread::Builder::new()
+                .name("test-server-tcp".to_owned())
+                .spawn(move || tcp_shared.serve_tcp(&tcp))?,
+        ];
+        Ok(Self {
+            addr,
+            shared,
+            threads,
+        })
+    }
+
+    /// The address that the server listens on, for both UDP and TCP.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Inject `fault` into the responses to queries for `name`.
+    ///
+    /// This replaces any fault previously set for `name`, and takes priority
+    /// over the default fault.
+    pub fn set_fault(&self, name: &str, fault: Fault) -> &Self {
+        self.shared
+            .faults
+            .lock()
+            .unwrap()
+            .insert(Some(canonical(name)), fault);
+        self
+    }
+
+    /// Inject `fault` into the responses to queries for names that have no
+    /// fault of their own.
+    pub fn set_default_fault(&self, fault: Fault) -> &Self {
+        self.shared.faults.lock().unwrap().insert(None, fault);
+        self
+    }
+
+    /// Remove all injected faults.
+    pub fn clear_faults(&self) -> &Self {
+        self.shared.faults.lock().unwrap().clear();
+        self
+    }
+
+    /// The queries that the server has received, in the order that it
+    /// received them.
+    ///
+    /// Queries that the server could not parse are not included.
+    pub fn requests(&self) -> Vec<Request> {
+        self.shared.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::SeqCst);
+
+        // Wake the listening threads, so that they notice the shutdown.
+        if let Ok(socket) = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)) {
+            let _ = socket.send_to(&[], self.addr);
+        }
+        let _ = TcpStream::connect(self.addr);
+        for connection in self.shared.connections.lock().unwrap().drain(..) {
+            let _ = connection.shutdown(Shutdown::Both);
+        }
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Shared {
+    fn serve_udp(&self, socket: &UdpSocket) {
+        let mut buf = [0; 65535];
+        loop {
+            // Errors here can be left over from earlier sends - an ICMP port unreachable from a
+            // client that has gone away, say - so they don't stop the server.
+            let received = socket.recv_from(&mut buf);
+            if self.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+            let Ok((len, peer)) = received else {
+                continue;
+            };
+            let Some((response, delay)) = self.handle(&buf[..len], SocketType::Datagram) else {
+                continue;
+            };
+            if delay.is_zero() {
+                let _ = socket.send_to(&response, peer);
+            } else if let Ok(socket) = socket.try_clone() {
+                thread::spawn(move || {
+                    thread::sleep(delay);
+                    let _ = socket.send_to(&response, peer);
+                });
+            }
+        }
+    }
+
+    fn serve_tcp(self: &Arc<Self>, listener: &TcpListener) {
+        for stream in listener.incoming() {
+            if self.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+            let Ok(stream) = stream else {
+                continue;
+            };
+            if let Ok(clone) = stream.try_clone() {
+                self.connections.lock().unwrap().push(clone);
+            }
+            let shared = Arc::clone(self);
+            thread::spawn(move || shared.serve_connection(stream));
+        }
+    }
+
+    // Messages over TCP are preceded by their length, as a two-byte integer.
+    fn serve_connection(&self, mut stream: TcpStream) {
+        loop {
+            let mut len = [0; 2];
+            if stream.read_exact(&mut len).is_err() {
+                break;
+            }
+            let mut message = vec![0; usize::from(u16::from_be_bytes(len))];
+            if stream.read_exact(&mut message).is_err() {
+                break;
+            }
+            let Some((response, delay)) = self.handle(&message, SocketType::Stream) else {
+                continue;
+            };
+            thread::sleep(delay);
+            let Ok(len) = u16::try_from(response.len()) else {
+                continue;
+            };
+            let mut framed = len.to_be_bytes().to_vec();
+            framed.extend_from_slice(&response);
+            if stream.write_all(&framed).is_err() {
+                break;
+            }
+        }
+    }
+
+    // Returns the response to `message`, and how long to wait before sending it - or `None` if
+    // there should be no response.
+    fn handle(&self, message: &[u8], transport: SocketType) -> Option<(Vec<u8>, Duration)> {
+        let query = DnsRecord::parse(message, DnsParseFlags::empty()).ok()?;
+        let (name, record_type, _) = query.query_get(0).ok()?;
+        self.requests.lock().unwrap().push(Request {
+            name: name.to_owned(),
+            record_type,
+            transport,
+        });
+
+        answer(&self.answers, &query, self.fault(name), transport)
+    }
+
+    fn fault(&self, name: &str) -> Option<Fault> {
+        let faults = self.faults.lock().unwrap();
+        faults
+            .get(&Some(canonical(name)))
+            .or_else(|| faults.get(&None))
+            .copied()
+    }
+}
+
+// Returns the response to `query` with `fault` applied, and how long to wait before sending it -
+// or `None` if there should be no response.
+pub(crate) fn answer(
+    answers: &Answers,
+    query: &DnsRecord,
+    fault: Option<Fault>,
+    transport: SocketType,
+) -> Option<(Vec<u8>, Duration)> {
+    let udp = transport == SocketType::Datagram;
+    let mut delay = Duration::ZERO;
+    let response = match fault {
+        Some(Fault::Drop) => return None,
+        Some(Fault::ServFail) => empty_response(query, DnsRcode::ServFail, DnsFlags::empty()),
+        Some(Fault::Refused) => empty_response(query, DnsRcode::Refused, DnsFlags::empty()),
+        Some(Fault::Truncate) if udp => empty_response(query, DnsRcode::NoError, DnsFlags::TC),
+        Some(Fault::Delay(duration)) => {
+            delay = duration;
+            answers.respond(query)
+        }
+        Some(Fault::Truncate) | None => answers.respond(query),
+    };
+    let response = response
+        .or_else(|_| empty_response(query, DnsRcode::ServFail, DnsFlags::empty()))
+        .ok()?;
+    let mut bytes = response.write().ok()?.to_vec();
+    if udp && bytes.len() > MAX_UDP_SIZE {
+        let truncated = empty_response(query, DnsRcode::NoError, DnsFlags::TC).ok()?;
+        bytes = truncated.write().ok()?.to_vec();
+    }
+    Some((bytes, delay))
+}
+
+// Where the server's answers come from.
+pub(crate) enum Answers {
+    Zone(Zone),
+    Replay(Replay),
+}
+
+impl Answers {
+    fn respond(&self, query: &DnsRecord) -> c_ares::Result<DnsRecord> {
+        match self {
+            Self::Zone(zone) => zone.respond(query),
+            Self::Replay(replay) => replay
+                .respond(query)
+                .unwrap_or_else(|| empty_response(query, DnsRcode::Refused, DnsFlags::empty())),
+        }
+    }
+}
+
+// c-ares talks to a server over UDP and TCP on the same port, so we need a port that is free for
+// both.
+fn bind() -> io::Result<(UdpSocket, TcpListener)> {
+    let mut attempts = 0;
+    loop {
+        let udp = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))?;
+        match TcpListener::bind(udp.local_addr()?) {
+            Ok(tcp) => return Ok((udp, tcp)),
+            Err(_) if attempts < 16 => attempts += 1,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use c_ares::{DnsCls, DnsRrKey};
+
+    fn query(name: &str, record_type: DnsRecordType) -> DnsRecord {
+        let mut query = DnsRecord::new(0x1234, DnsFlags::RD, DnsOpcode::Query, DnsRcode::NoError)
+            .expect("Failed to create DnsRecord");
+        query
+            .query_add(name, record_type, DnsCls::IN)
+            .expect("query_add failed");
+        query
+    }
+
+    // Sends `query` to the server over UDP, and returns the parsed response.
+    fn exchange(server: &TestServer, query: &DnsRecord) -> Option<DnsRecord> {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_millis(500)))
+            .unwrap();
+        socket
+            .send_to(&query.write().unwrap(), server.addr())
+            .unwrap();
+        let mut buf = [0; 65535];
+        let len = socket.recv(&mut buf).ok()?;
+        Some(DnsRecord::parse(&buf[..len], DnsParseFlags::empty()).unwrap())
+    }
+
+    #[test]
+    fn answers_from_fixtures() {
+        let server = TestServer::with_fixtures().unwrap();
+        let response = exchange(&server, &query("google.com", DnsRecordType::MX)).unwrap();
+        assert_eq!(response.id(), 0x1234);
+        assert!(response.flags().contains(DnsFlags::QR | DnsFlags::AA));
+        assert_eq!(response.rcode(), DnsRcode::NoError);
+        let mx = response.rr(DnsSection::Answer, 0).unwrap();
+        assert_eq!(mx.get_str(DnsRrKey::MX_EXCHANGE), Some("smtp.google.com"));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].name(), "google.com");
+        assert_eq!(requests[0].record_type(), DnsRecordType::MX);
+        assert_eq!(requests[0].transport(), SocketType::Datagram);
+    }
+
+    #[test]
+    fn follows_cnames() {
+        let server = TestServer::with_fixtures().unwrap();
+        let response = exchange(&server, &query("www.github.com", DnsRecordType::A)).unwrap();
+        let types: Vec<_> = response
+            .rrs(DnsSection::Answer)
+            .map(|rr| rr.rr_type())
+            .collect();
+        assert_eq!(types, [DnsRecordType::CNAME, DnsRecordType::A]);
+    }
+
+    #[test]
+    fn negative_answers() {
+        let server = TestServer::with_fixtures().unwrap();
+        let response = exchange(&server, &query("nope.google.com", DnsRecordType::A)).unwrap();
+        assert_eq!(response.rcode(), DnsRcode::NXDomain);
+        let soa = response.rr(DnsSection::Authority, 0).unwrap();
+        assert_eq!(soa.rr_type(), DnsRecordType::SOA);
+
+        let response = exchange(&server, &query("smtp.google.com", DnsRecordType::MX)).unwrap();
+        assert_eq!(response.rcode(), DnsRcode::NoError);
+        assert_eq!(response.rr_count(DnsSection::Answer), 0);
+    }
+
+    #[test]
+    fn injected_faults() {
+        let server = TestServer::with_fixtures().unwrap();
+        server.set_fault("google.com", Fault::Refused);
+        server.set_default_fault(Fault::ServFail);
+        let response = exchange(&server, &query("GOOGLE.com", DnsRecordType::A)).unwrap();
+        assert_eq!(response.rcode(), DnsRcode::Refused);
+        let response = exchange(&server, &query("github.com", DnsRecordType::A)).unwrap();
+        assert_eq!(response.rcode(), DnsRcode::ServFail);
+
+        server
+            .clear_faults()
+            .set_fault("google.com", Fault::Truncate);
+        let response = exchange(&server, &query("google.com", DnsRecordType::A)).unwrap();
+        assert!(response.flags().contains(DnsFlags::TC));
+        assert_eq!(response.rr_count(DnsSection::Answer), 0);
+
+        server.set_fault("google.com", Fault::Drop);
+        assert!(exchange(&server, &query("google.com", DnsRecordType::A)).is_none());
+    }
+
+    #[test]
+    fn replays_recording() {
+        let server = TestServer::with_fixtures().unwrap();
+        let mut recording = Recording::new();
+        for record_type in [DnsRecordType::A, DnsRecordType::MX] {
+            let response = exchange(&server, &query("google.com", record_type)).unwrap();
+            recording.push(&response).unwrap();
+        }
+        let text = format!("; Recorded from the fixtures\n\n{recording}");
+        let recording: Recording = text.parse().unwrap();
+        assert_eq!(recording.len(), 2);
+
+        let replay = TestServer::replay(&recording).unwrap();
+        let mut mx = query("Google.COM", DnsRecordType::MX);
+        mx.set_id(0x4321);
+        let response = exchange(&replay, &mx).unwrap();
+        assert_eq!(response.id(), 0x4321);
+        assert_eq!(response.query_get(0).unwrap().0, "Google.COM");
+        let mx = response.rr(DnsSection::Answer, 0).unwrap();
+        assert_eq!(mx.get_str(DnsRrKey::MX_EXCHANGE), Some("smtp.google.com"));
+
+        let response = exchange(&replay, &query("github.com", DnsRecordType::A)).unwrap();
+        assert_eq!(response.rcode(), DnsRcode::Refused);
+    }
+
+    #[test]
+    fn answers_over_tcp() {
+        let server = TestServer::with_fixtures().unwrap();
+        let message = query("google.com", DnsRecordType::A).write().unwrap();
+        let mut stream = TcpStream::connect(server.addr()).unwrap();
+        let mut framed = u16::try_from(message.len()).unwrap().to_be_bytes().to_vec();
+        framed.extend_from_slice(&message);
+        stream.write_all(&framed).unwrap();
+
+        let mut len = [0; 2];
+        stream.read_exact(&mut len).unwrap();
+        let mut response = vec![0; usize::from(u16::from_be_bytes(len))];
+        stream.read_exact(&mut response).unwrap();
+        let response = DnsRecord::parse(&response, DnsParseFlags::empty()).unwrap();
+        assert_eq!(response.rr_count(DnsSection::Answer), 2);
+        assert_eq!(server.requests()[0].transport(), SocketType::Stream);
+    }
+}
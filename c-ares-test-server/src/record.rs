@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use c_ares::{DnsCls, DnsFlags, DnsRecordType, Result, TraceDirection, TraceEvent};
+
+use crate::replay::Recording;
+use crate::zone::canonical;
+
+/// Records the responses that a channel receives, using its trace callback.
+///
+/// ```no_run
+/// use c_ares_test_server::Recorder;
+///
+/// let recorder = Recorder::new();
+/// let mut channel = c_ares::Channel::new().unwrap();
+/// channel.set_trace_callback(recorder.trace_callback()).unwrap();
+///
+/// // ... make queries ...
+///
+/// recorder.recording().unwrap().save("responses.txt").unwrap();
+/// ```
+///
+/// A response is recorded when it answers a query that the channel sent, to
+/// the server that it was sent to.  Truncated responses are not recorded:
+/// c-ares will retry over TCP, and it is the answer to that retry that we want.
+#[derive(Clone, Default)]
+pub struct Recorder {
+    state: Arc<Mutex<RecorderState>>,
+}
+
+#[derive(Default)]
+struct RecorderState {
+    // The questions that have been sent, and not yet answered, by server and query id.
+    pending: HashMap<(SocketAddr, u16), (String, DnsRecordType, DnsCls)>,
+    recording: Recording,
+}
+
+impl Recorder {
+    /// Create a recorder that has not recorded anything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a callback to pass to `set_trace_callback()` on a channel or
+    /// resolver.
+    ///
+    /// The callback may be installed on several channels, in which case the
+    /// recording includes responses from all of them.
+    pub fn trace_callback(&self) -> impl Fn(&TraceEvent<'_>) + Send + Sync + 'static {
+        let state = Arc::clone(&self.state);
+        move |event| {
+            let Ok(message) = event.record() else {
+                return;
+            };
+            let Ok((name, qtype, qclass)) = message.query_get(0) else {
+                return;
+            };
+            let key = (event.server(), event.qid());
+            let question = (canonical(name), qtype, qclass);
+            let mut state = state.lock().unwrap();
+            match event.direction() {
+                TraceDirection::Sent => {
+                    state.pending.insert(key, question);
+                }
+                TraceDirection::Received => {
+                    if message.flags().contains(DnsFlags::TC)
+                        || state.pending.get(&key) != Some(&question)
+                    {
+                        return;
+                    }
+                    state.pending.remove(&key);
+                    let _ = state.recording.push(&message);
+                }
+            }
+        }
+    }
+
+    /// A copy of what has been recorded so far.
+    pub fn recording(&self) -> Result<Recording> {
+        self.state.lock().unwrap().recording.try_clone()
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use c_ares::{DnsCls, DnsRecord, DnsRecordType, DnsSection, Error, Result};
+
+use crate::zone::canonical;
+
+// Each response in a recording starts with the header line written by `DnsRecord`'s `Display`.
+const HEADER: &str = ";; ->>HEADER<<-";
+
+/// DNS responses recorded from a channel, for a [`TestServer`] or
+/// [`ReplaySockets`] to replay.
+///
+/// Each response carries the question that it answers, so a recording pairs
+/// up queries and responses.
+///
+/// A recording is saved as the `dig`-style text of each response in turn, as
+/// written by the `Display` implementation on [`DnsRecord`].  Anything before
+/// the first response is ignored, so the file may start with comments.
+///
+/// [`TestServer`]: crate::TestServer
+/// [`ReplaySockets`]: crate::ReplaySockets
+#[derive(Debug, Default)]
+pub struct Recording {
+    responses: Vec<DnsRecord>,
+}
+
+impl Recording {
+    /// Create an empty recording.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a recording from the file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Write the recording to the file at `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    /// Add a response to the recording.
+    ///
+    /// Any OPT record is dropped: EDNS options such as cookies are particular
+    /// to the exchange in which they were sent, and would be wrong in a replay.
+    pub fn push(&mut self, response: &DnsRecord) -> Result<&mut Self> {
+        let mut response = response.try_clone()?;
+        let opts: Vec<usize> = response
+            .rrs(DnsSection::Additional)
+            .enumerate()
+            .filter(|(_, rr)| rr.rr_type() == DnsRecordType::OPT)
+            .map(|(idx, _)| idx)
+            .collect();
+        for idx in opts.into_iter().rev() {
+            response.rr_del(DnsSection::Additional, idx)?;
+        }
+        self.responses.push(response);
+        Ok(self)
+    }
+
+    /// The recorded responses, in the order that they were recorded.
+    pub fn responses(&self) -> impl Iterator<Item = &DnsRecord> {
+        self.responses.iter()
+    }
+
+    /// The number of recorded responses.
+    pub fn len(&self) -> usize {
+        self.responses.len()
+    }
+
+    /// Whether there are no recorded responses.
+    pub fn is_empty(&self) -> bool {
+        self.responses.is_empty()
+    }
+
+    /// Create a deep copy of this recording.
+    pub fn try_clone(&self) -> Result<Self> {
+        let responses = self
+            .responses
+            .iter()
+            .map(DnsRecord::try_clone)
+            .collect::<Result<_>>()?;
+        Ok(Self { responses })
+    }
+}
+
+impl fmt::Display for Recording {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, response) in self.responses.iter().enumerate() {
+            if idx > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{response}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Recording {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut chunks: Vec<String> = Vec::new();
+        for line in s.lines() {
+            if line.trim_start().starts_with(HEADER) {
+                chunks.push(String::new());
+            }
+            if let Some(chunk) = chunks.last_mut() {
+                chunk.push_str(line);
+                chunk.push('\n');
+            }
+        }
+        let responses = chunks
+            .iter()
+            .map(|chunk| chunk.parse())
+            .collect::<Result<_>>()?;
+        Ok(Self { responses })
+    }
+}
+
+// Answers queries from a recording.
+pub(crate) struct Replay {
+    responses: Vec<(Question, DnsRecord)>,
+}
+
+type Question = (String, DnsRecordType, DnsCls);
+
+impl Replay {
+    pub(crate) fn new(recording: &Recording) -> Result<Self> {
+        let mut responses = Vec::new();
+        for response in recording.responses() {
+            if let Ok((name, qtype, qclass)) = response.query_get(0) {
+                responses.push(((canonical(name), qtype, qclass), response.try_clone()?));
+            }
+        }
+        Ok(Self { responses })
+    }
+
+    // The recorded response to `query`, if there is one.  The first response recorded for a
+    // question is the one that is replayed.
+    pub(crate) fn respond(&self, query: &DnsRecord) -> Option<Result<DnsRecord>> {
+        let (qname, qtype, qclass) = query.query_get(0).ok()?;
+        let question = (canonical(qname), qtype, qclass);
+        let (_, recorded) = self.responses.iter().find(|(q, _)| *q == question)?;
+        Some(recorded.try_clone().and_then(|mut response| {
+            // The query may spell the name in a different case, and clients check that the
+            // response echoes it exactly.
+            response.set_id(query.id()).query_set_name(0, qname)?;
+            Ok(response)
+        }))
+    }
+}
@@ -0,0 +1,483 @@
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use c_ares::{
+    AddressFamily, Channel, DnsParseFlags, DnsRecord, DnsRecordType, FdEventFlags, FdEvents,
+    Options, ProcessFlags, Socket, SocketBindFlags, SocketConnectFlags, SocketFunctions,
+    SocketType,
+};
+
+use crate::replay::{Recording, Replay};
+use crate::zone::Zone;
+use crate::{Answers, Fault, answer, fixtures};
+
+/// A simulated network, carrying a channel's queries to simulated servers in
+/// memory.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use c_ares_test_server::{Fault, Simulation};
+///
+/// let sim = Simulation::with_fixtures();
+/// let server = sim.add_server();
+/// sim.script(server, [Fault::Drop]);
+///
+/// let mut options = c_ares::Options::new();
+/// options
+///     .set_timeout(Duration::from_millis(20))
+///     .set_max_timeout(Duration::from_millis(20))
+///     .set_tries(2);
+/// let mut channel = sim.channel(options).unwrap();
+/// channel.query_a("google.com", |result| assert!(result.is_ok()));
+/// sim.run(&mut channel);
+///
+/// let attempts = sim.attempts();
+/// assert_eq!(attempts.len(), 2);
+/// assert!(attempts[1].at() <= Duration::from_millis(20));
+/// ```
+///
+/// Servers answer from fixture records, like a [`TestServer`], or replay a
+/// [`Recording`] - which, unlike [`TestServer::replay()`], needs no sockets
+/// at all.  Each server
+/// can be given a script of [`Fault`]s, which apply to the queries that it
+/// receives in turn, and a fault for every query after that.
+///
+/// c-ares reads the system clock and has no way to be given another, so the
+/// simulation keeps a virtual clock alongside it.  [`run()`](Self::run) only
+/// ever waits for as long as the channel asks - until its next timeout, or the
+/// next simulated response - and advances the virtual clock by exactly that
+/// much.  Each [`Attempt`] is stamped with the virtual time.
+///
+/// The virtual clock is not deterministic.  When a wait oversleeps, c-ares
+/// sees the extra time on the system clock and asks for a shorter wait next
+/// time, which the virtual clock never sees: so the virtual clock can fall
+/// behind the channel's schedule, but never runs ahead of it.  Assert on the
+/// order of events, and on how long the channel waited at most - not at least.
+///
+/// Tests run in real time, so use timeouts of a few milliseconds; and set the
+/// maximum timeout too, since c-ares otherwise adapts its timeouts to its
+/// measurements of the servers, and will wait for hundreds of milliseconds
+/// however short the timeout.  Keep in mind, too, that a response delayed by
+/// nearly as long as a timeout may arrive on either side of it.
+///
+/// [`TestServer`]: crate::TestServer
+/// [`TestServer::replay()`]: crate::TestServer::replay
+#[derive(Clone)]
+pub struct Simulation {
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    answers: Answers,
+    now: Duration,
+    servers: Vec<SocketAddr>,
+    scripts: HashMap<SocketAddr, VecDeque<Option<Fault>>>,
+    faults: HashMap<SocketAddr, Fault>,
+    sockets: HashMap<Socket, SimSocket>,
+    next_socket: usize,
+    attempts: Vec<Attempt>,
+}
+
+struct SimSocket {
+    connection: usize,
+    transport: SocketType,
+    local: SocketAddr,
+    peer: Option<SocketAddr>,
+    // Bytes sent on a stream, that don't yet make up a whole message.
+    unsent: Vec<u8>,
+    // Data for the socket to receive, with the virtual time at which it arrives.
+    inbound: VecDeque<(Duration, Vec<u8>)>,
+}
+
+/// A query sent by a channel over a [`Simulation`].
+#[derive(Clone, Debug)]
+pub struct Attempt {
+    at: Duration,
+    server: SocketAddr,
+    transport: SocketType,
+    connection: usize,
+    name: String,
+    record_type: DnsRecordType,
+    fault: Option<Fault>,
+}
+
+impl Attempt {
+    /// The virtual time at which the query was sent.
+    pub fn at(&self) -> Duration {
+        self.at
+    }
+
+    /// The server that the query was sent to.
+    pub fn server(&self) -> SocketAddr {
+        self.server
+    }
+
+    /// The transport that carried the query: `Datagram` for UDP, `Stream` for
+    /// TCP.
+    pub fn transport(&self) -> SocketType {
+        self.transport
+    }
+
+    /// Identifies the socket that carried the query.  Each socket that the
+    /// channel opens gets a new number.
+    pub fn connection(&self) -> usize {
+        self.connection
+    }
+
+    /// The name that was queried.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The record type that was queried.
+    pub fn record_type(&self) -> DnsRecordType {
+        self.record_type
+    }
+
+    /// The fault injected into the response, if any.
+    ///
+    /// Queries sent to an address that is not a simulated server are
+    /// reported as [`Fault::Drop`].
+    pub fn fault(&self) -> Option<Fault> {
+        self.fault
+    }
+}
+
+impl Simulation {
+    /// Create a simulation with no servers, which will answer from the records
+    /// in `fixtures`.
+    pub fn new(fixtures: &DnsRecord) -> Self {
+        Self::serve(Answers::Zone(Zone::new(fixtures)))
+    }
+
+    /// Create a simulation with no servers, which will replay the responses in
+    /// `recording`.
+    ///
+    /// Each query gets the first response recorded for its question, with the
+    /// query's id.  Queries with no recorded response are refused.
+    pub fn replay(recording: &Recording) -> c_ares::Result<Self> {
+        Ok(Self::serve(Answers::Replay(Replay::new(recording)?)))
+    }
+
+    fn serve(answers: Answers) -> Self {
+        let state = State {
+            answers,
+            now: Duration::ZERO,
+            servers: Vec::new(),
+            scripts: HashMap::new(),
+            faults: HashMap::new(),
+            sockets: HashMap::new(),
+            next_socket: 1,
+            attempts: Vec::new(),
+        };
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Create a simulation with no servers, which will answer from
+    /// [`fixtures()`].
+    pub fn with_fixtures() -> Self {
+        Self::new(&fixtures())
+    }
+
+    /// Add a server, and return its address.
+    ///
+    /// Channels created by [`channel()`](Self::channel) use the servers in
+    /// the order that they were added.
+    pub fn add_server(&self) -> SocketAddr {
+        let mut state = self.state();
+        let host = u8::try_from(state.servers.len() + 1).expect("Too many servers");
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(198, 51, 100, host)), 53);
+        state.servers.push(addr);
+        addr
+    }
+
+    /// Inject `faults` into the responses to the next queries that `server`
+    /// receives, one query each, in order.  `None` lets a query be answered
+    /// normally.
+    ///
+    /// Scripted faults take priority over the fault set by
+    /// [`set_fault()`](Self::set_fault), and are added to any that have not
+    /// yet been used.
+    pub fn script<I>(&self, server: SocketAddr, faults: I) -> &Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Option<Fault>>,
+    {
+        self.state()
+            .scripts
+            .entry(server)
+            .or_default()
+            .extend(faults.into_iter().map(Into::into));
+        self
+    }
+
+    /// Inject `fault` into the responses to every query that `server`
+    /// receives, once its script has run out.
+    pub fn set_fault(&self, server: SocketAddr, fault: Fault) -> &Self {
+        self.state().faults.insert(server, fault);
+        self
+    }
+
+    /// Remove the fault set for `server`, and any of its script that is left.
+    pub fn clear_fault(&self, server: SocketAddr) -> &Self {
+        let mut state = self.state();
+        state.faults.remove(&server);
+        state.scripts.remove(&server);
+        self
+    }
+
+    /// Create a channel that uses the simulated network, and the simulated
+    /// servers.
+    pub fn channel(&self, mut options: Options) -> c_ares::Result<Channel> {
+        options.set_socket_functions(SimSockets {
+            state: Arc::clone(&self.state),
+        });
+        let mut channel = Channel::with_options(options)?;
+        let servers: Vec<String> = self
+            .state()
+            .servers
+            .iter()
+            .map(SocketAddr::to_string)
+            .collect();
+        channel.set_servers(servers)?;
+        Ok(channel)
+    }
+
+    /// Drive `channel` until it has no queries left.
+    ///
+    /// Simulated responses are delivered when the virtual clock reaches them,
+    /// and the channel's timeouts are processed as the clock passes them.
+    pub fn run(&self, channel: &mut Channel) {
+        loop {
+            let events = self.events(channel);
+            if !events.is_empty() {
+                let _ = channel.process_fds(&events, ProcessFlags::empty());
+                continue;
+            }
+            let Some(timeout) = channel.timeout(None) else {
+                break;
+            };
+            let wait = self
+                .next_delivery()
+                .map_or(timeout, |delivery| delivery.min(timeout));
+            thread::sleep(wait);
+            self.state().now += wait;
+            let _ = channel.process_fds(&[], ProcessFlags::empty());
+        }
+    }
+
+    /// The time on the virtual clock, which starts at zero.
+    pub fn now(&self) -> Duration {
+        self.state().now
+    }
+
+    /// The queries that channels have sent, in the order that they sent them.
+    pub fn attempts(&self) -> Vec<Attempt> {
+        self.state().attempts.clone()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    // The sockets that the channel should look at now: those with data that has arrived, and
+    // those that the channel wants to write to, which is always possible.
+    fn events(&self, channel: &Channel) -> Vec<FdEvents> {
+        let state = self.state();
+        let mut flags: HashMap<Socket, FdEventFlags> = state
+            .sockets
+            .iter()
+            .filter(|(_, socket)| {
+                socket
+                    .inbound
+                    .front()
+                    .is_some_and(|(at, _)| *at <= state.now)
+            })
+            .map(|(fd, _)| (*fd, FdEventFlags::READ))
+            .collect();
+        for (fd, _, writable) in &channel.sockets() {
+            if writable {
+                *flags.entry(fd).or_insert(FdEventFlags::empty()) |= FdEventFlags::WRITE;
+            }
+        }
+        flags
+            .into_iter()
+            .map(|(fd, flags)| FdEvents::new(fd, flags))
+            .collect()
+    }
+
+    // How long until the next response arrives, if one is on its way.
+    fn next_delivery(&self) -> Option<Duration> {
+        let state = self.state();
+        state
+            .sockets
+            .values()
+            .filter_map(|socket| socket.inbound.front())
+            .map(|(at, _)| at.saturating_sub(state.now))
+            .min()
+    }
+}
+
+impl State {
+    fn socket(&mut self, fd: Socket) -> io::Result<&mut SimSocket> {
+        self.sockets
+            .get_mut(&fd)
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    // Takes a query from `fd`, and queues any response to it.
+    fn deliver(&mut self, fd: Socket, message: &[u8]) -> io::Result<()> {
+        let now = self.now;
+        let socket = self.socket(fd)?;
+        let (connection, transport) = (socket.connection, socket.transport);
+        let server = socket.peer.ok_or(io::ErrorKind::NotConnected)?;
+        let Ok(query) = DnsRecord::parse(message, DnsParseFlags::empty()) else {
+            return Ok(());
+        };
+        let Ok((name, record_type, _)) = query.query_get(0) else {
+            return Ok(());
+        };
+
+        let fault = if self.servers.contains(&server) {
+            self.scripts
+                .get_mut(&server)
+                .and_then(VecDeque::pop_front)
+                .unwrap_or_else(|| self.faults.get(&server).copied())
+        } else {
+            Some(Fault::Drop)
+        };
+        self.attempts.push(Attempt {
+            at: now,
+            server,
+            transport,
+            connection,
+            name: name.to_owned(),
+            record_type,
+            fault,
+        });
+
+        let Some((mut response, delay)) = answer(&self.answers, &query, fault, transport) else {
+            return Ok(());
+        };
+        if transport == SocketType::Stream {
+            let Ok(len) = u16::try_from(response.len()) else {
+                return Ok(());
+            };
+            response.splice(0..0, len.to_be_bytes());
+        }
+        self.socket(fd)?.inbound.push_back((now + delay, response));
+        Ok(())
+    }
+}
+
+// The socket functions that carry a channel's traffic over the simulated network.
+struct SimSockets {
+    state: Arc<Mutex<State>>,
+}
+
+impl SocketFunctions for SimSockets {
+    fn open(&self, family: AddressFamily, socket_type: SocketType) -> io::Result<Socket> {
+        let mut state = self.state.lock().unwrap();
+        let connection = state.next_socket;
+        state.next_socket += 1;
+        let port = u16::try_from(1024 + connection).map_err(|_| io::ErrorKind::AddrInUse)?;
+        let ip = match family {
+            AddressFamily::INET => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            AddressFamily::INET6 => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            AddressFamily::UNSPEC => return Err(io::ErrorKind::InvalidInput.into()),
+        };
+        let fd = Socket::try_from(connection).map_err(|_| io::ErrorKind::OutOfMemory)?;
+        state.sockets.insert(
+            fd,
+            SimSocket {
+                connection,
+                transport: socket_type,
+                local: SocketAddr::new(ip, port),
+                peer: None,
+                unsent: Vec::new(),
+                inbound: VecDeque::new(),
+            },
+        );
+        Ok(fd)
+    }
+
+    fn close(&self, socket: Socket) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.sockets.remove(&socket);
+        Ok(())
+    }
+
+    fn connect(
+        &self,
+        socket: Socket,
+        addr: &SocketAddr,
+        _flags: SocketConnectFlags,
+    ) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.socket(socket)?.peer = Some(*addr);
+        Ok(())
+    }
+
+    fn recvfrom(&self, socket: Socket, buf: &mut [u8]) -> io::Result<(usize, Option<SocketAddr>)> {
+        let mut state = self.state.lock().unwrap();
+        let now = state.now;
+        let socket = state.socket(socket)?;
+        let Some((at, data)) = socket.inbound.pop_front() else {
+            return Err(io::ErrorKind::WouldBlock.into());
+        };
+        if at > now {
+            socket.inbound.push_front((at, data));
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        let len = data.len().min(buf.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        // A stream keeps whatever didn't fit, for the next read; a datagram loses it.
+        if socket.transport == SocketType::Stream && len < data.len() {
+            socket.inbound.push_front((at, data[len..].to_vec()));
+        }
+        Ok((len, socket.peer))
+    }
+
+    fn sendto(&self, socket: Socket, buf: &[u8], _addr: Option<&SocketAddr>) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        let sim_socket = state.socket(socket)?;
+        if sim_socket.transport == SocketType::Datagram {
+            state.deliver(socket, buf)?;
+            return Ok(buf.len());
+        }
+
+        // Messages over TCP are preceded by their length, as a two-byte integer.
+        sim_socket.unsent.extend_from_slice(buf);
+        loop {
+            let sim_socket = state.socket(socket)?;
+            let Some(len) = sim_socket
+                .unsent
+                .get(..2)
+                .map(|len| usize::from(u16::from_be_bytes([len[0], len[1]])))
+            else {
+                break;
+            };
+            if sim_socket.unsent.len() < 2 + len {
+                break;
+            }
+            let message: Vec<u8> = sim_socket.unsent.drain(..2 + len).skip(2).collect();
+            state.deliver(socket, &message)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn getsockname(&self, socket: Socket) -> io::Result<SocketAddr> {
+        let mut state = self.state.lock().unwrap();
+        Ok(state.socket(socket)?.local)
+    }
+
+    fn bind(&self, _socket: Socket, _addr: &SocketAddr, _flags: SocketBindFlags) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::os::fd::AsRawFd;
+use std::os::unix::net::{UnixDatagram, UnixStream};
+use std::sync::Mutex;
+
+use c_ares::{
+    AddressFamily, DnsFlags, DnsParseFlags, DnsRcode, DnsRecord, Socket, SocketBindFlags,
+    SocketConnectFlags, SocketFunctions, SocketType,
+};
+
+use crate::replay::{Recording, Replay};
+use crate::zone::empty_response;
+
+/// Socket functions that answer a channel's queries from a [`Recording`],
+/// without any network.
+///
+/// ```no_run
+/// use c_ares_test_server::{Recording, ReplaySockets};
+///
+/// let recording = Recording::load("responses.txt").unwrap();
+/// let mut options = c_ares::Options::new();
+/// options.set_socket_functions(ReplaySockets::new(&recording).unwrap());
+/// let channel = c_ares::Channel::with_options(options).unwrap();
+/// ```
+///
+/// Each socket that c-ares opens is one end of a Unix socket pair, and the
+/// responses are written to the other end.  So the sockets are real file
+/// descriptors, and can be watched by any event loop - the c-ares event thread
+/// included.  The servers that the channel is configured with are never
+/// contacted: every query is answered as though by the server it was sent to.
+///
+/// As with [`TestServer::replay()`], each query gets the first response
+/// recorded for its question, with the query's id.  Queries with no recorded
+/// response are refused.
+///
+/// [`TestServer::replay()`]: crate::TestServer::replay
+pub struct ReplaySockets {
+    replay: Replay,
+    sockets: Mutex<HashMap<Socket, ReplaySocket>>,
+}
+
+// A socket handed out to c-ares, and the end of the pair on which we answer it.
+struct ReplaySocket {
+    pair: Pair,
+    local: SocketAddr,
+    peer: Option<SocketAddr>,
+
+    // Bytes sent over a stream that don't yet make up a whole message.
+    unsent: Vec<u8>,
+}
+
+enum Pair {
+    Datagram(UnixDatagram, UnixDatagram),
+    Stream(UnixStream, UnixStream),
+}
+
+impl ReplaySockets {
+    /// Create socket functions that replay the responses in `recording`.
+    pub fn new(recording: &Recording) -> c_ares::Result<Self> {
+        Ok(Self {
+            replay: Replay::new(recording)?,
+            sockets: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // The response to `message`, if it is a query.
+    fn respond(&self, message: &[u8]) -> Option<Vec<u8>> {
+        let query = DnsRecord::parse(message, DnsParseFlags::empty()).ok()?;
+        let response = self
+            .replay
+            .respond(&query)
+            .unwrap_or_else(|| empty_response(&query, DnsRcode::Refused, DnsFlags::empty()))
+            .ok()?;
+        Some(response.write().ok()?.to_vec())
+    }
+}
+
+impl SocketFunctions for ReplaySockets {
+    fn open(&self, family: AddressFamily, socket_type: SocketType) -> io::Result<Socket> {
+        let ip = match family {
+            AddressFamily::INET => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            AddressFamily::INET6 => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            AddressFamily::UNSPEC => return Err(io::ErrorKind::InvalidInput.into()),
+        };
+        let (pair, fd) = match socket_type {
+            SocketType::Datagram => {
+                let (ours, theirs) = UnixDatagram::pair()?;
+                ours.set_nonblocking(true)?;
+                let fd = ours.as_raw_fd();
+                (Pair::Datagram(ours, theirs), fd)
+            }
+            SocketType::Stream => {
+                let (ours, theirs) = UnixStream::pair()?;
+                ours.set_nonblocking(true)?;
+                let fd = ours.as_raw_fd();
+                (Pair::Stream(ours, theirs), fd)
+            }
+        };
+        let port = u16::try_from(fd).map_or(u16::MAX, |fd| fd.saturating_add(1024));
+        let socket = ReplaySocket {
+            pair,
+            local: SocketAddr::new(ip, port),
+            peer: None,
+            unsent: Vec::new(),
+        };
+        self.sockets.lock().unwrap().insert(fd, socket);
+        Ok(fd)
+    }
+
+    fn close(&self, socket: Socket) -> io::Result<()> {
+        self.sockets
+            .lock()
+            .unwrap()
+            .remove(&socket)
+            .map(drop)
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    fn connect(
+        &self,
+        socket: Socket,
+        addr: &SocketAddr,
+        _flags: SocketConnectFlags,
+    ) -> io::Result<()> {
+        let mut sockets = self.sockets.lock().unwrap();
+        let socket = sockets.get_mut(&socket).ok_or(io::ErrorKind::NotFound)?;
+        socket.peer = Some(*addr);
+        Ok(())
+    }
+
+    fn recvfrom(&self, socket: Socket, buf: &mut [u8]) -> io::Result<(usize, Option<SocketAddr>)> {
+        let mut sockets = self.sockets.lock().unwrap();
+        let socket = sockets.get_mut(&socket).ok_or(io::ErrorKind::NotFound)?;
+        let len = match &mut socket.pair {
+            Pair::Datagram(ours, _) => ours.recv(buf)?,
+            Pair::Stream(ours, _) => ours.read(buf)?,
+        };
+        Ok((len, socket.peer))
+    }
+
+    fn sendto(&self, socket: Socket, buf: &[u8], _addr: Option<&SocketAddr>) -> io::Result<usize> {
+        let mut sockets = self.sockets.lock().unwrap();
+        let socket = sockets.get_mut(&socket).ok_or(io::ErrorKind::NotFound)?;
+        match &mut socket.pair {
+            Pair::Datagram(_, theirs) => {
+                if let Some(response) = self.respond(buf) {
+                    theirs.send(&response)?;
+                }
+            }
+
+            // Messages over TCP are preceded by their length, as a two-byte integer.
+            Pair::Stream(_, theirs) => {
+                socket.unsent.extend_from_slice(buf);
+                while let Some(len) = socket.unsent.get(..2) {
+                    let len = usize::from(u16::from_be_bytes([len[0], len[1]]));
+                    if socket.unsent.len() < 2 + len {
+                        break;
+                    }
+                    let message: Vec<u8> = socket.unsent.drain(..2 + len).skip(2).collect();
+                    let Some(response) = self.respond(&message) else {
+                        continue;
+                    };
+                    let Ok(len) = u16::try_from(response.len()) else {
+                        continue;
+                    };
+                    theirs.write_all(&len.to_be_bytes())?;
+                    theirs.write_all(&response)?;
+                }
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn getsockname(&self, socket: Socket) -> io::Result<SocketAddr> {
+        let sockets = self.sockets.lock().unwrap();
+        let socket = sockets.get(&socket).ok_or(io::ErrorKind::NotFound)?;
+        Ok(socket.local)
+    }
+
+    fn bind(&self, socket: Socket, addr: &SocketAddr, _flags: SocketBindFlags) -> io::Result<()> {
+        let mut sockets = self.sockets.lock().unwrap();
+        let socket = sockets.get_mut(&socket).ok_or(io::ErrorKind::NotFound)?;
+        socket.local = *addr;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+    use crate::zone::Zone;
+    use c_ares::{DnsCls, DnsOpcode, DnsRecordType};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    // A recording of google.com's MX records.
+    fn recording() -> Recording {
+        let mut query = DnsRecord::new(0, DnsFlags::RD, DnsOpcode::Query, DnsRcode::NoError)
+            .expect("Failed to create DnsRecord");
+        query
+            .query_add("google.com", DnsRecordType::MX, DnsCls::IN)
+            .expect("query_add failed");
+        let response = Zone::new(&fixtures()).respond(&query).unwrap();
+        let mut recording = Recording::new();
+        recording.push(&response).unwrap();
+        recording
+    }
+
+    // Query for google.com's MX records over a channel using `sockets`, driven by the c-ares
+    // event thread.
+    fn query_mx(mut options: c_ares::Options, sockets: ReplaySockets) -> c_ares::Result<String> {
+        options
+            .set_event_thread(c_ares::EventSys::Default)
+            .set_socket_functions(sockets);
+        let mut channel = c_ares::Channel::with_options(options).unwrap();
+        channel.set_servers(["192.0.2.53"]).unwrap();
+        let (tx, rx) = mpsc::channel();
+        channel.query_mx("google.com", move |result| {
+            tx.send(result.map(|mx| mx.to_string())).unwrap();
+        });
+        rx.recv_timeout(Duration::from_secs(5)).unwrap()
+    }
+
+    #[test]
+    fn replays_over_udp() {
+        let sockets = ReplaySockets::new(&recording()).unwrap();
+        let result = query_mx(c_ares::Options::new(), sockets).unwrap();
+        assert!(result.contains("smtp.google.com"));
+    }
+
+    #[test]
+    fn replays_over_tcp() {
+        let mut options = c_ares::Options::new();
+        options.set_flags(c_ares::Flags::USEVC);
+        let sockets = ReplaySockets::new(&recording()).unwrap();
+        let result = query_mx(options, sockets).unwrap();
+        assert!(result.contains("smtp.google.com"));
+    }
+
+    #[test]
+    fn refuses_unrecorded_queries() {
+        let mut options = c_ares::Options::new();
+        options.set_tries(1);
+        let sockets = ReplaySockets::new(&Recording::new()).unwrap();
+        let result = query_mx(options, sockets);
+        assert_eq!(result, Err(c_ares::Error::EREFUSED));
+    }
+}
@@ -0,0 +1,180 @@
+use c_ares::{
+    DnsFlags, DnsOpcode, DnsRcode, DnsRecord, DnsRecordType, DnsRrKey, DnsSection, Result,
+};
+
+// Limits how many CNAMEs we follow, so that a loop in the fixtures doesn't hang the server.
+const MAX_CNAME_CHAIN: usize = 8;
+
+// A resource record from the fixtures, kept in presentation format so that it can be copied into
+// responses.
+struct Entry {
+    owner: String,
+    rr_type: DnsRecordType,
+    target: Option<String>,
+    text: String,
+}
+
+// The records that the server is authoritative for.
+pub(crate) struct Zone {
+    entries: Vec<Entry>,
+}
+
+impl Zone {
+    pub(crate) fn new(fixtures: &DnsRecord) -> Self {
+        let sections = [
+            DnsSection::Answer,
+            DnsSection::Authority,
+            DnsSection::Additional,
+        ];
+        let entries = sections
+            .into_iter()
+            .flat_map(|section| fixtures.rrs(section))
+            .filter(|rr| rr.rr_type() != DnsRecordType::OPT)
+            .map(|rr| {
+                let target = match rr.rr_type() {
+                    DnsRecordType::CNAME => rr.get_str(DnsRrKey::CNAME_CNAME),
+                    DnsRecordType::NS => rr.get_str(DnsRrKey::NS_NSDNAME),
+                    _ => None,
+                };
+                Entry {
+                    owner: canonical(rr.name()),
+                    rr_type: rr.rr_type(),
+                    target: target.map(canonical),
+                    text: rr.to_string(),
+                }
+            })
+            .collect();
+        Self { entries }
+    }
+
+    // Builds the authoritative response to a question.
+    pub(crate) fn respond(&self, query: &DnsRecord) -> Result<DnsRecord> {
+        let (qname, qtype, qclass) = query.query_get(0)?;
+        let mut answers = Vec::new();
+        let mut name = canonical(qname);
+        for _ in 0..MAX_CNAME_CHAIN {
+            let matching: Vec<&Entry> = self
+                .records(&name)
+                .filter(|entry| qtype == DnsRecordType::ANY || entry.rr_type == qtype)
+                .collect();
+            if !matching.is_empty() {
+                answers.extend(matching);
+                break;
+            }
+            let Some(cname) = self
+                .records(&name)
+                .find(|entry| entry.rr_type == DnsRecordType::CNAME)
+            else {
+                break;
+            };
+            answers.push(cname);
+            match &cname.target {
+                Some(target) => name = target.clone(),
+                None => break,
+            }
+        }
+
+        let rcode = if answers.is_empty() && !self.exists(&name) {
+            DnsRcode::NXDomain
+        } else {
+            DnsRcode::NoError
+        };
+        let mut response = response(query, rcode, DnsFlags::empty())?;
+        response.query_add(qname, qtype, qclass)?;
+        for entry in &answers {
+            response.add_rr_str(DnsSection::Answer, &entry.text)?;
+        }
+
+        if answers.is_empty() {
+            // Negative answers carry the SOA of the enclosing zone, so that they can be cached.
+            if let Some(soa) = self.soa(&name) {
+                response.add_rr_str(DnsSection::Authority, &soa.text)?;
+            }
+        } else {
+            // Glue for any name servers in the answer.
+            let glue = answers
+                .iter()
+                .filter(|entry| entry.rr_type == DnsRecordType::NS)
+                .filter_map(|entry| entry.target.as_deref())
+                .flat_map(|target| self.records(target))
+                .filter(|entry| matches!(entry.rr_type, DnsRecordType::A | DnsRecordType::AAAA));
+            for entry in glue {
+                response.add_rr_str(DnsSection::Additional, &entry.text)?;
+            }
+        }
+        Ok(response)
+    }
+
+    fn records<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a Entry> {
+        self.entries.iter().filter(move |entry| entry.owner == name)
+    }
+
+    // Whether `name` owns any records, or has descendants that do.
+    fn exists(&self, name: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| is_subdomain(&entry.owner, name))
+    }
+
+    // The SOA record of the closest zone containing `name`.
+    fn soa(&self, name: &str) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.rr_type == DnsRecordType::SOA)
+            .filter(|entry| is_subdomain(name, &entry.owner))
+            .max_by_key(|entry| entry.owner.len())
+    }
+}
+
+// Builds a response to `query` with the given rcode and extra flags, and nothing but the question
+// in it.
+pub(crate) fn empty_response(
+    query: &DnsRecord,
+    rcode: DnsRcode,
+    flags: DnsFlags,
+) -> Result<DnsRecord> {
+    let mut response = response(query, rcode, flags)?;
+    for (name, qtype, qclass) in query.queries() {
+        response.query_add(name, qtype, qclass)?;
+    }
+    Ok(response)
+}
+
+fn response(query: &DnsRecord, rcode: DnsRcode, flags: DnsFlags) -> Result<DnsRecord> {
+    let flags = flags | DnsFlags::QR | DnsFlags::AA | (query.flags() & DnsFlags::RD);
+    DnsRecord::new(query.id(), flags, DnsOpcode::Query, rcode)
+}
+
+// Names compare case-insensitively, and with or without a trailing dot.
+pub(crate) fn canonical(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+// Whether `name` is `parent` or lies beneath it.
+fn is_subdomain(name: &str, parent: &str) -> bool {
+    parent.is_empty()
+        || name == parent
+        || name
+            .strip_suffix(parent)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subdomains() {
+        assert!(is_subdomain("www.example.com", "example.com"));
+        assert!(is_subdomain("example.com", "example.com"));
+        assert!(is_subdomain("example.com", ""));
+        assert!(!is_subdomain("badexample.com", "example.com"));
+        assert!(!is_subdomain("example.com", "www.example.com"));
+    }
+
+    #[test]
+    fn canonical_names() {
+        assert_eq!(canonical("WWW.Example.COM."), "www.example.com");
+        assert_eq!(canonical("example.com"), "example.com");
+    }
+}
@@ -7,6 +7,7 @@ use itertools::Itertools;
 
 use crate::error::{Error, Result};
 use crate::panic;
+use crate::query_handle::{Outcome, take_pending};
 use crate::types::AddressFamily;
 use crate::utils::{hostname_as_str, ipv4_from_in_addr, ipv6_from_in6_addr, sockaddr_in6_scope_id};
 
@@ -345,7 +346,9 @@ pub(crate) unsafe extern "C" fn get_addrinfo_callback<F>(
     } else {
         Err(Error::from(status))
     };
-    let handler = unsafe { Box::from_raw(arg.cast::<F>()) };
+    let Some(handler) = (unsafe { take_pending::<F>(arg, &Outcome::Status(status)) }) else {
+        return;
+    };
     panic::abort_on_panic(|| handler(result));
 }
 
@@ -0,0 +1,189 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use super::Channel;
+use crate::error::{Error, Result};
+use crate::events::{FdEventFlags, FdEvents, ProcessFlags};
+
+// The longest that `run_until_idle()` waits at a time when it has no deadline.
+const MAX_WAIT: Duration = Duration::from_secs(1);
+
+impl Channel {
+    /// Wait up to `max_wait` for activity on the channel's sockets, then process that activity
+    /// along with any timeouts that have expired.
+    ///
+    /// The wait ends early when a socket becomes ready, or when the channel's next timeout is due.
+    /// If the channel has no queries pending, this returns at once.
+    ///
+    /// Returns whether the channel still has queries pending.
+    ///
+    /// This provides a simple single-threaded way to drive a channel that does not use the
+    /// built-in event thread.  Applications with an event loop of their own should instead wait
+    /// on [`sockets()`](Self::sockets) and call [`process_fds()`](Self::process_fds) themselves.
+    ///
+    /// The channel's sockets are waited on as operating system sockets, so custom
+    /// [`SocketFunctions`](crate::SocketFunctions) must hand out real file descriptors to be
+    /// driven this way.  Sockets that cannot be waited on are skipped: queries on them can only
+    /// time out.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::EFILE`] if waiting on the sockets fails, and otherwise any error from
+    /// `process_fds()`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// let mut channel = c_ares::Channel::new().unwrap();
+    /// channel.query_a("example.com", |result| println!("{result:?}"));
+    /// while channel.poll_once(Duration::from_millis(100)).unwrap() {}
+    /// ```
+    pub fn poll_once(&mut self, max_wait: Duration) -> Result<bool> {
+        self.with_poller(|channel, poller| channel.poll_once_with(poller, max_wait))
+    }
+
+    /// Drive the channel until it has no queries pending, or `deadline` passes.
+    ///
+    /// Pass `None` to wait for as long as it takes.  Since every query eventually completes or
+    /// times out, this always returns.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ETIMEOUT`] if queries are still pending at the deadline, and otherwise
+    /// any error from [`poll_once()`](Self::poll_once).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let mut channel = c_ares::Channel::new().unwrap();
+    /// channel.query_a("example.com", |result| println!("{result:?}"));
+    /// channel
+    ///     .run_until_idle(Some(Instant::now() + Duration::from_secs(5)))
+    ///     .unwrap();
+    /// ```
+    pub fn run_until_idle(&mut self, deadline: Option<Instant>) -> Result<()> {
+        self.with_poller(|channel, poller| channel.run_until_idle_with(poller, deadline))
+    }
+
+    // Run `f` with the channel's poller, creating it if this is the first use.
+    fn with_poller<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self, &polling::Poller) -> Result<T>,
+    ) -> Result<T> {
+        let poller = match self.poller.take() {
+            Some(poller) => poller,
+            None => polling::Poller::new().map_err(|_| Error::EFILE)?,
+        };
+        let result = f(self, &poller);
+        self.poller = Some(poller);
+        result
+    }
+
+    // As `poll_once()`, waiting on `poller`.
+    pub(super) fn poll_once_with(
+        &mut self,
+        poller: &polling::Poller,
+        max_wait: Duration,
+    ) -> Result<bool> {
+        if self.queue_active_queries() == 0 {
+            return Ok(false);
+        }
+
+        // Send anything held back for a pending write callback.
+        self.process_pending_write();
+
+        let timeout = self.timeout(Some(max_wait)).unwrap_or(max_wait);
+        let events = self.wait(poller, timeout).map_err(|_| Error::EFILE)?;
+        self.process_fds(&events, ProcessFlags::empty())?;
+        Ok(self.queue_active_queries() > 0)
+    }
+
+    // As `run_until_idle()`, waiting on `poller`.
+    pub(super) fn run_until_idle_with(
+        &mut self,
+        poller: &polling::Poller,
+        deadline: Option<Instant>,
+    ) -> Result<()> {
+        loop {
+            let max_wait = match deadline {
+                None => MAX_WAIT,
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return if self.queue_active_queries() == 0 {
+                            Ok(())
+                        } else {
+                            Err(Error::ETIMEOUT)
+                        };
+                    }
+                    deadline - now
+                }
+            };
+            if !self.poll_once_with(poller, max_wait)? {
+                return Ok(());
+            }
+        }
+    }
+
+    // Wait up to `timeout` on `poller` for the channel's sockets to become ready.
+    //
+    // The sockets are registered for the wait and removed again afterwards, which saves tracking
+    // which of them c-ares has since closed.  Sockets that the poller won't accept - such as those
+    // from socket functions that don't hand out file descriptors - are left out.
+    fn wait(&self, poller: &polling::Poller, timeout: Duration) -> io::Result<Vec<FdEvents>> {
+        let mut registered = Vec::new();
+        let mut events = polling::Events::new();
+        for (socket, readable, writable) in &self.sockets() {
+            let Ok(key) = usize::try_from(socket) else {
+                continue;
+            };
+            let event = polling::Event::new(key, readable, writable);
+            // Safety: c-ares is telling us that this socket is open, and nothing here closes it
+            // before it is removed from the poller again.
+            if unsafe { poller.add(socket, event) }.is_ok() {
+                registered.push(socket);
+            }
+        }
+        let result = match poller.wait(&mut events, Some(timeout)) {
+            Err(err) if err.kind() != io::ErrorKind::Interrupted => Err(err),
+            _ => Ok(()),
+        };
+        for socket in registered {
+            // Safety: the socket is still open, as above.
+            let _ = poller.delete(unsafe { borrow_socket(socket) });
+        }
+        result?;
+
+        let fd_events = events
+            .iter()
+            .map(|event| {
+                let socket = crate::Socket::try_from(event.key).unwrap();
+                let mut flags = FdEventFlags::empty();
+                if event.readable {
+                    flags.insert(FdEventFlags::READ);
+                }
+                if event.writable {
+                    flags.insert(FdEventFlags::WRITE);
+                }
+                FdEvents::new(socket, flags)
+            })
+            .collect();
+        Ok(fd_events)
+    }
+}
+
+#[cfg(unix)]
+// Safety: `socket` must be open, and stay open for as long as the result is used.
+unsafe fn borrow_socket(socket: crate::Socket) -> std::os::fd::BorrowedFd<'static> {
+    unsafe { std::os::fd::BorrowedFd::borrow_raw(socket) }
+}
+
+#[cfg(windows)]
+// Safety: `socket` must be open, and stay open for as long as the result is used.
+unsafe fn borrow_socket(socket: crate::Socket) -> std::os::windows::io::BorrowedSocket<'static> {
+    unsafe { std::os::windows::io::BorrowedSocket::borrow_raw(socket as _) }
+}
@@ -0,0 +1,440 @@
+use core::ffi::{c_char, c_int};
+use std::ffi::CStr;
+use std::mem;
+use std::slice;
+use std::time::Duration;
+
+use super::options::Options;
+#[cfg(cares1_29)]
+use super::options::ServerFailoverOptions;
+use crate::Flags;
+use crate::error::{Error, Result};
+
+/// A snapshot of the options that a `Channel` was configured with.
+///
+/// Obtain one with [`Channel::effective_options`](crate::Channel::effective_options).
+///
+/// c-ares reports only the options that were set explicitly.  It offers no way to read back the
+/// values that it picked up from the system configuration - eg resolv.conf - so those options are
+/// `None` or empty here, and the channel uses whatever c-ares loaded for them.
+///
+/// The snapshot converts into an [`Options`], so that a new channel can be created with the same
+/// configuration, perhaps with some settings changed.
+#[derive(Clone, Debug)]
+pub struct EffectiveOptions {
+    flags: Flags,
+    timeout: Option<Duration>,
+    tries: Option<u32>,
+    ndots: Option<u32>,
+    udp_port: Option<u16>,
+    tcp_port: Option<u16>,
+    sock_send_buffer_size: Option<u32>,
+    sock_receive_buffer_size: Option<u32>,
+    domains: Vec<String>,
+    lookups: Option<String>,
+    sortlist: Vec<String>,
+    rotate: Option<bool>,
+    ednspsz: Option<u32>,
+    resolvconf_path: Option<String>,
+    #[cfg(cares1_19)]
+    hosts_path: Option<String>,
+    #[cfg(cares1_20)]
+    udp_max_queries: Option<u32>,
+    #[cfg(cares1_22)]
+    max_timeout: Option<Duration>,
+    #[cfg(cares1_23)]
+    query_cache_max_ttl: Option<u32>,
+    #[cfg(cares1_29)]
+    server_failover_options: Option<ServerFailoverOptions>,
+}
+
+impl EffectiveOptions {
+    // Read the options from a channel with `ares_save_options()`.  c-ares has no public
+    // representation of a sortlist, so the caller supplies the one it configured.
+    pub(super) fn from_channel(
+        channel: *const c_ares_sys::ares_channel_t,
+        sortlist: &[String],
+    ) -> Result<Self> {
+        let mut ares_options: c_ares_sys::ares_options =
+            unsafe { mem::MaybeUninit::zeroed().assume_init() };
+        let mut optmask = 0;
+        let rc = unsafe {
+            c_ares_sys::ares_save_options(channel, &raw mut ares_options, &raw mut optmask)
+        };
+        let result = if rc == c_ares_sys::ares_status_t::ARES_SUCCESS as i32 {
+            Ok(unsafe { Self::from_raw(&ares_options, optmask, sortlist.to_vec()) })
+        } else {
+            Err(Error::from(rc))
+        };
+
+        // Even on failure, `ares_save_options()` may have allocated some fields.
+        unsafe { c_ares_sys::ares_destroy_options(&raw mut ares_options) }
+        result
+    }
+
+    unsafe fn from_raw(
+        options: &c_ares_sys::ares_options,
+        optmask: c_int,
+        sortlist: Vec<String>,
+    ) -> Self {
+        let has = |opt: c_int| optmask & opt != 0;
+        let positive = |value: c_int| u32::try_from(value).ok().filter(|&v| v > 0);
+
+        let domains = if has(c_ares_sys::ARES_OPT_DOMAINS) && !options.domains.is_null() {
+            let len = usize::try_from(options.ndomains).unwrap_or(0);
+            unsafe { slice::from_raw_parts(options.domains, len) }
+                .iter()
+                .filter_map(|&domain| unsafe { owned_string(domain) })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let rotate = if has(c_ares_sys::ARES_OPT_ROTATE) {
+            Some(true)
+        } else if has(c_ares_sys::ARES_OPT_NOROTATE) {
+            Some(false)
+        } else {
+            None
+        };
+
+        Self {
+            flags: Flags::from_bits_truncate(options.flags),
+            timeout: u64::try_from(options.timeout)
+                .ok()
+                .filter(|_| has(c_ares_sys::ARES_OPT_TIMEOUTMS))
+                .map(Duration::from_millis),
+            tries: u32::try_from(options.tries)
+                .ok()
+                .filter(|_| has(c_ares_sys::ARES_OPT_TRIES)),
+            ndots: u32::try_from(options.ndots)
+                .ok()
+                .filter(|_| has(c_ares_sys::ARES_OPT_NDOTS)),
+            udp_port: has(c_ares_sys::ARES_OPT_UDP_PORT).then_some(options.udp_port),
+            tcp_port: has(c_ares_sys::ARES_OPT_TCP_PORT).then_some(options.tcp_port),
+            sock_send_buffer_size: positive(options.socket_send_buffer_size)
+                .filter(|_| has(c_ares_sys::ARES_OPT_SOCK_SNDBUF)),
+            sock_receive_buffer_size: positive(options.socket_receive_buffer_size)
+                .filter(|_| has(c_ares_sys::ARES_OPT_SOCK_RCVBUF)),
+            domains,
+            lookups: unsafe { owned_string(options.lookups) }
+                .filter(|_| has(c_ares_sys::ARES_OPT_LOOKUPS)),
+            sortlist,
+            rotate,
+            ednspsz: positive(options.ednspsz).filter(|_| has(c_ares_sys::ARES_OPT_EDNSPSZ)),
+            resolvconf_path: unsafe { owned_string(options.resolvconf_path) }
+                .filter(|_| has(c_ares_sys::ARES_OPT_RESOLVCONF)),
+            #[cfg(cares1_19)]
+            hosts_path: unsafe { owned_string(options.hosts_path) }
+                .filter(|_| has(c_ares_sys::ARES_OPT_HOSTS_FILE)),
+            #[cfg(cares1_20)]
+            udp_max_queries: positive(options.udp_max_queries)
+                .filter(|_| has(c_ares_sys::ARES_OPT_UDP_MAX_QUERIES)),
+            #[cfg(cares1_22)]
+            max_timeout: positive(options.maxtimeout)
+                .filter(|_| has(c_ares_sys::ARES_OPT_MAXTIMEOUTMS))
+                .map(|ms| Duration::from_millis(u64::from(ms))),
+            #[cfg(cares1_23)]
+            query_cache_max_ttl: has(c_ares_sys::ARES_OPT_QUERY_CACHE)
+                .then_some(options.qcache_max_ttl),
+            #[cfg(cares1_29)]
+            server_failover_options: has(c_ares_sys::ARES_OPT_SERVER_FAILOVER).then(|| {
+                let mut failover = ServerFailoverOptions::new();
+                failover
+                    .set_retry_chance(options.server_failover_opts.retry_chance)
+                    .set_retry_delay(Duration::from_millis(
+                        options.server_failover_opts.retry_delay as u64,
+                    ));
+                failover
+            }),
+        }
+    }
+
+    /// The flags controlling the behaviour of the resolver.
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+
+    /// The timeout for the first try of a query against each name server, if one was configured.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// The number of tries the resolver makes against each name server, if configured.
+    pub fn tries(&self) -> Option<u32> {
+        self.tries
+    }
+
+    /// The number of dots which must be present in a domain name for it to be queried "as is", if
+    /// configured.
+    pub fn ndots(&self) -> Option<u32> {
+        self.ndots
+    }
+
+    /// The UDP port used for queries, if one was configured.
+    pub fn udp_port(&self) -> Option<u16> {
+        self.udp_port
+    }
+
+    /// The TCP port used for queries, if one was configured.
+    pub fn tcp_port(&self) -> Option<u16> {
+        self.tcp_port
+    }
+
+    /// The socket send buffer size, if one was configured.
+    pub fn sock_send_buffer_size(&self) -> Option<u32> {
+        self.sock_send_buffer_size
+    }
+
+    /// The socket receive buffer size, if one was configured.
+    pub fn sock_receive_buffer_size(&self) -> Option<u32> {
+        self.sock_receive_buffer_size
+    }
+
+    /// The domains to search.
+    pub fn domains(&self) -> &[String] {
+        &self.domains
+    }
+
+    /// The lookups to perform for host queries, eg "fb".
+    pub fn lookups(&self) -> Option<&str> {
+        self.lookups.as_deref()
+    }
+
+    /// The sortlist, as it was configured.
+    pub fn sortlist(&self) -> &[String] {
+        &self.sortlist
+    }
+
+    /// Whether round robin selection of nameservers is configured, if explicitly set.
+    pub fn rotate(&self) -> Option<bool> {
+        self.rotate
+    }
+
+    /// The EDNS packet size, if one was configured.
+    pub fn ednspsz(&self) -> Option<u32> {
+        self.ednspsz
+    }
+
+    /// The path used for reading the resolv.conf file, if one was configured.
+    pub fn resolvconf_path(&self) -> Option<&str> {
+        self.resolvconf_path.as_deref()
+    }
+
+    /// The path used for reading the hosts file, if one was configured.
+    #[cfg(cares1_19)]
+    pub fn hosts_path(&self) -> Option<&str> {
+        self.hosts_path.as_deref()
+    }
+
+    /// The maximum number of UDP queries per ephemeral port, or `None` for unlimited.
+    #[cfg(cares1_20)]
+    pub fn udp_max_queries(&self) -> Option<u32> {
+        self.udp_max_queries
+    }
+
+    /// The upper bound for timeout between sequential retry attempts, if one was configured.
+    #[cfg(cares1_22)]
+    pub fn max_timeout(&self) -> Option<Duration> {
+        self.max_timeout
+    }
+
+    /// The maximum number of seconds a query result may be cached, if the query cache is
+    /// configured.
+    #[cfg(cares1_23)]
+    pub fn query_cache_max_ttl(&self) -> Option<u32> {
+        self.query_cache_max_ttl
+    }
+
+    /// The server failover options, if configured.
+    #[cfg(cares1_29)]
+    pub fn server_failover_options(&self) -> Option<&ServerFailoverOptions> {
+        self.server_failover_options.as_ref()
+    }
+}
+
+// Copy a possibly-null C string.
+unsafe fn owned_string(ptr: *const c_char) -> Option<String> {
+    (!ptr.is_null()).then(|| {
+        unsafe { CStr::from_ptr(ptr) }
+            .to_string_lossy()
+            .into_owned()
+    })
+}
+
+impl From<&EffectiveOptions> for Options {
+    fn from(effective: &EffectiveOptions) -> Self {
+        let mut options = Options::new();
+        options.set_flags(effective.flags);
+        if let Some(timeout) = effective.timeout {
+            options.set_timeout(timeout);
+        }
+        if let Some(tries) = effective.tries {
+            options.set_tries(tries);
+        }
+        if let Some(ndots) = effective.ndots {
+            options.set_ndots(ndots);
+        }
+        if let Some(udp_port) = effective.udp_port {
+            options.set_udp_port(udp_port);
+        }
+        if let Some(tcp_port) = effective.tcp_port {
+            options.set_tcp_port(tcp_port);
+        }
+        if let Some(size) = effective.sock_send_buffer_size {
+            options.set_sock_send_buffer_size(size);
+        }
+        if let Some(size) = effective.sock_receive_buffer_size {
+            options.set_sock_receive_buffer_size(size);
+        }
+        if !effective.domains.is_empty() {
+            // The strings came from C, so cannot contain interior nuls.
+            let _ = options.set_domains(&effective.domains);
+        }
+        if let Some(lookups) = &effective.lookups {
+            let _ = options.set_lookups(lookups);
+        }
+        if !effective.sortlist.is_empty() {
+            let _ = options.set_sortlist(&effective.sortlist);
+        }
+        match effective.rotate {
+            Some(true) => {
+                options.set_rotate();
+            }
+            Some(false) => {
+                options.set_no_rotate();
+            }
+            None => {}
+        }
+        if let Some(size) = effective.ednspsz {
+            options.set_ednspsz(size);
+        }
+        if let Some(path) = &effective.resolvconf_path {
+            let _ = options.set_resolvconf_path(path);
+        }
+        #[cfg(cares1_19)]
+        if let Some(path) = &effective.hosts_path {
+            let _ = options.set_hosts_path(path);
+        }
+        #[cfg(cares1_20)]
+        if let Some(udp_max_queries) = effective.udp_max_queries {
+            options.set_udp_max_queries(Some(udp_max_queries));
+        }
+        #[cfg(cares1_22)]
+        if let Some(max_timeout) = effective.max_timeout {
+            options.set_max_timeout(max_timeout);
+        }
+        #[cfg(cares1_23)]
+        if let Some(ttl) = effective.query_cache_max_ttl {
+            options.set_query_cache_max_ttl(ttl);
+        }
+        #[cfg(cares1_29)]
+        if let Some(failover) = &effective.server_failover_options {
+            options.set_server_failover_options(failover);
+        }
+        options
+    }
+}
+
+impl From<EffectiveOptions> for Options {
+    fn from(effective: EffectiveOptions) -> Self {
+        Self::from(&effective)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Channel;
+    use super::*;
+
+    #[test]
+    fn effective_options_reflect_explicit_settings() {
+        let mut options = Options::new();
+        options
+            .set_timeout(Duration::from_millis(1500))
+            .set_tries(4)
+            .set_ndots(2)
+            .set_domains(["example.com", "example.org"])
+            .unwrap()
+            .set_lookups("b")
+            .unwrap();
+        let channel = Channel::with_options(options).unwrap();
+        let effective = channel.effective_options().unwrap();
+        assert_eq!(effective.timeout(), Some(Duration::from_millis(1500)));
+        assert_eq!(effective.tries(), Some(4));
+        assert_eq!(effective.ndots(), Some(2));
+        assert_eq!(effective.domains(), ["example.com", "example.org"]);
+        assert_eq!(effective.lookups(), Some("b"));
+    }
+
+    #[test]
+    fn effective_options_debug() {
+        let channel = Channel::new().unwrap();
+        let effective = channel.effective_options().unwrap();
+        assert!(format!("{effective:?}").starts_with("EffectiveOptions"));
+    }
+
+    #[test]
+    fn effective_options_into_options() {
+        let mut options = Options::new();
+        options.set_tries(5).set_ndots(3);
+        let channel = Channel::with_options(options).unwrap();
+
+        let mut options = Options::from(channel.effective_options().unwrap());
+        options.set_tries(2);
+        let cloned = Channel::with_options(options).unwrap();
+        let effective = cloned.effective_options().unwrap();
+        assert_eq!(effective.tries(), Some(2));
+        assert_eq!(effective.ndots(), Some(3));
+    }
+
+    #[test]
+    fn effective_options_omit_defaults() {
+        let channel = Channel::new().unwrap();
+        let effective = channel.effective_options().unwrap();
+        assert_eq!(effective.timeout(), None);
+        assert_eq!(effective.tries(), None);
+        assert_eq!(effective.ndots(), None);
+        assert_eq!(effective.udp_port(), None);
+        assert_eq!(effective.tcp_port(), None);
+
+        // Converting back leaves the defaults alone, rather than setting them to zero.
+        let cloned = Channel::with_options(Options::from(&effective)).unwrap();
+        let effective = cloned.effective_options().unwrap();
+        assert_eq!(effective.tries(), None);
+        assert_eq!(effective.udp_port(), None);
+    }
+
+    #[test]
+    fn effective_options_omit_system_configuration() {
+        let path = std::env::temp_dir().join(format!("c-ares-resolv-{}.conf", std::process::id()));
+        std::fs::write(&path, "nameserver 127.0.0.1\noptions ndots:3 attempts:4\n").unwrap();
+        let mut options = Options::new();
+        options.set_resolvconf_path(path.to_str().unwrap()).unwrap();
+        let channel = Channel::with_options(options);
+        std::fs::remove_file(&path).unwrap();
+
+        // c-ares reads the file, but reports only the options that were set explicitly.
+        let effective = channel.unwrap().effective_options().unwrap();
+        assert_eq!(effective.resolvconf_path(), path.to_str());
+        assert_eq!(effective.ndots(), None);
+        assert_eq!(effective.tries(), None);
+    }
+
+    #[test]
+    fn effective_options_sortlist() {
+        let mut options = Options::new();
+        options
+            .set_sortlist(["130.155.160.0/20", "2001:db8::/32"])
+            .unwrap();
+        let channel = Channel::with_options(options).unwrap();
+        let effective = channel.effective_options().unwrap();
+        assert_eq!(effective.sortlist(), ["130.155.160.0/20", "2001:db8::/32"]);
+
+        let cloned = Channel::with_options(Options::from(&effective)).unwrap();
+        assert_eq!(
+            cloned.effective_options().unwrap().sortlist(),
+            effective.sortlist()
+        );
+    }
+}
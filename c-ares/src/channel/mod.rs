@@ -8,12 +8,20 @@ use std::ptr;
 use std::sync::Arc;
 use std::time::Duration;
 
+#[cfg(cares1_34)]
+mod driver;
+mod effective_options;
 mod options;
+#[cfg(cares1_23)]
+mod shared;
 mod sockets;
 
+pub use effective_options::EffectiveOptions;
 pub use options::Options;
 #[cfg(cares1_29)]
 pub use options::ServerFailoverOptions;
+#[cfg(cares1_23)]
+pub use shared::SharedChannel;
 pub use sockets::{Sockets, SocketsIter};
 
 use options::SocketStateCallback;
@@ -22,7 +30,7 @@ use options::SocketStateCallback;
 use crate::ServerStateFlags;
 use crate::a::AResults;
 use crate::aaaa::AAAAResults;
-use crate::addrinfo::{AddrInfoHints, AddrInfoResults, get_addrinfo_callback};
+use crate::addrinfo::{AddrInfoFlags, AddrInfoHints, AddrInfoResults, get_addrinfo_callback};
 use crate::caa::CAAResults;
 use crate::cname::CNameResults;
 #[cfg(cares1_28)]
@@ -42,13 +50,20 @@ use crate::ns::NSResults;
 use crate::panic;
 use crate::ptr::PTRResults;
 use crate::query::{query_callback, raw_query_callback};
+use crate::query_handle::{PendingHandler, QueryHandle};
 use crate::record::QueryRecord;
+#[cfg(cares1_24)]
+use crate::server_config::ServerConfig;
 use crate::soa::SOAResult;
+#[cfg(cares1_34)]
+use crate::socket_functions::{SocketFunctions, SocketFunctionsTable, SystemSocketFunctions};
 use crate::srv::SRVResults;
 #[cfg(cares1_24)]
 use crate::string::AresString;
+#[cfg(cares1_34)]
+use crate::trace::{TraceCallback, TraceEvent, TracedSockets, TracingSocketFunctions};
 use crate::txt::TXTResults;
-use crate::types::{AddressFamily, DnsClass, Socket};
+use crate::types::{AddressFamily, DnsClass, Socket, SocketType};
 use crate::uri::URIResults;
 #[allow(unused_imports)]
 use crate::utils::{
@@ -57,8 +72,30 @@ use crate::utils::{
 };
 use std::sync::Mutex;
 
-// ares_library_init is not thread-safe, so we put a lock around it.
-static ARES_LIBRARY_LOCK: Mutex<()> = Mutex::new(());
+// ares_library_init is not thread-safe, so we put a lock around it.  The lock also counts the
+// references that channels hold on the library: `ares_library_initialized()` can't tell us that,
+// since on most platforms it always reports success.
+pub(crate) static ARES_LIBRARY_LOCK: Mutex<usize> = Mutex::new(0);
+
+// Take a reference on the library, initializing it if this is the first.
+fn library_init() -> Result<()> {
+    let mut users = ARES_LIBRARY_LOCK.lock().unwrap();
+    let rc = unsafe { c_ares_sys::ares_library_init(c_ares_sys::ARES_LIB_INIT_ALL) };
+    if rc != c_ares_sys::ares_status_t::ARES_SUCCESS as i32 {
+        return Err(Error::from(rc));
+    }
+    *users += 1;
+    Ok(())
+}
+
+// Release a reference taken by `library_init()`.
+fn library_cleanup() {
+    let mut users = ARES_LIBRARY_LOCK.lock().unwrap();
+    unsafe { c_ares_sys::ares_library_cleanup() }
+    *users -= 1;
+}
+
+type SocketCallback = dyn Fn(Socket, SocketType) -> Result<()> + Send + Sync + 'static;
 
 #[cfg(cares1_29)]
 type ServerStateCallback = dyn Fn(&str, bool, ServerStateFlags) + Send + Sync + 'static;
@@ -76,6 +113,12 @@ pub struct Channel {
     // For ownership only.
     socket_state_callback: Option<Arc<SocketStateCallback>>,
 
+    // For ownership only.
+    socket_created_callback: Option<Arc<SocketCallback>>,
+
+    // For ownership only.
+    socket_configure_callback: Option<Arc<SocketCallback>>,
+
     // For ownership only.
     #[cfg(cares1_29)]
     server_state_callback: Option<Arc<ServerStateCallback>>,
@@ -83,6 +126,28 @@ pub struct Channel {
     // For ownership only.
     #[cfg(cares1_34)]
     pending_write_callback: Option<Arc<PendingWriteCallback>>,
+
+    // The socket functions set by the user, if any.
+    #[cfg(cares1_34)]
+    socket_functions: Option<SocketFunctionsTable>,
+
+    #[cfg(cares1_34)]
+    trace_callback: Option<Arc<TraceCallback>>,
+
+    // For ownership only: when tracing, these wrap the user's socket functions.
+    #[cfg(cares1_34)]
+    tracing_socket_functions: Option<SocketFunctionsTable>,
+
+    // The sockets being traced, kept across changes to the trace callback or socket functions.
+    #[cfg(cares1_34)]
+    traced_sockets: Arc<TracedSockets>,
+
+    // Used by `poll_once()` and `run_until_idle()`, and created on first use.
+    #[cfg(cares1_34)]
+    poller: Option<polling::Poller>,
+
+    // The sortlist as configured: c-ares offers no way to read it back.
+    sortlist: Vec<String>,
 }
 
 impl Channel {
@@ -121,13 +186,7 @@ impl Channel {
     /// ```
     pub fn with_options(mut options: Options) -> Result<Channel> {
         // Initialize the library.
-        let lib_rc = {
-            let _lock = ARES_LIBRARY_LOCK.lock().unwrap();
-            unsafe { c_ares_sys::ares_library_init(c_ares_sys::ARES_LIB_INIT_ALL) }
-        };
-        if lib_rc != c_ares_sys::ares_status_t::ARES_SUCCESS as i32 {
-            return Err(Error::from(lib_rc));
-        }
+        library_init()?;
 
         // We deferred setting up domains in the options - do it now.
         let domains: Vec<_> = options.domains.iter().map(|s| s.as_ptr()).collect();
@@ -160,19 +219,42 @@ impl Channel {
             )
         };
         if channel_rc != c_ares_sys::ares_status_t::ARES_SUCCESS as i32 {
-            let _lock = ARES_LIBRARY_LOCK.lock().unwrap();
-            unsafe { c_ares_sys::ares_library_cleanup() }
+            library_cleanup();
             return Err(Error::from(channel_rc));
         }
 
-        let channel = Channel {
+        #[cfg_attr(not(cares1_34), allow(unused_mut))]
+        let mut channel = Channel {
             ares_channel,
             socket_state_callback: options.socket_state_callback,
+            socket_created_callback: None,
+            socket_configure_callback: None,
             #[cfg(cares1_29)]
             server_state_callback: None,
             #[cfg(cares1_34)]
             pending_write_callback: None,
+            #[cfg(cares1_34)]
+            socket_functions: options.socket_functions,
+            #[cfg(cares1_34)]
+            trace_callback: options.trace_callback,
+            #[cfg(cares1_34)]
+            tracing_socket_functions: None,
+            #[cfg(cares1_34)]
+            traced_sockets: Arc::default(),
+            #[cfg(cares1_34)]
+            poller: None,
+            sortlist: vec![],
         };
+
+        // There's no public representation of a sortlist in the options, so it too is applied to
+        // the channel.
+        if !options.sortlist.is_empty() {
+            channel.set_sortlist(&options.sortlist)?;
+        }
+
+        // Socket functions can only be installed on an existing channel.
+        #[cfg(cares1_34)]
+        channel.install_socket_functions()?;
         Ok(channel)
     }
 
@@ -184,26 +266,37 @@ impl Channel {
         Ok(self)
     }
 
+    /// Returns a snapshot of the options that this channel was configured with.
+    ///
+    /// c-ares does not report the values that it read from the system configuration: see
+    /// [`EffectiveOptions`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let channel = c_ares::Channel::new().unwrap();
+    /// let effective = channel.effective_options().unwrap();
+    /// println!("timeout: {:?}, tries: {:?}", effective.timeout(), effective.tries());
+    /// ```
+    pub fn effective_options(&self) -> Result<EffectiveOptions> {
+        EffectiveOptions::from_channel(self.ares_channel, &self.sortlist)
+    }
+
     /// Duplicate a channel.
     pub fn try_clone(&self) -> Result<Channel> {
-        // Balance the ares_library_cleanup() that will run when the clone is dropped.
-        let lib_rc = {
-            let _lock = ARES_LIBRARY_LOCK.lock().unwrap();
-            unsafe { c_ares_sys::ares_library_init(c_ares_sys::ARES_LIB_INIT_ALL) }
-        };
-        if lib_rc != c_ares_sys::ares_status_t::ARES_SUCCESS as i32 {
-            return Err(Error::from(lib_rc));
-        }
+        // Balance the library_cleanup() that will run when the clone is dropped.
+        library_init()?;
 
         let mut ares_channel = ptr::null_mut();
         let rc = unsafe { c_ares_sys::ares_dup(&raw mut ares_channel, self.ares_channel) };
         if rc != c_ares_sys::ares_status_t::ARES_SUCCESS as i32 {
-            let _lock = ARES_LIBRARY_LOCK.lock().unwrap();
-            unsafe { c_ares_sys::ares_library_cleanup() }
+            library_cleanup();
             return Err(Error::from(rc));
         }
 
         let socket_state_callback = self.socket_state_callback.clone();
+        let socket_created_callback = self.socket_created_callback.clone();
+        let socket_configure_callback = self.socket_configure_callback.clone();
 
         #[cfg(cares1_29)]
         let server_state_callback = self.server_state_callback.clone();
@@ -211,13 +304,36 @@ impl Channel {
         #[cfg(cares1_34)]
         let pending_write_callback = self.pending_write_callback.clone();
 
+        // The duplicate shares the original's socket functions.
+        #[cfg(cares1_34)]
+        let socket_functions = self.socket_functions.clone();
+        #[cfg(cares1_34)]
+        let trace_callback = self.trace_callback.clone();
+        #[cfg(cares1_34)]
+        let tracing_socket_functions = self.tracing_socket_functions.clone();
+        #[cfg(cares1_34)]
+        let traced_sockets = Arc::clone(&self.traced_sockets);
+
         let channel = Channel {
             ares_channel,
             socket_state_callback,
+            socket_created_callback,
+            socket_configure_callback,
             #[cfg(cares1_29)]
             server_state_callback,
             #[cfg(cares1_34)]
             pending_write_callback,
+            #[cfg(cares1_34)]
+            socket_functions,
+            #[cfg(cares1_34)]
+            trace_callback,
+            #[cfg(cares1_34)]
+            tracing_socket_functions,
+            #[cfg(cares1_34)]
+            traced_sockets,
+            #[cfg(cares1_34)]
+            poller: None,
+            sortlist: self.sortlist.clone(),
         };
         Ok(channel)
     }
@@ -330,6 +446,26 @@ impl Channel {
         Self::parse_servers_csv(csv)
     }
 
+    /// Set the list of servers to contact, from structured [`ServerConfig`]s.
+    ///
+    /// This is equivalent to [`set_servers`](Self::set_servers), but allows the TCP port,
+    /// link-local interface and scope id of each server to be specified.
+    #[cfg(cares1_24)]
+    pub fn set_servers_config<'a, I>(&mut self, servers: I) -> Result<&mut Self>
+    where
+        I: IntoIterator<Item = &'a ServerConfig>,
+    {
+        self.set_servers(servers.into_iter().map(ServerConfig::to_string))
+    }
+
+    /// Retrieves the list of configured servers, as structured [`ServerConfig`]s.
+    ///
+    /// Fails if c-ares reports a server that cannot be parsed as a `ServerConfig`.
+    #[cfg(cares1_24)]
+    pub fn servers_config(&self) -> Result<Vec<ServerConfig>> {
+        self.servers().iter().map(|server| server.parse()).collect()
+    }
+
     // Parse the result of `ares_get_servers_csv()` into a list of servers,
     // taking ownership of (and freeing) the c-ares-allocated string.
     #[cfg(cares1_24)]
@@ -380,23 +516,65 @@ impl Channel {
         I: IntoIterator<Item = S>,
         S: AsRef<str>,
     {
-        let mut sortlist_str = String::new();
-        for (i, s) in sortlist.into_iter().enumerate() {
-            if i > 0 {
-                sortlist_str.push(' ');
-            }
-            sortlist_str.push_str(s.as_ref());
-        }
-        let c_sortlist = CString::new(sortlist_str).map_err(|_| Error::EBADSTR)?;
+        let sortlist: Vec<_> = sortlist
+            .into_iter()
+            .map(|s| s.as_ref().to_owned())
+            .collect();
+        let c_sortlist = CString::new(sortlist.join(" ")).map_err(|_| Error::EBADSTR)?;
         let ares_rc =
             unsafe { c_ares_sys::ares_set_sortlist(self.ares_channel, c_sortlist.as_ptr()) };
         if ares_rc == c_ares_sys::ares_status_t::ARES_SUCCESS as i32 {
+            self.sortlist = sortlist;
             Ok(self)
         } else {
             Err(Error::from(ares_rc))
         }
     }
 
+    /// Set a callback function to be invoked after a socket has been created and connected.
+    ///
+    /// `callback(socket, socket_type)` is called with the new socket and its type.  Returning an
+    /// error causes c-ares to abandon the connection.
+    pub fn set_socket_created_callback<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(Socket, SocketType) -> Result<()> + Send + Sync + 'static,
+    {
+        let boxed_callback = Arc::new(callback);
+        let data = Arc::as_ptr(&boxed_callback).cast_mut().cast();
+        unsafe {
+            c_ares_sys::ares_set_socket_callback(
+                self.ares_channel,
+                Some(socket_callback::<F>),
+                data,
+            );
+        }
+        self.socket_created_callback = Some(boxed_callback);
+        self
+    }
+
+    /// Set a callback function to be invoked after a socket has been created, but before it is
+    /// connected.
+    ///
+    /// This is the place to apply socket options - for example `SO_MARK`, `IP_TOS` or
+    /// `SO_BINDTODEVICE`.  `callback(socket, socket_type)` is called with the new socket and its
+    /// type.  Returning an error causes c-ares to abandon the connection.
+    pub fn set_socket_configure_callback<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(Socket, SocketType) -> Result<()> + Send + Sync + 'static,
+    {
+        let boxed_callback = Arc::new(callback);
+        let data = Arc::as_ptr(&boxed_callback).cast_mut().cast();
+        unsafe {
+            c_ares_sys::ares_set_socket_configure_callback(
+                self.ares_channel,
+                Some(socket_callback::<F>),
+                data,
+            );
+        }
+        self.socket_configure_callback = Some(boxed_callback);
+        self
+    }
+
     /// Set a callback function to be invoked whenever a query on the channel completes.
     ///
     /// `callback(server, success, flags)` will be called when a query completes.
@@ -442,256 +620,317 @@ impl Channel {
         self
     }
 
+    /// Set the socket functions that c-ares should use in place of the operating system's own.
+    ///
+    /// This should be called before any queries are made on the channel.  See
+    /// [`SocketFunctions`] for details.
+    #[cfg(cares1_34)]
+    pub fn set_socket_functions<S>(&mut self, socket_functions: S) -> Result<&mut Self>
+    where
+        S: SocketFunctions,
+    {
+        self.socket_functions = Some(SocketFunctionsTable::new(socket_functions));
+        self.install_socket_functions()?;
+        Ok(self)
+    }
+
+    /// Set a callback function to be invoked for every DNS message that the channel sends to, or
+    /// receives from, a server.
+    ///
+    /// `callback(event)` is called with each message, together with the server's address, the
+    /// transport and a timestamp: see [`TraceEvent`].  The callback runs on whichever thread is
+    /// processing the channel's sockets.
+    ///
+    /// Tracing works by wrapping the channel's socket functions - those set by
+    /// [`set_socket_functions()`](Self::set_socket_functions), or else
+    /// [`SystemSocketFunctions`](crate::SystemSocketFunctions) - so this should be called before
+    /// any queries are made on the channel.  Connections that the channel opened before tracing
+    /// began are never traced.  Replacing the trace callback, or the socket functions, carries on
+    /// tracing the connections that were already traced.
+    #[cfg(cares1_34)]
+    pub fn set_trace_callback<F>(&mut self, callback: F) -> Result<&mut Self>
+    where
+        F: Fn(&TraceEvent<'_>) + Send + Sync + 'static,
+    {
+        self.trace_callback = Some(Arc::new(callback));
+        self.install_socket_functions()?;
+        Ok(self)
+    }
+
+    // Install the user's socket functions, wrapped to report traffic if there is a trace callback.
+    #[cfg(cares1_34)]
+    fn install_socket_functions(&mut self) -> Result<()> {
+        let Some(callback) = &self.trace_callback else {
+            if let Some(socket_functions) = &self.socket_functions {
+                socket_functions.install(self.ares_channel)?;
+            }
+            return Ok(());
+        };
+        let inner = match &self.socket_functions {
+            Some(socket_functions) => socket_functions.functions(),
+            None => Arc::new(SystemSocketFunctions),
+        };
+        let tracing = TracingSocketFunctions::new(
+            inner,
+            Arc::clone(callback),
+            Arc::clone(&self.traced_sockets),
+        );
+        let tracing = SocketFunctionsTable::new(tracing);
+        tracing.install(self.ares_channel)?;
+        self.tracing_socket_functions = Some(tracing);
+        Ok(())
+    }
+
     /// Initiate a single-question DNS query for the A records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_a<F>(&mut self, name: &str, handler: F)
+    pub fn query_a<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<AResults>) + Send + 'static,
     {
-        self.do_query(name, handler);
+        self.do_query(name, handler)
     }
 
     /// Initiate a series of single-question DNS queries for the A records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn search_a<F>(&mut self, name: &str, handler: F)
+    pub fn search_a<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<AResults>) + Send + 'static,
     {
-        self.do_search(name, handler);
+        self.do_search(name, handler)
     }
 
     /// Initiate a single-question DNS query for the AAAA records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_aaaa<F>(&mut self, name: &str, handler: F)
+    pub fn query_aaaa<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<AAAAResults>) + Send + 'static,
     {
-        self.do_query(name, handler);
+        self.do_query(name, handler)
     }
 
     /// Initiate a series of single-question DNS queries for the AAAA records associated with
     /// `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn search_aaaa<F>(&mut self, name: &str, handler: F)
+    pub fn search_aaaa<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<AAAAResults>) + Send + 'static,
     {
-        self.do_search(name, handler);
+        self.do_search(name, handler)
     }
 
     /// Initiate a single-question DNS query for the CAA records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_caa<F>(&mut self, name: &str, handler: F)
+    pub fn query_caa<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<CAAResults>) + Send + 'static,
     {
-        self.do_query(name, handler);
+        self.do_query(name, handler)
     }
 
     /// Initiate a series of single-question DNS queries for the CAA records associated with
     /// `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn search_caa<F>(&mut self, name: &str, handler: F)
+    pub fn search_caa<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<CAAResults>) + Send + 'static,
     {
-        self.do_search(name, handler);
+        self.do_search(name, handler)
     }
 
     /// Initiate a single-question DNS query for the CNAME records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_cname<F>(&mut self, name: &str, handler: F)
+    pub fn query_cname<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<CNameResults>) + Send + 'static,
     {
-        self.do_query(name, handler);
+        self.do_query(name, handler)
     }
 
     /// Initiate a series of single-question DNS queries for the CNAME records associated with
     /// `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn search_cname<F>(&mut self, name: &str, handler: F)
+    pub fn search_cname<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<CNameResults>) + Send + 'static,
     {
-        self.do_search(name, handler);
+        self.do_search(name, handler)
     }
 
     /// Initiate a single-question DNS query for the MX records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_mx<F>(&mut self, name: &str, handler: F)
+    pub fn query_mx<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<MXResults>) + Send + 'static,
     {
-        self.do_query(name, handler);
+        self.do_query(name, handler)
     }
 
     /// Initiate a series of single-question DNS queries for the MX records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn search_mx<F>(&mut self, name: &str, handler: F)
+    pub fn search_mx<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<MXResults>) + Send + 'static,
     {
-        self.do_search(name, handler);
+        self.do_search(name, handler)
     }
 
     /// Initiate a single-question DNS query for the NAPTR records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_naptr<F>(&mut self, name: &str, handler: F)
+    pub fn query_naptr<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<NAPTRResults>) + Send + 'static,
     {
-        self.do_query(name, handler);
+        self.do_query(name, handler)
     }
 
     /// Initiate a series of single-question DNS queries for the NAPTR records associated with
     /// `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn search_naptr<F>(&mut self, name: &str, handler: F)
+    pub fn search_naptr<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<NAPTRResults>) + Send + 'static,
     {
-        self.do_search(name, handler);
+        self.do_search(name, handler)
     }
 
     /// Initiate a single-question DNS query for the NS records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_ns<F>(&mut self, name: &str, handler: F)
+    pub fn query_ns<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<NSResults>) + Send + 'static,
     {
-        self.do_query(name, handler);
+        self.do_query(name, handler)
     }
 
     /// Initiate a series of single-question DNS queries for the NS records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn search_ns<F>(&mut self, name: &str, handler: F)
+    pub fn search_ns<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<NSResults>) + Send + 'static,
     {
-        self.do_search(name, handler);
+        self.do_search(name, handler)
     }
 
     /// Initiate a single-question DNS query for the PTR records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_ptr<F>(&mut self, name: &str, handler: F)
+    pub fn query_ptr<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<PTRResults>) + Send + 'static,
     {
-        self.do_query(name, handler);
+        self.do_query(name, handler)
     }
 
     /// Initiate a series of single-question DNS queries for the PTR records associated with
     /// `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn search_ptr<F>(&mut self, name: &str, handler: F)
+    pub fn search_ptr<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<PTRResults>) + Send + 'static,
     {
-        self.do_search(name, handler);
+        self.do_search(name, handler)
     }
 
     /// Initiate a single-question DNS query for the SOA records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_soa<F>(&mut self, name: &str, handler: F)
+    pub fn query_soa<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<SOAResult>) + Send + 'static,
     {
-        self.do_query(name, handler);
+        self.do_query(name, handler)
     }
 
     /// Initiate a series of single-question DNS queries for the SOA records associated with
     /// `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn search_soa<F>(&mut self, name: &str, handler: F)
+    pub fn search_soa<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<SOAResult>) + Send + 'static,
     {
-        self.do_search(name, handler);
+        self.do_search(name, handler)
     }
 
     /// Initiate a single-question DNS query for the SRV records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_srv<F>(&mut self, name: &str, handler: F)
+    pub fn query_srv<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<SRVResults>) + Send + 'static,
     {
-        self.do_query(name, handler);
+        self.do_query(name, handler)
     }
 
     /// Initiate a series of single-question DNS queries for the SRV records associated with
     /// `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn search_srv<F>(&mut self, name: &str, handler: F)
+    pub fn search_srv<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<SRVResults>) + Send + 'static,
     {
-        self.do_search(name, handler);
+        self.do_search(name, handler)
     }
 
     /// Initiate a single-question DNS query for the TXT records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_txt<F>(&mut self, name: &str, handler: F)
+    pub fn query_txt<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<TXTResults>) + Send + 'static,
     {
-        self.do_query(name, handler);
+        self.do_query(name, handler)
     }
 
     /// Initiate a series of single-question DNS queries for the TXT records associated with
     /// `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn search_txt<F>(&mut self, name: &str, handler: F)
+    pub fn search_txt<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<TXTResults>) + Send + 'static,
     {
-        self.do_search(name, handler);
+        self.do_search(name, handler)
     }
 
     /// Initiate a single-question DNS query for the URI records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_uri<F>(&mut self, name: &str, handler: F)
+    pub fn query_uri<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<URIResults>) + Send + 'static,
     {
-        self.do_query(name, handler);
+        self.do_query(name, handler)
     }
 
     /// Initiate a series of single-question DNS queries for the URI records associated with
     /// `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn search_uri<F>(&mut self, name: &str, handler: F)
+    pub fn search_uri<F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<URIResults>) + Send + 'static,
     {
-        self.do_search(name, handler);
+        self.do_search(name, handler)
     }
 
-    fn do_query<R, F>(&mut self, name: &str, handler: F)
+    fn do_query<R, F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         R: QueryRecord,
         F: FnOnce(Result<R>) + Send + 'static,
@@ -703,10 +942,10 @@ impl Channel {
             R::QUERY_TYPE,
             query_callback::<R, F>,
             handler
-        );
+        )
     }
 
-    fn do_search<R, F>(&mut self, name: &str, handler: F)
+    fn do_search<R, F>(&mut self, name: &str, handler: F) -> QueryHandle
     where
         R: QueryRecord,
         F: FnOnce(Result<R>) + Send + 'static,
@@ -718,7 +957,7 @@ impl Channel {
             R::QUERY_TYPE,
             query_callback::<R, F>,
             handler
-        );
+        )
     }
 
     /// Perform a host query by address.
@@ -727,7 +966,7 @@ impl Channel {
     // `in_addr` and `in6_addr` mirror the C struct names; the apparent
     // similarity is deliberate.
     #[allow(clippy::similar_names)]
-    pub fn get_host_by_address<F>(&mut self, address: &IpAddr, handler: F)
+    pub fn get_host_by_address<F>(&mut self, address: &IpAddr, handler: F) -> QueryHandle
     where
         F: FnOnce(Result<&HostResults>) + Send + 'static,
     {
@@ -747,7 +986,9 @@ impl Channel {
             IpAddr::V4(_) => (AddressFamily::INET, mem::size_of::<c_types::in_addr>()),
             IpAddr::V6(_) => (AddressFamily::INET6, mem::size_of::<c_types::in6_addr>()),
         };
-        let c_arg = Box::into_raw(Box::new(handler));
+        let pending = PendingHandler::new(handler);
+        instrument!(pending, "gethostbyaddr", Some(&address.to_string()), None);
+        let handle = pending.handle(|handler| handler(Err(Error::ECANCELLED)));
         unsafe {
             c_ares_sys::ares_gethostbyaddr(
                 self.ares_channel,
@@ -755,32 +996,103 @@ impl Channel {
                 length as i32,
                 family as i32,
                 Some(get_host_callback::<F>),
-                c_arg.cast(),
+                pending.into_raw(),
             );
         }
+        handle
     }
 
     /// Perform a host query by name.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn get_host_by_name<F>(&mut self, name: &str, family: AddressFamily, handler: F)
+    pub fn get_host_by_name<F>(
+        &mut self,
+        name: &str,
+        family: AddressFamily,
+        handler: F,
+    ) -> QueryHandle
     where
         F: FnOnce(Result<&HostResults>) + Send + 'static,
     {
         let Ok(c_name) = CString::new(name) else {
             handler(Err(Error::EBADNAME));
-            return;
+            return QueryHandle::completed();
         };
-        let c_arg = Box::into_raw(Box::new(handler));
+        let pending = PendingHandler::new(handler);
+        instrument!(pending, "gethostbyname", Some(name), None);
+        let handle = pending.handle(|handler| handler(Err(Error::ECANCELLED)));
         unsafe {
             c_ares_sys::ares_gethostbyname(
                 self.ares_channel,
                 c_name.as_ptr(),
                 family as i32,
                 Some(get_host_callback::<F>),
-                c_arg.cast(),
+                pending.into_raw(),
             );
         }
+        handle
+    }
+
+    /// Look up `name` in the hosts file only, without contacting any DNS server.
+    ///
+    /// The hosts file is the channel's configured hosts path, or the system default.  Returns
+    /// [`Error::ENOTFOUND`] if the hosts file has no entry for `name`.
+    pub fn get_host_by_name_file(
+        &mut self,
+        name: &str,
+        family: AddressFamily,
+    ) -> Result<HostResults> {
+        let c_name = CString::new(name).map_err(|_| Error::EBADNAME)?;
+        let mut hostent: *mut c_types::hostent = ptr::null_mut();
+        let rc = unsafe {
+            c_ares_sys::ares_gethostbyname_file(
+                self.ares_channel,
+                c_name.as_ptr(),
+                family as i32,
+                &raw mut hostent,
+            )
+        };
+        if rc == c_ares_sys::ares_status_t::ARES_SUCCESS as i32 {
+            Ok(HostResults::new(hostent))
+        } else {
+            Err(Error::from(rc))
+        }
+    }
+
+    /// Look up `name` and `service` in the hosts file only, without contacting any DNS server.
+    ///
+    /// This is the addrinfo-style counterpart of
+    /// [`get_host_by_name_file`](Self::get_host_by_name_file).  It performs the lookup on a
+    /// temporary channel that shares this channel's configured options, except that only the hosts
+    /// file is consulted.  The results are not sorted, since sorting probes the routes to each
+    /// address.  Returns [`Error::ENOTFOUND`] if the hosts file has no entry for `name`.
+    pub fn get_addrinfo_file(
+        &mut self,
+        name: &str,
+        service: Option<&str>,
+        hints: &AddrInfoHints,
+    ) -> Result<AddrInfoResults> {
+        let mut options = Options::from(self.effective_options()?);
+        options.set_lookups("f")?;
+        let mut file_channel = Channel::with_options(options)?;
+        let hints = AddrInfoHints {
+            flags: hints.flags | AddrInfoFlags::NOSORT,
+            ..*hints
+        };
+
+        // Hosts file lookups complete synchronously; and if that should somehow not be so, then
+        // destroying the channel completes the query.
+        let (tx, rx) = std::sync::mpsc::sync_channel(1);
+        file_channel.get_addrinfo(name, service, &hints, move |result| {
+            let _ = tx.send(result);
+        });
+        drop(file_channel);
+
+        // c-ares reports a lookup that finds nothing with its initial status, `ECONNREFUSED`.
+        match rx.try_recv().unwrap_or(Err(Error::EDESTRUCTION)) {
+            Err(Error::ECONNREFUSED) => Err(Error::ENOTFOUND),
+            result => result,
+        }
     }
 
     /// Address-to-nodename translation in protocol-independent manner.
@@ -788,7 +1100,12 @@ impl Channel {
     /// The valid values for `flags` are documented [here](ni_flags/index.html).
     ///
     /// On completion, `handler` is called with the result.
-    pub fn get_name_info<F>(&mut self, address: &SocketAddr, flags: NIFlags, handler: F)
+    pub fn get_name_info<F>(
+        &mut self,
+        address: &SocketAddr,
+        flags: NIFlags,
+        handler: F,
+    ) -> QueryHandle
     where
         F: FnOnce(Result<NameInfoResult>) + Send + 'static,
     {
@@ -808,7 +1125,9 @@ impl Channel {
             SocketAddr::V4(_) => mem::size_of::<c_types::sockaddr_in>(),
             SocketAddr::V6(_) => mem::size_of::<c_types::sockaddr_in6>(),
         };
-        let c_arg = Box::into_raw(Box::new(handler));
+        let pending = PendingHandler::new(handler);
+        instrument!(pending, "getnameinfo", Some(&address.to_string()), None);
+        let handle = pending.handle(|handler| handler(Err(Error::ECANCELLED)));
         unsafe {
             c_ares_sys::ares_getnameinfo(
                 self.ares_channel,
@@ -816,9 +1135,10 @@ impl Channel {
                 length as c_ares_sys::ares_socklen_t,
                 flags.bits(),
                 Some(get_name_info_callback::<F>),
-                c_arg.cast(),
+                pending.into_raw(),
             );
         }
+        handle
     }
 
     /// Initiate a host query by name and service.
@@ -833,19 +1153,22 @@ impl Channel {
         service: Option<&str>,
         hints: &AddrInfoHints,
         handler: F,
-    ) where
+    ) -> QueryHandle
+    where
         F: FnOnce(Result<AddrInfoResults>) + Send + 'static,
     {
         let Ok(c_name) = CString::new(name) else {
             handler(Err(Error::EBADNAME));
-            return;
+            return QueryHandle::completed();
         };
         let Ok(c_service) = service.map(CString::new).transpose() else {
             handler(Err(Error::EBADNAME));
-            return;
+            return QueryHandle::completed();
         };
         let c_hints: c_ares_sys::ares_addrinfo_hints = hints.into();
-        let c_arg = Box::into_raw(Box::new(handler));
+        let pending = PendingHandler::new(handler);
+        instrument!(pending, "getaddrinfo", Some(name), None);
+        let handle = pending.handle(|handler| handler(Err(Error::ECANCELLED)));
         unsafe {
             c_ares_sys::ares_getaddrinfo(
                 self.ares_channel,
@@ -853,9 +1176,10 @@ impl Channel {
                 c_service.as_ref().map_or(ptr::null(), |s| s.as_ptr()),
                 &raw const c_hints,
                 Some(get_addrinfo_callback::<F>),
-                c_arg.cast(),
+                pending.into_raw(),
             );
         }
+        handle
     }
 
     /// Initiate a single-question DNS query for `name`.  The class and type of the query are per
@@ -866,7 +1190,13 @@ impl Channel {
     /// This method is provided so that users can query DNS types for which `c-ares` does not
     /// provide a parser.  This is expected to be a last resort; if a suitable `query_xxx()` is
     /// available, that should be preferred.
-    pub fn query<F>(&mut self, name: &str, dns_class: u16, query_type: u16, handler: F)
+    pub fn query<F>(
+        &mut self,
+        name: &str,
+        dns_class: u16,
+        query_type: u16,
+        handler: F,
+    ) -> QueryHandle
     where
         F: FnOnce(Result<&[u8]>) + Send + 'static,
     {
@@ -877,7 +1207,7 @@ impl Channel {
             c_int::from(query_type),
             raw_query_callback::<F>,
             handler
-        );
+        )
     }
 
     /// Initiate a series of single-question DNS queries for `name`.  The class and type of the
@@ -888,7 +1218,13 @@ impl Channel {
     /// This method is provided so that users can search DNS types for which `c-ares` does not
     /// provide a parser.  This is expected to be a last resort; if a suitable `search_xxx()` is
     /// available, that should be preferred.
-    pub fn search<F>(&mut self, name: &str, dns_class: u16, query_type: u16, handler: F)
+    pub fn search<F>(
+        &mut self,
+        name: &str,
+        dns_class: u16,
+        query_type: u16,
+        handler: F,
+    ) -> QueryHandle
     where
         F: FnOnce(Result<&[u8]>) + Send + 'static,
     {
@@ -899,7 +1235,59 @@ impl Channel {
             c_int::from(query_type),
             raw_query_callback::<F>,
             handler
-        );
+        )
+    }
+
+    /// Send a DNS query that is already in wire format.
+    ///
+    /// The query is subject to the channel's usual retry and server failover behaviour.  On
+    /// completion, `handler` is called with the raw response.
+    ///
+    /// This method is provided for callers - such as proxies - that already hold queries as bytes,
+    /// and so would gain nothing by parsing them into a [`DnsRecord`](crate::DnsRecord) only to
+    /// have them serialized again.
+    ///
+    /// c-ares sends the query under a query ID of its own choosing, and the server answers with
+    /// that ID.  The response is handed back with the ID from `query` restored, so that it
+    /// matches the query as the caller knows it.
+    pub fn send_raw<F>(&mut self, query: &[u8], handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<&[u8]>) + Send + 'static,
+    {
+        let Ok(qlen) = c_int::try_from(query.len()) else {
+            handler(Err(Error::EBADQUERY));
+            return QueryHandle::completed();
+        };
+        let id = query.get(..2).map(|id| [id[0], id[1]]);
+        let handler = move |result: Result<&[u8]>| match (result, id) {
+            (Ok(response), Some(id)) if response.len() >= 2 => {
+                let mut response = response.to_vec();
+                response[..2].copy_from_slice(&id);
+                handler(Ok(&response));
+            }
+            (result, _) => handler(result),
+        };
+        self.send_raw_with_len(query, qlen, handler)
+    }
+
+    // As `send_raw()`, once the query's length is known to fit.
+    fn send_raw_with_len<F>(&mut self, query: &[u8], qlen: c_int, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<&[u8]>) + Send + 'static,
+    {
+        let pending = PendingHandler::new(handler);
+        instrument!(pending, "send", None, None);
+        let handle = pending.handle(|handler| handler(Err(Error::ECANCELLED)));
+        unsafe {
+            c_ares_sys::ares_send(
+                self.ares_channel,
+                query.as_ptr(),
+                qlen,
+                Some(raw_query_callback::<F>),
+                pending.into_raw(),
+            );
+        }
+        handle
     }
 
     /// Send a DNS query using a pre-built [`DnsRecord`].
@@ -907,7 +1295,7 @@ impl Channel {
     /// On completion, `handler` is called with a `Result<DnsRecord>` containing
     /// the parsed response.
     ///
-    /// Returns the query ID on success.
+    /// Returns a handle to the query, carrying its query ID, on success.
     ///
     /// # Examples
     ///
@@ -928,30 +1316,32 @@ impl Channel {
     /// // ... drive the event loop ...
     /// ```
     #[cfg(cares1_28)]
-    pub fn send_dnsrec<F>(&mut self, dnsrec: &DnsRecord, handler: F) -> Result<u16>
+    pub fn send_dnsrec<F>(&mut self, dnsrec: &DnsRecord, handler: F) -> Result<QueryHandle>
     where
         F: FnOnce(Result<&DnsRecord>) + Send + 'static,
     {
         let mut qid: u16 = 0;
-        let c_arg = Box::into_raw(Box::new(handler));
+        let pending = PendingHandler::new(handler);
+        instrument!(pending, "send_dnsrec", dnsrec);
+        let handle = pending.handle(|handler| handler(Err(Error::ECANCELLED)));
         let status = unsafe {
             c_ares_sys::ares_send_dnsrec(
                 self.ares_channel,
                 dnsrec.as_raw(),
                 Some(dnsrec_callback::<F>),
-                c_arg.cast(),
+                pending.into_raw(),
                 &raw mut qid,
             )
         };
 
         status_to_result(status)?;
-        Ok(qid)
+        Ok(handle.with_qid(qid))
     }
 
     /// Initiate a DNS query for `name` with the given class and type, receiving
     /// a parsed [`DnsRecord`] in the callback.
     ///
-    /// Returns the query ID on success.
+    /// Returns a handle to the query, carrying its query ID, on success.
     ///
     /// # Examples
     ///
@@ -981,13 +1371,20 @@ impl Channel {
         dns_class: DnsCls,
         query_type: DnsRecordType,
         handler: F,
-    ) -> Result<u16>
+    ) -> Result<QueryHandle>
     where
         F: FnOnce(Result<&DnsRecord>) + Send + 'static,
     {
         let c_name = CString::new(name).map_err(|_| Error::EBADNAME)?;
         let mut qid: u16 = 0;
-        let c_arg = Box::into_raw(Box::new(handler));
+        let pending = PendingHandler::new(handler);
+        instrument!(
+            pending,
+            "query_dnsrec",
+            Some(name),
+            u16::try_from(c_ares_sys::ares_dns_rec_type_t::from(query_type).0).ok()
+        );
+        let handle = pending.handle(|handler| handler(Err(Error::ECANCELLED)));
         let status = unsafe {
             c_ares_sys::ares_query_dnsrec(
                 self.ares_channel,
@@ -995,13 +1392,13 @@ impl Channel {
                 dns_class.into(),
                 query_type.into(),
                 Some(dnsrec_callback::<F>),
-                c_arg.cast(),
+                pending.into_raw(),
                 &raw mut qid,
             )
         };
 
         status_to_result(status)?;
-        Ok(qid)
+        Ok(handle.with_qid(qid))
     }
 
     /// Initiate a series of DNS queries using a pre-built [`DnsRecord`],
@@ -1026,21 +1423,24 @@ impl Channel {
     /// // ... drive the event loop ...
     /// ```
     #[cfg(cares1_28)]
-    pub fn search_dnsrec<F>(&mut self, dnsrec: &DnsRecord, handler: F) -> Result<()>
+    pub fn search_dnsrec<F>(&mut self, dnsrec: &DnsRecord, handler: F) -> Result<QueryHandle>
     where
         F: FnOnce(Result<&DnsRecord>) + Send + 'static,
     {
-        let c_arg = Box::into_raw(Box::new(handler));
+        let pending = PendingHandler::new(handler);
+        instrument!(pending, "search_dnsrec", dnsrec);
+        let handle = pending.handle(|handler| handler(Err(Error::ECANCELLED)));
         let status = unsafe {
             c_ares_sys::ares_search_dnsrec(
                 self.ares_channel,
                 dnsrec.as_raw(),
                 Some(dnsrec_callback::<F>),
-                c_arg.cast(),
+                pending.into_raw(),
             )
         };
 
-        status_to_result(status)
+        status_to_result(status)?;
+        Ok(handle)
     }
 
     /// Cancel all requests made on this `Channel`.
@@ -1114,10 +1514,7 @@ impl Channel {
 impl Drop for Channel {
     fn drop(&mut self) {
         unsafe { c_ares_sys::ares_destroy(self.ares_channel) }
-        {
-            let _lock = ARES_LIBRARY_LOCK.lock().unwrap();
-            unsafe { c_ares_sys::ares_library_cleanup() }
-        }
+        library_cleanup();
     }
 }
 
@@ -1143,6 +1540,22 @@ unsafe extern "C" fn socket_state_callback<F>(
     panic::abort_on_panic(|| handler(socket_fd, readable != 0, writable != 0));
 }
 
+unsafe extern "C" fn socket_callback<F>(
+    socket_fd: c_ares_sys::ares_socket_t,
+    socket_type: c_int,
+    data: *mut c_void,
+) -> c_int
+where
+    F: Fn(Socket, SocketType) -> Result<()> + Send + Sync + 'static,
+{
+    let handler = data.cast::<F>();
+    let handler = unsafe { &*handler };
+    panic::abort_on_panic(|| match SocketType::from_raw(socket_type) {
+        Some(socket_type) if handler(socket_fd, socket_type).is_ok() => 0,
+        _ => -1,
+    })
+}
+
 #[cfg(cares1_29)]
 unsafe extern "C" fn server_state_callback<F>(
     server_string: *const c_char,
@@ -1155,13 +1568,11 @@ unsafe extern "C" fn server_state_callback<F>(
     let handler = data.cast::<F>();
     let handler = unsafe { &*handler };
     let server = unsafe { c_string_as_str_unchecked(server_string) };
-    panic::abort_on_panic(|| {
-        handler(
-            server,
-            success != c_ares_sys::ares_bool_t::ARES_FALSE,
-            ServerStateFlags::from_bits_truncate(flags),
-        );
-    });
+    let success = success != c_ares_sys::ares_bool_t::ARES_FALSE;
+    let flags = ServerStateFlags::from_bits_truncate(flags);
+    #[cfg(feature = "tracing")]
+    tracing::debug!(server, success, ?flags, "server state");
+    panic::abort_on_panic(|| handler(server, success, flags));
 }
 
 #[cfg(cares1_34)]
@@ -1289,6 +1700,20 @@ mod tests {
         assert!(servers.iter().any(|s| s.contains("8.8.8.8")));
     }
 
+    #[cfg(cares1_24)]
+    #[test]
+    fn channel_servers_config_round_trip() {
+        let mut channel = Channel::new().unwrap();
+        let mut config = ServerConfig::new(IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)));
+        config.set_udp_port(5353).set_tcp_port(5354);
+        channel.set_servers_config([&config]).unwrap();
+        let servers = channel.servers_config().unwrap();
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].addr(), config.addr());
+        assert_eq!(servers[0].udp_port(), Some(5353));
+        assert_eq!(servers[0].tcp_port(), Some(5354));
+    }
+
     #[test]
     fn channel_set_servers_owned_strings() {
         let mut channel = Channel::new().unwrap();
@@ -1350,7 +1775,161 @@ mod tests {
     fn query_dnsrec_rejects_nul_in_name_with_ebadname() {
         let mut channel = Channel::new().unwrap();
         let result = channel.query_dnsrec("ex\0ample.com", DnsCls::IN, DnsRecordType::A, |_| {});
-        assert_eq!(result, Err(Error::EBADNAME));
+        assert_eq!(result.err(), Some(Error::EBADNAME));
+    }
+
+    // A channel whose queries never complete, since its server doesn't exist.
+    fn unresponsive_channel() -> Channel {
+        let mut channel = Channel::new().unwrap();
+        channel.set_servers(["192.0.2.1"]).unwrap();
+        channel
+    }
+
+    #[test]
+    fn query_handle_cancels_only_its_query() {
+        let mut channel = unresponsive_channel();
+        let (tx1, rx1) = std::sync::mpsc::channel();
+        let (tx2, rx2) = std::sync::mpsc::channel();
+        let handle = channel.query_a("example.com", move |result| {
+            tx1.send(result.err()).unwrap();
+        });
+        let _other = channel.query_a("example.org", move |result| {
+            tx2.send(result.err()).unwrap();
+        });
+
+        handle.cancel();
+        assert_eq!(rx1.try_recv(), Ok(Some(Error::ECANCELLED)));
+        assert!(rx2.try_recv().is_err());
+
+        // Cancelling the channel completes the other query, but not the first one again.
+        channel.cancel();
+        assert_eq!(rx2.try_recv(), Ok(Some(Error::ECANCELLED)));
+        assert!(rx1.try_recv().is_err());
+    }
+
+    #[test]
+    fn query_handle_cancel_after_completion_is_noop() {
+        let mut channel = unresponsive_channel();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let handle = channel.get_name_info(
+            &SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)), 53),
+            NIFlags::LOOKUPHOST,
+            move |result| tx.send(result.err()).unwrap(),
+        );
+        channel.cancel();
+        handle.cancel();
+        assert_eq!(rx.try_recv(), Ok(Some(Error::ECANCELLED)));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn query_handle_for_bad_name() {
+        let mut channel = Channel::new().unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let handle = channel.query_a("ex\0ample.com", move |result| {
+            tx.send(result.err()).unwrap();
+        });
+        handle.cancel();
+        assert_eq!(rx.try_recv(), Ok(Some(Error::EBADNAME)));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[cfg(cares1_28)]
+    #[test]
+    fn query_dnsrec_handle_has_qid() {
+        let mut channel = unresponsive_channel();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let handle = channel
+            .query_dnsrec("example.com", DnsCls::IN, DnsRecordType::A, move |result| {
+                tx.send(result.err()).unwrap();
+            })
+            .unwrap();
+        assert!(handle.qid().is_some());
+        handle.cancel();
+        assert_eq!(rx.try_recv(), Ok(Some(Error::ECANCELLED)));
+    }
+
+    #[test]
+    fn send_raw_rejects_malformed_query() {
+        let mut channel = Channel::new().unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        channel.send_raw(&[0x12, 0x34, 0x01], move |result| {
+            tx.send(result.map(<[u8]>::to_vec)).unwrap();
+        });
+        assert!(rx.recv().unwrap().is_err());
+    }
+
+    // A temporary hosts file, removed on drop.
+    #[cfg(cares1_19)]
+    struct HostsFile(std::path::PathBuf);
+
+    #[cfg(cares1_19)]
+    impl Drop for HostsFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[cfg(cares1_19)]
+    fn hosts_file_channel(test: &str) -> (Channel, HostsFile) {
+        let file_name = format!("c-ares-hosts-{}-{test}", std::process::id());
+        let hosts_file = HostsFile(std::env::temp_dir().join(file_name));
+        std::fs::write(&hosts_file.0, "192.0.2.7 pinned.example pinned\n").unwrap();
+        let mut options = Options::new();
+        options
+            .set_hosts_path(hosts_file.0.to_str().unwrap())
+            .unwrap();
+        (Channel::with_options(options).unwrap(), hosts_file)
+    }
+
+    #[cfg(cares1_19)]
+    #[test]
+    fn get_host_by_name_file_found() {
+        let (mut channel, _hosts_file) = hosts_file_channel("get_host_by_name_file_found");
+        let results = channel
+            .get_host_by_name_file("pinned.example", AddressFamily::INET)
+            .unwrap();
+        let addresses: Vec<_> = results.addresses().collect();
+        assert_eq!(addresses, [IpAddr::V4(Ipv4Addr::new(192, 0, 2, 7))]);
+        assert!(results.aliases().any(|alias| alias == "pinned"));
+    }
+
+    #[cfg(cares1_19)]
+    #[test]
+    fn get_host_by_name_file_not_found() {
+        let (mut channel, _hosts_file) = hosts_file_channel("get_host_by_name_file_not_found");
+        let result = channel.get_host_by_name_file("absent.example", AddressFamily::INET);
+        assert_eq!(result.err(), Some(Error::ENOTFOUND));
+    }
+
+    #[cfg(cares1_19)]
+    #[test]
+    fn get_addrinfo_file_found() {
+        let (mut channel, _hosts_file) = hosts_file_channel("get_addrinfo_file_found");
+        let hints = AddrInfoHints {
+            family: Some(AddressFamily::INET),
+            ..AddrInfoHints::default()
+        };
+        let results = channel
+            .get_addrinfo_file("pinned.example", None, &hints)
+            .unwrap();
+        let addresses: Vec<_> = results.nodes().filter_map(|node| node.ip_addr()).collect();
+        assert_eq!(addresses, [IpAddr::V4(Ipv4Addr::new(192, 0, 2, 7))]);
+    }
+
+    #[cfg(cares1_19)]
+    #[test]
+    fn get_addrinfo_file_not_found() {
+        let (mut channel, _hosts_file) = hosts_file_channel("get_addrinfo_file_not_found");
+        let result = channel.get_addrinfo_file("absent.example", None, &AddrInfoHints::default());
+        assert_eq!(result.err(), Some(Error::ENOTFOUND));
+    }
+
+    #[test]
+    fn get_host_by_name_file_rejects_nul() {
+        let mut channel = Channel::new().unwrap();
+        let result = channel.get_host_by_name_file("ex\0ample", AddressFamily::INET);
+        assert_eq!(result.err(), Some(Error::EBADNAME));
     }
 
     #[test]
@@ -1407,6 +1986,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn channel_set_socket_callbacks() {
+        let configured = Arc::new(Mutex::new(Vec::new()));
+        let configured_clone = Arc::clone(&configured);
+        let mut channel = Channel::new().unwrap();
+        channel
+            .set_socket_created_callback(|_socket, _socket_type| Ok(()))
+            .set_socket_configure_callback(move |_socket, socket_type| {
+                configured_clone.lock().unwrap().push(socket_type);
+                Err(Error::ECONNREFUSED)
+            })
+            .set_servers(["127.0.0.1"])
+            .unwrap();
+        channel.query_a("example.com", |_result| {});
+        assert_eq!(
+            configured.lock().unwrap().first(),
+            Some(&SocketType::Datagram)
+        );
+    }
+
     #[cfg(cares1_34)]
     #[test]
     fn channel_set_pending_write_callback() {
@@ -7,6 +7,10 @@ use std::time::Duration;
 
 use crate::Flags;
 use crate::error::{Error, Result};
+#[cfg(cares1_34)]
+use crate::socket_functions::{SocketFunctions, SocketFunctionsTable};
+#[cfg(cares1_34)]
+use crate::trace::{TraceCallback, TraceEvent};
 #[cfg(cares1_26)]
 use crate::types::EventSys;
 use crate::types::Socket;
@@ -20,7 +24,7 @@ pub(crate) type SocketStateCallback = dyn Fn(Socket, bool, bool) + Send + Sync +
 /// However, in order to detect when such a server has recovered, c-ares will occasionally retry
 /// failed servers.  `ServerFailoverOptions` contains options to control this behaviour.
 #[cfg(cares1_29)]
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ServerFailoverOptions {
     retry_chance: u16,
     retry_delay: Duration,
@@ -43,6 +47,16 @@ impl ServerFailoverOptions {
         Self::default()
     }
 
+    /// The probability (1/N) of retrying a failed server on any given query.
+    pub fn retry_chance(&self) -> u16 {
+        self.retry_chance
+    }
+
+    /// The minimum delay that c-ares will wait before retrying a specific failed server.
+    pub fn retry_delay(&self) -> Duration {
+        self.retry_delay
+    }
+
     /// The `retry_chance` sets the probability (1/N) of retrying a failed server on any given
     /// query.  Setting to a value of 0 disables retries.
     pub fn set_retry_chance(&mut self, retry_chance: u16) -> &mut Self {
@@ -71,6 +85,11 @@ pub struct Options {
     #[cfg(cares1_19)]
     pub(super) hosts_path: Option<CString>,
     pub(super) socket_state_callback: Option<Arc<SocketStateCallback>>,
+    pub(super) sortlist: Vec<String>,
+    #[cfg(cares1_34)]
+    pub(super) socket_functions: Option<SocketFunctionsTable>,
+    #[cfg(cares1_34)]
+    pub(super) trace_callback: Option<Arc<TraceCallback>>,
 }
 
 impl fmt::Debug for Options {
@@ -90,6 +109,11 @@ impl Default for Options {
             #[cfg(cares1_19)]
             hosts_path: None,
             socket_state_callback: None,
+            sortlist: vec![],
+            #[cfg(cares1_34)]
+            socket_functions: None,
+            #[cfg(cares1_34)]
+            trace_callback: None,
         }
     }
 }
@@ -187,6 +211,24 @@ impl Options {
         Ok(self)
     }
 
+    /// Set the sortlist, used to order the addresses returned for host queries.  See
+    /// [`Channel::set_sortlist`](super::Channel::set_sortlist) for the format.
+    pub fn set_sortlist<I, S>(&mut self, sortlist: I) -> Result<&mut Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let sortlist: Vec<_> = sortlist
+            .into_iter()
+            .map(|s| s.as_ref().to_owned())
+            .collect();
+        if sortlist.iter().any(|s| s.contains('\0')) {
+            return Err(Error::EBADSTR);
+        }
+        self.sortlist = sortlist;
+        Ok(self)
+    }
+
     /// Set the callback function to be invoked when a socket changes state.
     ///
     /// `callback(socket, read, write)` will be called when a socket changes state:
@@ -205,6 +247,31 @@ impl Options {
         self
     }
 
+    /// Set the socket functions that c-ares should use in place of the operating system's own.
+    ///
+    /// See [`SocketFunctions`] for details.
+    #[cfg(cares1_34)]
+    pub fn set_socket_functions<S>(&mut self, socket_functions: S) -> &mut Self
+    where
+        S: SocketFunctions,
+    {
+        self.socket_functions = Some(SocketFunctionsTable::new(socket_functions));
+        self
+    }
+
+    /// Set a callback function to be invoked for every DNS message that the channel sends to, or
+    /// receives from, a server.
+    ///
+    /// See [`Channel::set_trace_callback()`](crate::Channel::set_trace_callback) for details.
+    #[cfg(cares1_34)]
+    pub fn set_trace_callback<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&TraceEvent<'_>) + Send + Sync + 'static,
+    {
+        self.trace_callback = Some(Arc::new(callback));
+        self
+    }
+
     /// Set the socket send buffer size.
     pub fn set_sock_send_buffer_size(&mut self, size: u32) -> &mut Self {
         self.ares_options.socket_send_buffer_size = c_int::try_from(size).unwrap_or(c_int::MAX);
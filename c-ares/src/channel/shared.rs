@@ -0,0 +1,1024 @@
+use std::fmt;
+use std::mem::ManuallyDrop;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Mutex;
+use std::time::Duration;
+#[cfg(cares1_34)]
+use std::time::Instant;
+
+use super::{Channel, EffectiveOptions, Sockets};
+#[cfg(cares1_29)]
+use crate::ServerStateFlags;
+use crate::a::AResults;
+use crate::aaaa::AAAAResults;
+use crate::addrinfo::{AddrInfoHints, AddrInfoResults};
+use crate::caa::CAAResults;
+use crate::cname::CNameResults;
+#[cfg(cares1_28)]
+use crate::dns::{DnsCls, DnsRecord, DnsRecordType};
+use crate::error::Result;
+#[cfg(cares1_34)]
+use crate::events::{FdEvents, ProcessFlags};
+use crate::host::HostResults;
+use crate::mx::MXResults;
+use crate::nameinfo::NameInfoResult;
+use crate::naptr::NAPTRResults;
+use crate::ni_flags::NIFlags;
+use crate::ns::NSResults;
+use crate::ptr::PTRResults;
+use crate::query_handle::QueryHandle;
+#[cfg(cares1_24)]
+use crate::server_config::ServerConfig;
+use crate::soa::SOAResult;
+#[cfg(cares1_34)]
+use crate::socket_functions::SocketFunctions;
+use crate::srv::SRVResults;
+#[cfg(cares1_34)]
+use crate::trace::TraceEvent;
+use crate::txt::TXTResults;
+use crate::types::{AddressFamily, Socket, SocketType};
+use crate::uri::URIResults;
+use crate::utils::thread_safety;
+
+/// A [`Channel`] that can be used from many threads at once, without a lock.
+///
+/// This is available only when the underlying c-ares library was built with thread safety - see
+/// [`thread_safety()`](crate::thread_safety) - so that c-ares does its own locking.  Queries,
+/// event processing and configuration then go straight to c-ares, through `&self`.
+///
+/// Setting a callback does take a lock, since the `SharedChannel` must own the callback.  Because
+/// another thread might be running a callback at the moment that it is replaced, callbacks that
+/// are replaced are kept alive until a callback is set at a moment when the channel has no
+/// queries in flight, or else until the `SharedChannel` is dropped.  (Before c-ares 1.27 they are
+/// always kept until the `SharedChannel` is dropped.)  Replacing callbacks over and over on a
+/// busy channel therefore holds on to all of the old ones.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// let channel = c_ares::Channel::new().unwrap();
+/// let channel = Arc::new(c_ares::SharedChannel::new(channel).expect("c-ares is not thread-safe"));
+/// let threads: Vec<_> = ["example.com", "example.org"]
+///     .into_iter()
+///     .map(|name| {
+///         let channel = Arc::clone(&channel);
+///         std::thread::spawn(move || {
+///             channel.query_a(name, |result| println!("{result:?}"));
+///         })
+///     })
+///     .collect();
+/// for thread in threads {
+///     thread.join().unwrap();
+/// }
+///
+/// // ... and meanwhile, drive the channel from an event loop, or have c-ares do so on its
+/// // built-in event thread.
+/// ```
+pub struct SharedChannel {
+    // A copy of the channel's own pointer, through which calls bypass the lock.
+    ares_channel: c_ares_sys::ares_channel,
+
+    inner: Mutex<Inner>,
+
+    // Used by `poll_once()` and `run_until_idle()`, and created on first use.
+    #[cfg(cares1_34)]
+    poller: Mutex<Option<polling::Poller>>,
+}
+
+struct Inner {
+    // Dropped first, so that c-ares is done with the retired callbacks before they go.
+    channel: Channel,
+
+    // Callbacks that have been replaced, which another thread may still have been running.
+    retired: Vec<Box<dyn Send + Sync>>,
+}
+
+impl Inner {
+    // Keep a replaced callback until it is safe to drop.
+    fn retire(&mut self, callback: Box<dyn Send + Sync>) {
+        self.retired.push(callback);
+    }
+
+    // Drop the retired callbacks if the channel is quiescent.  With no queries in flight, c-ares
+    // is not in the middle of sending or processing anything, so it cannot still be running a
+    // callback that has since been replaced.
+    fn collect_retired(&mut self) {
+        #[cfg(cares1_27)]
+        if self.channel.queue_active_queries() == 0 {
+            self.retired.clear();
+        }
+    }
+}
+
+impl SharedChannel {
+    /// Share `channel` between threads.
+    ///
+    /// Fails, handing back the channel, if c-ares was not built with thread safety.
+    // Handing back the channel on failure is the point; boxing it would only get in the way.
+    #[allow(clippy::result_large_err)]
+    pub fn new(channel: Channel) -> std::result::Result<Self, Channel> {
+        if !thread_safety() {
+            return Err(channel);
+        }
+        let inner = Inner {
+            channel,
+            retired: Vec::new(),
+        };
+        Ok(Self {
+            ares_channel: inner.channel.ares_channel,
+            inner: Mutex::new(inner),
+            #[cfg(cares1_34)]
+            poller: Mutex::new(None),
+        })
+    }
+
+    /// Recover the `Channel`.
+    ///
+    /// Callbacks that were replaced while the channel was shared are dropped.
+    pub fn into_inner(self) -> Channel {
+        let inner = self
+            .inner
+            .into_inner()
+            .unwrap_or_else(|err| err.into_inner());
+        inner.channel
+    }
+
+    /// Returns the raw `ares_channel` pointer.
+    ///
+    /// The returned pointer is only valid for the lifetime of this `SharedChannel`.
+    pub fn as_raw(&self) -> c_ares_sys::ares_channel {
+        self.ares_channel
+    }
+
+    // A `Channel` through which to call into c-ares without the lock.  It owns none of the
+    // callbacks, and must not be dropped: that would destroy the underlying channel.
+    fn view(&self) -> ManuallyDrop<Channel> {
+        ManuallyDrop::new(Channel {
+            ares_channel: self.ares_channel,
+            socket_state_callback: None,
+            socket_created_callback: None,
+            socket_configure_callback: None,
+            #[cfg(cares1_29)]
+            server_state_callback: None,
+            #[cfg(cares1_34)]
+            pending_write_callback: None,
+            #[cfg(cares1_34)]
+            socket_functions: None,
+            #[cfg(cares1_34)]
+            trace_callback: None,
+            #[cfg(cares1_34)]
+            tracing_socket_functions: None,
+            #[cfg(cares1_34)]
+            traced_sockets: Default::default(),
+            #[cfg(cares1_34)]
+            poller: None,
+            sortlist: vec![],
+        })
+    }
+
+    /// Reinitialize a channel from system configuration.
+    #[cfg(cares1_22)]
+    pub fn reinit(&self) -> Result<&Self> {
+        self.view().reinit()?;
+        Ok(self)
+    }
+
+    /// Returns a snapshot of the options that this channel was configured with.
+    ///
+    /// c-ares does not report the values that it read from the system configuration: see
+    /// [`EffectiveOptions`].
+    pub fn effective_options(&self) -> Result<EffectiveOptions> {
+        self.inner.lock().unwrap().channel.effective_options()
+    }
+
+    /// Duplicate a channel.
+    pub fn try_clone(&self) -> Result<Channel> {
+        self.inner.lock().unwrap().channel.try_clone()
+    }
+
+    /// Handle input, output, and timeout events associated with the specified file descriptors
+    /// (sockets).
+    ///
+    /// Providing a value for `read_fd` indicates that the identified socket is readable; likewise
+    /// providing a value for `write_fd` indicates that the identified socket is writable.  Use
+    /// `None` for "no action".
+    pub fn process_fd(&self, read_fd: Option<Socket>, write_fd: Option<Socket>) {
+        self.view().process_fd(read_fd, write_fd)
+    }
+
+    /// Handle input and output events associated with the specified file descriptors (sockets).
+    /// Also handles timeouts associated with the `Channel`.
+    pub fn process(&self, read_fds: &mut c_types::fd_set, write_fds: &mut c_types::fd_set) {
+        self.view().process(read_fds, write_fds)
+    }
+
+    /// Process events on multiple file descriptors based on the event mask associated with each
+    /// file descriptor.  Recommended over calling `process_fd()` multiple times since it would
+    /// trigger additional logic such as timeout processing on each call.
+    #[cfg(cares1_34)]
+    pub fn process_fds(&self, events: &[FdEvents], flags: ProcessFlags) -> Result<()> {
+        self.view().process_fds(events, flags)
+    }
+
+    /// Retrieve the set of socket descriptors which the calling application should wait on for
+    /// reading and / or writing.
+    pub fn sockets(&self) -> Sockets {
+        self.view().sockets()
+    }
+
+    /// Retrieve the set of socket descriptors which the calling application should wait on for
+    /// reading and / or writing.
+    pub fn fds(&self, read_fds: &mut c_types::fd_set, write_fds: &mut c_types::fd_set) -> u32 {
+        self.view().fds(read_fds, write_fds)
+    }
+
+    /// Set the list of servers to contact, instead of the servers specified in resolv.conf or the
+    /// local named.
+    ///
+    /// String format is `host[:port]`.  IPv6 addresses with ports require square brackets eg
+    /// `[2001:4860:4860::8888]:53`.
+    pub fn set_servers<I, S>(&self, servers: I) -> Result<&Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.view().set_servers(servers)?;
+        Ok(self)
+    }
+
+    /// Retrieves the list of configured servers.
+    ///
+    /// Each entry is in `host[:port]` format, matching what [`set_servers`](Self::set_servers)
+    /// accepts.
+    #[cfg(cares1_24)]
+    pub fn servers(&self) -> Vec<String> {
+        self.view().servers()
+    }
+
+    /// Set the list of servers to contact, from structured [`ServerConfig`]s.
+    ///
+    /// This is equivalent to [`set_servers`](Self::set_servers), but allows the TCP port,
+    /// link-local interface and scope id of each server to be specified.
+    #[cfg(cares1_24)]
+    pub fn set_servers_config<'a, I>(&self, servers: I) -> Result<&Self>
+    where
+        I: IntoIterator<Item = &'a ServerConfig>,
+    {
+        self.view().set_servers_config(servers)?;
+        Ok(self)
+    }
+
+    /// Retrieves the list of configured servers, as structured [`ServerConfig`]s.
+    ///
+    /// Fails if c-ares reports a server that cannot be parsed as a `ServerConfig`.
+    #[cfg(cares1_24)]
+    pub fn servers_config(&self) -> Result<Vec<ServerConfig>> {
+        self.view().servers_config()
+    }
+
+    /// Set the local IPv4 address from which to make queries.
+    pub fn set_local_ipv4(&self, ipv4: Ipv4Addr) -> &Self {
+        self.view().set_local_ipv4(ipv4);
+        self
+    }
+
+    /// Set the local IPv6 address from which to make queries.
+    pub fn set_local_ipv6(&self, ipv6: Ipv6Addr) -> &Self {
+        self.view().set_local_ipv6(ipv6);
+        self
+    }
+
+    /// Set the local device from which to make queries.
+    pub fn set_local_device(&self, device: &str) -> Result<&Self> {
+        self.view().set_local_device(device)?;
+        Ok(self)
+    }
+
+    /// Initializes an address sortlist configuration, so that addresses returned by
+    /// `get_host_by_name()` are sorted according to the sortlist.
+    ///
+    /// Each element of the sortlist holds an IP-address/netmask pair. The netmask is optional but
+    /// follows the address after a slash if present. For example: "130.155.160.0/255.255.240.0",
+    /// or "130.155.0.0".
+    pub fn set_sortlist<I, S>(&self, sortlist: I) -> Result<&Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.inner.lock().unwrap().channel.set_sortlist(sortlist)?;
+        Ok(self)
+    }
+
+    /// Set a callback function to be invoked after a socket has been created and connected.
+    ///
+    /// `callback(socket, socket_type)` is called with the new socket and its type.  Returning an
+    /// error causes c-ares to abandon the connection.
+    pub fn set_socket_created_callback<F>(&self, callback: F) -> &Self
+    where
+        F: Fn(Socket, SocketType) -> Result<()> + Send + Sync + 'static,
+    {
+        let inner = &mut *self.inner.lock().unwrap();
+        inner.retire(Box::new(inner.channel.socket_created_callback.clone()));
+        inner.channel.set_socket_created_callback(callback);
+        inner.collect_retired();
+        self
+    }
+
+    /// Set a callback function to be invoked after a socket has been created, but before it is
+    /// connected.
+    ///
+    /// This is the place to apply socket options - for example `SO_MARK`, `IP_TOS` or
+    /// `SO_BINDTODEVICE`.  `callback(socket, socket_type)` is called with the new socket and its
+    /// type.  Returning an error causes c-ares to abandon the connection.
+    pub fn set_socket_configure_callback<F>(&self, callback: F) -> &Self
+    where
+        F: Fn(Socket, SocketType) -> Result<()> + Send + Sync + 'static,
+    {
+        let inner = &mut *self.inner.lock().unwrap();
+        inner.retire(Box::new(inner.channel.socket_configure_callback.clone()));
+        inner.channel.set_socket_configure_callback(callback);
+        inner.collect_retired();
+        self
+    }
+
+    /// Set a callback function to be invoked whenever a query on the channel completes.
+    ///
+    /// `callback(server, success, flags)` will be called when a query completes.
+    ///
+    /// - `server` indicates the DNS server that was used for the query.
+    /// - `success` indicates whether the query succeeded or not.
+    /// - `flags` is a bitmask of flags describing various aspects of the query.
+    #[cfg(cares1_29)]
+    pub fn set_server_state_callback<F>(&self, callback: F) -> &Self
+    where
+        F: Fn(&str, bool, ServerStateFlags) + Send + Sync + 'static,
+    {
+        let inner = &mut *self.inner.lock().unwrap();
+        inner.retire(Box::new(inner.channel.server_state_callback.clone()));
+        inner.channel.set_server_state_callback(callback);
+        inner.collect_retired();
+        self
+    }
+
+    /// Set a callback function to be invoked when there is potential pending data
+    /// which needs to be written.
+    #[cfg(cares1_34)]
+    pub fn set_pending_write_callback<F>(&self, callback: F) -> &Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let inner = &mut *self.inner.lock().unwrap();
+        inner.retire(Box::new(inner.channel.pending_write_callback.clone()));
+        inner.channel.set_pending_write_callback(callback);
+        inner.collect_retired();
+        self
+    }
+
+    /// Set the socket functions that c-ares should use in place of the operating system's own.
+    ///
+    /// This should be called before any queries are made on the channel.  See
+    /// [`SocketFunctions`] for details.
+    #[cfg(cares1_34)]
+    pub fn set_socket_functions<S>(&self, socket_functions: S) -> Result<&Self>
+    where
+        S: SocketFunctions,
+    {
+        let inner = &mut *self.inner.lock().unwrap();
+        inner.retire(Box::new(inner.channel.socket_functions.clone()));
+        inner.retire(Box::new(inner.channel.tracing_socket_functions.clone()));
+        inner.channel.set_socket_functions(socket_functions)?;
+        inner.collect_retired();
+        Ok(self)
+    }
+
+    /// Set a callback function to be invoked for every DNS message that the channel sends to, or
+    /// receives from, a server.
+    ///
+    /// `callback(event)` is called with each message, together with the server's address, the
+    /// transport and a timestamp: see [`TraceEvent`].  The callback runs on whichever thread is
+    /// processing the channel's sockets.
+    ///
+    /// Tracing works by wrapping the channel's socket functions - those set by
+    /// [`set_socket_functions()`](Self::set_socket_functions), or else
+    /// [`SystemSocketFunctions`](crate::SystemSocketFunctions) - so this should be called before
+    /// any queries are made on the channel.  Connections that the channel opened before tracing
+    /// began are never traced.  Replacing the trace callback, or the socket functions, carries on
+    /// tracing the connections that were already traced.
+    #[cfg(cares1_34)]
+    pub fn set_trace_callback<F>(&self, callback: F) -> Result<&Self>
+    where
+        F: Fn(&TraceEvent<'_>) + Send + Sync + 'static,
+    {
+        let inner = &mut *self.inner.lock().unwrap();
+        inner.retire(Box::new(inner.channel.trace_callback.clone()));
+        inner.retire(Box::new(inner.channel.tracing_socket_functions.clone()));
+        inner.channel.set_trace_callback(callback)?;
+        inner.collect_retired();
+        Ok(self)
+    }
+
+    /// Initiate a single-question DNS query for the A records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_a<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<AResults>) + Send + 'static,
+    {
+        self.view().query_a(name, handler)
+    }
+
+    /// Initiate a series of single-question DNS queries for the A records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn search_a<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<AResults>) + Send + 'static,
+    {
+        self.view().search_a(name, handler)
+    }
+
+    /// Initiate a single-question DNS query for the AAAA records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_aaaa<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<AAAAResults>) + Send + 'static,
+    {
+        self.view().query_aaaa(name, handler)
+    }
+
+    /// Initiate a series of single-question DNS queries for the AAAA records associated with
+    /// `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn search_aaaa<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<AAAAResults>) + Send + 'static,
+    {
+        self.view().search_aaaa(name, handler)
+    }
+
+    /// Initiate a single-question DNS query for the CAA records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_caa<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<CAAResults>) + Send + 'static,
+    {
+        self.view().query_caa(name, handler)
+    }
+
+    /// Initiate a series of single-question DNS queries for the CAA records associated with
+    /// `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn search_caa<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<CAAResults>) + Send + 'static,
+    {
+        self.view().search_caa(name, handler)
+    }
+
+    /// Initiate a single-question DNS query for the CNAME records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_cname<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<CNameResults>) + Send + 'static,
+    {
+        self.view().query_cname(name, handler)
+    }
+
+    /// Initiate a series of single-question DNS queries for the CNAME records associated with
+    /// `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn search_cname<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<CNameResults>) + Send + 'static,
+    {
+        self.view().search_cname(name, handler)
+    }
+
+    /// Initiate a single-question DNS query for the MX records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_mx<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<MXResults>) + Send + 'static,
+    {
+        self.view().query_mx(name, handler)
+    }
+
+    /// Initiate a series of single-question DNS queries for the MX records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn search_mx<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<MXResults>) + Send + 'static,
+    {
+        self.view().search_mx(name, handler)
+    }
+
+    /// Initiate a single-question DNS query for the NAPTR records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_naptr<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<NAPTRResults>) + Send + 'static,
+    {
+        self.view().query_naptr(name, handler)
+    }
+
+    /// Initiate a series of single-question DNS queries for the NAPTR records associated with
+    /// `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn search_naptr<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<NAPTRResults>) + Send + 'static,
+    {
+        self.view().search_naptr(name, handler)
+    }
+
+    /// Initiate a single-question DNS query for the NS records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_ns<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<NSResults>) + Send + 'static,
+    {
+        self.view().query_ns(name, handler)
+    }
+
+    /// Initiate a series of single-question DNS queries for the NS records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn search_ns<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<NSResults>) + Send + 'static,
+    {
+        self.view().search_ns(name, handler)
+    }
+
+    /// Initiate a single-question DNS query for the PTR records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_ptr<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<PTRResults>) + Send + 'static,
+    {
+        self.view().query_ptr(name, handler)
+    }
+
+    /// Initiate a series of single-question DNS queries for the PTR records associated with
+    /// `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn search_ptr<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<PTRResults>) + Send + 'static,
+    {
+        self.view().search_ptr(name, handler)
+    }
+
+    /// Initiate a single-question DNS query for the SOA records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_soa<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<SOAResult>) + Send + 'static,
+    {
+        self.view().query_soa(name, handler)
+    }
+
+    /// Initiate a series of single-question DNS queries for the SOA records associated with
+    /// `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn search_soa<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<SOAResult>) + Send + 'static,
+    {
+        self.view().search_soa(name, handler)
+    }
+
+    /// Initiate a single-question DNS query for the SRV records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_srv<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<SRVResults>) + Send + 'static,
+    {
+        self.view().query_srv(name, handler)
+    }
+
+    /// Initiate a series of single-question DNS queries for the SRV records associated with
+    /// `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn search_srv<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<SRVResults>) + Send + 'static,
+    {
+        self.view().search_srv(name, handler)
+    }
+
+    /// Initiate a single-question DNS query for the TXT records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_txt<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<TXTResults>) + Send + 'static,
+    {
+        self.view().query_txt(name, handler)
+    }
+
+    /// Initiate a series of single-question DNS queries for the TXT records associated with
+    /// `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn search_txt<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<TXTResults>) + Send + 'static,
+    {
+        self.view().search_txt(name, handler)
+    }
+
+    /// Initiate a single-question DNS query for the URI records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_uri<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<URIResults>) + Send + 'static,
+    {
+        self.view().query_uri(name, handler)
+    }
+
+    /// Initiate a series of single-question DNS queries for the URI records associated with
+    /// `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn search_uri<F>(&self, name: &str, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<URIResults>) + Send + 'static,
+    {
+        self.view().search_uri(name, handler)
+    }
+
+    /// Perform a host query by address.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn get_host_by_address<F>(&self, address: &IpAddr, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<&HostResults>) + Send + 'static,
+    {
+        self.view().get_host_by_address(address, handler)
+    }
+
+    /// Perform a host query by name.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn get_host_by_name<F>(&self, name: &str, family: AddressFamily, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<&HostResults>) + Send + 'static,
+    {
+        self.view().get_host_by_name(name, family, handler)
+    }
+
+    /// Look up `name` in the hosts file only, without contacting any DNS server.
+    ///
+    /// The hosts file is the channel's configured hosts path, or the system default.  Returns
+    /// [`Error::ENOTFOUND`](crate::Error::ENOTFOUND) if the hosts file has no entry for `name`.
+    pub fn get_host_by_name_file(&self, name: &str, family: AddressFamily) -> Result<HostResults> {
+        self.view().get_host_by_name_file(name, family)
+    }
+
+    /// Look up `name` and `service` in the hosts file only, without contacting any DNS server.
+    ///
+    /// This is the addrinfo-style counterpart of
+    /// [`get_host_by_name_file`](Self::get_host_by_name_file).  It performs the lookup on a
+    /// temporary channel that shares this channel's configured options, except that only the hosts
+    /// file is consulted.  The results are not sorted, since sorting probes the routes to each
+    /// address.  Returns [`Error::ENOTFOUND`](crate::Error::ENOTFOUND) if the hosts file has no
+    /// entry for `name`.
+    pub fn get_addrinfo_file(
+        &self,
+        name: &str,
+        service: Option<&str>,
+        hints: &AddrInfoHints,
+    ) -> Result<AddrInfoResults> {
+        self.inner
+            .lock()
+            .unwrap()
+            .channel
+            .get_addrinfo_file(name, service, hints)
+    }
+
+    /// Address-to-nodename translation in protocol-independent manner.
+    ///
+    /// The valid values for `flags` are documented [here](ni_flags/index.html).
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn get_name_info<F>(&self, address: &SocketAddr, flags: NIFlags, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<NameInfoResult>) + Send + 'static,
+    {
+        self.view().get_name_info(address, flags, handler)
+    }
+
+    /// Initiate a host query by name and service.
+    ///
+    /// The `hints` parameter controls the desired address family, socket type, protocol, and
+    /// behaviour flags.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn get_addrinfo<F>(
+        &self,
+        name: &str,
+        service: Option<&str>,
+        hints: &AddrInfoHints,
+        handler: F,
+    ) -> QueryHandle
+    where
+        F: FnOnce(Result<AddrInfoResults>) + Send + 'static,
+    {
+        self.view().get_addrinfo(name, service, hints, handler)
+    }
+
+    /// Initiate a single-question DNS query for `name`.  The class and type of the query are per
+    /// the provided parameters, taking values as defined in `arpa/nameser.h`.
+    ///
+    /// On completion, `handler` is called with the result.
+    ///
+    /// This method is provided so that users can query DNS types for which `c-ares` does not
+    /// provide a parser.  This is expected to be a last resort; if a suitable `query_xxx()` is
+    /// available, that should be preferred.
+    pub fn query<F>(&self, name: &str, dns_class: u16, query_type: u16, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<&[u8]>) + Send + 'static,
+    {
+        self.view().query(name, dns_class, query_type, handler)
+    }
+
+    /// Initiate a series of single-question DNS queries for `name`.  The class and type of the
+    /// query are per the provided parameters, taking values as defined in `arpa/nameser.h`.
+    ///
+    /// On completion, `handler` is called with the result.
+    ///
+    /// This method is provided so that users can search DNS types for which `c-ares` does not
+    /// provide a parser.  This is expected to be a last resort; if a suitable `search_xxx()` is
+    /// available, that should be preferred.
+    pub fn search<F>(&self, name: &str, dns_class: u16, query_type: u16, handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<&[u8]>) + Send + 'static,
+    {
+        self.view().search(name, dns_class, query_type, handler)
+    }
+
+    /// Send a DNS query that is already in wire format.
+    ///
+    /// The query is subject to the channel's usual retry and server failover behaviour.  On
+    /// completion, `handler` is called with the raw response.
+    ///
+    /// This method is provided for callers - such as proxies - that already hold queries as bytes,
+    /// and so would gain nothing by parsing them into a [`DnsRecord`](crate::DnsRecord) only to
+    /// have them serialized again.
+    ///
+    /// c-ares sends the query under a query ID of its own choosing, and the server answers with
+    /// that ID.  The response is handed back with the ID from `query` restored, so that it
+    /// matches the query as the caller knows it.
+    pub fn send_raw<F>(&self, query: &[u8], handler: F) -> QueryHandle
+    where
+        F: FnOnce(Result<&[u8]>) + Send + 'static,
+    {
+        self.view().send_raw(query, handler)
+    }
+
+    /// Send a DNS query using a pre-built [`DnsRecord`].
+    ///
+    /// On completion, `handler` is called with a `Result<DnsRecord>` containing
+    /// the parsed response.
+    ///
+    /// Returns a handle to the query, carrying its query ID, on success.
+    #[cfg(cares1_28)]
+    pub fn send_dnsrec<F>(&self, dnsrec: &DnsRecord, handler: F) -> Result<QueryHandle>
+    where
+        F: FnOnce(Result<&DnsRecord>) + Send + 'static,
+    {
+        self.view().send_dnsrec(dnsrec, handler)
+    }
+
+    /// Initiate a DNS query for `name` with the given class and type, receiving
+    /// a parsed [`DnsRecord`] in the callback.
+    ///
+    /// Returns a handle to the query, carrying its query ID, on success.
+    #[cfg(cares1_28)]
+    pub fn query_dnsrec<F>(
+        &self,
+        name: &str,
+        dns_class: DnsCls,
+        query_type: DnsRecordType,
+        handler: F,
+    ) -> Result<QueryHandle>
+    where
+        F: FnOnce(Result<&DnsRecord>) + Send + 'static,
+    {
+        self.view()
+            .query_dnsrec(name, dns_class, query_type, handler)
+    }
+
+    /// Initiate a series of DNS queries using a pre-built [`DnsRecord`],
+    /// receiving a parsed [`DnsRecord`] in the callback.
+    #[cfg(cares1_28)]
+    pub fn search_dnsrec<F>(&self, dnsrec: &DnsRecord, handler: F) -> Result<QueryHandle>
+    where
+        F: FnOnce(Result<&DnsRecord>) + Send + 'static,
+    {
+        self.view().search_dnsrec(dnsrec, handler)
+    }
+
+    /// Cancel all requests made on this channel.
+    ///
+    /// Callbacks will be invoked for each pending query, passing a result
+    /// `Err(Error::ECANCELLED)`.
+    pub fn cancel(&self) {
+        self.view().cancel()
+    }
+
+    /// Kick c-ares to process a pending write.
+    #[cfg(cares1_34)]
+    pub fn process_pending_write(&self) {
+        self.view().process_pending_write()
+    }
+
+    /// Return the maximum time to wait before processing timeouts.
+    ///
+    /// If there are pending queries, returns the time until the next timeout
+    /// fires, optionally capped at `max_timeout`. If there are no pending
+    /// queries, returns `max_timeout` (which may be `None`).
+    pub fn timeout(&self, max_timeout: Option<Duration>) -> Option<Duration> {
+        self.view().timeout(max_timeout)
+    }
+
+    /// Block until notified that there are no longer any queries in queue, or
+    /// the specified timeout has expired.
+    ///
+    /// Pass `None` to wait indefinitely.
+    #[cfg(cares1_27)]
+    pub fn queue_wait_empty(&self, timeout: Option<Duration>) -> Result<()> {
+        self.view().queue_wait_empty(timeout)
+    }
+
+    /// Retrieve the total number of active queries pending answers from servers.
+    ///
+    /// Some c-ares requests may spawn multiple queries, such as
+    /// `get_addrinfo()` when using `AddressFamily::UNSPEC`, which will be
+    /// reflected in this number.
+    #[cfg(cares1_27)]
+    pub fn queue_active_queries(&self) -> usize {
+        self.view().queue_active_queries()
+    }
+
+    /// Wait up to `max_wait` for activity on the channel's sockets, then process that activity
+    /// along with any timeouts that have expired.
+    ///
+    /// The wait ends early when a socket becomes ready, or when the channel's next timeout is due.
+    /// If the channel has no queries pending, this returns at once.
+    ///
+    /// Returns whether the channel still has queries pending.
+    ///
+    /// This provides a simple single-threaded way to drive a channel that does not use the
+    /// built-in event thread.  Applications with an event loop of their own should instead wait
+    /// on [`sockets()`](Self::sockets) and call [`process_fds()`](Self::process_fds) themselves.
+    ///
+    /// Only one thread at a time drives the channel this way: calls to this and to
+    /// [`run_until_idle()`](Self::run_until_idle) from other threads wait their turn.
+    ///
+    /// The channel's sockets are waited on as operating system sockets, so custom
+    /// [`SocketFunctions`](crate::SocketFunctions) must hand out real file descriptors to be
+    /// driven this way.  Sockets that cannot be waited on are skipped: queries on them can only
+    /// time out.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::EFILE`](crate::Error::EFILE) if waiting on the sockets fails, and
+    /// otherwise any error from `process_fds()`.
+    #[cfg(cares1_34)]
+    pub fn poll_once(&self, max_wait: Duration) -> Result<bool> {
+        self.with_poller(|poller| self.view().poll_once_with(poller, max_wait))
+    }
+
+    /// Drive the channel until it has no queries pending, or `deadline` passes.
+    ///
+    /// Pass `None` to wait for as long as it takes.  Since every query eventually completes or
+    /// times out, this always returns.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ETIMEOUT`](crate::Error::ETIMEOUT) if queries are still pending at the
+    /// deadline, and otherwise any error from [`poll_once()`](Self::poll_once).
+    #[cfg(cares1_34)]
+    pub fn run_until_idle(&self, deadline: Option<Instant>) -> Result<()> {
+        self.with_poller(|poller| self.view().run_until_idle_with(poller, deadline))
+    }
+
+    // Run `f` with the channel's poller, creating it if this is the first use.  Only one thread
+    // at a time can wait on the poller, so other threads driving the channel wait their turn.
+    #[cfg(cares1_34)]
+    fn with_poller<T>(&self, f: impl FnOnce(&polling::Poller) -> Result<T>) -> Result<T> {
+        let mut poller = self.poller.lock().unwrap();
+        let poller = match &mut *poller {
+            Some(poller) => poller,
+            None => poller.insert(polling::Poller::new().map_err(|_| crate::Error::EFILE)?),
+        };
+        f(poller)
+    }
+}
+
+unsafe impl Send for SharedChannel {}
+unsafe impl Sync for SharedChannel {}
+
+impl fmt::Debug for SharedChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedChannel").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+
+    #[test]
+    fn shared_channel_is_send() {
+        assert_send::<SharedChannel>();
+    }
+
+    #[test]
+    fn shared_channel_is_sync() {
+        assert_sync::<SharedChannel>();
+    }
+
+    #[test]
+    fn new_requires_thread_safety() {
+        let channel = Channel::new().unwrap();
+        let shared = SharedChannel::new(channel);
+        assert_eq!(shared.is_ok(), thread_safety());
+    }
+
+    #[test]
+    fn into_inner_returns_channel() {
+        let channel = Channel::new().unwrap();
+        let ares_channel = channel.as_raw();
+        if let Ok(shared) = SharedChannel::new(channel) {
+            assert_eq!(shared.as_raw(), ares_channel);
+            assert_eq!(shared.into_inner().as_raw(), ares_channel);
+        }
+    }
+
+    #[cfg(cares1_27)]
+    #[test]
+    fn replaced_callbacks_freed_when_idle() {
+        let token = Arc::new(());
+        let callback = |token: &Arc<()>| {
+            let token = Arc::clone(token);
+            move |_: Socket, _: SocketType| {
+                let _ = &token;
+                Ok(())
+            }
+        };
+        if let Ok(shared) = SharedChannel::new(Channel::new().unwrap()) {
+            shared.set_socket_configure_callback(callback(&token));
+            shared.set_socket_configure_callback(callback(&token));
+            assert_eq!(Arc::strong_count(&token), 2);
+        }
+    }
+
+    // Every public method on `Channel` should be available on `SharedChannel` too, except for the
+    // constructor that a `SharedChannel` has no need of.
+    #[test]
+    fn shared_channel_has_every_channel_method() {
+        let methods = |source: &'static str| -> Vec<&'static str> {
+            source
+                .lines()
+                .filter_map(|line| line.strip_prefix("    pub fn "))
+                .filter_map(|rest| rest.split(['(', '<']).next())
+                .collect()
+        };
+        let shared = methods(include_str!("shared.rs"));
+        for source in [include_str!("mod.rs"), include_str!("driver.rs")] {
+            for method in methods(source) {
+                assert!(
+                    method == "with_options" || shared.contains(&method),
+                    "SharedChannel has no {method}()"
+                );
+            }
+        }
+    }
+}
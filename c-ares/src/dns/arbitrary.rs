@@ -0,0 +1,847 @@
+//! [`proptest`] generators for DNS messages and resource records.
+//!
+//! [`DnsRecord`], [`DnsRecordType`] and [`ArbitraryRr`] implement
+//! [`Arbitrary`], so that `any::<DnsRecord>()` generates messages that c-ares
+//! can write and parse again.  Because a [`DnsRr`] only exists inside a
+//! `DnsRecord`, resource records are generated as owned [`ArbitraryRr`]
+//! values: add one to a message with [`ArbitraryRr::add_to`], and read one
+//! back with [`ArbitraryRr::from_rr`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use c_ares::{
+//!     ArbitraryRr, DnsCls, DnsFlags, DnsOpcode, DnsParseFlags, DnsRcode, DnsRecord,
+//!     DnsRecordType,
+//! };
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     #[test]
+//!     fn survives_my_handling(rr in any::<ArbitraryRr>()) {
+//!         let mut rec = DnsRecord::new(0, DnsFlags::QR, DnsOpcode::Query, DnsRcode::NoError)?;
+//!         // c-ares parses only messages with exactly one question.
+//!         rec.query_add("example.com", DnsRecordType::A, DnsCls::IN)?;
+//!         rr.add_to(&mut rec)?;
+//!         let wire = rec.write()?;
+//!         let rec = DnsRecord::parse(&wire, DnsParseFlags::empty())?;
+//!         // ... hand `rec` to the code under test ...
+//!     }
+//! }
+//! ```
+//!
+//! Generated values stay within what survives a round trip through the wire
+//! format: names are lower-case, TTLs fit in 31 bits, and option and
+//! parameter codes are unique and in ascending order.
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use proptest::collection::{btree_map, vec};
+use proptest::option;
+use proptest::prelude::*;
+use proptest::sample::select;
+
+use crate::error::Result;
+
+use super::enums::{DnsCls, DnsFlags, DnsOpcode, DnsRcode, DnsRecordType, DnsRrKey, DnsSection};
+use super::record::DnsRecord;
+use super::rr::DnsRr;
+use super::typed::TypedRr;
+
+// The record types that have typed views, and so can be generated.
+const RECORD_TYPES: [DnsRecordType; 19] = [
+    DnsRecordType::A,
+    DnsRecordType::NS,
+    DnsRecordType::CNAME,
+    DnsRecordType::SOA,
+    DnsRecordType::PTR,
+    DnsRecordType::HINFO,
+    DnsRecordType::MX,
+    DnsRecordType::TXT,
+    DnsRecordType::SIG,
+    DnsRecordType::AAAA,
+    DnsRecordType::SRV,
+    DnsRecordType::NAPTR,
+    DnsRecordType::OPT,
+    DnsRecordType::TLSA,
+    DnsRecordType::SVCB,
+    DnsRecordType::HTTPS,
+    DnsRecordType::URI,
+    DnsRecordType::CAA,
+    DnsRecordType::RAW_RR,
+];
+
+/// A resource record, held outside any [`DnsRecord`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ArbitraryRr {
+    /// The section that the record belongs in.
+    pub section: DnsSection,
+    /// The owner name.
+    pub name: String,
+    /// The TTL in seconds.
+    pub ttl: u32,
+    /// The type-specific fields.
+    pub rdata: ArbitraryRdata,
+}
+
+/// The type-specific fields of an [`ArbitraryRr`], one variant per typed
+/// view in [`TypedRr`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ArbitraryRdata {
+    /// IPv4 address record.
+    A(Ipv4Addr),
+    /// IPv6 address record.
+    Aaaa(Ipv6Addr),
+    /// Authoritative nameserver record, holding the nameserver's name.
+    Ns(String),
+    /// Canonical name record, holding the canonical name.
+    Cname(String),
+    /// Start of authority record.
+    Soa {
+        /// The primary nameserver for the zone.
+        mname: String,
+        /// The mailbox of the person responsible for the zone.
+        rname: String,
+        /// The zone's serial number.
+        serial: u32,
+        /// Seconds before secondaries should refresh the zone.
+        refresh: u32,
+        /// Seconds before secondaries should retry a failed refresh.
+        retry: u32,
+        /// Seconds after which secondaries stop answering for the zone.
+        expire: u32,
+        /// The TTL for negative responses.
+        minimum: u32,
+    },
+    /// Domain name pointer record, holding the name pointed to.
+    Ptr(String),
+    /// Host information record.
+    Hinfo {
+        /// The CPU type.
+        cpu: String,
+        /// The operating system.
+        os: String,
+    },
+    /// Mail exchange record.
+    Mx {
+        /// The preference, lower values being preferred.
+        preference: u16,
+        /// The mail exchanger's name.
+        exchange: String,
+    },
+    /// Text record, holding its character strings.
+    Txt(Vec<Vec<u8>>),
+    /// SIG (RFC 2535 / 2931) record.
+    Sig {
+        /// The type of the records covered by the signature.
+        type_covered: u16,
+        /// The signature algorithm.
+        algorithm: u8,
+        /// The number of labels in the signed owner name.
+        labels: u8,
+        /// The TTL of the covered records.
+        original_ttl: u32,
+        /// When the signature expires, in seconds since the epoch.
+        expiration: u32,
+        /// When the signature was made, in seconds since the epoch.
+        inception: u32,
+        /// The key tag of the signing key.
+        key_tag: u16,
+        /// The signer's name.
+        signers_name: String,
+        /// The signature.
+        signature: Vec<u8>,
+    },
+    /// Service location record.
+    Srv {
+        /// The priority, lower values being preferred.
+        priority: u16,
+        /// The weight among targets of equal priority.
+        weight: u16,
+        /// The port on which the service is found.
+        port: u16,
+        /// The name of the host providing the service.
+        target: String,
+    },
+    /// Naming authority pointer record.
+    Naptr {
+        /// The order in which records must be processed.
+        order: u16,
+        /// The preference among records of equal order.
+        preference: u16,
+        /// The flags controlling rewriting.
+        flags: String,
+        /// The services available down this rewrite path.
+        services: String,
+        /// The substitution expression.
+        regexp: String,
+        /// The next name to query.
+        replacement: String,
+    },
+    /// EDNS0 OPT pseudo-record.
+    Opt {
+        /// The requestor's UDP payload size.
+        udp_size: u16,
+        /// The EDNS version.
+        version: u8,
+        /// The EDNS flags.
+        flags: u16,
+        /// The options, as code and value pairs.
+        options: Vec<(u16, Vec<u8>)>,
+    },
+    /// DANE TLSA record.
+    Tlsa {
+        /// The certificate usage.
+        cert_usage: u8,
+        /// The selector.
+        selector: u8,
+        /// The matching type.
+        matching_type: u8,
+        /// The certificate association data.
+        data: Vec<u8>,
+    },
+    /// Service binding record.
+    Svcb {
+        /// The priority, zero meaning alias mode.
+        priority: u16,
+        /// The target name.
+        target: String,
+        /// The service parameters, as key and value pairs.
+        params: Vec<(u16, Vec<u8>)>,
+    },
+    /// HTTPS service binding record.
+    Https {
+        /// The priority, zero meaning alias mode.
+        priority: u16,
+        /// The target name.
+        target: String,
+        /// The service parameters, as key and value pairs.
+        params: Vec<(u16, Vec<u8>)>,
+    },
+    /// URI record.
+    Uri {
+        /// The priority, lower values being preferred.
+        priority: u16,
+        /// The weight among targets of equal priority.
+        weight: u16,
+        /// The target URI.
+        target: String,
+    },
+    /// Certification authority authorization record.
+    Caa {
+        /// The flags.
+        flags: u8,
+        /// The property tag.
+        tag: String,
+        /// The property value.
+        value: Vec<u8>,
+    },
+    /// Raw record, of a type that c-ares has no typed view for.
+    RawRr {
+        /// The record type, as a number.
+        raw_type: u16,
+        /// The record data.
+        data: Vec<u8>,
+    },
+}
+
+impl ArbitraryRr {
+    /// Returns the type of the record.
+    pub fn rr_type(&self) -> DnsRecordType {
+        self.rdata.rr_type()
+    }
+
+    /// Add the record to `rec`.
+    ///
+    /// An [`Opt`](ArbitraryRdata::Opt) record always goes in the additional
+    /// section, with no owner name or TTL.
+    pub fn add_to(&self, rec: &mut DnsRecord) -> Result<()> {
+        let (section, name, ttl) = (self.section, self.name.as_str(), self.ttl);
+        match &self.rdata {
+            ArbitraryRdata::A(addr) => rec.add_a(section, name, ttl, *addr),
+            ArbitraryRdata::Aaaa(addr) => rec.add_aaaa(section, name, ttl, *addr),
+            ArbitraryRdata::Ns(nsdname) => rec.add_ns(section, name, ttl, nsdname),
+            ArbitraryRdata::Cname(cname) => rec.add_cname(section, name, ttl, cname),
+            ArbitraryRdata::Soa {
+                mname,
+                rname,
+                serial,
+                refresh,
+                retry,
+                expire,
+                minimum,
+            } => rec.add_soa(
+                section, name, ttl, mname, rname, *serial, *refresh, *retry, *expire, *minimum,
+            ),
+            ArbitraryRdata::Ptr(dname) => rec.add_ptr(section, name, ttl, dname),
+            ArbitraryRdata::Hinfo { cpu, os } => rec.add_hinfo(section, name, ttl, cpu, os),
+            ArbitraryRdata::Mx {
+                preference,
+                exchange,
+            } => rec.add_mx(section, name, ttl, *preference, exchange),
+            ArbitraryRdata::Txt(entries) => rec.add_txt(section, name, ttl, entries),
+            ArbitraryRdata::Sig {
+                type_covered,
+                algorithm,
+                labels,
+                original_ttl,
+                expiration,
+                inception,
+                key_tag,
+                signers_name,
+                signature,
+            } => rec.add_typed(section, name, DnsRecordType::SIG, DnsCls::IN, ttl, |rr| {
+                rr.set_u16(DnsRrKey::SIG_TYPE_COVERED, *type_covered)?
+                    .set_u8(DnsRrKey::SIG_ALGORITHM, *algorithm)?
+                    .set_u8(DnsRrKey::SIG_LABELS, *labels)?
+                    .set_u32(DnsRrKey::SIG_ORIGINAL_TTL, *original_ttl)?
+                    .set_u32(DnsRrKey::SIG_EXPIRATION, *expiration)?
+                    .set_u32(DnsRrKey::SIG_INCEPTION, *inception)?
+                    .set_u16(DnsRrKey::SIG_KEY_TAG, *key_tag)?
+                    .set_str(DnsRrKey::SIG_SIGNERS_NAME, signers_name)?
+                    .set_bin(DnsRrKey::SIG_SIGNATURE, signature)?;
+                Ok(())
+            }),
+            ArbitraryRdata::Srv {
+                priority,
+                weight,
+                port,
+                target,
+            } => rec.add_srv(section, name, ttl, *priority, *weight, *port, target),
+            ArbitraryRdata::Naptr {
+                order,
+                preference,
+                flags,
+                services,
+                regexp,
+                replacement,
+            } => rec.add_naptr(
+                section,
+                name,
+                ttl,
+                *order,
+                *preference,
+                flags,
+                services,
+                regexp,
+                replacement,
+            ),
+            ArbitraryRdata::Opt {
+                udp_size,
+                version,
+                flags,
+                options,
+            } => rec.add_opt(*udp_size, *version, *flags, options.iter().cloned()),
+            ArbitraryRdata::Tlsa {
+                cert_usage,
+                selector,
+                matching_type,
+                data,
+            } => rec.add_tlsa(
+                section,
+                name,
+                ttl,
+                *cert_usage,
+                *selector,
+                *matching_type,
+                data,
+            ),
+            ArbitraryRdata::Svcb {
+                priority,
+                target,
+                params,
+            } => rec.add_svcb(
+                section,
+                name,
+                ttl,
+                *priority,
+                target,
+                params.iter().cloned(),
+            ),
+            ArbitraryRdata::Https {
+                priority,
+                target,
+                params,
+            } => rec.add_https(
+                section,
+                name,
+                ttl,
+                *priority,
+                target,
+                params.iter().cloned(),
+            ),
+            ArbitraryRdata::Uri {
+                priority,
+                weight,
+                target,
+            } => rec.add_uri(section, name, ttl, *priority, *weight, target),
+            ArbitraryRdata::Caa { flags, tag, value } => {
+                rec.add_caa(section, name, ttl, *flags, tag, value)
+            }
+            ArbitraryRdata::RawRr { raw_type, data } => rec.add_typed(
+                section,
+                name,
+                DnsRecordType::RAW_RR,
+                DnsCls::IN,
+                ttl,
+                |rr| {
+                    rr.set_u16(DnsRrKey::RAW_RR_TYPE, *raw_type)?
+                        .set_bin(DnsRrKey::RAW_RR_DATA, data)?;
+                    Ok(())
+                },
+            ),
+        }?;
+        Ok(())
+    }
+
+    /// Read a record from the given section of a [`DnsRecord`], through its
+    /// typed view.
+    ///
+    /// Returns `None` for records of a type that has no typed view.
+    pub fn from_rr(section: DnsSection, rr: &DnsRr) -> Option<Self> {
+        let rdata = match rr.as_typed() {
+            TypedRr::A(a) => ArbitraryRdata::A(a.addr()),
+            TypedRr::Aaaa(aaaa) => ArbitraryRdata::Aaaa(aaaa.addr()),
+            TypedRr::Ns(ns) => ArbitraryRdata::Ns(ns.nsdname().to_owned()),
+            TypedRr::Cname(cname) => ArbitraryRdata::Cname(cname.cname().to_owned()),
+            TypedRr::Soa(soa) => ArbitraryRdata::Soa {
+                mname: soa.mname().to_owned(),
+                rname: soa.rname().to_owned(),
+                serial: soa.serial(),
+                refresh: soa.refresh(),
+                retry: soa.retry(),
+                expire: soa.expire(),
+                minimum: soa.minimum(),
+            },
+            TypedRr::Ptr(ptr) => ArbitraryRdata::Ptr(ptr.dname().to_owned()),
+            TypedRr::Hinfo(hinfo) => ArbitraryRdata::Hinfo {
+                cpu: hinfo.cpu().to_owned(),
+                os: hinfo.os().to_owned(),
+            },
+            TypedRr::Mx(mx) => ArbitraryRdata::Mx {
+                preference: mx.preference(),
+                exchange: mx.exchange().to_owned(),
+            },
+            TypedRr::Txt(txt) => ArbitraryRdata::Txt(txt.entries().map(<[u8]>::to_vec).collect()),
+            TypedRr::Sig(sig) => ArbitraryRdata::Sig {
+                type_covered: sig.type_covered(),
+                algorithm: sig.algorithm(),
+                labels: sig.labels(),
+                original_ttl: sig.original_ttl(),
+                expiration: sig.expiration(),
+                inception: sig.inception(),
+                key_tag: sig.key_tag(),
+                signers_name: sig.signers_name().to_owned(),
+                signature: sig.signature().to_vec(),
+            },
+            TypedRr::Srv(srv) => ArbitraryRdata::Srv {
+                priority: srv.priority(),
+                weight: srv.weight(),
+                port: srv.port(),
+                target: srv.target().to_owned(),
+            },
+            TypedRr::Naptr(naptr) => ArbitraryRdata::Naptr {
+                order: naptr.order(),
+                preference: naptr.preference(),
+                flags: naptr.flags().to_owned(),
+                services: naptr.services().to_owned(),
+                regexp: naptr.regexp().to_owned(),
+                replacement: naptr.replacement().to_owned(),
+            },
+            TypedRr::Opt(opt) => {
+                let rdata = ArbitraryRdata::Opt {
+                    udp_size: opt.udp_size(),
+                    version: opt.version(),
+                    flags: opt.flags(),
+                    options: owned_pairs(opt.raw_options()),
+                };
+                return Some(Self {
+                    section: DnsSection::Additional,
+                    name: String::new(),
+                    ttl: 0,
+                    rdata,
+                });
+            }
+            TypedRr::Tlsa(tlsa) => ArbitraryRdata::Tlsa {
+                cert_usage: tlsa.cert_usage(),
+                selector: tlsa.selector(),
+                matching_type: tlsa.matching_type(),
+                data: tlsa.data().to_vec(),
+            },
+            TypedRr::Svcb(svcb) => ArbitraryRdata::Svcb {
+                priority: svcb.priority(),
+                target: svcb.target().to_owned(),
+                params: owned_pairs(svcb.raw_params()),
+            },
+            TypedRr::Https(https) => ArbitraryRdata::Https {
+                priority: https.priority(),
+                target: https.target().to_owned(),
+                params: owned_pairs(https.raw_params()),
+            },
+            TypedRr::Uri(uri) => ArbitraryRdata::Uri {
+                priority: uri.priority(),
+                weight: uri.weight(),
+                target: uri.target().to_owned(),
+            },
+            TypedRr::Caa(caa) => ArbitraryRdata::Caa {
+                flags: caa.flags(),
+                tag: caa.tag().to_owned(),
+                value: caa.value().to_vec(),
+            },
+            TypedRr::RawRr(raw) => ArbitraryRdata::RawRr {
+                raw_type: raw.raw_type(),
+                data: raw.data().to_vec(),
+            },
+            TypedRr::Any(_) | TypedRr::Unknown(_) => return None,
+        };
+        Some(Self {
+            section,
+            name: rr.name().to_owned(),
+            ttl: rr.ttl(),
+            rdata,
+        })
+    }
+}
+
+impl ArbitraryRdata {
+    /// Returns the type of record that these fields belong to.
+    pub fn rr_type(&self) -> DnsRecordType {
+        match self {
+            Self::A(_) => DnsRecordType::A,
+            Self::Aaaa(_) => DnsRecordType::AAAA,
+            Self::Ns(_) => DnsRecordType::NS,
+            Self::Cname(_) => DnsRecordType::CNAME,
+            Self::Soa { .. } => DnsRecordType::SOA,
+            Self::Ptr(_) => DnsRecordType::PTR,
+            Self::Hinfo { .. } => DnsRecordType::HINFO,
+            Self::Mx { .. } => DnsRecordType::MX,
+            Self::Txt(_) => DnsRecordType::TXT,
+            Self::Sig { .. } => DnsRecordType::SIG,
+            Self::Srv { .. } => DnsRecordType::SRV,
+            Self::Naptr { .. } => DnsRecordType::NAPTR,
+            Self::Opt { .. } => DnsRecordType::OPT,
+            Self::Tlsa { .. } => DnsRecordType::TLSA,
+            Self::Svcb { .. } => DnsRecordType::SVCB,
+            Self::Https { .. } => DnsRecordType::HTTPS,
+            Self::Uri { .. } => DnsRecordType::URI,
+            Self::Caa { .. } => DnsRecordType::CAA,
+            Self::RawRr { .. } => DnsRecordType::RAW_RR,
+        }
+    }
+
+    /// Returns a strategy for the fields of records of type `rr_type`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rr_type` has no typed view: that is, for
+    /// [`ANY`](DnsRecordType::ANY) and [`UNKNOWN`](DnsRecordType::UNKNOWN).
+    pub fn strategy(rr_type: DnsRecordType) -> BoxedStrategy<Self> {
+        match rr_type {
+            DnsRecordType::A => any::<Ipv4Addr>().prop_map(Self::A).boxed(),
+            DnsRecordType::AAAA => any::<Ipv6Addr>().prop_map(Self::Aaaa).boxed(),
+            DnsRecordType::NS => name().prop_map(Self::Ns).boxed(),
+            DnsRecordType::CNAME => name().prop_map(Self::Cname).boxed(),
+            DnsRecordType::SOA => (name(), name(), any::<[u32; 5]>())
+                .prop_map(
+                    |(mname, rname, [serial, refresh, retry, expire, minimum])| Self::Soa {
+                        mname,
+                        rname,
+                        serial,
+                        refresh,
+                        retry,
+                        expire,
+                        minimum,
+                    },
+                )
+                .boxed(),
+            DnsRecordType::PTR => name().prop_map(Self::Ptr).boxed(),
+            DnsRecordType::HINFO => (character_string(), character_string())
+                .prop_map(|(cpu, os)| Self::Hinfo { cpu, os })
+                .boxed(),
+            DnsRecordType::MX => (any::<u16>(), name())
+                .prop_map(|(preference, exchange)| Self::Mx {
+                    preference,
+                    exchange,
+                })
+                .boxed(),
+            DnsRecordType::TXT => vec(vec(any::<u8>(), 0..=255), 1..4)
+                .prop_map(Self::Txt)
+                .boxed(),
+            DnsRecordType::SIG => (
+                (any::<u16>(), any::<u8>(), any::<u8>(), any::<[u32; 3]>()),
+                (any::<u16>(), name(), bytes()),
+            )
+                .prop_map(
+                    |(
+                        (type_covered, algorithm, labels, [original_ttl, expiration, inception]),
+                        (key_tag, signers_name, signature),
+                    )| Self::Sig {
+                        type_covered,
+                        algorithm,
+                        labels,
+                        original_ttl,
+                        expiration,
+                        inception,
+                        key_tag,
+                        signers_name,
+                        signature,
+                    },
+                )
+                .boxed(),
+            DnsRecordType::SRV => (any::<[u16; 3]>(), name())
+                .prop_map(|([priority, weight, port], target)| Self::Srv {
+                    priority,
+                    weight,
+                    port,
+                    target,
+                })
+                .boxed(),
+            DnsRecordType::NAPTR => (
+                any::<[u16; 2]>(),
+                character_string(),
+                character_string(),
+                character_string(),
+                name(),
+            )
+                .prop_map(
+                    |([order, preference], flags, services, regexp, replacement)| Self::Naptr {
+                        order,
+                        preference,
+                        flags,
+                        services,
+                        regexp,
+                        replacement,
+                    },
+                )
+                .boxed(),
+            DnsRecordType::OPT => (any::<u16>(), any::<u8>(), any::<u16>(), options())
+                .prop_map(|(udp_size, version, flags, options)| Self::Opt {
+                    udp_size,
+                    version,
+                    flags,
+                    options,
+                })
+                .boxed(),
+            DnsRecordType::TLSA => (any::<[u8; 3]>(), bytes())
+                .prop_map(|([cert_usage, selector, matching_type], data)| Self::Tlsa {
+                    cert_usage,
+                    selector,
+                    matching_type,
+                    data,
+                })
+                .boxed(),
+            DnsRecordType::SVCB => (any::<u16>(), name(), options())
+                .prop_map(|(priority, target, params)| Self::Svcb {
+                    priority,
+                    target,
+                    params,
+                })
+                .boxed(),
+            DnsRecordType::HTTPS => (any::<u16>(), name(), options())
+                .prop_map(|(priority, target, params)| Self::Https {
+                    priority,
+                    target,
+                    params,
+                })
+                .boxed(),
+            DnsRecordType::URI => (any::<[u16; 2]>(), "[!-~]{1,40}")
+                .prop_map(|([priority, weight], target)| Self::Uri {
+                    priority,
+                    weight,
+                    target,
+                })
+                .boxed(),
+            DnsRecordType::CAA => (any::<u8>(), "[a-z0-9]{1,15}", bytes())
+                .prop_map(|(flags, tag, value)| Self::Caa { flags, tag, value })
+                .boxed(),
+            // Type codes from the private use range, which c-ares will never parse as anything
+            // other than a raw record.
+            DnsRecordType::RAW_RR => (65280..=65534u16, bytes())
+                .prop_map(|(raw_type, data)| Self::RawRr { raw_type, data })
+                .boxed(),
+            DnsRecordType::ANY | DnsRecordType::UNKNOWN(_) => {
+                panic!("No typed view for {rr_type:?}")
+            }
+        }
+    }
+}
+
+/// Generates only the record types that [`ArbitraryRdata::strategy`]
+/// supports.
+impl Arbitrary for DnsRecordType {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        select(RECORD_TYPES.to_vec()).boxed()
+    }
+}
+
+impl Arbitrary for ArbitraryRr {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        any::<DnsRecordType>()
+            .prop_flat_map(|rr_type| {
+                if rr_type == DnsRecordType::OPT {
+                    ArbitraryRdata::strategy(rr_type)
+                        .prop_map(|rdata| Self {
+                            section: DnsSection::Additional,
+                            name: String::new(),
+                            ttl: 0,
+                            rdata,
+                        })
+                        .boxed()
+                } else {
+                    rr_in_section(rr_type).boxed()
+                }
+            })
+            .boxed()
+    }
+}
+
+/// Generates messages with a single question, up to eight records
+/// spread across the sections, and perhaps an OPT record.
+impl Arbitrary for DnsRecord {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        let header = (
+            any::<u16>(),
+            any::<u16>().prop_map(DnsFlags::from_bits_truncate),
+            select(vec![
+                DnsOpcode::Query,
+                DnsOpcode::IQuery,
+                DnsOpcode::Status,
+                DnsOpcode::Notify,
+            ]),
+            select(vec![
+                DnsRcode::NoError,
+                DnsRcode::FormErr,
+                DnsRcode::ServFail,
+                DnsRcode::NXDomain,
+                DnsRcode::NotImp,
+                DnsRcode::Refused,
+            ]),
+        );
+        // c-ares parses only messages with exactly one question.
+        let question = (
+            name(),
+            any::<DnsRecordType>().prop_filter("not a question type", |qtype| {
+                !matches!(qtype, DnsRecordType::OPT | DnsRecordType::RAW_RR)
+            }),
+        );
+        let rrs = vec(
+            any::<DnsRecordType>()
+                .prop_filter("OPT is added separately", |t| *t != DnsRecordType::OPT)
+                .prop_flat_map(rr_in_section),
+            0..8,
+        );
+        let opt = option::of(ArbitraryRdata::strategy(DnsRecordType::OPT));
+        (header, question, rrs, opt)
+            .prop_map(|((id, flags, opcode, rcode), (name, qtype), rrs, opt)| {
+                let mut rec = DnsRecord::new(id, flags, opcode, rcode).expect("DnsRecord::new");
+                rec.query_add(&name, qtype, DnsCls::IN)
+                    .expect("DnsRecord::query_add");
+                let opt = opt.map(|rdata| ArbitraryRr {
+                    section: DnsSection::Additional,
+                    name: String::new(),
+                    ttl: 0,
+                    rdata,
+                });
+                for rr in rrs.iter().chain(&opt) {
+                    rr.add_to(&mut rec).expect("ArbitraryRr::add_to");
+                }
+                rec
+            })
+            .boxed()
+    }
+}
+
+// A record of type `rr_type` in any section.
+fn rr_in_section(rr_type: DnsRecordType) -> impl Strategy<Value = ArbitraryRr> {
+    let sections = [
+        DnsSection::Answer,
+        DnsSection::Authority,
+        DnsSection::Additional,
+    ];
+    (
+        select(sections.to_vec()),
+        name(),
+        0..=0x7fff_ffffu32,
+        ArbitraryRdata::strategy(rr_type),
+    )
+        .prop_map(|(section, name, ttl, rdata)| ArbitraryRr {
+            section,
+            name,
+            ttl,
+            rdata,
+        })
+}
+
+// A domain name of one to four labels.  Names are lower-case, since c-ares compresses names
+// without regard to case.
+fn name() -> impl Strategy<Value = String> {
+    vec("[a-z0-9]([a-z0-9-]{0,8}[a-z0-9])?", 1..=4).prop_map(|labels| labels.join("."))
+}
+
+// A printable character-string.
+fn character_string() -> impl Strategy<Value = String> {
+    "[ -~]{0,20}"
+}
+
+fn bytes() -> impl Strategy<Value = Vec<u8>> {
+    vec(any::<u8>(), 1..32)
+}
+
+// EDNS options or SvcParams, which c-ares keeps one of per code.
+fn options() -> impl Strategy<Value = Vec<(u16, Vec<u8>)>> {
+    btree_map(0..32u16, vec(any::<u8>(), 0..16), 0..4)
+        .prop_map(|options| options.into_iter().collect())
+}
+
+fn owned_pairs<'a>(pairs: impl Iterator<Item = (u16, &'a [u8])>) -> Vec<(u16, Vec<u8>)> {
+    pairs.map(|(code, value)| (code, value.to_vec())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::test_util::{new_rec, round_trip};
+
+    fn rrs(rec: &DnsRecord) -> Vec<ArbitraryRr> {
+        [
+            DnsSection::Answer,
+            DnsSection::Authority,
+            DnsSection::Additional,
+        ]
+        .into_iter()
+        .flat_map(|section| {
+            rec.rrs(section)
+                .map(move |rr| ArbitraryRr::from_rr(section, rr).unwrap())
+        })
+        .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn rr_round_trip(rr in any::<ArbitraryRr>()) {
+            let mut rec = new_rec();
+            rr.add_to(&mut rec).unwrap();
+            prop_assert_eq!(rrs(&round_trip(&rec)), [rr]);
+        }
+
+        #[test]
+        fn record_round_trip(rec in any::<DnsRecord>()) {
+            let parsed = round_trip(&rec);
+            prop_assert_eq!(parsed.id(), rec.id());
+            prop_assert_eq!(parsed.flags(), rec.flags());
+            prop_assert_eq!(parsed.opcode(), rec.opcode());
+            prop_assert_eq!(parsed.rcode(), rec.rcode());
+            prop_assert!(parsed.queries().eq(rec.queries()));
+            prop_assert_eq!(rrs(&parsed), rrs(&rec));
+        }
+    }
+}
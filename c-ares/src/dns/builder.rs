@@ -0,0 +1,653 @@
+//! Strongly-typed constructors for DNS resource records.
+//!
+//! [`DnsRecord::rr_add`] creates an empty record of any type, whose fields
+//! must then be filled in through [`DnsRr`]'s key-based setters. The
+//! `add_*` methods here are the write-side counterparts of the typed views
+//! returned by [`DnsRr::as_typed`]: each one takes exactly the fields of its
+//! record type, with Rust types matching their datatypes, so there is no way
+//! to pair a key with the wrong record type or setter.
+//!
+//! # Example
+//!
+//! ```
+//! use c_ares::{DnsFlags, DnsOpcode, DnsRcode, DnsRecord, DnsSection};
+//! use std::net::Ipv4Addr;
+//!
+//! # fn main() -> c_ares::Result<()> {
+//! let mut rec = DnsRecord::new(0, DnsFlags::QR, DnsOpcode::Query, DnsRcode::NoError)?;
+//! rec.add_a(DnsSection::Answer, "example.com", 300, Ipv4Addr::new(192, 0, 2, 1))?
+//!     .add_mx(DnsSection::Answer, "example.com", 300, 10, "mail.example.com")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! If setting any field fails, the partially-built record is removed again,
+//! so that a failed call leaves the `DnsRecord` unchanged.
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::error::Result;
+
+use super::enums::{DnsCls, DnsRecordType, DnsRrKey, DnsSection};
+use super::record::DnsRecord;
+use super::rr::DnsRr;
+
+// The typed constructors mirror the wire format field-for-field, so some of them necessarily take
+// many arguments.
+#[allow(clippy::too_many_arguments)]
+impl DnsRecord {
+    /// Add an [`A`](DnsRecordType::A) record.
+    pub fn add_a(
+        &mut self,
+        section: DnsSection,
+        name: &str,
+        ttl: u32,
+        addr: Ipv4Addr,
+    ) -> Result<&mut Self> {
+        self.add_typed(section, name, DnsRecordType::A, DnsCls::IN, ttl, |rr| {
+            rr.set_addr(DnsRrKey::A_ADDR, addr)?;
+            Ok(())
+        })
+    }
+
+    /// Add an [`AAAA`](DnsRecordType::AAAA) record.
+    pub fn add_aaaa(
+        &mut self,
+        section: DnsSection,
+        name: &str,
+        ttl: u32,
+        addr: Ipv6Addr,
+    ) -> Result<&mut Self> {
+        self.add_typed(section, name, DnsRecordType::AAAA, DnsCls::IN, ttl, |rr| {
+            rr.set_addr6(DnsRrKey::AAAA_ADDR, addr)?;
+            Ok(())
+        })
+    }
+
+    /// Add an [`NS`](DnsRecordType::NS) record.
+    pub fn add_ns(
+        &mut self,
+        section: DnsSection,
+        name: &str,
+        ttl: u32,
+        nsdname: &str,
+    ) -> Result<&mut Self> {
+        self.add_typed(section, name, DnsRecordType::NS, DnsCls::IN, ttl, |rr| {
+            rr.set_str(DnsRrKey::NS_NSDNAME, nsdname)?;
+            Ok(())
+        })
+    }
+
+    /// Add a [`CNAME`](DnsRecordType::CNAME) record.
+    pub fn add_cname(
+        &mut self,
+        section: DnsSection,
+        name: &str,
+        ttl: u32,
+        cname: &str,
+    ) -> Result<&mut Self> {
+        self.add_typed(section, name, DnsRecordType::CNAME, DnsCls::IN, ttl, |rr| {
+            rr.set_str(DnsRrKey::CNAME_CNAME, cname)?;
+            Ok(())
+        })
+    }
+
+    /// Add an [`SOA`](DnsRecordType::SOA) record.
+    pub fn add_soa(
+        &mut self,
+        section: DnsSection,
+        name: &str,
+        ttl: u32,
+        mname: &str,
+        rname: &str,
+        serial: u32,
+        refresh: u32,
+        retry: u32,
+        expire: u32,
+        minimum: u32,
+    ) -> Result<&mut Self> {
+        self.add_typed(section, name, DnsRecordType::SOA, DnsCls::IN, ttl, |rr| {
+            rr.set_str(DnsRrKey::SOA_MNAME, mname)?
+                .set_str(DnsRrKey::SOA_RNAME, rname)?
+                .set_u32(DnsRrKey::SOA_SERIAL, serial)?
+                .set_u32(DnsRrKey::SOA_REFRESH, refresh)?
+                .set_u32(DnsRrKey::SOA_RETRY, retry)?
+                .set_u32(DnsRrKey::SOA_EXPIRE, expire)?
+                .set_u32(DnsRrKey::SOA_MINIMUM, minimum)?;
+            Ok(())
+        })
+    }
+
+    /// Add a [`PTR`](DnsRecordType::PTR) record.
+    pub fn add_ptr(
+        &mut self,
+        section: DnsSection,
+        name: &str,
+        ttl: u32,
+        dname: &str,
+    ) -> Result<&mut Self> {
+        self.add_typed(section, name, DnsRecordType::PTR, DnsCls::IN, ttl, |rr| {
+            rr.set_str(DnsRrKey::PTR_DNAME, dname)?;
+            Ok(())
+        })
+    }
+
+    /// Add an [`HINFO`](DnsRecordType::HINFO) record.
+    pub fn add_hinfo(
+        &mut self,
+        section: DnsSection,
+        name: &str,
+        ttl: u32,
+        cpu: &str,
+        os: &str,
+    ) -> Result<&mut Self> {
+        self.add_typed(section, name, DnsRecordType::HINFO, DnsCls::IN, ttl, |rr| {
+            rr.set_str(DnsRrKey::HINFO_CPU, cpu)?
+                .set_str(DnsRrKey::HINFO_OS, os)?;
+            Ok(())
+        })
+    }
+
+    /// Add an [`MX`](DnsRecordType::MX) record.
+    pub fn add_mx(
+        &mut self,
+        section: DnsSection,
+        name: &str,
+        ttl: u32,
+        preference: u16,
+        exchange: &str,
+    ) -> Result<&mut Self> {
+        self.add_typed(section, name, DnsRecordType::MX, DnsCls::IN, ttl, |rr| {
+            rr.set_u16(DnsRrKey::MX_PREFERENCE, preference)?
+                .set_str(DnsRrKey::MX_EXCHANGE, exchange)?;
+            Ok(())
+        })
+    }
+
+    /// Add a [`TXT`](DnsRecordType::TXT) record, with one character-string per
+    /// item of `entries`.
+    pub fn add_txt<I>(
+        &mut self,
+        section: DnsSection,
+        name: &str,
+        ttl: u32,
+        entries: I,
+    ) -> Result<&mut Self>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        self.add_typed(section, name, DnsRecordType::TXT, DnsCls::IN, ttl, |rr| {
+            for entry in entries {
+                rr.add_abin(DnsRrKey::TXT_DATA, entry.as_ref())?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Add an [`SRV`](DnsRecordType::SRV) record.
+    pub fn add_srv(
+        &mut self,
+        section: DnsSection,
+        name: &str,
+        ttl: u32,
+        priority: u16,
+        weight: u16,
+        port: u16,
+        target: &str,
+    ) -> Result<&mut Self> {
+        self.add_typed(section, name, DnsRecordType::SRV, DnsCls::IN, ttl, |rr| {
+            rr.set_u16(DnsRrKey::SRV_PRIORITY, priority)?
+                .set_u16(DnsRrKey::SRV_WEIGHT, weight)?
+                .set_u16(DnsRrKey::SRV_PORT, port)?
+                .set_str(DnsRrKey::SRV_TARGET, target)?;
+            Ok(())
+        })
+    }
+
+    /// Add a [`NAPTR`](DnsRecordType::NAPTR) record.
+    pub fn add_naptr(
+        &mut self,
+        section: DnsSection,
+        name: &str,
+        ttl: u32,
+        order: u16,
+        preference: u16,
+        flags: &str,
+        services: &str,
+        regexp: &str,
+        replacement: &str,
+    ) -> Result<&mut Self> {
+        self.add_typed(section, name, DnsRecordType::NAPTR, DnsCls::IN, ttl, |rr| {
+            rr.set_u16(DnsRrKey::NAPTR_ORDER, order)?
+                .set_u16(DnsRrKey::NAPTR_PREFERENCE, preference)?
+                .set_str(DnsRrKey::NAPTR_FLAGS, flags)?
+                .set_str(DnsRrKey::NAPTR_SERVICES, services)?
+                .set_str(DnsRrKey::NAPTR_REGEXP, regexp)?
+                .set_str(DnsRrKey::NAPTR_REPLACEMENT, replacement)?;
+            Ok(())
+        })
+    }
+
+    /// Add an [`OPT`](DnsRecordType::OPT) (EDNS0) record to the additional
+    /// section, with options given as `(option_code, value)` pairs.
+    pub fn add_opt<I, V>(
+        &mut self,
+        udp_size: u16,
+        version: u8,
+        flags: u16,
+        options: I,
+    ) -> Result<&mut Self>
+    where
+        I: IntoIterator<Item = (u16, V)>,
+        V: AsRef<[u8]>,
+    {
+        self.add_typed(
+            DnsSection::Additional,
+            "",
+            DnsRecordType::OPT,
+            DnsCls::IN,
+            0,
+            |rr| {
+                rr.set_u16(DnsRrKey::OPT_UDP_SIZE, udp_size)?
+                    .set_u8(DnsRrKey::OPT_VERSION, version)?
+                    .set_u16(DnsRrKey::OPT_FLAGS, flags)?;
+                for (code, value) in options {
+                    rr.set_opt(DnsRrKey::OPT_OPTIONS, code, value.as_ref())?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Add a [`TLSA`](DnsRecordType::TLSA) record.
+    pub fn add_tlsa(
+        &mut self,
+        section: DnsSection,
+        name: &str,
+        ttl: u32,
+        cert_usage: u8,
+        selector: u8,
+        matching_type: u8,
+        data: &[u8],
+    ) -> Result<&mut Self> {
+        self.add_typed(section, name, DnsRecordType::TLSA, DnsCls::IN, ttl, |rr| {
+            rr.set_u8(DnsRrKey::TLSA_CERT_USAGE, cert_usage)?
+                .set_u8(DnsRrKey::TLSA_SELECTOR, selector)?
+                .set_u8(DnsRrKey::TLSA_MATCH, matching_type)?
+                .set_bin(DnsRrKey::TLSA_DATA, data)?;
+            Ok(())
+        })
+    }
+
+    /// Add an [`SVCB`](DnsRecordType::SVCB) record, with SvcParams given as
+    /// `(param_key, value)` pairs.
+    pub fn add_svcb<I, V>(
+        &mut self,
+        section: DnsSection,
+        name: &str,
+        ttl: u32,
+        priority: u16,
+        target: &str,
+        params: I,
+    ) -> Result<&mut Self>
+    where
+        I: IntoIterator<Item = (u16, V)>,
+        V: AsRef<[u8]>,
+    {
+        self.add_typed(section, name, DnsRecordType::SVCB, DnsCls::IN, ttl, |rr| {
+            rr.set_u16(DnsRrKey::SVCB_PRIORITY, priority)?
+                .set_str(DnsRrKey::SVCB_TARGET, target)?;
+            for (key, value) in params {
+                rr.set_opt(DnsRrKey::SVCB_PARAMS, key, value.as_ref())?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Add an [`HTTPS`](DnsRecordType::HTTPS) record, with SvcParams given as
+    /// `(param_key, value)` pairs.
+    pub fn add_https<I, V>(
+        &mut self,
+        section: DnsSection,
+        name: &str,
+        ttl: u32,
+        priority: u16,
+        target: &str,
+        params: I,
+    ) -> Result<&mut Self>
+    where
+        I: IntoIterator<Item = (u16, V)>,
+        V: AsRef<[u8]>,
+    {
+        self.add_typed(section, name, DnsRecordType::HTTPS, DnsCls::IN, ttl, |rr| {
+            rr.set_u16(DnsRrKey::HTTPS_PRIORITY, priority)?
+                .set_str(DnsRrKey::HTTPS_TARGET, target)?;
+            for (key, value) in params {
+                rr.set_opt(DnsRrKey::HTTPS_PARAMS, key, value.as_ref())?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Add a [`URI`](DnsRecordType::URI) record.
+    pub fn add_uri(
+        &mut self,
+        section: DnsSection,
+        name: &str,
+        ttl: u32,
+        priority: u16,
+        weight: u16,
+        target: &str,
+    ) -> Result<&mut Self> {
+        self.add_typed(section, name, DnsRecordType::URI, DnsCls::IN, ttl, |rr| {
+            rr.set_u16(DnsRrKey::URI_PRIORITY, priority)?
+                .set_u16(DnsRrKey::URI_WEIGHT, weight)?
+                .set_str(DnsRrKey::URI_TARGET, target)?;
+            Ok(())
+        })
+    }
+
+    /// Add a [`CAA`](DnsRecordType::CAA) record.
+    ///
+    /// `flags` is the raw flags byte, in which `0x80` is the issuer-critical
+    /// bit.
+    pub fn add_caa(
+        &mut self,
+        section: DnsSection,
+        name: &str,
+        ttl: u32,
+        flags: u8,
+        tag: &str,
+        value: &[u8],
+    ) -> Result<&mut Self> {
+        self.add_typed(section, name, DnsRecordType::CAA, DnsCls::IN, ttl, |rr| {
+            rr.set_u8(DnsRrKey::CAA_CRITICAL, flags)?
+                .set_str(DnsRrKey::CAA_TAG, tag)?
+                .set_bin(DnsRrKey::CAA_VALUE, value)?;
+            Ok(())
+        })
+    }
+
+    // Adds an RR, and fills it in with `populate`.  If that fails, the RR is removed again.
+    pub(super) fn add_typed<F>(
+        &mut self,
+        section: DnsSection,
+        name: &str,
+        rr_type: DnsRecordType,
+        rclass: DnsCls,
+        ttl: u32,
+        populate: F,
+    ) -> Result<&mut Self>
+    where
+        F: FnOnce(&mut DnsRr) -> Result<()>,
+    {
+        let rr = self.rr_add(section, name, rr_type, rclass, ttl)?;
+        if let Err(error) = populate(rr) {
+            let idx = self.rr_count(section) - 1;
+            self.rr_del(section, idx)?;
+            return Err(error);
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::DnsCls;
+    use crate::dns::test_util::{new_rec, round_trip};
+    use crate::error::Error;
+
+    #[test]
+    fn add_address_records() {
+        let mut rec = new_rec();
+        rec.add_a(
+            DnsSection::Answer,
+            "example.com",
+            300,
+            Ipv4Addr::new(192, 0, 2, 1),
+        )
+        .unwrap()
+        .add_aaaa(DnsSection::Answer, "example.com", 60, Ipv6Addr::LOCALHOST)
+        .unwrap();
+        let rec = round_trip(&rec);
+
+        let a = rec.rr(DnsSection::Answer, 0).unwrap().as_a().unwrap();
+        assert_eq!(a.name(), "example.com");
+        assert_eq!(a.ttl(), 300);
+        assert_eq!(a.dns_class(), DnsCls::IN);
+        assert_eq!(a.addr(), Ipv4Addr::new(192, 0, 2, 1));
+
+        let aaaa = rec.rr(DnsSection::Answer, 1).unwrap().as_aaaa().unwrap();
+        assert_eq!(aaaa.ttl(), 60);
+        assert_eq!(aaaa.addr(), Ipv6Addr::LOCALHOST);
+    }
+
+    #[test]
+    fn add_name_records() {
+        let mut rec = new_rec();
+        rec.add_ns(DnsSection::Authority, "example.com", 1, "ns1.example.com")
+            .unwrap()
+            .add_cname(DnsSection::Answer, "www.example.com", 2, "example.com")
+            .unwrap()
+            .add_ptr(
+                DnsSection::Answer,
+                "1.2.0.192.in-addr.arpa",
+                3,
+                "example.com",
+            )
+            .unwrap()
+            .add_mx(DnsSection::Answer, "example.com", 4, 10, "mail.example.com")
+            .unwrap();
+        let rec = round_trip(&rec);
+
+        let ns = rec.rr(DnsSection::Authority, 0).unwrap().as_ns().unwrap();
+        assert_eq!(ns.nsdname(), "ns1.example.com");
+        let cname = rec.rr(DnsSection::Answer, 0).unwrap().as_cname().unwrap();
+        assert_eq!(cname.cname(), "example.com");
+        let ptr = rec.rr(DnsSection::Answer, 1).unwrap().as_ptr_rr().unwrap();
+        assert_eq!(ptr.dname(), "example.com");
+        let mx = rec.rr(DnsSection::Answer, 2).unwrap().as_mx().unwrap();
+        assert_eq!(mx.preference(), 10);
+        assert_eq!(mx.exchange(), "mail.example.com");
+    }
+
+    #[test]
+    fn add_soa_record() {
+        let mut rec = new_rec();
+        rec.add_soa(
+            DnsSection::Authority,
+            "example.com",
+            3600,
+            "ns1.example.com",
+            "hostmaster.example.com",
+            2024010101,
+            7200,
+            900,
+            1209600,
+            300,
+        )
+        .unwrap();
+        let rec = round_trip(&rec);
+
+        let soa = rec.rr(DnsSection::Authority, 0).unwrap().as_soa().unwrap();
+        assert_eq!(soa.mname(), "ns1.example.com");
+        assert_eq!(soa.rname(), "hostmaster.example.com");
+        assert_eq!(soa.serial(), 2024010101);
+        assert_eq!(soa.refresh(), 7200);
+        assert_eq!(soa.retry(), 900);
+        assert_eq!(soa.expire(), 1209600);
+        assert_eq!(soa.minimum(), 300);
+    }
+
+    #[test]
+    fn add_text_records() {
+        let mut rec = new_rec();
+        rec.add_txt(DnsSection::Answer, "example.com", 300, ["v=spf1 -all", "x"])
+            .unwrap()
+            .add_hinfo(DnsSection::Answer, "example.com", 300, "x86_64", "Linux")
+            .unwrap();
+        let rec = round_trip(&rec);
+
+        let txt = rec.rr(DnsSection::Answer, 0).unwrap().as_txt().unwrap();
+        let entries: Vec<_> = txt.entries().collect();
+        assert_eq!(entries, [&b"v=spf1 -all"[..], b"x"]);
+        let hinfo = rec.rr(DnsSection::Answer, 1).unwrap().as_hinfo().unwrap();
+        assert_eq!(hinfo.cpu(), "x86_64");
+        assert_eq!(hinfo.os(), "Linux");
+    }
+
+    #[test]
+    fn add_service_records() {
+        let mut rec = new_rec();
+        rec.add_srv(
+            DnsSection::Answer,
+            "_sip._tcp.example.com",
+            300,
+            10,
+            60,
+            5060,
+            "sip.example.com",
+        )
+        .unwrap()
+        .add_naptr(
+            DnsSection::Answer,
+            "example.com",
+            300,
+            100,
+            10,
+            "S",
+            "SIP+D2U",
+            "",
+            "_sip._udp.example.com",
+        )
+        .unwrap()
+        .add_uri(
+            DnsSection::Answer,
+            "_http._tcp.example.com",
+            300,
+            1,
+            2,
+            "https://example.com/",
+        )
+        .unwrap();
+        let rec = round_trip(&rec);
+
+        let srv = rec.rr(DnsSection::Answer, 0).unwrap().as_srv().unwrap();
+        assert_eq!(
+            (srv.priority(), srv.weight(), srv.port(), srv.target()),
+            (10, 60, 5060, "sip.example.com")
+        );
+        let naptr = rec.rr(DnsSection::Answer, 1).unwrap().as_naptr().unwrap();
+        assert_eq!(naptr.order(), 100);
+        assert_eq!(naptr.preference(), 10);
+        assert_eq!(naptr.flags(), "S");
+        assert_eq!(naptr.services(), "SIP+D2U");
+        assert_eq!(naptr.regexp(), "");
+        assert_eq!(naptr.replacement(), "_sip._udp.example.com");
+        let uri = rec.rr(DnsSection::Answer, 2).unwrap().as_uri().unwrap();
+        assert_eq!(
+            (uri.priority(), uri.weight(), uri.target()),
+            (1, 2, "https://example.com/")
+        );
+    }
+
+    #[test]
+    fn add_svcb_and_https_records() {
+        // SvcParamKey 3 is "port".
+        let port = 8443u16.to_be_bytes();
+        let mut rec = new_rec();
+        rec.add_svcb(
+            DnsSection::Answer,
+            "_dns.example.com",
+            300,
+            1,
+            "dns.example.com",
+            [(3, port)],
+        )
+        .unwrap()
+        .add_https(
+            DnsSection::Answer,
+            "example.com",
+            300,
+            1,
+            ".",
+            [(3, &port[..])],
+        )
+        .unwrap();
+        let rec = round_trip(&rec);
+
+        let svcb = rec.rr(DnsSection::Answer, 0).unwrap().as_svcb().unwrap();
+        assert_eq!(svcb.priority(), 1);
+        assert_eq!(svcb.target(), "dns.example.com");
+        let params: Vec<_> = svcb.raw_params().collect();
+        assert_eq!(params, [(3, &port[..])]);
+
+        let https = rec.rr(DnsSection::Answer, 1).unwrap().as_https().unwrap();
+        assert_eq!(https.priority(), 1);
+        assert_eq!(https.param_count(), 1);
+    }
+
+    #[test]
+    fn add_tlsa_and_caa_records() {
+        let mut rec = new_rec();
+        rec.add_tlsa(
+            DnsSection::Answer,
+            "_443._tcp.example.com",
+            300,
+            3,
+            1,
+            1,
+            &[0xab; 32],
+        )
+        .unwrap()
+        .add_caa(
+            DnsSection::Answer,
+            "example.com",
+            300,
+            0x80,
+            "issue",
+            b"ca.example.net",
+        )
+        .unwrap();
+        let rec = round_trip(&rec);
+
+        let tlsa = rec.rr(DnsSection::Answer, 0).unwrap().as_tlsa().unwrap();
+        assert_eq!(
+            (tlsa.cert_usage(), tlsa.selector(), tlsa.matching_type()),
+            (3, 1, 1)
+        );
+        assert_eq!(tlsa.data(), &[0xab; 32]);
+        let caa = rec.rr(DnsSection::Answer, 1).unwrap().as_caa().unwrap();
+        assert!(caa.is_critical());
+        assert_eq!(caa.tag(), "issue");
+        assert_eq!(caa.value(), b"ca.example.net");
+    }
+
+    #[test]
+    fn add_opt_record() {
+        // Option code 10 is the EDNS cookie.
+        let mut rec = new_rec();
+        rec.add_opt(1232, 0, 0x8000, [(10, [1u8; 8])]).unwrap();
+        let rec = round_trip(&rec);
+
+        let opt = rec.rr(DnsSection::Additional, 0).unwrap().as_opt().unwrap();
+        assert_eq!(opt.udp_size(), 1232);
+        assert_eq!(opt.version(), 0);
+        assert_eq!(opt.flags(), 0x8000);
+        let options: Vec<_> = opt.raw_options().collect();
+        assert_eq!(options, [(10, &[1u8; 8][..])]);
+    }
+
+    #[test]
+    fn failed_add_leaves_record_unchanged() {
+        let mut rec = new_rec();
+        rec.add_a(DnsSection::Answer, "example.com", 300, Ipv4Addr::LOCALHOST)
+            .unwrap();
+        let result = rec.add_mx(DnsSection::Answer, "example.com", 300, 10, "bad\0name");
+        assert_eq!(result.err(), Some(Error::EBADSTR));
+        assert_eq!(rec.rr_count(DnsSection::Answer), 1);
+        assert!(rec.rr(DnsSection::Answer, 0).unwrap().as_a().is_some());
+    }
+}
@@ -1,9 +1,10 @@
-use core::ffi::c_void;
+use core::ffi::{c_int, c_void};
 use std::mem::ManuallyDrop;
 
 use super::DnsRecord;
 use crate::error::{Error, Result};
 use crate::panic;
+use crate::query_handle::{Outcome, take_pending};
 
 pub(crate) unsafe extern "C" fn dnsrec_callback<F>(
     arg: *mut c_void,
@@ -13,17 +14,18 @@ pub(crate) unsafe extern "C" fn dnsrec_callback<F>(
 ) where
     F: FnOnce(Result<&DnsRecord>) + Send + 'static,
 {
-    let handler = unsafe { Box::from_raw(arg.cast::<F>()) };
+    // We wrap in ManuallyDrop so we don't call ares_dns_record_destroy — c-ares owns this record
+    // and will free it after we return.
+    let rec = (!dnsrec.is_null())
+        .then(|| ManuallyDrop::new(unsafe { DnsRecord::from_raw(dnsrec.cast_mut()) }));
+    let outcome = Outcome::Record(status as c_int, rec.as_deref());
+    let Some(handler) = (unsafe { take_pending::<F>(arg, &outcome) }) else {
+        return;
+    };
 
-    panic::abort_on_panic(|| {
-        if let Ok(err) = Error::try_from(status) {
-            handler(Err(err));
-        } else {
-            // We wrap in ManuallyDrop so we don't call ares_dns_record_destroy
-            // — c-ares owns this record and will free it after we return.
-            let rec = unsafe { DnsRecord::from_raw(dnsrec.cast_mut()) };
-            let rec = ManuallyDrop::new(rec);
-            handler(Ok(&rec));
-        }
+    panic::abort_on_panic(|| match (Error::try_from(status), rec) {
+        (Ok(err), _) => handler(Err(err)),
+        (Err(_), Some(rec)) => handler(Ok(&rec)),
+        (Err(_), None) => handler(Err(Error::EBADRESP)),
     });
 }
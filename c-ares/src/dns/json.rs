@@ -0,0 +1,636 @@
+//! RFC 8427 JSON representation of DNS messages.
+//!
+//! With the `serde` feature, [`DnsRecord`] implements `Serialize` and
+//! `Deserialize` using the message object that RFC 8427 describes:
+//!
+//! ```json
+//! {
+//!   "ID": 4660, "QR": true, "Opcode": 0, "AA": false, "TC": false,
+//!   "RD": true, "RA": true, "AD": false, "CD": false, "RCODE": 0,
+//!   "QDCOUNT": 1, "ANCOUNT": 1, "NSCOUNT": 0, "ARCOUNT": 0,
+//!   "QNAME": "example.com.", "QTYPE": 15, "QTYPEname": "MX",
+//!   "QCLASS": 1, "QCLASSname": "IN",
+//!   "answerRRs": [
+//!     {
+//!       "NAME": "example.com.", "TYPE": 15, "TYPEname": "MX",
+//!       "CLASS": 1, "CLASSname": "IN", "TTL": 300,
+//!       "rdataMX": "10 mail.example.com."
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Header flags are written as booleans.  A message with a single question
+//! describes it with the `QNAME`, `QTYPE` and `QCLASS` members, and any other
+//! number of questions are listed in `questionRRs`.  Empty sections are left
+//! out.
+//!
+//! RDATA is written in presentation format, as by the `Display`
+//! implementation on [`DnsRr`], in a member named `rdata` followed by the type
+//! name.  Records that c-ares does not parse natively are written with
+//! `RDATAHEX` instead.  So are OPT records, which carry their EDNS values in
+//! `CLASS` and `TTL` as they do on the wire; their options are also listed,
+//! for information only, in `rdataOPT`.  Records of type `ANY`, or of a type
+//! that c-ares does not know at all, hold no RDATA and cannot be serialized.
+//!
+//! When reading, flags may also be given as `0` or `1`, the section counts
+//! are ignored, and type and class numbers take precedence over names.  A
+//! missing TTL is taken to be zero, and a missing class to be `IN`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::result;
+
+use serde::de::{self, Deserialize, Deserializer, IgnoredAny, Unexpected, Visitor};
+use serde::ser::{self, Serialize, SerializeMap, Serializer};
+
+use crate::error::{Error, Result};
+
+use super::dns_opt::OptValue;
+use super::enums::{DnsCls, DnsFlags, DnsOpcode, DnsRcode, DnsRecordType, DnsRrKey, DnsSection};
+use super::presentation::{
+    Name, OPCODES, RCODES, Rdata, absolute_name, add_generic_rr, add_parsed_rr, hex_decode,
+    hex_encode, opt_rdata, opt_ttl, parse_class, parse_type, record_type, tokenize,
+};
+use super::record::DnsRecord;
+use super::rr::DnsRr;
+use super::typed::TypedRr;
+
+// The header flags after QR, in the order that RFC 8427 lists them.
+const FLAG_MEMBERS: [(DnsFlags, &str); 6] = [
+    (DnsFlags::AA, "AA"),
+    (DnsFlags::TC, "TC"),
+    (DnsFlags::RD, "RD"),
+    (DnsFlags::RA, "RA"),
+    (DnsFlags::AD, "AD"),
+    (DnsFlags::CD, "CD"),
+];
+
+const SECTION_MEMBERS: [(DnsSection, &str); 3] = [
+    (DnsSection::Answer, "answerRRs"),
+    (DnsSection::Authority, "authorityRRs"),
+    (DnsSection::Additional, "additionalRRs"),
+];
+
+fn opcode_number(opcode: DnsOpcode) -> u16 {
+    c_ares_sys::ares_dns_opcode_t::from(opcode) as u16
+}
+
+fn rcode_number(rcode: DnsRcode) -> u16 {
+    c_ares_sys::ares_dns_rcode_t::from(rcode) as u16
+}
+
+fn type_number(rr_type: DnsRecordType) -> u32 {
+    c_ares_sys::ares_dns_rec_type_t::from(rr_type).0
+}
+
+fn class_number(class: DnsCls) -> u32 {
+    c_ares_sys::ares_dns_class_t::from(class).0
+}
+
+// =============================================================================
+// Serializing
+// =============================================================================
+
+// Serializes a value as the string that it displays as.
+struct Text<T>(T);
+
+impl<T: fmt::Display> Serialize for Text<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+impl Serialize for DnsRecord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        let flags = self.flags();
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("ID", &self.id())?;
+        map.serialize_entry("QR", &flags.contains(DnsFlags::QR))?;
+        map.serialize_entry("Opcode", &opcode_number(self.opcode()))?;
+        for (flag, member) in FLAG_MEMBERS {
+            map.serialize_entry(member, &flags.contains(flag))?;
+        }
+        map.serialize_entry("RCODE", &rcode_number(self.rcode()))?;
+        map.serialize_entry("QDCOUNT", &self.query_count())?;
+        map.serialize_entry("ANCOUNT", &self.rr_count(DnsSection::Answer))?;
+        map.serialize_entry("NSCOUNT", &self.rr_count(DnsSection::Authority))?;
+        map.serialize_entry("ARCOUNT", &self.rr_count(DnsSection::Additional))?;
+
+        match self.query_count() {
+            0 => {}
+            1 => {
+                for (name, qtype, qclass) in self.queries() {
+                    map.serialize_entry("QNAME", &Text(Name(name)))?;
+                    map.serialize_entry("QTYPE", &type_number(qtype))?;
+                    map.serialize_entry("QTYPEname", &Text(qtype))?;
+                    map.serialize_entry("QCLASS", &class_number(qclass))?;
+                    map.serialize_entry("QCLASSname", &Text(qclass))?;
+                }
+            }
+            _ => map.serialize_entry("questionRRs", &Questions(self))?,
+        }
+
+        for (section, member) in SECTION_MEMBERS {
+            if self.rr_count(section) > 0 {
+                map.serialize_entry(member, &Section(self, section))?;
+            }
+        }
+        map.end()
+    }
+}
+
+struct Questions<'a>(&'a DnsRecord);
+
+impl Serialize for Questions<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.queries().map(|(name, qtype, qclass)| Question {
+            name,
+            qtype,
+            qclass,
+        }))
+    }
+}
+
+struct Question<'a> {
+    name: &'a str,
+    qtype: DnsRecordType,
+    qclass: DnsCls,
+}
+
+impl Serialize for Question<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("NAME", &Text(Name(self.name)))?;
+        serialize_type_and_class(&mut map, self.qtype, self.qclass)?;
+        map.end()
+    }
+}
+
+struct Section<'a>(&'a DnsRecord, DnsSection);
+
+impl Serialize for Section<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.rrs(self.1).map(JsonRr))
+    }
+}
+
+struct JsonRr<'a>(&'a DnsRr);
+
+impl Serialize for JsonRr<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        let rr = self.0;
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("NAME", &Text(Name(rr.name())))?;
+        match rr.as_typed() {
+            TypedRr::Opt(opt) => {
+                let udp_size = c_ares_sys::ares_dns_class_t(u32::from(opt.udp_size()));
+                serialize_type_and_class(&mut map, DnsRecordType::OPT, DnsCls::from(udp_size))?;
+                map.serialize_entry("TTL", &opt_ttl(opt))?;
+                map.serialize_entry("RDATAHEX", &hex_encode(&opt_rdata(opt)))?;
+                let options: Vec<_> = opt
+                    .options()
+                    .map(|(code, value)| OptOption {
+                        code,
+                        value: value.ok(),
+                    })
+                    .collect();
+                map.serialize_entry("rdataOPT", &options)?;
+            }
+            TypedRr::RawRr(raw) => {
+                serialize_type_and_class(&mut map, record_type(raw.raw_type()), rr.dns_class())?;
+                map.serialize_entry("TTL", &rr.ttl())?;
+                map.serialize_entry("RDATAHEX", &hex_encode(raw.data()))?;
+            }
+            TypedRr::Any(_) | TypedRr::Unknown(_) => {
+                // c-ares keeps no RDATA for these, so there is nothing true to write.
+                let message = format!("no RDATA for {} records", rr.rr_type());
+                return Err(ser::Error::custom(message));
+            }
+            typed => {
+                serialize_type_and_class(&mut map, rr.rr_type(), rr.dns_class())?;
+                map.serialize_entry("TTL", &rr.ttl())?;
+                map.serialize_entry(&format!("rdata{}", rr.rr_type()), &Text(Rdata(typed)))?;
+            }
+        }
+        map.end()
+    }
+}
+
+fn serialize_type_and_class<M: SerializeMap>(
+    map: &mut M,
+    rr_type: DnsRecordType,
+    class: DnsCls,
+) -> result::Result<(), M::Error> {
+    map.serialize_entry("TYPE", &type_number(rr_type))?;
+    map.serialize_entry("TYPEname", &Text(rr_type))?;
+    map.serialize_entry("CLASS", &class_number(class))?;
+    map.serialize_entry("CLASSname", &Text(class))
+}
+
+// An EDNS option, with its value parsed where c-ares knows how.
+struct OptOption {
+    code: u16,
+    value: Option<OptValue>,
+}
+
+impl Serialize for OptOption {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("CODE", &self.code)?;
+        if let Some(name) = DnsRr::opt_name(DnsRrKey::OPT_OPTIONS, self.code) {
+            map.serialize_entry("CODEname", name)?;
+        }
+        if let Some(value) = &self.value {
+            map.serialize_entry("VALUE", &Text(value))?;
+        }
+        map.end()
+    }
+}
+
+// =============================================================================
+// Deserializing
+// =============================================================================
+
+impl<'de> Deserialize<'de> for DnsRecord {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        let message = MessageObject::deserialize(deserializer)?;
+        message.into_record().map_err(de::Error::custom)
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct MessageObject {
+    #[serde(rename = "ID", default)]
+    id: u16,
+    #[serde(rename = "QR", default, deserialize_with = "flag")]
+    qr: bool,
+    #[serde(rename = "Opcode", default)]
+    opcode: u16,
+    #[serde(rename = "AA", default, deserialize_with = "flag")]
+    aa: bool,
+    #[serde(rename = "TC", default, deserialize_with = "flag")]
+    tc: bool,
+    #[serde(rename = "RD", default, deserialize_with = "flag")]
+    rd: bool,
+    #[serde(rename = "RA", default, deserialize_with = "flag")]
+    ra: bool,
+    #[serde(rename = "AD", default, deserialize_with = "flag")]
+    ad: bool,
+    #[serde(rename = "CD", default, deserialize_with = "flag")]
+    cd: bool,
+    #[serde(rename = "RCODE", default)]
+    rcode: u16,
+    #[serde(rename = "QNAME")]
+    qname: Option<String>,
+    #[serde(rename = "QTYPE")]
+    qtype: Option<u16>,
+    #[serde(rename = "QTYPEname")]
+    qtype_name: Option<String>,
+    #[serde(rename = "QCLASS")]
+    qclass: Option<u16>,
+    #[serde(rename = "QCLASSname")]
+    qclass_name: Option<String>,
+    #[serde(rename = "questionRRs", default)]
+    question_rrs: Vec<RrObject>,
+    #[serde(rename = "answerRRs", default)]
+    answer_rrs: Vec<RrObject>,
+    #[serde(rename = "authorityRRs", default)]
+    authority_rrs: Vec<RrObject>,
+    #[serde(rename = "additionalRRs", default)]
+    additional_rrs: Vec<RrObject>,
+}
+
+impl MessageObject {
+    fn into_record(self) -> Result<DnsRecord> {
+        let mut flags = DnsFlags::empty();
+        flags.set(DnsFlags::QR, self.qr);
+        flags.set(DnsFlags::AA, self.aa);
+        flags.set(DnsFlags::TC, self.tc);
+        flags.set(DnsFlags::RD, self.rd);
+        flags.set(DnsFlags::RA, self.ra);
+        flags.set(DnsFlags::AD, self.ad);
+        flags.set(DnsFlags::CD, self.cd);
+        let opcode = OPCODES
+            .into_iter()
+            .find(|&opcode| opcode_number(opcode) == self.opcode)
+            .ok_or(Error::EBADSTR)?;
+        let rcode = RCODES
+            .into_iter()
+            .find(|&rcode| rcode_number(rcode) == self.rcode)
+            .ok_or(Error::EBADSTR)?;
+        let mut rec = DnsRecord::new(self.id, flags, opcode, rcode)?;
+
+        if let Some(qname) = &self.qname {
+            let qtype = rr_type(self.qtype, self.qtype_name.as_deref())?;
+            let qclass = class(self.qclass, self.qclass_name.as_deref())?;
+            rec.query_add(&absolute_name(qname, None)?, qtype, qclass)?;
+        }
+        for question in &self.question_rrs {
+            let qtype = rr_type(question.rr_type, question.type_name.as_deref())?;
+            let qclass = class(question.class, question.class_name.as_deref())?;
+            rec.query_add(&absolute_name(&question.name, None)?, qtype, qclass)?;
+        }
+
+        let sections = [
+            (DnsSection::Answer, self.answer_rrs),
+            (DnsSection::Authority, self.authority_rrs),
+            (DnsSection::Additional, self.additional_rrs),
+        ];
+        for (section, rrs) in sections {
+            for rr in rrs {
+                rr.add_to(&mut rec, section)?;
+            }
+        }
+        Ok(rec)
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct RrObject {
+    #[serde(rename = "NAME")]
+    name: String,
+    #[serde(rename = "TYPE")]
+    rr_type: Option<u16>,
+    #[serde(rename = "TYPEname")]
+    type_name: Option<String>,
+    #[serde(rename = "CLASS")]
+    class: Option<u16>,
+    #[serde(rename = "CLASSname")]
+    class_name: Option<String>,
+    #[serde(rename = "TTL")]
+    ttl: Option<u32>,
+    #[serde(rename = "RDATAHEX")]
+    rdata_hex: Option<String>,
+    // The `rdata` members are named for the record type, so are picked out from the rest.
+    #[serde(flatten)]
+    others: HashMap<String, Member>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum Member {
+    Text(String),
+    Other(IgnoredAny),
+}
+
+impl RrObject {
+    fn add_to(self, rec: &mut DnsRecord, section: DnsSection) -> Result<()> {
+        let owner = absolute_name(&self.name, None)?;
+        let rr_type = rr_type(self.rr_type, self.type_name.as_deref())?;
+        let class = class(self.class, self.class_name.as_deref())?;
+        let ttl = self.ttl.unwrap_or(0);
+        let rdata_member = format!("rdata{rr_type}");
+        if let Some(Member::Text(rdata)) = self.others.get(rdata_member.as_str()) {
+            let entries = tokenize(rdata)?;
+            let tokens = match entries.as_slice() {
+                [] => &[],
+                [entry] => entry.tokens.as_slice(),
+                _ => return Err(Error::EBADSTR),
+            };
+            return add_parsed_rr(rec, section, &owner, ttl, class, rr_type, tokens, None);
+        }
+        let data = hex_decode(self.rdata_hex.as_deref().ok_or(Error::EBADSTR)?)?;
+        add_generic_rr(rec, section, &owner, ttl, class, rr_type, &data)
+    }
+}
+
+fn rr_type(number: Option<u16>, name: Option<&str>) -> Result<DnsRecordType> {
+    match (number, name) {
+        (Some(number), _) => Ok(record_type(number)),
+        (None, Some(name)) => parse_type(name),
+        (None, None) => Err(Error::EBADSTR),
+    }
+}
+
+fn class(number: Option<u16>, name: Option<&str>) -> Result<DnsCls> {
+    match (number, name) {
+        (Some(number), _) => Ok(DnsCls::from(c_ares_sys::ares_dns_class_t(u32::from(
+            number,
+        )))),
+        (None, Some(name)) => parse_class(name).ok_or(Error::EBADSTR),
+        (None, None) => Ok(DnsCls::IN),
+    }
+}
+
+// RFC 8427 describes the flags as booleans, but its examples write them as numbers.
+fn flag<'de, D: Deserializer<'de>>(deserializer: D) -> result::Result<bool, D::Error> {
+    struct FlagVisitor;
+
+    impl Visitor<'_> for FlagVisitor {
+        type Value = bool;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a boolean, or 0 or 1")
+        }
+
+        fn visit_bool<E: de::Error>(self, value: bool) -> result::Result<bool, E> {
+            Ok(value)
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> result::Result<bool, E> {
+            match value {
+                0 => Ok(false),
+                1 => Ok(true),
+                _ => Err(E::invalid_value(Unexpected::Unsigned(value), &self)),
+            }
+        }
+    }
+
+    deserializer.deserialize_any(FlagVisitor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::DnsParseFlags;
+    use serde_json::json;
+    use std::net::Ipv4Addr;
+
+    fn sample() -> DnsRecord {
+        let mut rec = DnsRecord::new(
+            0x1234,
+            DnsFlags::QR | DnsFlags::RD,
+            DnsOpcode::Query,
+            DnsRcode::NoError,
+        )
+        .unwrap();
+        rec.query_add("example.com", DnsRecordType::MX, DnsCls::IN)
+            .unwrap();
+        rec.add_mx(
+            DnsSection::Answer,
+            "example.com",
+            300,
+            10,
+            "mail.example.com",
+        )
+        .unwrap()
+        .add_txt(DnsSection::Answer, "example.com", 60, ["v=spf1 -all"])
+        .unwrap()
+        .add_rr_str(
+            DnsSection::Additional,
+            "example.com. 5 IN TYPE65534 \\# 3 ABCDEF",
+        )
+        .unwrap()
+        .add_opt(1232, 0, 0x8000, [(10_u16, [1_u8; 8])])
+        .unwrap();
+        rec
+    }
+
+    #[test]
+    fn serialize_message() {
+        let value = serde_json::to_value(sample()).unwrap();
+        assert_eq!(value["ID"], 0x1234);
+        assert_eq!(value["QR"], true);
+        assert_eq!(value["AA"], false);
+        assert_eq!(value["RD"], true);
+        assert_eq!(value["Opcode"], 0);
+        assert_eq!(value["RCODE"], 0);
+        assert_eq!(value["QDCOUNT"], 1);
+        assert_eq!(value["ANCOUNT"], 2);
+        assert_eq!(value["NSCOUNT"], 0);
+        assert_eq!(value["ARCOUNT"], 2);
+        assert_eq!(value["QNAME"], "example.com.");
+        assert_eq!(value["QTYPE"], 15);
+        assert_eq!(value["QTYPEname"], "MX");
+        assert_eq!(value["QCLASSname"], "IN");
+        assert!(value.get("questionRRs").is_none());
+        assert!(value.get("authorityRRs").is_none());
+        assert_eq!(
+            value["answerRRs"],
+            json!([
+                {
+                    "NAME": "example.com.",
+                    "TYPE": 15,
+                    "TYPEname": "MX",
+                    "CLASS": 1,
+                    "CLASSname": "IN",
+                    "TTL": 300,
+                    "rdataMX": "10 mail.example.com.",
+                },
+                {
+                    "NAME": "example.com.",
+                    "TYPE": 16,
+                    "TYPEname": "TXT",
+                    "CLASS": 1,
+                    "CLASSname": "IN",
+                    "TTL": 60,
+                    "rdataTXT": "\"v=spf1 -all\"",
+                },
+            ])
+        );
+        assert_eq!(value["additionalRRs"][0]["TYPE"], 65534);
+        assert_eq!(value["additionalRRs"][0]["RDATAHEX"], "ABCDEF");
+        let opt = &value["additionalRRs"][1];
+        assert_eq!(opt["NAME"], ".");
+        assert_eq!(opt["TYPEname"], "OPT");
+        assert_eq!(opt["CLASS"], 1232);
+        assert_eq!(opt["TTL"], 0x8000);
+        assert_eq!(opt["RDATAHEX"], "000A00080101010101010101");
+        assert_eq!(opt["rdataOPT"][0]["CODE"], 10);
+        assert_eq!(opt["rdataOPT"][0]["CODEname"], "COOKIE");
+    }
+
+    #[test]
+    fn round_trip() {
+        let rec = sample();
+        let json = serde_json::to_string(&rec).unwrap();
+        let parsed: DnsRecord = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.to_string(), rec.to_string());
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+    }
+
+    #[test]
+    fn round_trip_wire() {
+        let wire = sample().write().unwrap();
+        let rec = DnsRecord::parse(&wire, DnsParseFlags::empty()).unwrap();
+        let parsed: DnsRecord =
+            serde_json::from_value(serde_json::to_value(&rec).unwrap()).unwrap();
+        assert_eq!(&*parsed.write().unwrap(), &*wire);
+    }
+
+    #[test]
+    fn multiple_questions() {
+        let mut rec =
+            DnsRecord::new(1, DnsFlags::empty(), DnsOpcode::Query, DnsRcode::NoError).unwrap();
+        rec.query_add("a.example", DnsRecordType::A, DnsCls::IN)
+            .unwrap()
+            .query_add("b.example", DnsRecordType::AAAA, DnsCls::CHAOS)
+            .unwrap();
+        let value = serde_json::to_value(&rec).unwrap();
+        assert!(value.get("QNAME").is_none());
+        assert_eq!(
+            value["questionRRs"],
+            json!([
+                {"NAME": "a.example.", "TYPE": 1, "TYPEname": "A", "CLASS": 1, "CLASSname": "IN"},
+                {"NAME": "b.example.", "TYPE": 28, "TYPEname": "AAAA", "CLASS": 3, "CLASSname": "CH"},
+            ])
+        );
+        let parsed: DnsRecord = serde_json::from_value(value).unwrap();
+        let queries: Vec<_> = parsed.queries().collect();
+        assert_eq!(
+            queries,
+            [
+                ("a.example", DnsRecordType::A, DnsCls::IN),
+                ("b.example", DnsRecordType::AAAA, DnsCls::CHAOS),
+            ]
+        );
+    }
+
+    #[test]
+    fn deserialize_rfc_example() {
+        // From RFC 8427 section 5.1, with numeric flags and names lacking the trailing dot.
+        let rec: DnsRecord = serde_json::from_value(json!({
+            "ID": 19678, "QR": 0, "Opcode": 0,
+            "AA": 0, "TC": 0, "RD": 0, "RA": 0, "AD": 0, "CD": 0, "RCODE": 0,
+            "QDCOUNT": 1, "ANCOUNT": 0, "NSCOUNT": 0, "ARCOUNT": 0,
+            "QNAME": "example.com", "QTYPE": 1, "QCLASS": 1
+        }))
+        .unwrap();
+        assert_eq!(rec.id(), 19678);
+        assert_eq!(rec.flags(), DnsFlags::empty());
+        assert_eq!(
+            rec.query_get(0).unwrap(),
+            ("example.com", DnsRecordType::A, DnsCls::IN)
+        );
+    }
+
+    #[test]
+    fn deserialize_names_and_defaults() {
+        let rec: DnsRecord = serde_json::from_value(json!({
+            "QR": true,
+            "answerRRs": [
+                {"NAME": "example.com", "TYPEname": "A", "rdataA": "192.0.2.1"},
+                {"NAME": "example.com", "TYPEname": "TYPE65534", "CLASSname": "CLASS3", "RDATAHEX": "abcd"},
+            ],
+        }))
+        .unwrap();
+        let a = rec.rr(DnsSection::Answer, 0).unwrap();
+        assert_eq!(a.ttl(), 0);
+        assert_eq!(a.dns_class(), DnsCls::IN);
+        assert_eq!(a.as_a().unwrap().addr(), Ipv4Addr::new(192, 0, 2, 1));
+        let raw = rec.rr(DnsSection::Answer, 1).unwrap().as_raw_rr().unwrap();
+        assert_eq!(raw.raw_type(), 65534);
+        assert_eq!(raw.data(), [0xab, 0xcd]);
+        assert_eq!(raw.dns_class(), DnsCls::CHAOS);
+    }
+
+    #[test]
+    fn deserialize_errors() {
+        let bad = [
+            json!({"Opcode": 3}),
+            json!({"RCODE": 4000}),
+            json!({"QR": 2}),
+            json!({"answerRRs": [{"NAME": "example.com", "TYPE": 1}]}),
+            json!({"answerRRs": [{"NAME": "example.com", "TYPE": 1, "rdataA": "nonsense"}]}),
+            json!({"answerRRs": [{"NAME": "example.com", "TYPE": 1, "RDATAHEX": "ABC"}]}),
+            json!({"answerRRs": [{"NAME": "example.com", "rdataA": "192.0.2.1"}]}),
+        ];
+        for value in bad {
+            assert!(
+                serde_json::from_value::<DnsRecord>(value.clone()).is_err(),
+                "{value}"
+            );
+        }
+    }
+}
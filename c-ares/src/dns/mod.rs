@@ -1,10 +1,21 @@
+#[cfg(feature = "proptest")]
+mod arbitrary;
+mod builder;
 pub(crate) mod callback;
 mod dns_opt;
 mod enums;
+#[cfg(feature = "serde")]
+mod json;
+mod presentation;
 mod record;
 mod rr;
+#[cfg(test)]
+mod test_util;
 mod typed;
+mod zone;
 
+#[cfg(feature = "proptest")]
+pub use arbitrary::{ArbitraryRdata, ArbitraryRr};
 pub use dns_opt::{OptParseError, OptValue, parse_opt_value};
 pub use enums::{
     DnsCls, DnsDataType, DnsFlags, DnsOpcode, DnsOptDataType, DnsParseFlags, DnsRcode,
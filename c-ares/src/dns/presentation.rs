@@ -0,0 +1,1670 @@
+//! RFC 1035 presentation format for DNS records.
+//!
+//! Resource records format as a single line of zone-file text, in the same
+//! form that `dig` and BIND use:
+//!
+//! ```text
+//! example.com.    300    IN    MX    10 mail.example.com.
+//! ```
+//!
+//! Character-strings (as in TXT records) are quoted, with `"` and `\`
+//! backslash-escaped and non-printable bytes written as `\DDD`. Records that
+//! c-ares does not parse natively are written in the RFC 3597 generic form,
+//! `TYPE65534 \# 3 ABCDEF`.
+//!
+//! A whole [`DnsRecord`] formats like the body of `dig` output: a header
+//! comment, followed by the question and resource record sections.
+//!
+//! In the other direction, [`DnsRecord::add_rr_str`] parses a single record
+//! in presentation format, and `DnsRecord` implements [`FromStr`] to parse
+//! its own `Display` output.
+
+use std::fmt::{self, Write as _};
+use std::iter::Peekable;
+use std::mem;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::str::{Chars, FromStr};
+
+use crate::error::{Error, Result};
+
+use super::dns_opt::{OptValue, parse_opt_value};
+use super::enums::{
+    DnsCls, DnsFlags, DnsOpcode, DnsOptDataType, DnsRcode, DnsRecordType, DnsRrKey, DnsSection,
+};
+use super::record::DnsRecord;
+use super::rr::DnsRr;
+use super::typed::{
+    ARecord, AaaaRecord, CaaRecord, CnameRecord, HinfoRecord, HttpsRecord, MxRecord, NaptrRecord,
+    NsRecord, OptRecord, PtrRecord, RawRrRecord, SigRecord, SoaRecord, SrvRecord, SvcbRecord,
+    TlsaRecord, TxtRecord, TypedRr, UriRecord,
+};
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+const FLAG_NAMES: [(DnsFlags, &str); 7] = [
+    (DnsFlags::QR, "qr"),
+    (DnsFlags::AA, "aa"),
+    (DnsFlags::TC, "tc"),
+    (DnsFlags::RD, "rd"),
+    (DnsFlags::RA, "ra"),
+    (DnsFlags::AD, "ad"),
+    (DnsFlags::CD, "cd"),
+];
+
+const SECTION_NAMES: [(DnsSection, &str); 3] = [
+    (DnsSection::Answer, "ANSWER"),
+    (DnsSection::Authority, "AUTHORITY"),
+    (DnsSection::Additional, "ADDITIONAL"),
+];
+
+pub(crate) const OPCODES: [DnsOpcode; 5] = [
+    DnsOpcode::Query,
+    DnsOpcode::IQuery,
+    DnsOpcode::Status,
+    DnsOpcode::Notify,
+    DnsOpcode::Update,
+];
+
+pub(crate) const RCODES: [DnsRcode; 20] = [
+    DnsRcode::NoError,
+    DnsRcode::FormErr,
+    DnsRcode::ServFail,
+    DnsRcode::NXDomain,
+    DnsRcode::NotImp,
+    DnsRcode::Refused,
+    DnsRcode::YXDomain,
+    DnsRcode::YXRRSet,
+    DnsRcode::NXRRSet,
+    DnsRcode::NotAuth,
+    DnsRcode::NotZone,
+    DnsRcode::DSOTypeI,
+    DnsRcode::BadSig,
+    DnsRcode::BadKey,
+    DnsRcode::BadTime,
+    DnsRcode::BadMode,
+    DnsRcode::BadName,
+    DnsRcode::BadAlg,
+    DnsRcode::BadTrunc,
+    DnsRcode::BadCookie,
+];
+
+// SvcParamKeys are looked up by name among the codes up to this one.
+const MAX_NAMED_SVC_PARAM: u16 = 64;
+
+// =============================================================================
+// Formatting
+// =============================================================================
+
+impl fmt::Display for DnsRr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(opt) = self.as_opt() {
+            return write_opt(f, opt);
+        }
+        write!(
+            f,
+            "{}\t{}\t{}\t",
+            Name(self.name()),
+            self.ttl(),
+            self.dns_class()
+        )?;
+        match self.as_typed() {
+            TypedRr::RawRr(raw) => {
+                write!(f, "TYPE{}\t", raw.raw_type())?;
+                write_generic(f, raw.data())
+            }
+            typed => write!(f, "{}\t{}", self.rr_type(), Rdata(typed)),
+        }
+    }
+}
+
+// OPT is a pseudo-record with no zone-file syntax.  Write it in the generic form, with the fields
+// that it re-purposes - class and TTL - holding the EDNS values that they carry on the wire.
+fn write_opt(f: &mut fmt::Formatter<'_>, opt: OptRecord<'_>) -> fmt::Result {
+    write_name(f, opt.name())?;
+    write!(f, "\t{}\tCLASS{}\tOPT\t", opt_ttl(opt), opt.udp_size())?;
+    write_generic(f, &opt_rdata(opt))
+}
+
+// The value that an OPT record carries in its TTL field on the wire.
+pub(crate) fn opt_ttl(opt: OptRecord<'_>) -> u32 {
+    (u32::from(opt.version()) << 16) | u32::from(opt.flags())
+}
+
+// The wire form of an OPT record's options.
+pub(crate) fn opt_rdata(opt: OptRecord<'_>) -> Vec<u8> {
+    let mut data = Vec::new();
+    for (code, value) in opt.raw_options() {
+        data.extend(code.to_be_bytes());
+        data.extend((value.len() as u16).to_be_bytes());
+        data.extend(value);
+    }
+    data
+}
+
+// Formats just the RDATA of a record.
+pub(crate) struct Rdata<'a>(pub(crate) TypedRr<'a>);
+
+impl fmt::Display for Rdata<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_rdata(f, self.0)
+    }
+}
+
+fn write_rdata(f: &mut fmt::Formatter<'_>, typed: TypedRr<'_>) -> fmt::Result {
+    match typed {
+        TypedRr::A(a) => write!(f, "{}", a.addr()),
+        TypedRr::Aaaa(aaaa) => write!(f, "{}", aaaa.addr()),
+        TypedRr::Ns(ns) => write_name(f, ns.nsdname()),
+        TypedRr::Cname(cname) => write_name(f, cname.cname()),
+        TypedRr::Ptr(ptr) => write_name(f, ptr.dname()),
+        TypedRr::Soa(soa) => {
+            write_name(f, soa.mname())?;
+            f.write_char(' ')?;
+            write_name(f, soa.rname())?;
+            write!(
+                f,
+                " {} {} {} {} {}",
+                soa.serial(),
+                soa.refresh(),
+                soa.retry(),
+                soa.expire(),
+                soa.minimum()
+            )
+        }
+        TypedRr::Hinfo(hinfo) => {
+            write_char_string(f, hinfo.cpu().as_bytes())?;
+            f.write_char(' ')?;
+            write_char_string(f, hinfo.os().as_bytes())
+        }
+        TypedRr::Mx(mx) => {
+            write!(f, "{} ", mx.preference())?;
+            write_name(f, mx.exchange())
+        }
+        TypedRr::Txt(txt) => {
+            if txt.entry_count() == 0 {
+                return f.write_str("\"\"");
+            }
+            for (i, entry) in txt.entries().enumerate() {
+                if i > 0 {
+                    f.write_char(' ')?;
+                }
+                write_char_string(f, entry)?;
+            }
+            Ok(())
+        }
+        TypedRr::Sig(sig) => {
+            write!(
+                f,
+                "{} {} {} {} ",
+                record_type(sig.type_covered()),
+                sig.algorithm(),
+                sig.labels(),
+                sig.original_ttl()
+            )?;
+            write_timestamp(f, sig.expiration())?;
+            f.write_char(' ')?;
+            write_timestamp(f, sig.inception())?;
+            write!(f, " {} ", sig.key_tag())?;
+            write_name(f, sig.signers_name())?;
+            write!(f, " {}", base64_encode(sig.signature()))
+        }
+        TypedRr::Srv(srv) => {
+            write!(f, "{} {} {} ", srv.priority(), srv.weight(), srv.port())?;
+            write_name(f, srv.target())
+        }
+        TypedRr::Naptr(naptr) => {
+            write!(f, "{} {} ", naptr.order(), naptr.preference())?;
+            write_char_string(f, naptr.flags().as_bytes())?;
+            f.write_char(' ')?;
+            write_char_string(f, naptr.services().as_bytes())?;
+            f.write_char(' ')?;
+            write_char_string(f, naptr.regexp().as_bytes())?;
+            f.write_char(' ')?;
+            write_name(f, naptr.replacement())
+        }
+        TypedRr::Tlsa(tlsa) => {
+            write!(
+                f,
+                "{} {} {} ",
+                tlsa.cert_usage(),
+                tlsa.selector(),
+                tlsa.matching_type()
+            )?;
+            write_hex(f, tlsa.data())
+        }
+        TypedRr::Svcb(svcb) => {
+            write!(f, "{} ", svcb.priority())?;
+            write_name(f, svcb.target())?;
+            write_svc_params(f, DnsRrKey::SVCB_PARAMS, svcb.raw_params())
+        }
+        TypedRr::Https(https) => {
+            write!(f, "{} ", https.priority())?;
+            write_name(f, https.target())?;
+            write_svc_params(f, DnsRrKey::HTTPS_PARAMS, https.raw_params())
+        }
+        TypedRr::Uri(uri) => {
+            write!(f, "{} {} ", uri.priority(), uri.weight())?;
+            write_char_string(f, uri.target().as_bytes())
+        }
+        TypedRr::Caa(caa) => {
+            write!(f, "{} {} ", caa.flags(), caa.tag())?;
+            write_char_string(f, caa.value())
+        }
+        TypedRr::Opt(opt) => write_opt(f, opt),
+        TypedRr::RawRr(raw) => write_generic(f, raw.data()),
+        TypedRr::Any(_) | TypedRr::Unknown(_) => write_generic(f, &[]),
+    }
+}
+
+fn write_svc_params<'a>(
+    f: &mut fmt::Formatter<'_>,
+    key: DnsRrKey,
+    params: impl Iterator<Item = (u16, &'a [u8])>,
+) -> fmt::Result {
+    for (code, value) in params {
+        f.write_char(' ')?;
+        let name = DnsRr::opt_name(key, code);
+        let parsed = name.and_then(|_| parse_opt_value(key, code, value).ok());
+        let (Some(name), Some(parsed)) = (name, parsed) else {
+            // RFC 9460 generic form.
+            write!(f, "key{code}")?;
+            if !value.is_empty() {
+                f.write_char('=')?;
+                write_char_string(f, value)?;
+            }
+            continue;
+        };
+        f.write_str(name)?;
+        match parsed {
+            OptValue::None => {}
+            OptValue::StrList(items) => {
+                f.write_str("=\"")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_escaped(f, item.as_bytes(), b"\"\\,")?;
+                }
+                f.write_char('"')?;
+            }
+            OptValue::U8List(values) => write_list(f, &values)?,
+            OptValue::U16(value) => write!(f, "={value}")?,
+            OptValue::U16List(codes) => {
+                f.write_char('=')?;
+                for (i, code) in codes.into_iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    match DnsRr::opt_name(key, code) {
+                        Some(name) => f.write_str(name)?,
+                        None => write!(f, "key{code}")?,
+                    }
+                }
+            }
+            OptValue::U32(value) => write!(f, "={value}")?,
+            OptValue::U32List(values) => write_list(f, &values)?,
+            OptValue::InAddr4List(addrs) => write_list(f, &addrs)?,
+            OptValue::InAddr6List(addrs) => write_list(f, &addrs)?,
+            OptValue::Bin(data) => write!(f, "={}", base64_encode(&data))?,
+            OptValue::Name(name) => {
+                f.write_char('=')?;
+                write_name(f, &name)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_list<T: fmt::Display>(f: &mut fmt::Formatter<'_>, values: &[T]) -> fmt::Result {
+    f.write_char('=')?;
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            f.write_char(',')?;
+        }
+        write!(f, "{value}")?;
+    }
+    Ok(())
+}
+
+// Formats a name from c-ares in absolute form.
+pub(crate) struct Name<'a>(pub(crate) &'a str);
+
+impl fmt::Display for Name<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_name(f, self.0)
+    }
+}
+
+// Names from c-ares are already escaped, and lack the trailing dot that makes them absolute.
+fn write_name(f: &mut fmt::Formatter<'_>, name: &str) -> fmt::Result {
+    if name.ends_with('.') {
+        f.write_str(name)
+    } else {
+        write!(f, "{name}.")
+    }
+}
+
+fn write_char_string(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    f.write_char('"')?;
+    write_escaped(f, bytes, b"\"\\")?;
+    f.write_char('"')
+}
+
+// Writes printable ASCII as-is, except for `specials` which are backslash-escaped, and everything
+// else as `\DDD`.
+fn write_escaped(f: &mut fmt::Formatter<'_>, bytes: &[u8], specials: &[u8]) -> fmt::Result {
+    for &b in bytes {
+        if specials.contains(&b) {
+            write!(f, "\\{}", char::from(b))?;
+        } else if b == b' ' || b.is_ascii_graphic() {
+            f.write_char(char::from(b))?;
+        } else {
+            write!(f, "\\{b:03}")?;
+        }
+    }
+    Ok(())
+}
+
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    f.write_str(&hex_encode(bytes))
+}
+
+// RFC 3597 generic RDATA.
+fn write_generic(f: &mut fmt::Formatter<'_>, data: &[u8]) -> fmt::Result {
+    write!(f, "\\# {}", data.len())?;
+    if !data.is_empty() {
+        f.write_char(' ')?;
+        write_hex(f, data)?;
+    }
+    Ok(())
+}
+
+// RFC 4034 section 3.2: YYYYMMDDHHmmSS, in UTC.
+fn write_timestamp(f: &mut fmt::Formatter<'_>, secs: u32) -> fmt::Result {
+    let (year, month, day) = civil_from_days(i64::from(secs / 86400));
+    let secs = secs % 86400;
+    write!(
+        f,
+        "{year:04}{month:02}{day:02}{:02}{:02}{:02}",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+pub(crate) fn record_type(code: u16) -> DnsRecordType {
+    DnsRecordType::from(c_ares_sys::ares_dns_rec_type_t(u32::from(code)))
+}
+
+impl fmt::Display for DnsRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            ";; ->>HEADER<<- opcode: {}, status: {}, id: {}",
+            self.opcode(),
+            self.rcode(),
+            self.id()
+        )?;
+        f.write_str(";; flags:")?;
+        for (flag, name) in FLAG_NAMES {
+            if self.flags().contains(flag) {
+                write!(f, " {name}")?;
+            }
+        }
+        writeln!(
+            f,
+            "; QUERY: {}, ANSWER: {}, AUTHORITY: {}, ADDITIONAL: {}",
+            self.query_count(),
+            self.rr_count(DnsSection::Answer),
+            self.rr_count(DnsSection::Authority),
+            self.rr_count(DnsSection::Additional)
+        )?;
+        if self.query_count() > 0 {
+            f.write_str("\n;; QUESTION SECTION:\n")?;
+            for (name, qtype, qclass) in self.queries() {
+                f.write_char(';')?;
+                write_name(f, name)?;
+                writeln!(f, "\t\t{qclass}\t{qtype}")?;
+            }
+        }
+        for (section, label) in SECTION_NAMES {
+            if self.rr_count(section) == 0 {
+                continue;
+            }
+            writeln!(f, "\n;; {label} SECTION:")?;
+            for rr in self.rrs(section) {
+                writeln!(f, "{rr}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+macro_rules! display_via_rr {
+    ($($view:ident),* $(,)?) => {
+        $(
+            impl fmt::Display for $view<'_> {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    fmt::Display::fmt(self.as_dns_rr(), f)
+                }
+            }
+        )*
+    };
+}
+
+display_via_rr!(
+    ARecord,
+    AaaaRecord,
+    NsRecord,
+    CnameRecord,
+    SoaRecord,
+    PtrRecord,
+    HinfoRecord,
+    MxRecord,
+    SigRecord,
+    SrvRecord,
+    NaptrRecord,
+    TlsaRecord,
+    UriRecord,
+    TxtRecord,
+    OptRecord,
+    SvcbRecord,
+    HttpsRecord,
+    CaaRecord,
+    RawRrRecord,
+);
+
+impl fmt::Display for TypedRr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rr = match *self {
+            TypedRr::A(view) => view.as_dns_rr(),
+            TypedRr::Aaaa(view) => view.as_dns_rr(),
+            TypedRr::Ns(view) => view.as_dns_rr(),
+            TypedRr::Cname(view) => view.as_dns_rr(),
+            TypedRr::Soa(view) => view.as_dns_rr(),
+            TypedRr::Ptr(view) => view.as_dns_rr(),
+            TypedRr::Hinfo(view) => view.as_dns_rr(),
+            TypedRr::Mx(view) => view.as_dns_rr(),
+            TypedRr::Txt(view) => view.as_dns_rr(),
+            TypedRr::Sig(view) => view.as_dns_rr(),
+            TypedRr::Srv(view) => view.as_dns_rr(),
+            TypedRr::Naptr(view) => view.as_dns_rr(),
+            TypedRr::Opt(view) => view.as_dns_rr(),
+            TypedRr::Tlsa(view) => view.as_dns_rr(),
+            TypedRr::Svcb(view) => view.as_dns_rr(),
+            TypedRr::Https(view) => view.as_dns_rr(),
+            TypedRr::Uri(view) => view.as_dns_rr(),
+            TypedRr::Caa(view) => view.as_dns_rr(),
+            TypedRr::RawRr(view) => view.as_dns_rr(),
+            TypedRr::Any(rr) | TypedRr::Unknown(rr) => rr,
+        };
+        fmt::Display::fmt(rr, f)
+    }
+}
+
+// =============================================================================
+// Tokenizing
+// =============================================================================
+
+// A single field of presentation-format text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Token {
+    // The field's text, with any quotes removed but escapes left in place.
+    pub(crate) text: String,
+    // Whether any part of the field was quoted.
+    pub(crate) quoted: bool,
+}
+
+impl Token {
+    // Whether this is the `\#` that introduces RFC 3597 generic RDATA.
+    fn is_generic_marker(&self) -> bool {
+        !self.quoted && self.text == "\\#"
+    }
+}
+
+// The fields making up one entry - usually one line - of presentation-format text.
+#[derive(Clone, Debug)]
+pub(crate) struct Entry {
+    // Whether the entry starts with whitespace, and so has no owner name.
+    pub(crate) indented: bool,
+    pub(crate) tokens: Vec<Token>,
+}
+
+impl Entry {
+    fn new() -> Self {
+        Entry {
+            indented: false,
+            tokens: Vec::new(),
+        }
+    }
+}
+
+// Splits text into entries.  Comments are dropped, and parentheses continue an entry across lines.
+pub(crate) fn tokenize(s: &str) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut entry = Entry::new();
+    let mut depth = 0_usize;
+    let mut line_start = true;
+    let mut chars = s.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            '\n' => {
+                chars.next();
+                if depth == 0 {
+                    let done = mem::replace(&mut entry, Entry::new());
+                    if !done.tokens.is_empty() {
+                        entries.push(done);
+                    }
+                    line_start = true;
+                }
+            }
+            ';' => while chars.next_if(|&c| c != '\n').is_some() {},
+            '(' => {
+                chars.next();
+                depth += 1;
+                line_start = false;
+            }
+            ')' => {
+                chars.next();
+                depth = depth.checked_sub(1).ok_or(Error::EBADSTR)?;
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+                entry.indented |= line_start;
+            }
+            _ => {
+                line_start = false;
+                entry.tokens.push(read_token(&mut chars)?);
+            }
+        }
+    }
+    if depth != 0 {
+        return Err(Error::EBADSTR);
+    }
+    if !entry.tokens.is_empty() {
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+fn read_token(chars: &mut Peekable<Chars<'_>>) -> Result<Token> {
+    let mut token = Token {
+        text: String::new(),
+        quoted: false,
+    };
+    let mut in_quotes = false;
+    while let Some(&c) = chars.peek() {
+        if !in_quotes && (c.is_whitespace() || "();".contains(c)) {
+            break;
+        }
+        chars.next();
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                token.quoted = true;
+            }
+            '\\' => {
+                token.text.push('\\');
+                token.text.push(chars.next().ok_or(Error::EBADSTR)?);
+            }
+            c => token.text.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(Error::EBADSTR);
+    }
+    Ok(token)
+}
+
+// =============================================================================
+// Parsing
+// =============================================================================
+
+// The fields of a resource record that precede its RDATA.
+pub(crate) struct RrHeader {
+    pub(crate) ttl: Option<u32>,
+    pub(crate) class: Option<DnsCls>,
+    pub(crate) rr_type: DnsRecordType,
+}
+
+// Parses `[ttl] [class] type` - or `[class] [ttl] type` - from the start of `tokens`, returning
+// the header and the RDATA tokens that follow it.
+pub(crate) fn parse_rr_header(tokens: &[Token]) -> Result<(RrHeader, &[Token])> {
+    let mut ttl = None;
+    let mut class = None;
+    let mut rest = tokens;
+    loop {
+        let (token, tail) = rest.split_first().ok_or(Error::EBADSTR)?;
+        rest = tail;
+        // The type must be followed by RDATA, so a final field can only be the type.  That
+        // resolves the ambiguity of `ANY`, which is both a class and a type.
+        let has_rdata = !rest.is_empty();
+        if ttl.is_none() && has_rdata {
+            ttl = parse_ttl(&token.text);
+            if ttl.is_some() {
+                continue;
+            }
+        }
+        if class.is_none() && has_rdata {
+            class = parse_class(&token.text);
+            if class.is_some() {
+                continue;
+            }
+        }
+        let rr_type = parse_type(&token.text)?;
+        return Ok((
+            RrHeader {
+                ttl,
+                class,
+                rr_type,
+            },
+            rest,
+        ));
+    }
+}
+
+// Parses a TTL, either as plain seconds or with BIND-style units, eg `1h30m`.
+pub(crate) fn parse_ttl(text: &str) -> Option<u32> {
+    if !text.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let mut total: u32 = 0;
+    let mut value: u32 = 0;
+    for c in text.chars() {
+        if let Some(digit) = c.to_digit(10) {
+            value = value.checked_mul(10)?.checked_add(digit)?;
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 604_800,
+            _ => return None,
+        };
+        total = total.checked_add(value.checked_mul(unit)?)?;
+        value = 0;
+    }
+    total.checked_add(value)
+}
+
+pub(crate) fn parse_class(text: &str) -> Option<DnsCls> {
+    let upper = text.to_ascii_uppercase();
+    match upper.strip_prefix("CLASS") {
+        Some(code) => {
+            let code: u16 = code.parse().ok()?;
+            Some(DnsCls::from(c_ares_sys::ares_dns_class_t(u32::from(code))))
+        }
+        None => upper.parse().ok(),
+    }
+}
+
+pub(crate) fn parse_type(text: &str) -> Result<DnsRecordType> {
+    let upper = text.to_ascii_uppercase();
+    match upper.strip_prefix("TYPE") {
+        Some(code) => code.parse().map(record_type).map_err(|_| Error::EBADSTR),
+        None => upper.parse().map_err(|_| Error::EBADSTR),
+    }
+}
+
+// Converts a name to the absolute form that c-ares uses, with no trailing dot.  Relative names
+// are taken to be relative to `origin`, or - if there is no origin - to the root.
+pub(crate) fn absolute_name(text: &str, origin: Option<&str>) -> Result<String> {
+    if text == "@" {
+        return origin.map(str::to_owned).ok_or(Error::EBADSTR);
+    }
+    let escaped_dot = text.ends_with("\\.") && !text.ends_with("\\\\.");
+    if let Some(name) = text.strip_suffix('.').filter(|_| !escaped_dot) {
+        return Ok(name.to_owned());
+    }
+    match origin {
+        Some("") | None => Ok(text.to_owned()),
+        Some(origin) => Ok(format!("{text}.{origin}")),
+    }
+}
+
+// Adds a resource record with the given owner, header values and RDATA tokens.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn add_parsed_rr(
+    rec: &mut DnsRecord,
+    section: DnsSection,
+    owner: &str,
+    ttl: u32,
+    class: DnsCls,
+    rr_type: DnsRecordType,
+    rdata: &[Token],
+    origin: Option<&str>,
+) -> Result<()> {
+    let mut fields = Fields {
+        tokens: rdata.iter(),
+        origin,
+    };
+    if rdata.first().is_some_and(Token::is_generic_marker) {
+        fields.token()?;
+        let data = fields.generic()?;
+        return add_generic_rr(rec, section, owner, ttl, class, rr_type, &data);
+    }
+    // c-ares refuses to add some of the types that have no type-specific syntax, with an error
+    // that says nothing about the text; so reject those before getting that far.
+    if matches!(
+        rr_type,
+        DnsRecordType::RAW_RR | DnsRecordType::ANY | DnsRecordType::UNKNOWN(_)
+    ) {
+        return Err(Error::EBADSTR);
+    }
+    rec.add_typed(section, owner, rr_type, class, ttl, |rr| {
+        set_rdata(rr, rr_type, &mut fields)
+    })?;
+    Ok(())
+}
+
+// Adds a resource record with RFC 3597 generic RDATA.  OPT records are unpacked, taking their EDNS
+// values from `class` and `ttl`; other types are added as `RAW_RR`.
+//
+// c-ares cannot hold a `RAW_RR` with no data, so that case is refused with `ENOTIMP`.
+pub(crate) fn add_generic_rr(
+    rec: &mut DnsRecord,
+    section: DnsSection,
+    owner: &str,
+    ttl: u32,
+    class: DnsCls,
+    rr_type: DnsRecordType,
+    data: &[u8],
+) -> Result<()> {
+    if rr_type == DnsRecordType::OPT {
+        let udp_size = c_ares_sys::ares_dns_class_t::from(class).0;
+        let udp_size = u16::try_from(udp_size).map_err(|_| Error::EBADSTR)?;
+        rec.add_typed(section, owner, rr_type, DnsCls::IN, 0, |rr| {
+            set_opt_rdata(rr, udp_size, ttl, data)
+        })?;
+    } else {
+        if data.is_empty() {
+            return Err(Error::ENOTIMP);
+        }
+        let code = c_ares_sys::ares_dns_rec_type_t::from(rr_type).0;
+        let code = u16::try_from(code).map_err(|_| Error::EBADSTR)?;
+        rec.add_typed(section, owner, DnsRecordType::RAW_RR, class, ttl, |rr| {
+            rr.set_u16(DnsRrKey::RAW_RR_TYPE, code)?
+                .set_bin(DnsRrKey::RAW_RR_DATA, data)?;
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+fn set_opt_rdata(rr: &mut DnsRr, udp_size: u16, ttl: u32, data: &[u8]) -> Result<()> {
+    rr.set_u16(DnsRrKey::OPT_UDP_SIZE, udp_size)?
+        .set_u8(DnsRrKey::OPT_VERSION, (ttl >> 16) as u8)?
+        .set_u16(DnsRrKey::OPT_FLAGS, ttl as u16)?;
+    let mut rest = data;
+    while !rest.is_empty() {
+        let [c0, c1, l0, l1, tail @ ..] = rest else {
+            return Err(Error::EBADSTR);
+        };
+        let code = u16::from_be_bytes([*c0, *c1]);
+        let len = usize::from(u16::from_be_bytes([*l0, *l1]));
+        let value = tail.get(..len).ok_or(Error::EBADSTR)?;
+        rr.set_opt(DnsRrKey::OPT_OPTIONS, code, value)?;
+        rest = &tail[len..];
+    }
+    Ok(())
+}
+
+fn set_rdata(rr: &mut DnsRr, rr_type: DnsRecordType, fields: &mut Fields<'_>) -> Result<()> {
+    match rr_type {
+        DnsRecordType::A => {
+            rr.set_addr(DnsRrKey::A_ADDR, fields.number()?)?;
+        }
+        DnsRecordType::AAAA => {
+            rr.set_addr6(DnsRrKey::AAAA_ADDR, fields.number()?)?;
+        }
+        DnsRecordType::NS => {
+            rr.set_str(DnsRrKey::NS_NSDNAME, &fields.name()?)?;
+        }
+        DnsRecordType::CNAME => {
+            rr.set_str(DnsRrKey::CNAME_CNAME, &fields.name()?)?;
+        }
+        DnsRecordType::PTR => {
+            rr.set_str(DnsRrKey::PTR_DNAME, &fields.name()?)?;
+        }
+        DnsRecordType::SOA => {
+            rr.set_str(DnsRrKey::SOA_MNAME, &fields.name()?)?
+                .set_str(DnsRrKey::SOA_RNAME, &fields.name()?)?
+                .set_u32(DnsRrKey::SOA_SERIAL, fields.number()?)?
+                .set_u32(DnsRrKey::SOA_REFRESH, fields.ttl()?)?
+                .set_u32(DnsRrKey::SOA_RETRY, fields.ttl()?)?
+                .set_u32(DnsRrKey::SOA_EXPIRE, fields.ttl()?)?
+                .set_u32(DnsRrKey::SOA_MINIMUM, fields.ttl()?)?;
+        }
+        DnsRecordType::HINFO => {
+            rr.set_str(DnsRrKey::HINFO_CPU, &fields.string()?)?
+                .set_str(DnsRrKey::HINFO_OS, &fields.string()?)?;
+        }
+        DnsRecordType::MX => {
+            rr.set_u16(DnsRrKey::MX_PREFERENCE, fields.number()?)?
+                .set_str(DnsRrKey::MX_EXCHANGE, &fields.name()?)?;
+        }
+        DnsRecordType::TXT => {
+            for token in fields.tokens.by_ref() {
+                rr.add_abin(DnsRrKey::TXT_DATA, &unescape(&token.text)?)?;
+            }
+        }
+        DnsRecordType::SIG => {
+            let type_covered = parse_type(fields.text()?)?;
+            let type_covered = c_ares_sys::ares_dns_rec_type_t::from(type_covered).0;
+            let type_covered = u16::try_from(type_covered).map_err(|_| Error::EBADSTR)?;
+            rr.set_u16(DnsRrKey::SIG_TYPE_COVERED, type_covered)?
+                .set_u8(DnsRrKey::SIG_ALGORITHM, fields.number()?)?
+                .set_u8(DnsRrKey::SIG_LABELS, fields.number()?)?
+                .set_u32(DnsRrKey::SIG_ORIGINAL_TTL, fields.number()?)?
+                .set_u32(DnsRrKey::SIG_EXPIRATION, fields.timestamp()?)?
+                .set_u32(DnsRrKey::SIG_INCEPTION, fields.timestamp()?)?
+                .set_u16(DnsRrKey::SIG_KEY_TAG, fields.number()?)?
+                .set_str(DnsRrKey::SIG_SIGNERS_NAME, &fields.name()?)?
+                .set_bin(DnsRrKey::SIG_SIGNATURE, &base64_decode(&fields.rest())?)?;
+        }
+        DnsRecordType::SRV => {
+            rr.set_u16(DnsRrKey::SRV_PRIORITY, fields.number()?)?
+                .set_u16(DnsRrKey::SRV_WEIGHT, fields.number()?)?
+                .set_u16(DnsRrKey::SRV_PORT, fields.number()?)?
+                .set_str(DnsRrKey::SRV_TARGET, &fields.name()?)?;
+        }
+        DnsRecordType::NAPTR => {
+            rr.set_u16(DnsRrKey::NAPTR_ORDER, fields.number()?)?
+                .set_u16(DnsRrKey::NAPTR_PREFERENCE, fields.number()?)?
+                .set_str(DnsRrKey::NAPTR_FLAGS, &fields.string()?)?
+                .set_str(DnsRrKey::NAPTR_SERVICES, &fields.string()?)?
+                .set_str(DnsRrKey::NAPTR_REGEXP, &fields.string()?)?
+                .set_str(DnsRrKey::NAPTR_REPLACEMENT, &fields.name()?)?;
+        }
+        DnsRecordType::TLSA => {
+            rr.set_u8(DnsRrKey::TLSA_CERT_USAGE, fields.number()?)?
+                .set_u8(DnsRrKey::TLSA_SELECTOR, fields.number()?)?
+                .set_u8(DnsRrKey::TLSA_MATCH, fields.number()?)?
+                .set_bin(DnsRrKey::TLSA_DATA, &hex_decode(&fields.rest())?)?;
+        }
+        DnsRecordType::SVCB => {
+            rr.set_u16(DnsRrKey::SVCB_PRIORITY, fields.number()?)?
+                .set_str(DnsRrKey::SVCB_TARGET, &fields.name()?)?;
+            set_svc_params(rr, DnsRrKey::SVCB_PARAMS, fields)?;
+        }
+        DnsRecordType::HTTPS => {
+            rr.set_u16(DnsRrKey::HTTPS_PRIORITY, fields.number()?)?
+                .set_str(DnsRrKey::HTTPS_TARGET, &fields.name()?)?;
+            set_svc_params(rr, DnsRrKey::HTTPS_PARAMS, fields)?;
+        }
+        DnsRecordType::URI => {
+            rr.set_u16(DnsRrKey::URI_PRIORITY, fields.number()?)?
+                .set_u16(DnsRrKey::URI_WEIGHT, fields.number()?)?
+                .set_str(DnsRrKey::URI_TARGET, &fields.string()?)?;
+        }
+        DnsRecordType::CAA => {
+            rr.set_u8(DnsRrKey::CAA_CRITICAL, fields.number()?)?
+                .set_str(DnsRrKey::CAA_TAG, fields.text()?)?
+                .set_bin(DnsRrKey::CAA_VALUE, &fields.char_string()?)?;
+        }
+        // These have no type-specific syntax, so must use the generic form.
+        DnsRecordType::OPT
+        | DnsRecordType::RAW_RR
+        | DnsRecordType::ANY
+        | DnsRecordType::UNKNOWN(_) => return Err(Error::EBADSTR),
+    }
+    fields.finish()
+}
+
+fn set_svc_params(rr: &mut DnsRr, key: DnsRrKey, fields: &mut Fields<'_>) -> Result<()> {
+    for token in fields.tokens.by_ref() {
+        let (name, value) = token
+            .text
+            .split_once('=')
+            .unwrap_or((token.text.as_str(), ""));
+        let code = svc_param_code(key, name)?;
+        rr.set_opt(key, code, &encode_svc_param(key, code, value)?)?;
+    }
+    Ok(())
+}
+
+fn svc_param_code(key: DnsRrKey, name: &str) -> Result<u16> {
+    if let Some(code) = name.strip_prefix("key") {
+        return code.parse().map_err(|_| Error::EBADSTR);
+    }
+    (0..=MAX_NAMED_SVC_PARAM)
+        .find(|&code| DnsRr::opt_name(key, code) == Some(name))
+        .ok_or(Error::EBADSTR)
+}
+
+fn encode_svc_param(key: DnsRrKey, code: u16, value: &str) -> Result<Vec<u8>> {
+    if DnsRr::opt_name(key, code).is_none() {
+        return unescape(value);
+    }
+    let mut data = Vec::new();
+    match DnsRr::opt_datatype(key, code) {
+        DnsOptDataType::None => {
+            if !value.is_empty() {
+                return Err(Error::EBADSTR);
+            }
+        }
+        DnsOptDataType::StrList => {
+            for item in split_list(value) {
+                let item = unescape(item)?;
+                data.push(u8::try_from(item.len()).map_err(|_| Error::EBADSTR)?);
+                data.extend(item);
+            }
+        }
+        DnsOptDataType::U8List => {
+            for item in split_list(value) {
+                data.push(parse_number::<u8>(item)?);
+            }
+        }
+        DnsOptDataType::U16 => data.extend(parse_number::<u16>(value)?.to_be_bytes()),
+        DnsOptDataType::U16List => {
+            for item in split_list(value) {
+                let code = svc_param_code(key, item).or_else(|_| parse_number(item))?;
+                data.extend(code.to_be_bytes());
+            }
+        }
+        DnsOptDataType::U32 => data.extend(parse_number::<u32>(value)?.to_be_bytes()),
+        DnsOptDataType::U32List => {
+            for item in split_list(value) {
+                data.extend(parse_number::<u32>(item)?.to_be_bytes());
+            }
+        }
+        DnsOptDataType::InAddr4List => {
+            for item in split_list(value) {
+                data.extend(parse_number::<Ipv4Addr>(item)?.octets());
+            }
+        }
+        DnsOptDataType::InAddr6List => {
+            for item in split_list(value) {
+                data.extend(parse_number::<Ipv6Addr>(item)?.octets());
+            }
+        }
+        DnsOptDataType::Bin => data = base64_decode(value)?,
+        DnsOptDataType::Name => {
+            for label in absolute_name(value, None)?.split('.') {
+                if label.is_empty() {
+                    continue;
+                }
+                data.push(u8::try_from(label.len()).map_err(|_| Error::EBADSTR)?);
+                data.extend(label.as_bytes());
+            }
+            data.push(0);
+        }
+    }
+    Ok(data)
+}
+
+// Splits a value-list at commas that are not escaped.
+fn split_list(value: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    if value.is_empty() {
+        return items;
+    }
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ',' => {
+                items.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&value[start..]);
+    items
+}
+
+fn parse_number<T: FromStr>(text: &str) -> Result<T> {
+    text.parse().map_err(|_| Error::EBADSTR)
+}
+
+// Iterates over the RDATA fields of a record.
+struct Fields<'a> {
+    tokens: std::slice::Iter<'a, Token>,
+    origin: Option<&'a str>,
+}
+
+impl<'a> Fields<'a> {
+    fn token(&mut self) -> Result<&'a Token> {
+        self.tokens.next().ok_or(Error::EBADSTR)
+    }
+
+    fn text(&mut self) -> Result<&'a str> {
+        Ok(&self.token()?.text)
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T> {
+        parse_number(self.text()?)
+    }
+
+    fn ttl(&mut self) -> Result<u32> {
+        parse_ttl(self.text()?).ok_or(Error::EBADSTR)
+    }
+
+    fn timestamp(&mut self) -> Result<u32> {
+        parse_timestamp(self.text()?)
+    }
+
+    fn name(&mut self) -> Result<String> {
+        let text = self.text()?;
+        absolute_name(text, self.origin)
+    }
+
+    fn char_string(&mut self) -> Result<Vec<u8>> {
+        unescape(self.text()?)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        String::from_utf8(self.char_string()?).map_err(|_| Error::EBADSTR)
+    }
+
+    // The remaining fields, concatenated - as used for base64 and hex data that may be split by
+    // whitespace.
+    fn rest(&mut self) -> String {
+        self.tokens
+            .by_ref()
+            .map(|token| token.text.as_str())
+            .collect()
+    }
+
+    // RFC 3597 generic RDATA, following the `\#`.
+    fn generic(&mut self) -> Result<Vec<u8>> {
+        let len: usize = self.number()?;
+        let data = hex_decode(&self.rest())?;
+        if data.len() == len {
+            Ok(data)
+        } else {
+            Err(Error::EBADSTR)
+        }
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        match self.tokens.next() {
+            Some(_) => Err(Error::EBADSTR),
+            None => Ok(()),
+        }
+    }
+}
+
+// Decodes the escapes in a character-string: `\DDD` is a byte in decimal, and `\X` is `X`.
+pub(crate) fn unescape(text: &str) -> Result<Vec<u8>> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while let Some(&b) = bytes.get(i) {
+        if b != b'\\' {
+            out.push(b);
+            i += 1;
+            continue;
+        }
+        match bytes.get(i + 1..i + 4) {
+            Some(digits) if digits.iter().all(u8::is_ascii_digit) => {
+                let value = digits
+                    .iter()
+                    .fold(0_u16, |acc, d| acc * 10 + u16::from(d - b'0'));
+                out.push(u8::try_from(value).map_err(|_| Error::EBADSTR)?);
+                i += 4;
+            }
+            _ => {
+                out.push(*bytes.get(i + 1).ok_or(Error::EBADSTR)?);
+                i += 2;
+            }
+        }
+    }
+    Ok(out)
+}
+
+pub(crate) fn hex_encode(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{b:02X}")).collect()
+}
+
+pub(crate) fn hex_decode(text: &str) -> Result<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(Error::EBADSTR);
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).map_err(|_| Error::EBADSTR))
+        .collect()
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0_u32, |acc, (i, &b)| acc | (u32::from(b) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (n >> (18 - 6 * i)) & 0x3f;
+                out.push(char::from(BASE64_ALPHABET[index as usize]));
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn base64_decode(text: &str) -> Result<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = BASE64_ALPHABET
+            .iter()
+            .position(|&a| a == c)
+            .ok_or(Error::EBADSTR)?;
+        acc = (acc << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Ok(out)
+}
+
+// Parses a SIG timestamp, either as YYYYMMDDHHmmSS or as seconds since the epoch.
+fn parse_timestamp(text: &str) -> Result<u32> {
+    if text.len() != 14 || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return parse_number(text);
+    }
+    let field = |range: std::ops::Range<usize>| -> Result<u32> { parse_number(&text[range]) };
+    let (year, month, day) = (field(0..4)?, field(4..6)?, field(6..8)?);
+    let (hour, minute, second) = (field(8..10)?, field(10..12)?, field(12..14)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return Err(Error::EBADSTR);
+    }
+    let days = days_from_civil(i64::from(year), month, day);
+    let secs = days * 86400 + i64::from(hour * 3600 + minute * 60 + second);
+    u32::try_from(secs).map_err(|_| Error::EBADSTR)
+}
+
+// Howard Hinnant's conversions between days since the epoch and the proleptic Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = i64::from(month);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// =============================================================================
+// DnsRecord parsing
+// =============================================================================
+
+impl DnsRecord {
+    /// Add a resource record given in presentation format, such as
+    /// `example.com. 300 IN MX 10 mail.example.com.`
+    ///
+    /// The owner name and type are required; the TTL defaults to zero and the
+    /// class to `IN`.  Names are taken to be absolute, whether or not they
+    /// have a trailing dot.  The record may span several lines if they are
+    /// enclosed in parentheses, as in a zone file.
+    ///
+    /// This is the counterpart of the `Display` implementation on [`DnsRr`]:
+    /// `DnsRr` has no `FromStr` implementation, because a `DnsRr` only exists
+    /// as part of a `DnsRecord`.
+    ///
+    /// Returns `Error::EBADSTR` if the text cannot be parsed.  Returns
+    /// `Error::ENOTIMP` for RFC 3597 generic RDATA with no data, such as
+    /// `example.com. 300 IN TYPE65534 \# 0`, because c-ares cannot represent
+    /// an empty record of a type that it does not know.
+    pub fn add_rr_str(&mut self, section: DnsSection, s: &str) -> Result<&mut Self> {
+        let entries = tokenize(s)?;
+        let [entry] = entries.as_slice() else {
+            return Err(Error::EBADSTR);
+        };
+        let (owner, rest) = entry.tokens.split_first().ok_or(Error::EBADSTR)?;
+        let owner = absolute_name(&owner.text, None)?;
+        let (header, rdata) = parse_rr_header(rest)?;
+        add_parsed_rr(
+            self,
+            section,
+            &owner,
+            header.ttl.unwrap_or(0),
+            header.class.unwrap_or(DnsCls::IN),
+            header.rr_type,
+            rdata,
+            None,
+        )?;
+        Ok(self)
+    }
+}
+
+// Which part of a `dig`-style message we are reading.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Part {
+    Question,
+    Section(DnsSection),
+}
+
+impl FromStr for DnsRecord {
+    type Err = Error;
+
+    /// Parses the `dig`-style text written by the `Display` implementation.
+    ///
+    /// Resource records before any section heading are added to the answer
+    /// section, and header fields that are not given take default values.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut id = 0;
+        let mut flags = DnsFlags::empty();
+        let mut opcode = DnsOpcode::Query;
+        let mut rcode = DnsRcode::NoError;
+        for line in s.lines() {
+            let line = line.trim();
+            if let Some(header) = line.strip_prefix(";; ->>HEADER<<-") {
+                for field in header.split(',') {
+                    let (key, value) = field.split_once(':').ok_or(Error::EBADSTR)?;
+                    let value = value.trim();
+                    match key.trim() {
+                        "opcode" => opcode = find_named(&OPCODES, value)?,
+                        "status" => rcode = find_named(&RCODES, value)?,
+                        "id" => id = parse_number(value)?,
+                        _ => {}
+                    }
+                }
+            } else if let Some(rest) = line.strip_prefix(";; flags:") {
+                let names = rest.split(';').next().unwrap_or_default();
+                for name in names.split_whitespace() {
+                    let (flag, _) = FLAG_NAMES
+                        .iter()
+                        .find(|(_, flag_name)| name.eq_ignore_ascii_case(flag_name))
+                        .ok_or(Error::EBADSTR)?;
+                    flags |= *flag;
+                }
+            }
+        }
+
+        let mut rec = DnsRecord::new(id, flags, opcode, rcode)?;
+        let mut part = Part::Section(DnsSection::Answer);
+        for line in s.lines() {
+            let line = line.trim();
+            if let Some(comment) = line.strip_prefix(";;") {
+                let comment = comment.trim();
+                if comment == "QUESTION SECTION:" {
+                    part = Part::Question;
+                } else if let Some(&(section, _)) = SECTION_NAMES
+                    .iter()
+                    .find(|(_, label)| comment.strip_suffix(" SECTION:") == Some(label))
+                {
+                    part = Part::Section(section);
+                }
+            } else if let Some(question) = line.strip_prefix(';') {
+                if part == Part::Question {
+                    add_question(&mut rec, question)?;
+                }
+            } else if !line.is_empty() {
+                let Part::Section(section) = part else {
+                    return Err(Error::EBADSTR);
+                };
+                rec.add_rr_str(section, line)?;
+            }
+        }
+        Ok(rec)
+    }
+}
+
+fn add_question(rec: &mut DnsRecord, question: &str) -> Result<()> {
+    let entries = tokenize(question)?;
+    let [entry] = entries.as_slice() else {
+        return Err(Error::EBADSTR);
+    };
+    let (name, rest) = entry.tokens.split_first().ok_or(Error::EBADSTR)?;
+    let (qclass, qtype) = match rest {
+        [qtype] => (DnsCls::IN, qtype),
+        [qclass, qtype] => (parse_class(&qclass.text).ok_or(Error::EBADSTR)?, qtype),
+        _ => return Err(Error::EBADSTR),
+    };
+    let name = absolute_name(&name.text, None)?;
+    rec.query_add(&name, parse_type(&qtype.text)?, qclass)?;
+    Ok(())
+}
+
+fn find_named<T: Copy + fmt::Display>(values: &[T], name: &str) -> Result<T> {
+    values
+        .iter()
+        .copied()
+        .find(|value| value.to_string().eq_ignore_ascii_case(name))
+        .ok_or(Error::EBADSTR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::test_util::{new_rec, round_trip};
+
+    fn first(rec: &DnsRecord) -> &DnsRr {
+        rec.rr(DnsSection::Answer, 0).unwrap()
+    }
+
+    // Parses `text`, checks that it formats as `expected`, and that the result survives a trip
+    // through the wire format.
+    fn check(text: &str, expected: &str) {
+        let mut rec = new_rec();
+        rec.add_rr_str(DnsSection::Answer, text).unwrap();
+        assert_eq!(first(&rec).to_string(), expected);
+        assert_eq!(first(&round_trip(&rec)).to_string(), expected);
+    }
+
+    #[test]
+    fn address_records() {
+        check(
+            "example.com. 300 IN A 192.0.2.1",
+            "example.com.\t300\tIN\tA\t192.0.2.1",
+        );
+        check(
+            "example.com 60 AAAA 2001:db8::1",
+            "example.com.\t60\tIN\tAAAA\t2001:db8::1",
+        );
+    }
+
+    #[test]
+    fn name_records() {
+        check(
+            "example.com. 300 IN NS ns1.example.com.",
+            "example.com.\t300\tIN\tNS\tns1.example.com.",
+        );
+        check(
+            "www.example.com. IN 300 CNAME example.com.",
+            "www.example.com.\t300\tIN\tCNAME\texample.com.",
+        );
+        check(
+            "1.2.0.192.in-addr.arpa. 300 IN PTR example.com.",
+            "1.2.0.192.in-addr.arpa.\t300\tIN\tPTR\texample.com.",
+        );
+        check(
+            "example.com. 300 IN MX 10 mail.example.com.",
+            "example.com.\t300\tIN\tMX\t10 mail.example.com.",
+        );
+    }
+
+    #[test]
+    fn soa_record_with_parentheses() {
+        check(
+            "example.com. 1h IN SOA ns1.example.com. hostmaster.example.com. (\n\
+             \t2024010101 ; serial\n\
+             \t2h 15m 2w 5m )",
+            "example.com.\t3600\tIN\tSOA\tns1.example.com. hostmaster.example.com. \
+             2024010101 7200 900 1209600 300",
+        );
+    }
+
+    #[test]
+    fn txt_escaping() {
+        check(
+            r#"example.com. 300 IN TXT "v=spf1 -all" "say \"hi\"" "back\\slash" "\007\255""#,
+            "example.com.\t300\tIN\tTXT\t\"v=spf1 -all\" \"say \\\"hi\\\"\" \"back\\\\slash\" \
+             \"\\007\\255\"",
+        );
+        check(
+            "example.com. 300 IN TXT unquoted",
+            "example.com.\t300\tIN\tTXT\t\"unquoted\"",
+        );
+    }
+
+    #[test]
+    fn string_records() {
+        check(
+            r#"example.com. 300 IN HINFO "x86 64" Linux"#,
+            "example.com.\t300\tIN\tHINFO\t\"x86 64\" \"Linux\"",
+        );
+        check(
+            r#"example.com. 300 IN NAPTR 100 10 "S" "SIP+D2U" "" _sip._udp.example.com."#,
+            "example.com.\t300\tIN\tNAPTR\t100 10 \"S\" \"SIP+D2U\" \"\" _sip._udp.example.com.",
+        );
+        check(
+            r#"example.com. 300 IN CAA 128 issue "ca.example.net""#,
+            "example.com.\t300\tIN\tCAA\t128 issue \"ca.example.net\"",
+        );
+    }
+
+    #[test]
+    fn service_records() {
+        check(
+            "_sip._tcp.example.com. 300 IN SRV 10 60 5060 sip.example.com.",
+            "_sip._tcp.example.com.\t300\tIN\tSRV\t10 60 5060 sip.example.com.",
+        );
+        check(
+            "_443._tcp.example.com. 300 IN TLSA 3 1 1 ( ABCD\n ef01 )",
+            "_443._tcp.example.com.\t300\tIN\tTLSA\t3 1 1 ABCDEF01",
+        );
+    }
+
+    #[test]
+    fn svcb_records() {
+        check(
+            r#"example.com. 300 IN HTTPS 1 . alpn="h2,h3" port=8443 ipv4hint=192.0.2.1,192.0.2.2"#,
+            "example.com.\t300\tIN\tHTTPS\t1 . alpn=\"h2,h3\" port=8443 \
+             ipv4hint=192.0.2.1,192.0.2.2",
+        );
+        check(
+            "_dns.example.com. 300 IN SVCB 1 dns.example.com. mandatory=alpn alpn=dot \
+             no-default-alpn ipv6hint=2001:db8::1 key65000=\"x\\\"y\"",
+            "_dns.example.com.\t300\tIN\tSVCB\t1 dns.example.com. mandatory=alpn alpn=\"dot\" \
+             no-default-alpn ipv6hint=2001:db8::1 key65000=\"x\\\"y\"",
+        );
+    }
+
+    #[test]
+    fn sig_record() {
+        check(
+            "example.com. 300 IN SIG A 8 2 300 20240201000000 1704067200 12345 example.com. \
+             AQID BA==",
+            "example.com.\t300\tIN\tSIG\tA 8 2 300 20240201000000 20240101000000 12345 \
+             example.com. AQIDBA==",
+        );
+    }
+
+    #[test]
+    fn generic_records() {
+        check(
+            "example.com. 300 IN TYPE65534 \\# 3 ABCDEF",
+            "example.com.\t300\tIN\tTYPE65534\t\\# 3 ABCDEF",
+        );
+        // c-ares cannot hold an empty record of an unknown type.
+        let mut rec = new_rec();
+        let result = rec.add_rr_str(DnsSection::Answer, "example.com. 300 IN TYPE65534 \\# 0");
+        assert_eq!(result.err(), Some(Error::ENOTIMP));
+        assert_eq!(rec.rr_count(DnsSection::Answer), 0);
+        // Known types may use the generic form too.
+        let mut rec = new_rec();
+        rec.add_rr_str(DnsSection::Answer, "example.com. 300 IN A \\# 4 C0000201")
+            .unwrap();
+        assert_eq!(
+            first(&round_trip(&rec)).to_string(),
+            "example.com.\t300\tIN\tA\t192.0.2.1"
+        );
+    }
+
+    #[test]
+    fn opt_record() {
+        let mut rec = new_rec();
+        rec.add_opt(1232, 0, 0x8000, [(10, [1_u8, 2])]).unwrap();
+        let opt = rec.rr(DnsSection::Additional, 0).unwrap();
+        let text = opt.to_string();
+        assert_eq!(text, ".\t32768\tCLASS1232\tOPT\t\\# 6 000A00020102");
+
+        let mut copy = new_rec();
+        copy.add_rr_str(DnsSection::Additional, &text).unwrap();
+        let opt = copy
+            .rr(DnsSection::Additional, 0)
+            .unwrap()
+            .as_opt()
+            .unwrap();
+        assert_eq!(opt.udp_size(), 1232);
+        assert_eq!(opt.flags(), 0x8000);
+        assert_eq!(
+            opt.raw_options().collect::<Vec<_>>(),
+            [(10, &[1_u8, 2][..])]
+        );
+    }
+
+    #[test]
+    fn typed_views_display_like_rr() {
+        let mut rec = new_rec();
+        rec.add_rr_str(
+            DnsSection::Answer,
+            "example.com. 300 IN MX 10 mx.example.com.",
+        )
+        .unwrap();
+        let rr = first(&rec);
+        let expected = rr.to_string();
+        assert_eq!(rr.as_mx().unwrap().to_string(), expected);
+        assert_eq!(rr.as_typed().to_string(), expected);
+    }
+
+    #[test]
+    fn invalid_rr_text() {
+        for text in [
+            "",
+            "example.com.",
+            "example.com. 300 IN",
+            "example.com. 300 IN NOTATYPE 1",
+            "example.com. 300 IN A 192.0.2",
+            "example.com. 300 IN A 192.0.2.1 extra",
+            "example.com. 300 IN MX 10",
+            "example.com. 300 IN TXT \"unterminated",
+            "example.com. 300 IN TXT ( \"unbalanced\"",
+            "example.com. 300 IN TXT \"\\256\"",
+            "example.com. 300 IN TYPE65534 \\# 2 ABCDEF",
+            "example.com. 300 IN TYPE65534 1 2 3",
+            "example.com. 300 IN SVCB 1 . nosuchkey=1",
+            "example.com. 300 IN A 192.0.2.1\nexample.com. 300 IN A 192.0.2.2",
+        ] {
+            let mut rec = new_rec();
+            let result = rec.add_rr_str(DnsSection::Answer, text);
+            assert_eq!(result.err(), Some(Error::EBADSTR), "{text:?}");
+            assert_eq!(rec.rr_count(DnsSection::Answer), 0, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let mut rec = DnsRecord::new(
+            0x1234,
+            DnsFlags::QR | DnsFlags::RD | DnsFlags::RA,
+            DnsOpcode::Query,
+            DnsRcode::NXDomain,
+        )
+        .unwrap();
+        rec.query_add("example.com", DnsRecordType::MX, DnsCls::IN)
+            .unwrap()
+            .add_rr_str(
+                DnsSection::Answer,
+                "example.com. 300 IN MX 10 mx.example.com.",
+            )
+            .unwrap()
+            .add_rr_str(
+                DnsSection::Authority,
+                "example.com. 300 IN NS ns.example.com.",
+            )
+            .unwrap()
+            .add_rr_str(DnsSection::Additional, "mx.example.com. 300 IN A 192.0.2.1")
+            .unwrap();
+
+        let text = rec.to_string();
+        assert_eq!(
+            text,
+            ";; ->>HEADER<<- opcode: QUERY, status: NXDOMAIN, id: 4660\n\
+             ;; flags: qr rd ra; QUERY: 1, ANSWER: 1, AUTHORITY: 1, ADDITIONAL: 1\n\
+             \n\
+             ;; QUESTION SECTION:\n\
+             ;example.com.\t\tIN\tMX\n\
+             \n\
+             ;; ANSWER SECTION:\n\
+             example.com.\t300\tIN\tMX\t10 mx.example.com.\n\
+             \n\
+             ;; AUTHORITY SECTION:\n\
+             example.com.\t300\tIN\tNS\tns.example.com.\n\
+             \n\
+             ;; ADDITIONAL SECTION:\n\
+             mx.example.com.\t300\tIN\tA\t192.0.2.1\n"
+        );
+
+        let parsed: DnsRecord = text.parse().unwrap();
+        assert_eq!(parsed.id(), 0x1234);
+        assert_eq!(parsed.flags(), DnsFlags::QR | DnsFlags::RD | DnsFlags::RA);
+        assert_eq!(parsed.rcode(), DnsRcode::NXDomain);
+        assert_eq!(parsed.to_string(), text);
+    }
+
+    #[test]
+    fn record_from_bare_rrs() {
+        let rec: DnsRecord = "; a comment\n\
+                              example.com. 300 IN A 192.0.2.1\n\
+                              example.com. 300 IN A 192.0.2.2\n"
+            .parse()
+            .unwrap();
+        assert_eq!(rec.id(), 0);
+        assert_eq!(rec.query_count(), 0);
+        assert_eq!(rec.rr_count(DnsSection::Answer), 2);
+    }
+
+    #[test]
+    fn timestamps() {
+        struct Stamp(u32);
+        impl fmt::Display for Stamp {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write_timestamp(f, self.0)
+            }
+        }
+        for (secs, text) in [
+            (0, "19700101000000"),
+            (951_782_400, "20000229000000"),
+            (1_704_067_199, "20231231235959"),
+            (u32::MAX, "21060207062815"),
+        ] {
+            assert_eq!(Stamp(secs).to_string(), text);
+            assert_eq!(parse_timestamp(text), Ok(secs));
+        }
+    }
+
+    #[test]
+    fn base64() {
+        for (data, text) in [
+            (&b""[..], ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+        ] {
+            assert_eq!(base64_encode(data), text);
+            assert_eq!(base64_decode(text).unwrap(), data);
+        }
+        assert!(base64_decode("Zm9v!").is_err());
+    }
+
+    #[test]
+    fn ttl_units() {
+        assert_eq!(parse_ttl("300"), Some(300));
+        assert_eq!(parse_ttl("1h30m"), Some(5400));
+        assert_eq!(parse_ttl("1W"), Some(604_800));
+        assert_eq!(parse_ttl("IN"), None);
+        assert_eq!(parse_ttl("1x"), None);
+    }
+}
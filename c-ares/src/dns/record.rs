@@ -18,6 +18,9 @@ use super::rr::DnsRr;
 /// additional).
 ///
 /// Created by parsing wire-format data with [`DnsRecord::parse()`].
+///
+/// With the `serde` feature, `DnsRecord` implements `Serialize` and
+/// `Deserialize` using the RFC 8427 JSON representation of DNS messages.
 pub struct DnsRecord {
     dnsrec: *mut c_ares_sys::ares_dns_record_t,
 }
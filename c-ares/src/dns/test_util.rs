@@ -0,0 +1,17 @@
+// Helpers shared by the tests of the DNS record modules.
+
+use crate::dns::{DnsCls, DnsFlags, DnsOpcode, DnsParseFlags, DnsRcode, DnsRecord, DnsRecordType};
+
+// An empty response with a single question: c-ares will not parse a message without one.
+pub(super) fn new_rec() -> DnsRecord {
+    let mut rec = DnsRecord::new(0, DnsFlags::QR, DnsOpcode::Query, DnsRcode::NoError).unwrap();
+    rec.query_add("example.com", DnsRecordType::A, DnsCls::IN)
+        .unwrap();
+    rec
+}
+
+// Writes `rec` in wire format, and parses it back.
+pub(super) fn round_trip(rec: &DnsRecord) -> DnsRecord {
+    let wire = rec.write().unwrap();
+    DnsRecord::parse(&wire, DnsParseFlags::empty()).unwrap()
+}
@@ -0,0 +1,327 @@
+//! RFC 1035 master files.
+//!
+//! A master (zone) file is a sequence of resource records in presentation
+//! format, together with the `$ORIGIN`, `$TTL` and `$INCLUDE` directives:
+//!
+//! ```text
+//! $ORIGIN example.com.
+//! $TTL 1h
+//! @       IN  SOA ns1 hostmaster (
+//!                 2024010101 ; serial
+//!                 2h 15m 2w 5m )
+//!         IN  NS  ns1
+//! ns1     IN  A   192.0.2.1
+//! www         CNAME @
+//! ```
+//!
+//! Names that do not end in a dot are relative to the current origin, and `@`
+//! stands for the origin itself.  A record that starts with whitespace has the
+//! same owner as the one before it; one with no class has the same class as
+//! the one before it.  A record with no TTL takes the `$TTL` value or, failing
+//! that, the TTL of the last record that gave one explicitly.
+//!
+//! A relative `$INCLUDE` path is read relative to the directory of the file
+//! that names it, or to the current directory for text that did not come from
+//! a file.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+
+use super::enums::{DnsCls, DnsSection};
+use super::presentation::{
+    Token, absolute_name, add_parsed_rr, parse_rr_header, parse_ttl, tokenize, unescape,
+};
+use super::record::DnsRecord;
+
+// Limits the nesting of `$INCLUDE`, so that a file that includes itself is an error rather than a
+// stack overflow.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+// The context in which each record of a master file is read.
+#[derive(Clone)]
+struct ZoneState {
+    origin: String,
+    default_ttl: Option<u32>,
+    last_ttl: Option<u32>,
+    last_owner: Option<String>,
+    last_class: DnsCls,
+}
+
+struct ZoneParser<'a> {
+    rec: &'a mut DnsRecord,
+    section: DnsSection,
+    depth: usize,
+}
+
+impl ZoneParser<'_> {
+    // Parses `zone`, resolving any relative `$INCLUDE` paths against `dir`.
+    fn parse(&mut self, zone: &str, dir: &Path, state: &mut ZoneState) -> Result<()> {
+        for entry in tokenize(zone)? {
+            let tokens = entry.tokens.as_slice();
+            let first = &tokens[0];
+            if !entry.indented && !first.quoted && first.text.starts_with('$') {
+                self.directive(&first.text, &tokens[1..], dir, state)?;
+                continue;
+            }
+            let (owner, rest) = if entry.indented {
+                let owner = state.last_owner.clone().ok_or(Error::EBADSTR)?;
+                (owner, tokens)
+            } else {
+                (
+                    absolute_name(&first.text, Some(&state.origin))?,
+                    &tokens[1..],
+                )
+            };
+            let (header, rdata) = parse_rr_header(rest)?;
+            let ttl = header
+                .ttl
+                .or(state.default_ttl)
+                .or(state.last_ttl)
+                .ok_or(Error::EBADSTR)?;
+            let class = header.class.unwrap_or(state.last_class);
+            add_parsed_rr(
+                self.rec,
+                self.section,
+                &owner,
+                ttl,
+                class,
+                header.rr_type,
+                rdata,
+                Some(&state.origin),
+            )?;
+            state.last_owner = Some(owner);
+            state.last_class = class;
+            if header.ttl.is_some() {
+                state.last_ttl = header.ttl;
+            }
+        }
+        Ok(())
+    }
+
+    fn directive(
+        &mut self,
+        name: &str,
+        args: &[Token],
+        dir: &Path,
+        state: &mut ZoneState,
+    ) -> Result<()> {
+        match (name.to_ascii_uppercase().as_str(), args) {
+            ("$ORIGIN", [origin]) => {
+                state.origin = absolute_name(&origin.text, Some(&state.origin))?;
+            }
+            ("$TTL", [ttl]) => {
+                state.default_ttl = Some(parse_ttl(&ttl.text).ok_or(Error::EBADSTR)?);
+            }
+            ("$INCLUDE", [file, origin @ ..]) if origin.len() <= 1 => {
+                // The included file starts out with the current context, but any changes that it
+                // makes - to the origin, say - don't leak back into this one.
+                let mut inner = state.clone();
+                if let [origin] = origin {
+                    inner.origin = absolute_name(&origin.text, Some(&state.origin))?;
+                }
+                let file = String::from_utf8(unescape(&file.text)?).map_err(|_| Error::EBADSTR)?;
+                self.include(&dir.join(file), &mut inner)?;
+            }
+            _ => return Err(Error::EBADSTR),
+        }
+        Ok(())
+    }
+
+    fn include(&mut self, path: &Path, state: &mut ZoneState) -> Result<()> {
+        if self.depth == MAX_INCLUDE_DEPTH {
+            return Err(Error::EBADSTR);
+        }
+        let zone = fs::read_to_string(path).map_err(|_| Error::EFILE)?;
+        let dir = path.parent().map_or_else(PathBuf::new, Path::to_path_buf);
+        self.depth += 1;
+        let result = self.parse(&zone, &dir, state);
+        self.depth -= 1;
+        result
+    }
+}
+
+impl DnsRecord {
+    /// Add the resource records from the master file text `zone` to `section`.
+    ///
+    /// Relative names are taken relative to `origin`, unless the text sets its
+    /// own with `$ORIGIN`.  Relative paths named by `$INCLUDE` in `zone` are
+    /// read relative to the current directory.
+    ///
+    /// Returns `Error::EBADSTR` if the text cannot be parsed, or
+    /// `Error::EFILE` if an included file cannot be read.  On error, the
+    /// record is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use c_ares::{DnsFlags, DnsOpcode, DnsRcode, DnsRecord, DnsSection};
+    ///
+    /// let mut rec = DnsRecord::new(0, DnsFlags::QR, DnsOpcode::Query, DnsRcode::NoError).unwrap();
+    /// rec.add_zone_str(
+    ///     DnsSection::Answer,
+    ///     "$TTL 300\n@ IN MX 10 mail\nmail IN A 192.0.2.1\n",
+    ///     "example.com",
+    /// )
+    /// .unwrap();
+    /// let mx = rec.rr(DnsSection::Answer, 0).unwrap().as_mx().unwrap();
+    /// assert_eq!(mx.exchange(), "mail.example.com");
+    /// ```
+    pub fn add_zone_str(
+        &mut self,
+        section: DnsSection,
+        zone: &str,
+        origin: &str,
+    ) -> Result<&mut Self> {
+        self.add_zone(section, origin, |parser, state| {
+            parser.parse(zone, Path::new(""), state)
+        })
+    }
+
+    /// Add the resource records from the master file at `path` to `section`.
+    ///
+    /// This is as [`add_zone_str()`](Self::add_zone_str), except that relative
+    /// paths named by `$INCLUDE` are read relative to the directory containing
+    /// the file that names them - so `path`, for the top-level file - rather
+    /// than the current directory.  Returns `Error::EFILE` if `path` cannot be
+    /// read.
+    pub fn add_zone_file<P: AsRef<Path>>(
+        &mut self,
+        section: DnsSection,
+        path: P,
+        origin: &str,
+    ) -> Result<&mut Self> {
+        self.add_zone(section, origin, |parser, state| {
+            parser.include(path.as_ref(), state)
+        })
+    }
+
+    // Runs `parse`, removing any records that it added if it fails.
+    fn add_zone<F>(&mut self, section: DnsSection, origin: &str, parse: F) -> Result<&mut Self>
+    where
+        F: FnOnce(&mut ZoneParser<'_>, &mut ZoneState) -> Result<()>,
+    {
+        let mut state = ZoneState {
+            origin: absolute_name(origin, None)?,
+            default_ttl: None,
+            last_ttl: None,
+            last_owner: None,
+            last_class: DnsCls::IN,
+        };
+        let count = self.rr_count(section);
+        let mut parser = ZoneParser {
+            rec: &mut *self,
+            section,
+            depth: 0,
+        };
+        if let Err(error) = parse(&mut parser, &mut state) {
+            while self.rr_count(section) > count {
+                self.rr_del(section, count)?;
+            }
+            return Err(error);
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::test_util::{new_rec, round_trip};
+
+    fn answers(rec: &DnsRecord) -> Vec<String> {
+        rec.rrs(DnsSection::Answer)
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    const ZONE: &str = r#"
+$ORIGIN example.com.
+$TTL 1h
+@       IN  SOA ns1 hostmaster (
+                2024010101 ; serial
+                2h 15m 2w 5m )
+        IN  NS  ns1
+        IN  MX  10 mail.example.net.
+ns1     IN  A   192.0.2.1
+www     60      CNAME @
+        TXT     "hello world"
+$ORIGIN sub
+host        A   192.0.2.2
+"#;
+
+    #[test]
+    fn parses_zone() {
+        let mut rec = new_rec();
+        rec.add_zone_str(DnsSection::Answer, ZONE, "ignored.")
+            .unwrap();
+        assert_eq!(
+            answers(&rec),
+            [
+                "example.com.\t3600\tIN\tSOA\tns1.example.com. hostmaster.example.com. \
+                 2024010101 7200 900 1209600 300",
+                "example.com.\t3600\tIN\tNS\tns1.example.com.",
+                "example.com.\t3600\tIN\tMX\t10 mail.example.net.",
+                "ns1.example.com.\t3600\tIN\tA\t192.0.2.1",
+                "www.example.com.\t60\tIN\tCNAME\texample.com.",
+                "www.example.com.\t3600\tIN\tTXT\t\"hello world\"",
+                "host.sub.example.com.\t3600\tIN\tA\t192.0.2.2",
+            ]
+        );
+    }
+
+    #[test]
+    fn origin_argument() {
+        let mut rec = new_rec();
+        rec.add_zone_str(DnsSection::Authority, "@ 300 NS ns1\n", "example.com")
+            .unwrap();
+        let rr = rec.rr(DnsSection::Authority, 0).unwrap();
+        assert_eq!(
+            rr.to_string(),
+            "example.com.\t300\tIN\tNS\tns1.example.com."
+        );
+    }
+
+    #[test]
+    fn ttl_without_directive_carries_forward() {
+        let mut rec = new_rec();
+        rec.add_zone_str(
+            DnsSection::Answer,
+            "a 120 A 192.0.2.1\nb A 192.0.2.2\n",
+            "example.com.",
+        )
+        .unwrap();
+        assert_eq!(
+            answers(&rec),
+            [
+                "a.example.com.\t120\tIN\tA\t192.0.2.1",
+                "b.example.com.\t120\tIN\tA\t192.0.2.2",
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trips_through_wire_format() {
+        let mut rec = new_rec();
+        rec.add_zone_str(DnsSection::Answer, ZONE, ".").unwrap();
+        assert_eq!(answers(&round_trip(&rec)), answers(&rec));
+    }
+
+    #[test]
+    fn failure_leaves_record_unchanged() {
+        let mut rec = new_rec();
+        rec.add_rr_str(DnsSection::Answer, "example.com. 300 IN A 192.0.2.1")
+            .unwrap();
+        for zone in [
+            "$TTL 300\na A 192.0.2.2\nb A not-an-address\n",
+            "$TTL 300\na A 192.0.2.2\n$UNKNOWN\n",
+            "$TTL 300\n  A 192.0.2.2\n",
+            "a A 192.0.2.2\n",
+        ] {
+            let result = rec.add_zone_str(DnsSection::Answer, zone, "example.com.");
+            assert_eq!(result.err(), Some(Error::EBADSTR), "{zone:?}");
+            assert_eq!(rec.rr_count(DnsSection::Answer), 1, "{zone:?}");
+        }
+    }
+}
@@ -7,6 +7,7 @@ use itertools::Itertools;
 
 use crate::error::{Error, Result};
 use crate::panic;
+use crate::query_handle::{Outcome, take_pending};
 use crate::types::AddressFamily;
 use crate::utils::{address_family, hostname_as_str};
 
@@ -73,7 +74,9 @@ pub(crate) unsafe extern "C" fn get_host_callback<F>(
 ) where
     F: FnOnce(Result<&HostResults>) + Send + 'static,
 {
-    let handler = unsafe { Box::from_raw(arg.cast::<F>()) };
+    let Some(handler) = (unsafe { take_pending::<F>(arg, &Outcome::Status(status)) }) else {
+        return;
+    };
 
     panic::abort_on_panic(|| {
         if status == c_ares_sys::ares_status_t::ARES_SUCCESS as i32 {
@@ -0,0 +1,117 @@
+use core::ffi::c_int;
+use std::time::Instant;
+
+use tracing::field;
+
+#[cfg(cares1_28)]
+use crate::dns::{DnsRecord, DnsSection};
+use crate::error::Error;
+use crate::query_handle::Outcome;
+
+// A span covering a single query, from when it is made until c-ares reports its outcome.
+//
+// The span is created on the thread making the query, so it is a child of whatever span is current
+// there.
+pub(crate) struct QuerySpan {
+    span: tracing::Span,
+    start: Instant,
+}
+
+impl QuerySpan {
+    pub(crate) fn new(method: &'static str, name: Option<&str>, query_type: Option<u16>) -> Self {
+        let span = tracing::debug_span!(
+            "dns_query",
+            method,
+            name,
+            query_type,
+            error = field::Empty,
+            rcode = field::Empty,
+            answers = field::Empty,
+            elapsed = field::Empty,
+        );
+        Self {
+            span,
+            start: Instant::now(),
+        }
+    }
+
+    // A span for sending `dnsrec`, described by its first question.
+    #[cfg(cares1_28)]
+    pub(crate) fn for_record(method: &'static str, dnsrec: &DnsRecord) -> Self {
+        match dnsrec.query_get(0) {
+            Ok((name, query_type, _)) => {
+                let query_type = c_ares_sys::ares_dns_rec_type_t::from(query_type).0;
+                Self::new(method, Some(name), u16::try_from(query_type).ok())
+            }
+            Err(_) => Self::new(method, None, None),
+        }
+    }
+
+    // Record how the query finished.
+    pub(crate) fn finish(&self, outcome: &Outcome<'_>) {
+        let elapsed = self.start.elapsed();
+        let status = outcome.status();
+        let error = (status != c_ares_sys::ares_status_t::ARES_SUCCESS as c_int)
+            .then(|| Error::from(status));
+        let (rcode, answers) = response_summary(outcome);
+        self.span.record("error", error.map(field::debug));
+        self.span.record("rcode", rcode);
+        self.span.record("answers", answers);
+        self.span.record("elapsed", field::debug(elapsed));
+        tracing::debug!(
+            parent: &self.span,
+            error = error.map(field::debug),
+            rcode,
+            answers,
+            elapsed = ?elapsed,
+            "query complete"
+        );
+    }
+}
+
+// The rcode and answer count of the response, where there was one.
+fn response_summary(outcome: &Outcome<'_>) -> (Option<u16>, Option<u16>) {
+    match *outcome {
+        Outcome::Status(_) => (None, None),
+        Outcome::Message(_, message) => match message {
+            Some([_, _, _, flags, _, _, high, low, ..]) => (
+                Some(u16::from(flags & 0x0f)),
+                Some(u16::from_be_bytes([*high, *low])),
+            ),
+            _ => (None, None),
+        },
+        #[cfg(cares1_28)]
+        Outcome::Record(_, record) => match record {
+            Some(record) => {
+                let rcode = c_ares_sys::ares_dns_rcode_t::from(record.rcode()) as u32;
+                let answers = record.rr_count(DnsSection::Answer);
+                (u16::try_from(rcode).ok(), u16::try_from(answers).ok())
+            }
+            None => (None, None),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NXDOMAIN: &[u8] = b"\x12\x34\x81\x83\x00\x01\x00\x00\x00\x01\x00\x00";
+    const ANSWERED: &[u8] = b"\x12\x34\x81\x80\x00\x01\x00\x02\x00\x00\x00\x00";
+
+    #[test]
+    fn summary_of_message() {
+        let outcome = Outcome::Message(0, Some(ANSWERED));
+        assert_eq!(response_summary(&outcome), (Some(0), Some(2)));
+        let outcome = Outcome::Message(4, Some(NXDOMAIN));
+        assert_eq!(response_summary(&outcome), (Some(3), Some(0)));
+    }
+
+    #[test]
+    fn summary_without_message() {
+        assert_eq!(response_summary(&Outcome::Status(11)), (None, None));
+        assert_eq!(response_summary(&Outcome::Message(11, None)), (None, None));
+        let truncated = Outcome::Message(0, Some(&ANSWERED[..7]));
+        assert_eq!(response_summary(&truncated), (None, None));
+    }
+}
@@ -38,6 +38,7 @@ mod macros;
 mod a;
 mod aaaa;
 mod addrinfo;
+mod allocator;
 mod caa;
 mod channel;
 mod cname;
@@ -48,6 +49,8 @@ mod error;
 mod events;
 mod flags;
 mod host;
+#[cfg(feature = "tracing")]
+mod instrument;
 mod mx;
 mod nameinfo;
 mod naptr;
@@ -56,11 +59,20 @@ mod ns;
 mod panic;
 mod ptr;
 mod query;
+mod query_handle;
 mod record;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(cares1_24)]
+mod server_config;
 mod server_state_flags;
 mod soa;
+#[cfg(cares1_34)]
+mod socket_functions;
 mod srv;
 mod string;
+#[cfg(cares1_34)]
+mod trace;
 mod txt;
 mod types;
 mod uri;
@@ -73,10 +85,15 @@ pub use crate::addrinfo::{
     AddrInfoCName, AddrInfoCNameIter, AddrInfoFlags, AddrInfoHints, AddrInfoNode, AddrInfoNodeIter,
     AddrInfoResults,
 };
+pub use crate::allocator::{
+    AllocationStats, allocation_stats, init_with_allocator, init_with_global_allocator,
+};
 pub use crate::caa::{CAAResult, CAAResults, CAAResultsIter};
 #[cfg(cares1_29)]
 pub use crate::channel::ServerFailoverOptions;
-pub use crate::channel::{Channel, Options, Sockets, SocketsIter};
+#[cfg(cares1_23)]
+pub use crate::channel::SharedChannel;
+pub use crate::channel::{Channel, EffectiveOptions, Options, Sockets, SocketsIter};
 pub use crate::cname::CNameResults;
 #[cfg(cares1_28)]
 pub use crate::dns::{
@@ -86,6 +103,8 @@ pub use crate::dns::{
     PtrRecord, RawRrRecord, SigRecord, SoaRecord, SrvRecord, SvcbRecord, TlsaRecord, TxtRecord,
     TypedRr, UriRecord, parse_opt_value,
 };
+#[cfg(all(cares1_28, feature = "proptest"))]
+pub use crate::dns::{ArbitraryRdata, ArbitraryRr};
 pub use crate::error::{Error, Result};
 #[cfg(cares1_34)]
 pub use crate::events::{FdEventFlags, FdEvents, ProcessFlags};
@@ -97,15 +116,24 @@ pub use crate::naptr::{NAPTRResult, NAPTRResults, NAPTRResultsIter};
 pub use crate::ni_flags::NIFlags;
 pub use crate::ns::NSResults;
 pub use crate::ptr::PTRResults;
+pub use crate::query_handle::QueryHandle;
+#[cfg(cares1_24)]
+pub use crate::server_config::ServerConfig;
 #[cfg(cares1_29)]
 pub use crate::server_state_flags::ServerStateFlags;
 pub use crate::soa::SOAResult;
+#[cfg(cares1_34)]
+pub use crate::socket_functions::{
+    SocketBindFlags, SocketConnectFlags, SocketFunctions, SocketOption, SystemSocketFunctions,
+};
 pub use crate::srv::{SRVResult, SRVResults, SRVResultsIter};
 pub use crate::string::{AresBuf, AresString};
+#[cfg(cares1_34)]
+pub use crate::trace::{TraceDirection, TraceEvent};
 pub use crate::txt::{TXTResult, TXTResults, TXTResultsIter};
 #[cfg(cares1_26)]
 pub use crate::types::EventSys;
-pub use crate::types::{AddressFamily, SOCKET_BAD, Socket};
+pub use crate::types::{AddressFamily, SOCKET_BAD, Socket, SocketType};
 pub use crate::uri::{URIResult, URIResults, URIResultsIter};
 #[cfg(cares1_23)]
 pub use crate::utils::expand_name;
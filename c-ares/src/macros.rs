@@ -1,7 +1,25 @@
+// With the `tracing` feature, attach a `QuerySpan` to a `PendingHandler`.
+macro_rules! instrument {
+    ($pending:ident, $method:expr, $name:expr, $query_type:expr) => {
+        #[cfg(feature = "tracing")]
+        let $pending = $pending.instrument(crate::instrument::QuerySpan::new(
+            $method,
+            $name,
+            $query_type,
+        ));
+    };
+    ($pending:ident, $method:expr, $dnsrec:expr) => {
+        #[cfg(feature = "tracing")]
+        let $pending =
+            $pending.instrument(crate::instrument::QuerySpan::for_record($method, $dnsrec));
+    };
+}
+
 // Most of our lookups follow the same pattern - macro out the repetition.
 macro_rules! ares_call {
     (
         $ares_call:ident,
+        $method:expr,
         $channel:expr,
         $name:expr,
         $dns_class:expr,
@@ -11,9 +29,16 @@ macro_rules! ares_call {
     ) => {{
         let Ok(c_name) = CString::new($name) else {
             $handler(Err(Error::EBADNAME));
-            return;
+            return QueryHandle::completed();
         };
-        let c_arg = Box::into_raw(Box::new($handler));
+        let pending = PendingHandler::new($handler);
+        instrument!(
+            pending,
+            $method,
+            Some($name),
+            u16::try_from($query_type as c_int).ok()
+        );
+        let handle = pending.handle(|handler| handler(Err(Error::ECANCELLED)));
         unsafe {
             c_ares_sys::$ares_call(
                 $channel,
@@ -21,23 +46,24 @@ macro_rules! ares_call {
                 $dns_class as c_int,
                 $query_type as c_int,
                 Some($callback),
-                c_arg.cast(),
+                pending.into_raw(),
             );
         }
+        handle
     }};
 }
 
 macro_rules! ares_query {
-    ($($arg:tt)*) => { ares_call!(ares_query, $($arg)*) }
+    ($($arg:tt)*) => { ares_call!(ares_query, "query", $($arg)*) }
 }
 
 macro_rules! ares_search {
-    ($($arg:tt)*) => { ares_call!(ares_search, $($arg)*) }
+    ($($arg:tt)*) => { ares_call!(ares_search, "search", $($arg)*) }
 }
 
 // Most of our `ares_callback` implementations are much the same - macro out the repetition.
 macro_rules! ares_callback {
-    ($arg:expr, $status:expr, $abuf:expr, $alen:expr, $parser:expr) => {{
+    ($pending:expr, $status:expr, $abuf:expr, $alen:expr, $parser:expr) => {{
         let result = if $status == c_ares_sys::ares_status_t::ARES_SUCCESS as i32 {
             debug_assert!(
                 $alen >= 0,
@@ -48,7 +74,9 @@ macro_rules! ares_callback {
         } else {
             Err(Error::from($status))
         };
-        let handler = unsafe { Box::from_raw($arg) };
+        let Some(handler) = (unsafe { $pending }) else {
+            return;
+        };
         panic::abort_on_panic(|| handler(result));
     }};
 }
@@ -3,6 +3,7 @@ use std::{fmt, str};
 
 use crate::error::{Error, Result};
 use crate::panic;
+use crate::query_handle::{Outcome, take_pending};
 use crate::utils::{c_string_as_str_unchecked, hostname_as_str};
 
 /// The result of a successful name-info lookup.
@@ -57,6 +58,8 @@ pub(crate) unsafe extern "C" fn get_name_info_callback<F>(
     } else {
         Err(Error::from(status))
     };
-    let handler = unsafe { Box::from_raw(arg.cast::<F>()) };
+    let Some(handler) = (unsafe { take_pending::<F>(arg, &Outcome::Status(status)) }) else {
+        return;
+    };
     panic::abort_on_panic(|| handler(result));
 }
@@ -3,6 +3,7 @@ use std::slice;
 
 use crate::error::{Error, Result};
 use crate::panic;
+use crate::query_handle::{Outcome, take_pending};
 use crate::record::QueryRecord;
 
 pub(crate) unsafe extern "C" fn raw_query_callback<F>(
@@ -20,7 +21,10 @@ pub(crate) unsafe extern "C" fn raw_query_callback<F>(
     } else {
         Err(Error::from(status))
     };
-    let handler = unsafe { Box::from_raw(arg.cast::<F>()) };
+    let outcome = Outcome::Message(status, unsafe { response(abuf, alen) });
+    let Some(handler) = (unsafe { take_pending::<F>(arg, &outcome) }) else {
+        return;
+    };
     panic::abort_on_panic(|| handler(result));
 }
 
@@ -34,5 +38,19 @@ pub(crate) unsafe extern "C" fn query_callback<R, F>(
     R: QueryRecord,
     F: FnOnce(Result<R>) + Send + 'static,
 {
-    ares_callback!(arg.cast::<F>(), status, abuf, alen, R::parse);
+    let outcome = Outcome::Message(status, unsafe { response(abuf, alen) });
+    ares_callback!(
+        take_pending::<F>(arg, &outcome),
+        status,
+        abuf,
+        alen,
+        R::parse
+    );
+}
+
+// The response that c-ares passed to a query callback, if any.  Failed queries may still have one:
+// for instance, when the server said NXDOMAIN.
+unsafe fn response<'a>(abuf: *const c_uchar, alen: c_int) -> Option<&'a [u8]> {
+    let len = usize::try_from(alen).ok()?;
+    (!abuf.is_null()).then(|| unsafe { slice::from_raw_parts(abuf, len) })
 }
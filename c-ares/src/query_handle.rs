@@ -0,0 +1,209 @@
+use core::ffi::{c_int, c_void};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+#[cfg(cares1_28)]
+use crate::dns::DnsRecord;
+#[cfg(feature = "tracing")]
+use crate::instrument::QuerySpan;
+
+/// A handle to an in-flight query, allowing its handler to be cancelled individually.
+///
+/// c-ares has no way to abandon a single query, so cancelling through this handle only detaches
+/// the handler: see [`cancel()`](Self::cancel).  To stop queries outright, use
+/// [`Channel::cancel()`](crate::Channel::cancel), which cancels every query on the channel.
+///
+/// Dropping the handle does not cancel the query.
+pub struct QueryHandle {
+    cancel: Box<dyn Fn() + Send + Sync>,
+    qid: Option<u16>,
+}
+
+impl QueryHandle {
+    /// Cancel this query's handler.
+    ///
+    /// If the query has not already completed, its handler is immediately called - on the current
+    /// thread - with `Error::ECANCELLED`.  Other queries on the channel are unaffected.
+    ///
+    /// The query itself is not stopped.  c-ares carries on with it until it completes or times
+    /// out: it still holds its sockets, still sends retries, and still counts towards
+    /// [`Channel::queue_active_queries()`](crate::Channel::queue_active_queries).  When it does
+    /// finish, the result is discarded.
+    ///
+    /// This has no effect if the query has already completed or been cancelled.
+    pub fn cancel(&self) {
+        (self.cancel)();
+    }
+
+    /// The query id, where c-ares provides one.
+    pub fn qid(&self) -> Option<u16> {
+        self.qid
+    }
+
+    // A handle for a query that has already completed, so cannot be cancelled.
+    pub(crate) fn completed() -> Self {
+        QueryHandle {
+            cancel: Box::new(|| {}),
+            qid: None,
+        }
+    }
+
+    pub(crate) fn with_qid(mut self, qid: u16) -> Self {
+        self.qid = Some(qid);
+        self
+    }
+}
+
+impl fmt::Debug for QueryHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueryHandle")
+            .field("qid", &self.qid)
+            .finish_non_exhaustive()
+    }
+}
+
+// Holds a query's handler until either the query completes or it is cancelled; whichever happens
+// first gets to call the handler.
+pub(crate) struct PendingHandler<F> {
+    handler: Arc<Mutex<Option<F>>>,
+    #[cfg(feature = "tracing")]
+    span: Option<QuerySpan>,
+}
+
+impl<F> PendingHandler<F>
+where
+    F: Send + 'static,
+{
+    pub(crate) fn new(handler: F) -> Self {
+        Self {
+            handler: Arc::new(Mutex::new(Some(handler))),
+            #[cfg(feature = "tracing")]
+            span: None,
+        }
+    }
+
+    // Attaches a span, which is told the outcome of the query when c-ares reports it.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn instrument(mut self, span: QuerySpan) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    // Returns the handler, unless the query has been cancelled.
+    pub(crate) fn take(&self) -> Option<F> {
+        self.handler.lock().unwrap().take()
+    }
+
+    // Returns a handle that cancels by taking the handler and passing it to `cancel`.
+    pub(crate) fn handle(&self, cancel: fn(F)) -> QueryHandle {
+        let handler = Arc::clone(&self.handler);
+        QueryHandle {
+            cancel: Box::new(move || {
+                let pending = handler.lock().unwrap().take();
+                if let Some(handler) = pending {
+                    cancel(handler);
+                }
+            }),
+            qid: None,
+        }
+    }
+
+    // Converts into a pointer suitable for passing to c-ares as callback data.  The callback must
+    // reclaim it with `take_pending()`.
+    pub(crate) fn into_raw(self) -> *mut c_void {
+        Box::into_raw(Box::new(self)).cast()
+    }
+}
+
+// How c-ares says that a query finished: with a status, and perhaps with the response.
+#[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+pub(crate) enum Outcome<'a> {
+    Status(c_int),
+    Message(c_int, Option<&'a [u8]>),
+    #[cfg(cares1_28)]
+    Record(c_int, Option<&'a DnsRecord>),
+}
+
+impl Outcome<'_> {
+    #[cfg(feature = "tracing")]
+    pub(crate) fn status(&self) -> c_int {
+        match *self {
+            Outcome::Status(status) | Outcome::Message(status, _) => status,
+            #[cfg(cares1_28)]
+            Outcome::Record(status, _) => status,
+        }
+    }
+}
+
+// Reclaims the callback data created by `PendingHandler::into_raw()`, returning the handler unless
+// the query has been cancelled.
+pub(crate) unsafe fn take_pending<F>(arg: *mut c_void, outcome: &Outcome<'_>) -> Option<F>
+where
+    F: Send + 'static,
+{
+    let pending = unsafe { Box::from_raw(arg.cast::<PendingHandler<F>>()) };
+    #[cfg(feature = "tracing")]
+    if let Some(span) = &pending.span {
+        span.finish(outcome);
+    }
+    #[cfg(not(feature = "tracing"))]
+    let _ = outcome;
+    pending.take()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{Error, Result};
+    use std::sync::mpsc;
+
+    fn pending(
+        tx: mpsc::Sender<Result<u32>>,
+    ) -> PendingHandler<impl FnOnce(Result<u32>) + Send + 'static> {
+        PendingHandler::new(move |result: Result<u32>| tx.send(result).unwrap())
+    }
+
+    #[test]
+    fn cancel_calls_handler_once() {
+        let (tx, rx) = mpsc::channel();
+        let pending = pending(tx);
+        let handle = pending
+            .handle(|handler| handler(Err(Error::ECANCELLED)))
+            .with_qid(7);
+        handle.cancel();
+        handle.cancel();
+        assert_eq!(rx.try_recv(), Ok(Err(Error::ECANCELLED)));
+        assert!(rx.try_recv().is_err());
+        assert!(pending.take().is_none());
+        assert_eq!(handle.qid(), Some(7));
+    }
+
+    #[test]
+    fn cancel_after_completion_is_noop() {
+        let (tx, rx) = mpsc::channel();
+        let pending = pending(tx);
+        let handle = pending.handle(|handler| handler(Err(Error::ECANCELLED)));
+        pending.take().unwrap()(Ok(1));
+        handle.cancel();
+        assert_eq!(rx.try_recv(), Ok(Ok(1)));
+        assert!(rx.try_recv().is_err());
+        assert_eq!(handle.qid(), None);
+    }
+
+    #[test]
+    fn take_pending_after_cancel() {
+        let pending = PendingHandler::<fn(Result<u32>)>::new(|_| {});
+        let handle = pending.handle(|handler| handler(Err(Error::ECANCELLED)));
+        let raw = pending.into_raw();
+        handle.cancel();
+        let outcome = Outcome::Status(c_ares_sys::ares_status_t::ARES_ECANCELLED as c_int);
+        let handler = unsafe { take_pending::<fn(Result<u32>)>(raw, &outcome) };
+        assert!(handler.is_none());
+    }
+
+    #[test]
+    fn query_handle_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<QueryHandle>();
+    }
+}
@@ -0,0 +1,245 @@
+// `Serialize` implementations for the query results, enabled by the `serde` feature.
+//
+// The collections serialize as sequences of their items, and the items as structs whose fields
+// are named for their accessors.  Binary data - TXT text and CAA values - serializes as a string
+// when it is valid UTF-8, and as bytes otherwise.
+use std::str;
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use crate::{
+    AAAAResult, AAAAResults, AResult, AResults, AddrInfoCName, AddrInfoNode, AddrInfoResults,
+    AddressFamily, CAAResult, CAAResults, HostResults, MXResult, MXResults, NAPTRResult,
+    NAPTRResults, SOAResult, SRVResult, SRVResults, TXTResult, TXTResults, URIResult, URIResults,
+};
+
+// Serializes binary data that is usually, but not necessarily, text.
+struct MaybeText<'a>(&'a [u8]);
+
+impl Serialize for MaybeText<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match str::from_utf8(self.0) {
+            Ok(text) => serializer.serialize_str(text),
+            Err(_) => serializer.serialize_bytes(self.0),
+        }
+    }
+}
+
+macro_rules! serialize_as_seq {
+    ($($results:ty),* $(,)?) => {
+        $(
+            impl Serialize for $results {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_seq(self)
+                }
+            }
+        )*
+    };
+}
+
+serialize_as_seq!(
+    AResults,
+    AAAAResults,
+    MXResults,
+    SRVResults,
+    TXTResults,
+    NAPTRResults,
+    CAAResults,
+    URIResults,
+);
+
+impl Serialize for AResult<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AResult", 2)?;
+        state.serialize_field("ipv4", &self.ipv4())?;
+        state.serialize_field("ttl", &self.ttl())?;
+        state.end()
+    }
+}
+
+impl Serialize for AAAAResult<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AAAAResult", 2)?;
+        state.serialize_field("ipv6", &self.ipv6())?;
+        state.serialize_field("ttl", &self.ttl())?;
+        state.end()
+    }
+}
+
+impl Serialize for MXResult<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("MXResult", 2)?;
+        state.serialize_field("host", self.host())?;
+        state.serialize_field("priority", &self.priority())?;
+        state.end()
+    }
+}
+
+impl Serialize for SRVResult<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("SRVResult", 4)?;
+        state.serialize_field("host", self.host())?;
+        state.serialize_field("weight", &self.weight())?;
+        state.serialize_field("priority", &self.priority())?;
+        state.serialize_field("port", &self.port())?;
+        state.end()
+    }
+}
+
+impl Serialize for TXTResult<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("TXTResult", 2)?;
+        state.serialize_field("record_start", &self.record_start())?;
+        state.serialize_field("text", &MaybeText(self.text()))?;
+        state.end()
+    }
+}
+
+impl Serialize for SOAResult {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("SOAResult", 7)?;
+        state.serialize_field("name_server", self.name_server())?;
+        state.serialize_field("hostmaster", self.hostmaster())?;
+        state.serialize_field("serial", &self.serial())?;
+        state.serialize_field("refresh", &self.refresh())?;
+        state.serialize_field("retry", &self.retry())?;
+        state.serialize_field("expire", &self.expire())?;
+        state.serialize_field("min_ttl", &self.min_ttl())?;
+        state.end()
+    }
+}
+
+impl Serialize for NAPTRResult<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("NAPTRResult", 6)?;
+        state.serialize_field("flags", self.flags())?;
+        state.serialize_field("service_name", self.service_name())?;
+        state.serialize_field("regexp", self.regexp())?;
+        state.serialize_field("replacement_pattern", self.replacement_pattern())?;
+        state.serialize_field("order", &self.order())?;
+        state.serialize_field("preference", &self.preference())?;
+        state.end()
+    }
+}
+
+impl Serialize for CAAResult<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("CAAResult", 3)?;
+        state.serialize_field("critical", &self.critical())?;
+        state.serialize_field("property", self.property())?;
+        state.serialize_field("value", &MaybeText(self.value()))?;
+        state.end()
+    }
+}
+
+impl Serialize for URIResult<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("URIResult", 4)?;
+        state.serialize_field("weight", &self.weight())?;
+        state.serialize_field("priority", &self.priority())?;
+        state.serialize_field("uri", self.uri())?;
+        state.serialize_field("ttl", &self.ttl())?;
+        state.end()
+    }
+}
+
+impl Serialize for HostResults {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct Seq<I>(I);
+        impl<I> Serialize for Seq<I>
+        where
+            I: Iterator + Clone,
+            I::Item: Serialize,
+        {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_seq(self.0.clone())
+            }
+        }
+
+        let mut state = serializer.serialize_struct("HostResults", 3)?;
+        state.serialize_field("hostname", self.hostname())?;
+        state.serialize_field("addresses", &Seq(self.addresses()))?;
+        state.serialize_field("aliases", &Seq(self.aliases()))?;
+        state.end()
+    }
+}
+
+impl Serialize for AddressFamily {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (index, name) = match self {
+            AddressFamily::INET => (0, "INET"),
+            AddressFamily::INET6 => (1, "INET6"),
+            AddressFamily::UNSPEC => (2, "UNSPEC"),
+        };
+        serializer.serialize_unit_variant("AddressFamily", index, name)
+    }
+}
+
+impl Serialize for AddrInfoResults {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let nodes: Vec<_> = self.nodes().collect();
+        let cnames: Vec<_> = self.cnames().collect();
+        let mut state = serializer.serialize_struct("AddrInfoResults", 3)?;
+        state.serialize_field("name", &self.name())?;
+        state.serialize_field("nodes", &nodes)?;
+        state.serialize_field("cnames", &cnames)?;
+        state.end()
+    }
+}
+
+impl Serialize for AddrInfoNode<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AddrInfoNode", 5)?;
+        state.serialize_field("ttl", &self.ttl())?;
+        state.serialize_field("family", &self.family())?;
+        state.serialize_field("socktype", &self.socktype())?;
+        state.serialize_field("protocol", &self.protocol())?;
+        state.serialize_field("socket_addr", &self.socket_addr())?;
+        state.end()
+    }
+}
+
+impl Serialize for AddrInfoCName<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AddrInfoCName", 3)?;
+        state.serialize_field("ttl", &self.ttl())?;
+        state.serialize_field("alias", self.alias())?;
+        state.serialize_field("name", self.name())?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // DNS MX response: example.com -> mail.example.com, priority 10, TTL 300
+    const ONE_MX_RECORD: &[u8] = &[
+        0x00, 0x00, 0x81, 0x80, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x07, 0x65, 0x78,
+        0x61, 0x6d, 0x70, 0x6c, 0x65, 0x03, 0x63, 0x6f, 0x6d, 0x00, 0x00, 0x0f, 0x00, 0x01, 0xc0,
+        0x0c, 0x00, 0x0f, 0x00, 0x01, 0x00, 0x00, 0x01, 0x2c, 0x00, 0x14, 0x00, 0x0a, 0x04, 0x6d,
+        0x61, 0x69, 0x6c, 0x07, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x03, 0x63, 0x6f, 0x6d,
+        0x00,
+    ];
+
+    #[test]
+    fn mx_results_to_json() {
+        let results = MXResults::parse_from(ONE_MX_RECORD).unwrap();
+        let json = serde_json::to_string(&results).unwrap();
+        assert_eq!(json, r#"[{"host":"mail.example.com","priority":10}]"#);
+    }
+
+    #[test]
+    fn maybe_text() {
+        let text = serde_json::to_string(&MaybeText(b"v=spf1")).unwrap();
+        assert_eq!(text, r#""v=spf1""#);
+        let binary = serde_json::to_string(&MaybeText(&[0xff, 0x00])).unwrap();
+        assert_eq!(binary, "[255,0]");
+    }
+
+    #[test]
+    fn address_family() {
+        let json = serde_json::to_string(&AddressFamily::INET6).unwrap();
+        assert_eq!(json, r#""INET6""#);
+    }
+}
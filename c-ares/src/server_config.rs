@@ -0,0 +1,348 @@
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+
+const URI_SCHEME: &str = "dns://";
+
+/// The configuration of a single DNS server.
+///
+/// This is a structured alternative to the strings accepted by
+/// [`Channel::set_servers`](crate::Channel::set_servers).  A `ServerConfig` formats to, and
+/// parses from, the c-ares URI syntax: `dns://host[:port][?tcpport=port]`.  IPv6 hosts are
+/// enclosed in square brackets, and may carry a link-local interface or scope id as a zone
+/// identifier, eg `dns://[fe80::1%25eth0]:53`.
+///
+/// Parsing also accepts the legacy `host[:port][%iface]` syntax.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ServerConfig {
+    addr: IpAddr,
+    udp_port: Option<u16>,
+    tcp_port: Option<u16>,
+    ll_interface: Option<String>,
+    scope_id: Option<u32>,
+}
+
+impl ServerConfig {
+    /// Returns a new `ServerConfig` for the server at `addr`, using default ports.
+    pub fn new(addr: IpAddr) -> Self {
+        Self {
+            addr,
+            udp_port: None,
+            tcp_port: None,
+            ll_interface: None,
+            scope_id: None,
+        }
+    }
+
+    /// The address of the server.
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    /// The UDP port of the server.  `None` means the channel's default.
+    pub fn udp_port(&self) -> Option<u16> {
+        self.udp_port
+    }
+
+    /// The TCP port of the server.  `None` means the same as the UDP port.
+    pub fn tcp_port(&self) -> Option<u16> {
+        self.tcp_port
+    }
+
+    /// The link-local interface through which the server should be contacted.
+    pub fn ll_interface(&self) -> Option<&str> {
+        self.ll_interface.as_deref()
+    }
+
+    /// The IPv6 scope id through which the server should be contacted.
+    pub fn scope_id(&self) -> Option<u32> {
+        self.scope_id
+    }
+
+    /// Set the UDP port of the server.
+    pub fn set_udp_port(&mut self, port: u16) -> &mut Self {
+        self.udp_port = Some(port);
+        self
+    }
+
+    /// Set the TCP port of the server.
+    pub fn set_tcp_port(&mut self, port: u16) -> &mut Self {
+        self.tcp_port = Some(port);
+        self
+    }
+
+    /// Set the link-local interface through which the server should be contacted.
+    ///
+    /// This is only meaningful for IPv6 servers, and takes precedence over any scope id.
+    pub fn set_ll_interface(&mut self, interface: &str) -> &mut Self {
+        self.ll_interface = Some(interface.to_owned());
+        self
+    }
+
+    /// Set the IPv6 scope id through which the server should be contacted.
+    ///
+    /// This is only meaningful for IPv6 servers.
+    pub fn set_scope_id(&mut self, scope_id: u32) -> &mut Self {
+        self.scope_id = Some(scope_id);
+        self
+    }
+
+    fn set_zone(&mut self, zone: String) {
+        match zone.parse() {
+            Ok(scope_id) => self.scope_id = Some(scope_id),
+            Err(_) => self.ll_interface = Some(zone),
+        }
+    }
+}
+
+impl From<IpAddr> for ServerConfig {
+    fn from(addr: IpAddr) -> Self {
+        Self::new(addr)
+    }
+}
+
+impl fmt::Display for ServerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(URI_SCHEME)?;
+        match self.addr {
+            IpAddr::V4(ipv4) => write!(f, "{ipv4}")?,
+            IpAddr::V6(ipv6) => {
+                write!(f, "[{ipv6}")?;
+                if let Some(interface) = &self.ll_interface {
+                    f.write_str("%25")?;
+                    for c in interface.chars() {
+                        if c.is_ascii_alphanumeric() || "-._~".contains(c) {
+                            write!(f, "{c}")?;
+                        } else {
+                            let mut buf = [0; 4];
+                            for b in c.encode_utf8(&mut buf).bytes() {
+                                write!(f, "%{b:02X}")?;
+                            }
+                        }
+                    }
+                } else if let Some(scope_id) = self.scope_id {
+                    write!(f, "%25{scope_id}")?;
+                }
+                f.write_str("]")?;
+            }
+        }
+        if let Some(port) = self.udp_port {
+            write!(f, ":{port}")?;
+        }
+        if let Some(port) = self.tcp_port.filter(|&port| Some(port) != self.udp_port) {
+            write!(f, "?tcpport={port}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ServerConfig {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.strip_prefix(URI_SCHEME) {
+            Some(uri) => parse_uri(uri),
+            None => parse_legacy(s),
+        }
+    }
+}
+
+// Parse `host[:port][/][?query]`, the part of a server URI following the scheme.
+fn parse_uri(uri: &str) -> Result<ServerConfig> {
+    let (authority, query) = match uri.split_once('?') {
+        Some((authority, query)) => (authority, Some(query)),
+        None => (uri, None),
+    };
+    let authority = authority.strip_suffix('/').unwrap_or(authority);
+    let (host, port) = split_host_port(authority)?;
+    // RFC 6874 encodes the zone separator as `%25`, but tolerate a bare `%` too.
+    let host = percent_decode(host).unwrap_or_else(|_| host.to_owned());
+    let (host, zone) = match host.split_once('%') {
+        Some((host, zone)) => (host, Some(zone)),
+        None => (host.as_str(), None),
+    };
+
+    let mut config = ServerConfig::new(host.parse().map_err(|_| Error::EBADSTR)?);
+    if let Some(zone) = zone {
+        config.set_zone(zone.to_owned());
+    }
+    if let Some(port) = port {
+        config.set_udp_port(port);
+    }
+    for param in query.into_iter().flat_map(|q| q.split('&')) {
+        let (key, value) = param.split_once('=').unwrap_or((param, ""));
+        if key.eq_ignore_ascii_case("tcpport") {
+            config.set_tcp_port(parse_port(value)?);
+        }
+    }
+    Ok(config)
+}
+
+// Parse the legacy `host[:port][%iface]` syntax.
+fn parse_legacy(s: &str) -> Result<ServerConfig> {
+    // The interface either follows the port, or - for an unbracketed IPv6 address - the host.
+    let (hostport, zone) = match s.rfind('%') {
+        Some(pos) if !s[pos..].contains(']') => (&s[..pos], Some(&s[pos + 1..])),
+        _ => (s, None),
+    };
+    let (host, port) = if hostport.starts_with('[') || hostport.matches(':').count() == 1 {
+        split_host_port(hostport)?
+    } else {
+        (hostport, None)
+    };
+    let (host, zone) = match host.split_once('%') {
+        Some((host, inner)) => (host, Some(inner)),
+        None => (host, zone),
+    };
+
+    let mut config = ServerConfig::new(host.parse().map_err(|_| Error::EBADSTR)?);
+    if let Some(zone) = zone {
+        config.set_zone(zone.to_owned());
+    }
+    if let Some(port) = port {
+        config.set_udp_port(port);
+    }
+    Ok(config)
+}
+
+// Split `host[:port]`, where an IPv6 host is enclosed in square brackets.
+fn split_host_port(s: &str) -> Result<(&str, Option<u16>)> {
+    let (host, rest) = match s.strip_prefix('[') {
+        Some(bracketed) => bracketed.split_once(']').ok_or(Error::EBADSTR)?,
+        None => match s.split_once(':') {
+            Some((host, _)) => (host, &s[host.len()..]),
+            None => (s, ""),
+        },
+    };
+    let port = match rest {
+        "" => None,
+        _ => Some(parse_port(rest.strip_prefix(':').ok_or(Error::EBADSTR)?)?),
+    };
+    Ok((host, port))
+}
+
+fn parse_port(s: &str) -> Result<u16> {
+    s.parse().map_err(|_| Error::EBADSTR)
+}
+
+fn percent_decode(s: &str) -> Result<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = tail.get(..2).ok_or(Error::EBADSTR)?;
+            let hex = std::str::from_utf8(hex).map_err(|_| Error::EBADSTR)?;
+            bytes.push(u8::from_str_radix(hex, 16).map_err(|_| Error::EBADSTR)?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| Error::EBADSTR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn format_ipv4() {
+        let config = ServerConfig::new(IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)));
+        assert_eq!(config.to_string(), "dns://8.8.8.8");
+    }
+
+    #[test]
+    fn format_ports() {
+        let mut config = ServerConfig::new(IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)));
+        config.set_udp_port(5353).set_tcp_port(5353);
+        assert_eq!(config.to_string(), "dns://8.8.8.8:5353");
+        config.set_tcp_port(53);
+        assert_eq!(config.to_string(), "dns://8.8.8.8:5353?tcpport=53");
+    }
+
+    #[test]
+    fn format_ipv6_interface() {
+        let mut config = ServerConfig::new("fe80::1".parse().unwrap());
+        config.set_udp_port(53).set_ll_interface("eth0");
+        assert_eq!(config.to_string(), "dns://[fe80::1%25eth0]:53");
+    }
+
+    #[test]
+    fn format_ipv6_scope_id() {
+        let mut config = ServerConfig::new(IpAddr::V6(Ipv6Addr::LOCALHOST));
+        config.set_scope_id(3);
+        assert_eq!(config.to_string(), "dns://[::1%253]");
+    }
+
+    #[test]
+    fn parse_uri_full() {
+        let config: ServerConfig = "dns://[fe80::1%25eth0]:5353?tcpport=53".parse().unwrap();
+        assert_eq!(config.addr(), "fe80::1".parse::<IpAddr>().unwrap());
+        assert_eq!(config.udp_port(), Some(5353));
+        assert_eq!(config.tcp_port(), Some(53));
+        assert_eq!(config.ll_interface(), Some("eth0"));
+        assert_eq!(config.scope_id(), None);
+    }
+
+    #[test]
+    fn parse_uri_scope_id() {
+        let config: ServerConfig = "dns://[fe80::1%253]/".parse().unwrap();
+        assert_eq!(config.scope_id(), Some(3));
+        assert_eq!(config.ll_interface(), None);
+        assert_eq!(config.udp_port(), None);
+    }
+
+    #[test]
+    fn parse_uri_bare_zone() {
+        let config: ServerConfig = "dns://[fe80::1%eth0]".parse().unwrap();
+        assert_eq!(config.ll_interface(), Some("eth0"));
+    }
+
+    #[test]
+    fn parse_legacy_forms() {
+        let config: ServerConfig = "8.8.4.4:53".parse().unwrap();
+        assert_eq!(config.addr(), IpAddr::V4(Ipv4Addr::new(8, 8, 4, 4)));
+        assert_eq!(config.udp_port(), Some(53));
+        assert_eq!(config.tcp_port(), None);
+
+        let config: ServerConfig = "2001:4860:4860::8888".parse().unwrap();
+        assert_eq!(config.udp_port(), None);
+
+        let config: ServerConfig = "[fe80::1]:53%eth0".parse().unwrap();
+        assert_eq!(config.udp_port(), Some(53));
+        assert_eq!(config.ll_interface(), Some("eth0"));
+
+        let config: ServerConfig = "fe80::1%eth0".parse().unwrap();
+        assert_eq!(config.ll_interface(), Some("eth0"));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for s in [
+            "",
+            "dns://",
+            "not-an-address",
+            "dns://8.8.8.8:99999",
+            "dns://[::1",
+            "dns://[::1]x",
+            "dns://8.8.8.8?tcpport=x",
+        ] {
+            assert_eq!(s.parse::<ServerConfig>(), Err(Error::EBADSTR), "{s}");
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut config = ServerConfig::new("fe80::1".parse().unwrap());
+        config
+            .set_udp_port(5353)
+            .set_tcp_port(5354)
+            .set_ll_interface("en0");
+        let parsed: ServerConfig = config.to_string().parse().unwrap();
+        assert_eq!(parsed, config);
+    }
+}
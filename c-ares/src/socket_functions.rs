@@ -0,0 +1,893 @@
+use core::ffi::{c_int, c_uint, c_void};
+use std::io;
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::net::SocketAddr;
+use std::slice;
+use std::sync::Arc;
+
+use bitflags::bitflags;
+use socket2::{Domain, SockAddr, Type};
+
+use crate::error::Result;
+use crate::panic;
+use crate::types::{AddressFamily, SOCKET_BAD, Socket, SocketType};
+use crate::utils::{
+    address_family, socket_addr_from_sockaddr, socket_addr_to_sockaddr, status_to_result,
+};
+
+bitflags!(
+    /// Flags passed to [`SocketFunctions::connect()`].
+    #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+    pub struct SocketConnectFlags: u32 {
+        /// Connect using TCP Fast Open.
+        const TCP_FASTOPEN =
+            c_ares_sys::ares_socket_connect_flags_t::ARES_SOCKET_CONN_TCP_FASTOPEN as u32;
+    }
+);
+
+bitflags!(
+    /// Flags passed to [`SocketFunctions::bind()`].
+    #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+    pub struct SocketBindFlags: u32 {
+        /// The bind is for a TCP connection.
+        const TCP = c_ares_sys::ares_socket_bind_flags_t::ARES_SOCKET_BIND_TCP as u32;
+        /// The bind is for a client connection, not a server.
+        const CLIENT = c_ares_sys::ares_socket_bind_flags_t::ARES_SOCKET_BIND_CLIENT as u32;
+    }
+);
+
+/// A socket option that c-ares asks to have set, via [`SocketFunctions::setsockopt()`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SocketOption<'a> {
+    /// The size of the send buffer (`SO_SNDBUF`).
+    SendBufferSize(usize),
+    /// The size of the receive buffer (`SO_RCVBUF`).
+    RecvBufferSize(usize),
+    /// The name of the network interface to send from (`SO_BINDTODEVICE`).
+    BindDevice(&'a [u8]),
+    /// Whether to use TCP Fast Open.
+    TcpFastOpen(bool),
+}
+
+/// Socket operations that c-ares should use in place of the operating system's own.
+///
+/// Install an implementation with
+/// [`Options::set_socket_functions()`](crate::Options::set_socket_functions) or
+/// [`Channel::set_socket_functions()`](crate::Channel::set_socket_functions).  All traffic on the
+/// channel then goes through these methods, so that queries can be carried over a custom
+/// transport - for instance an in-memory transport in tests.
+///
+/// The "sockets" handed out by [`open()`](Self::open) need not be real operating system sockets,
+/// but c-ares still reports them through the usual socket state callback and
+/// [`Channel::sockets()`](crate::Channel::sockets), and expects to be told when they are readable
+/// or writable via [`Channel::process_fd()`](crate::Channel::process_fd) or similar.  Only
+/// transports whose sockets are real file descriptors - which become readable when there is data
+/// to read - can be used with the c-ares event thread.
+///
+/// Sockets must behave as non-blocking: when there is nothing to read, or no room to write,
+/// return an error of kind [`io::ErrorKind::WouldBlock`].  Errors are reported to c-ares by
+/// setting `errno` (or the last WSA error on Windows): an error carrying a raw OS error code is
+/// passed on as is, otherwise a suitable code is chosen from the error's kind.
+///
+/// If any of these methods panics, the process is aborted.
+pub trait SocketFunctions: Send + Sync + 'static {
+    /// Create a new socket of the given address family and type.
+    fn open(&self, family: AddressFamily, socket_type: SocketType) -> io::Result<Socket>;
+
+    /// Close a socket previously returned by [`open()`](Self::open).
+    fn close(&self, socket: Socket) -> io::Result<()>;
+
+    /// Connect a socket to the given remote address.
+    ///
+    /// For datagram sockets, this fixes the peer that the socket sends to and receives from.
+    ///
+    /// A stream connection that cannot complete immediately should return an error of kind
+    /// [`io::ErrorKind::WouldBlock`], which is reported to c-ares as "in progress"; the socket
+    /// should then become writable once the connection is established.
+    fn connect(
+        &self,
+        socket: Socket,
+        addr: &SocketAddr,
+        flags: SocketConnectFlags,
+    ) -> io::Result<()>;
+
+    /// Receive data from a socket into `buf`.
+    ///
+    /// Returns the number of bytes read, and the address that the data came from.  c-ares
+    /// discards datagrams that do not come from the server that it is talking to, so datagram
+    /// sockets must report the sender's address.
+    fn recvfrom(&self, socket: Socket, buf: &mut [u8]) -> io::Result<(usize, Option<SocketAddr>)>;
+
+    /// Send data on a socket, optionally to the given address.
+    ///
+    /// Returns the number of bytes written.
+    fn sendto(&self, socket: Socket, buf: &[u8], addr: Option<&SocketAddr>) -> io::Result<usize>;
+
+    /// Retrieve the local address of a socket.
+    fn getsockname(&self, socket: Socket) -> io::Result<SocketAddr>;
+
+    /// Bind a socket to the given local address.
+    fn bind(&self, socket: Socket, addr: &SocketAddr, flags: SocketBindFlags) -> io::Result<()>;
+
+    /// Set an option on a socket.
+    ///
+    /// c-ares accepts an error of kind [`io::ErrorKind::Unsupported`] as a decision not to apply
+    /// the option, and carries on without it; any other error fails the connection.  The default
+    /// implementation returns `Unsupported` for every option.
+    fn setsockopt(&self, socket: Socket, option: SocketOption<'_>) -> io::Result<()> {
+        let _ = (socket, option);
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// The operating system's own socket operations.
+///
+/// This is what c-ares uses when no [`SocketFunctions`] are installed.  It is useful as the base
+/// for implementations that only want to observe or adjust traffic, rather than carry it
+/// themselves.
+///
+/// Stream sockets have `TCP_NODELAY` set.  Of the options passed to
+/// [`setsockopt()`](SocketFunctions::setsockopt), TCP Fast Open is not supported, and binding to a
+/// device is supported only on Linux, Android and Fuchsia.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemSocketFunctions;
+
+impl SystemSocketFunctions {
+    // Take ownership of a raw socket.
+    unsafe fn from_raw(socket: Socket) -> socket2::Socket {
+        #[cfg(unix)]
+        let socket = unsafe { <socket2::Socket as std::os::fd::FromRawFd>::from_raw_fd(socket) };
+        #[cfg(windows)]
+        let socket = unsafe {
+            <socket2::Socket as std::os::windows::io::FromRawSocket>::from_raw_socket(socket as _)
+        };
+        socket
+    }
+
+    // Borrow a raw socket, without taking ownership of it.
+    fn with_socket<T>(
+        socket: Socket,
+        f: impl FnOnce(&socket2::Socket) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let socket = ManuallyDrop::new(unsafe { Self::from_raw(socket) });
+        f(&socket)
+    }
+}
+
+impl SocketFunctions for SystemSocketFunctions {
+    fn open(&self, family: AddressFamily, socket_type: SocketType) -> io::Result<Socket> {
+        let domain = match family {
+            AddressFamily::INET => Domain::IPV4,
+            AddressFamily::INET6 => Domain::IPV6,
+            AddressFamily::UNSPEC => return Err(io::ErrorKind::InvalidInput.into()),
+        };
+        let socket = match socket_type {
+            SocketType::Stream => {
+                let socket = socket2::Socket::new(domain, Type::STREAM, None)?;
+                socket.set_tcp_nodelay(true)?;
+                socket
+            }
+            SocketType::Datagram => socket2::Socket::new(domain, Type::DGRAM, None)?,
+        };
+        socket.set_nonblocking(true)?;
+        #[cfg(unix)]
+        let raw = std::os::fd::IntoRawFd::into_raw_fd(socket);
+        #[cfg(windows)]
+        let raw = std::os::windows::io::IntoRawSocket::into_raw_socket(socket) as Socket;
+        Ok(raw)
+    }
+
+    fn close(&self, socket: Socket) -> io::Result<()> {
+        drop(unsafe { Self::from_raw(socket) });
+        Ok(())
+    }
+
+    fn connect(
+        &self,
+        socket: Socket,
+        addr: &SocketAddr,
+        _flags: SocketConnectFlags,
+    ) -> io::Result<()> {
+        Self::with_socket(socket, |socket| {
+            match socket.connect(&SockAddr::from(*addr)) {
+                Err(err) if err.raw_os_error() == Some(errno::EINPROGRESS) => {
+                    Err(io::ErrorKind::WouldBlock.into())
+                }
+                result => result,
+            }
+        })
+    }
+
+    fn recvfrom(&self, socket: Socket, buf: &mut [u8]) -> io::Result<(usize, Option<SocketAddr>)> {
+        // `recv_from()` never writes uninitialized bytes into the buffer.
+        let buf = unsafe { &mut *(std::ptr::from_mut(buf) as *mut [MaybeUninit<u8>]) };
+        Self::with_socket(socket, |socket| {
+            let (read, from) = socket.recv_from(buf)?;
+            Ok((read, from.as_socket()))
+        })
+    }
+
+    fn sendto(&self, socket: Socket, buf: &[u8], addr: Option<&SocketAddr>) -> io::Result<usize> {
+        Self::with_socket(socket, |socket| match addr {
+            Some(addr) => socket.send_to(buf, &SockAddr::from(*addr)),
+            None => socket.send(buf),
+        })
+    }
+
+    fn getsockname(&self, socket: Socket) -> io::Result<SocketAddr> {
+        Self::with_socket(socket, |socket| {
+            socket
+                .local_addr()?
+                .as_socket()
+                .ok_or_else(|| io::ErrorKind::Unsupported.into())
+        })
+    }
+
+    fn bind(&self, socket: Socket, addr: &SocketAddr, _flags: SocketBindFlags) -> io::Result<()> {
+        Self::with_socket(socket, |socket| socket.bind(&SockAddr::from(*addr)))
+    }
+
+    fn setsockopt(&self, socket: Socket, option: SocketOption<'_>) -> io::Result<()> {
+        Self::with_socket(socket, |socket| match option {
+            SocketOption::SendBufferSize(size) => socket.set_send_buffer_size(size),
+            SocketOption::RecvBufferSize(size) => socket.set_recv_buffer_size(size),
+            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+            SocketOption::BindDevice(device) => socket.bind_device(Some(device)),
+            _ => Err(io::ErrorKind::Unsupported.into()),
+        })
+    }
+}
+
+// A `SocketFunctions` implementation, together with the table of trampolines through which c-ares
+// calls it.
+#[derive(Clone)]
+pub(crate) struct SocketFunctionsTable {
+    table: c_ares_sys::ares_socket_functions_ex,
+    funcs: Arc<dyn SocketFunctions>,
+}
+
+impl SocketFunctionsTable {
+    pub(crate) fn new<S: SocketFunctions>(funcs: S) -> Self {
+        let table = c_ares_sys::ares_socket_functions_ex {
+            version: 1,
+            flags: c_ares_sys::ares_sockfunc_flags_t::ARES_SOCKFUNC_FLAG_NONBLOCKING as c_uint,
+            asocket: Some(asocket::<S>),
+            aclose: Some(aclose::<S>),
+            asetsockopt: Some(asetsockopt::<S>),
+            aconnect: Some(aconnect::<S>),
+            arecvfrom: Some(arecvfrom::<S>),
+            asendto: Some(asendto::<S>),
+            agetsockname: Some(agetsockname::<S>),
+            abind: Some(abind::<S>),
+            aif_nametoindex: None,
+            aif_indextoname: None,
+        };
+        let funcs = Arc::new(funcs);
+        Self { table, funcs }
+    }
+
+    // The functions that this table calls.
+    pub(crate) fn functions(&self) -> Arc<dyn SocketFunctions> {
+        Arc::clone(&self.funcs)
+    }
+
+    // Install on a channel.  The caller must keep this table alive for as long as the channel (or
+    // any duplicate of it) might use it.
+    pub(crate) fn install(&self, channel: c_ares_sys::ares_channel) -> Result<()> {
+        let user_data = Arc::as_ptr(&self.funcs).cast::<c_void>().cast_mut();
+        let rc = unsafe {
+            c_ares_sys::ares_set_socket_functions_ex(channel, &raw const self.table, user_data)
+        };
+        status_to_result(rc)
+    }
+}
+
+#[cfg(unix)]
+mod errno {
+    use core::ffi::c_int;
+
+    pub const EWOULDBLOCK: c_int = libc::EWOULDBLOCK;
+    pub const EINPROGRESS: c_int = libc::EINPROGRESS;
+    pub const ECONNREFUSED: c_int = libc::ECONNREFUSED;
+    pub const ECONNRESET: c_int = libc::ECONNRESET;
+    pub const ECONNABORTED: c_int = libc::ECONNABORTED;
+    pub const ENOTCONN: c_int = libc::ENOTCONN;
+    pub const EADDRINUSE: c_int = libc::EADDRINUSE;
+    pub const EADDRNOTAVAIL: c_int = libc::EADDRNOTAVAIL;
+    pub const ETIMEDOUT: c_int = libc::ETIMEDOUT;
+    pub const EINTR: c_int = libc::EINTR;
+    pub const EINVAL: c_int = libc::EINVAL;
+    pub const ENOSYS: c_int = libc::ENOSYS;
+    pub const EIO: c_int = libc::EIO;
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "emscripten",
+        target_os = "fuchsia",
+        target_os = "redox",
+        target_os = "hurd",
+    ))]
+    pub fn set(code: c_int) {
+        unsafe { *libc::__errno_location() = code }
+    }
+
+    #[cfg(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "tvos",
+        target_os = "watchos",
+        target_os = "visionos",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+    ))]
+    pub fn set(code: c_int) {
+        unsafe { *libc::__error() = code }
+    }
+
+    #[cfg(any(
+        target_os = "android",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "solaris",
+        target_os = "illumos",
+    ))]
+    pub fn set(code: c_int) {
+        unsafe { *libc::__errno() = code }
+    }
+}
+
+#[cfg(windows)]
+mod errno {
+    use core::ffi::c_int;
+
+    pub const EWOULDBLOCK: c_int = 10035; // WSAEWOULDBLOCK
+    pub const EINPROGRESS: c_int = 10035; // c-ares treats WSAEWOULDBLOCK as "in progress"
+    pub const ECONNREFUSED: c_int = 10061; // WSAECONNREFUSED
+    pub const ECONNRESET: c_int = 10054; // WSAECONNRESET
+    pub const ECONNABORTED: c_int = 10053; // WSAECONNABORTED
+    pub const ENOTCONN: c_int = 10057; // WSAENOTCONN
+    pub const EADDRINUSE: c_int = 10048; // WSAEADDRINUSE
+    pub const EADDRNOTAVAIL: c_int = 10049; // WSAEADDRNOTAVAIL
+    pub const ETIMEDOUT: c_int = 10060; // WSAETIMEDOUT
+    pub const EINTR: c_int = 10004; // WSAEINTR
+    pub const EINVAL: c_int = 10022; // WSAEINVAL
+    pub const ENOSYS: c_int = 10045; // WSAEOPNOTSUPP
+    pub const EIO: c_int = 10050; // WSAENETDOWN
+
+    #[link(name = "ws2_32")]
+    unsafe extern "system" {
+        fn WSASetLastError(error: c_int);
+    }
+
+    pub fn set(code: c_int) {
+        unsafe { WSASetLastError(code) }
+    }
+}
+
+// Choose the error code to report to c-ares for an `io::Error`.
+fn error_code(err: &io::Error) -> c_int {
+    if let Some(code) = err.raw_os_error() {
+        return code;
+    }
+    match err.kind() {
+        io::ErrorKind::WouldBlock => errno::EWOULDBLOCK,
+        io::ErrorKind::ConnectionRefused => errno::ECONNREFUSED,
+        io::ErrorKind::ConnectionReset => errno::ECONNRESET,
+        io::ErrorKind::ConnectionAborted => errno::ECONNABORTED,
+        io::ErrorKind::NotConnected => errno::ENOTCONN,
+        io::ErrorKind::AddrInUse => errno::EADDRINUSE,
+        io::ErrorKind::AddrNotAvailable => errno::EADDRNOTAVAIL,
+        io::ErrorKind::TimedOut => errno::ETIMEDOUT,
+        io::ErrorKind::Interrupted => errno::EINTR,
+        io::ErrorKind::InvalidInput => errno::EINVAL,
+        io::ErrorKind::Unsupported => errno::ENOSYS,
+        _ => errno::EIO,
+    }
+}
+
+// Report an error to c-ares, returning the conventional failure value.
+fn fail(err: &io::Error) -> c_int {
+    errno::set(error_code(err));
+    -1
+}
+
+fn status(result: io::Result<()>) -> c_int {
+    match result {
+        Ok(()) => 0,
+        Err(err) => fail(&err),
+    }
+}
+
+unsafe extern "C" fn asocket<S: SocketFunctions>(
+    domain: c_int,
+    socket_type: c_int,
+    _protocol: c_int,
+    user_data: *mut c_void,
+) -> c_ares_sys::ares_socket_t {
+    let funcs = unsafe { &*user_data.cast::<S>() };
+    panic::abort_on_panic(|| {
+        let family = address_family(domain as c_types::ADDRESS_FAMILY);
+        let result = match (family, SocketType::from_raw(socket_type)) {
+            (Some(family), Some(socket_type)) => funcs.open(family, socket_type),
+            _ => Err(io::ErrorKind::InvalidInput.into()),
+        };
+        result.unwrap_or_else(|err| {
+            fail(&err);
+            SOCKET_BAD
+        })
+    })
+}
+
+unsafe extern "C" fn aclose<S: SocketFunctions>(
+    sock: c_ares_sys::ares_socket_t,
+    user_data: *mut c_void,
+) -> c_int {
+    let funcs = unsafe { &*user_data.cast::<S>() };
+    panic::abort_on_panic(|| status(funcs.close(sock)))
+}
+
+unsafe extern "C" fn asetsockopt<S: SocketFunctions>(
+    sock: c_ares_sys::ares_socket_t,
+    opt: c_ares_sys::ares_socket_opt_t,
+    val: *const c_void,
+    val_size: c_ares_sys::ares_socklen_t,
+    user_data: *mut c_void,
+) -> c_int {
+    let funcs = unsafe { &*user_data.cast::<S>() };
+    let val = if val.is_null() {
+        &[]
+    } else {
+        unsafe { slice::from_raw_parts(val.cast::<u8>(), val_size as usize) }
+    };
+    panic::abort_on_panic(|| {
+        let Some(option) = socket_option(opt, val) else {
+            return fail(&io::ErrorKind::InvalidInput.into());
+        };
+        status(funcs.setsockopt(sock, option))
+    })
+}
+
+// Interpret the value that c-ares passes for a socket option: an `int` for sizes, an
+// `ares_bool_t` for flags, and a NUL-padded string for the device name.
+fn socket_option(opt: c_ares_sys::ares_socket_opt_t, val: &[u8]) -> Option<SocketOption<'_>> {
+    let int = || val.try_into().ok().map(c_int::from_ne_bytes);
+    let option = match opt {
+        c_ares_sys::ares_socket_opt_t::ARES_SOCKET_OPT_SENDBUF_SIZE => {
+            SocketOption::SendBufferSize(usize::try_from(int()?).ok()?)
+        }
+        c_ares_sys::ares_socket_opt_t::ARES_SOCKET_OPT_RECVBUF_SIZE => {
+            SocketOption::RecvBufferSize(usize::try_from(int()?).ok()?)
+        }
+        c_ares_sys::ares_socket_opt_t::ARES_SOCKET_OPT_BIND_DEVICE => {
+            let len = val.iter().position(|&b| b == 0).unwrap_or(val.len());
+            SocketOption::BindDevice(&val[..len])
+        }
+        c_ares_sys::ares_socket_opt_t::ARES_SOCKET_OPT_TCP_FASTOPEN => {
+            SocketOption::TcpFastOpen(int()? != 0)
+        }
+    };
+    Some(option)
+}
+
+unsafe extern "C" fn aconnect<S: SocketFunctions>(
+    sock: c_ares_sys::ares_socket_t,
+    address: *const c_types::sockaddr,
+    address_len: c_ares_sys::ares_socklen_t,
+    flags: c_uint,
+    user_data: *mut c_void,
+) -> c_int {
+    let funcs = unsafe { &*user_data.cast::<S>() };
+    let addr = unsafe { socket_addr_from_sockaddr(address, address_len as usize) };
+    panic::abort_on_panic(|| {
+        let Some(addr) = addr else {
+            return fail(&io::ErrorKind::InvalidInput.into());
+        };
+        let flags = SocketConnectFlags::from_bits_truncate(flags);
+        match funcs.connect(sock, &addr, flags) {
+            Ok(()) => 0,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                errno::set(errno::EINPROGRESS);
+                -1
+            }
+            Err(err) => fail(&err),
+        }
+    })
+}
+
+unsafe extern "C" fn arecvfrom<S: SocketFunctions>(
+    sock: c_ares_sys::ares_socket_t,
+    buffer: *mut c_void,
+    length: usize,
+    _flags: c_int,
+    address: *mut c_types::sockaddr,
+    address_len: *mut c_ares_sys::ares_socklen_t,
+    user_data: *mut c_void,
+) -> c_ares_sys::ares_ssize_t {
+    let funcs = unsafe { &*user_data.cast::<S>() };
+    let buf = unsafe { slice::from_raw_parts_mut(buffer.cast::<u8>(), length) };
+    panic::abort_on_panic(|| match funcs.recvfrom(sock, buf) {
+        Ok((read, from)) => {
+            if !address.is_null() && !address_len.is_null() {
+                match from {
+                    Some(from) => unsafe { socket_addr_to_sockaddr(&from, address, address_len) },
+                    None => unsafe { *address_len = 0 },
+                }
+            }
+            read as c_ares_sys::ares_ssize_t
+        }
+        Err(err) => fail(&err) as c_ares_sys::ares_ssize_t,
+    })
+}
+
+unsafe extern "C" fn asendto<S: SocketFunctions>(
+    sock: c_ares_sys::ares_socket_t,
+    buffer: *const c_void,
+    length: usize,
+    _flags: c_int,
+    address: *const c_types::sockaddr,
+    address_len: c_ares_sys::ares_socklen_t,
+    user_data: *mut c_void,
+) -> c_ares_sys::ares_ssize_t {
+    let funcs = unsafe { &*user_data.cast::<S>() };
+    let buf = unsafe { slice::from_raw_parts(buffer.cast::<u8>(), length) };
+    let addr = unsafe { socket_addr_from_sockaddr(address, address_len as usize) };
+    panic::abort_on_panic(|| match funcs.sendto(sock, buf, addr.as_ref()) {
+        Ok(written) => written as c_ares_sys::ares_ssize_t,
+        Err(err) => fail(&err) as c_ares_sys::ares_ssize_t,
+    })
+}
+
+unsafe extern "C" fn agetsockname<S: SocketFunctions>(
+    sock: c_ares_sys::ares_socket_t,
+    address: *mut c_types::sockaddr,
+    address_len: *mut c_ares_sys::ares_socklen_t,
+    user_data: *mut c_void,
+) -> c_int {
+    let funcs = unsafe { &*user_data.cast::<S>() };
+    panic::abort_on_panic(|| match funcs.getsockname(sock) {
+        Ok(addr) => {
+            unsafe { socket_addr_to_sockaddr(&addr, address, address_len) };
+            0
+        }
+        Err(err) => fail(&err),
+    })
+}
+
+unsafe extern "C" fn abind<S: SocketFunctions>(
+    sock: c_ares_sys::ares_socket_t,
+    flags: c_uint,
+    address: *const c_types::sockaddr,
+    address_len: c_types::socklen_t,
+    user_data: *mut c_void,
+) -> c_int {
+    let funcs = unsafe { &*user_data.cast::<S>() };
+    let addr = unsafe { socket_addr_from_sockaddr(address, address_len as usize) };
+    panic::abort_on_panic(|| {
+        let Some(addr) = addr else {
+            return fail(&io::ErrorKind::InvalidInput.into());
+        };
+        let flags = SocketBindFlags::from_bits_truncate(flags);
+        status(funcs.bind(sock, &addr, flags))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6};
+    use std::sync::Mutex;
+
+    use crate::{Channel, Options};
+
+    use crate::types::SOCK_STREAM;
+    use crate::utils::{socket_addrv4_as_sockaddr_in, socket_addrv6_as_sockaddr_in6};
+
+    // Records the calls made to it, and answers with canned results.
+    #[derive(Default)]
+    struct Recorder {
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Recorder {
+        fn record(&self, call: String) {
+            self.calls.lock().unwrap().push(call);
+        }
+    }
+
+    impl SocketFunctions for Recorder {
+        fn open(&self, family: AddressFamily, socket_type: SocketType) -> io::Result<Socket> {
+            self.record(format!("open {family:?} {socket_type:?}"));
+            Ok(7)
+        }
+
+        fn close(&self, socket: Socket) -> io::Result<()> {
+            self.record(format!("close {socket}"));
+            Ok(())
+        }
+
+        fn connect(
+            &self,
+            socket: Socket,
+            addr: &SocketAddr,
+            flags: SocketConnectFlags,
+        ) -> io::Result<()> {
+            self.record(format!("connect {socket} {addr} {flags:?}"));
+            Err(io::ErrorKind::WouldBlock.into())
+        }
+
+        fn recvfrom(
+            &self,
+            socket: Socket,
+            buf: &mut [u8],
+        ) -> io::Result<(usize, Option<SocketAddr>)> {
+            self.record(format!("recvfrom {socket} {}", buf.len()));
+            buf[..3].copy_from_slice(b"abc");
+            let from = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(192, 0, 2, 1), 53));
+            Ok((3, Some(from)))
+        }
+
+        fn sendto(
+            &self,
+            socket: Socket,
+            buf: &[u8],
+            addr: Option<&SocketAddr>,
+        ) -> io::Result<usize> {
+            self.record(format!("sendto {socket} {} {addr:?}", buf.len()));
+            Ok(buf.len())
+        }
+
+        fn getsockname(&self, _socket: Socket) -> io::Result<SocketAddr> {
+            Ok(SocketAddr::V6(SocketAddrV6::new(
+                Ipv6Addr::LOCALHOST,
+                5353,
+                0,
+                0,
+            )))
+        }
+
+        fn bind(
+            &self,
+            _socket: Socket,
+            _addr: &SocketAddr,
+            _flags: SocketBindFlags,
+        ) -> io::Result<()> {
+            Err(io::ErrorKind::AddrInUse.into())
+        }
+    }
+
+    fn user_data(recorder: &Recorder) -> *mut c_void {
+        std::ptr::from_ref(recorder).cast_mut().cast()
+    }
+
+    #[test]
+    fn table_is_complete() {
+        let table = SocketFunctionsTable::new(Recorder::default()).table;
+        assert_eq!(table.version, 1);
+        assert!(table.asocket.is_some());
+        assert!(table.aclose.is_some());
+        assert!(table.asetsockopt.is_some());
+        assert!(table.aconnect.is_some());
+        assert!(table.arecvfrom.is_some());
+        assert!(table.asendto.is_some());
+        assert!(table.agetsockname.is_some());
+        assert!(table.abind.is_some());
+    }
+
+    #[test]
+    fn open_and_close() {
+        let recorder = Recorder::default();
+        let data = user_data(&recorder);
+        let socket =
+            unsafe { asocket::<Recorder>(c_types::AF_INET as c_int, SOCK_STREAM, 0, data) };
+        assert_eq!(socket, 7);
+        assert_eq!(unsafe { aclose::<Recorder>(socket, data) }, 0);
+        let calls = recorder.calls.lock().unwrap();
+        assert_eq!(*calls, ["open INET Stream", "close 7"]);
+    }
+
+    #[test]
+    fn open_bad_type() {
+        let recorder = Recorder::default();
+        let socket =
+            unsafe { asocket::<Recorder>(c_types::AF_INET as c_int, -1, 0, user_data(&recorder)) };
+        assert_eq!(socket, SOCKET_BAD);
+        assert!(recorder.calls.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn connect_in_progress() {
+        let recorder = Recorder::default();
+        let addr = SocketAddrV4::new(Ipv4Addr::new(192, 0, 2, 1), 53);
+        let sockaddr = socket_addrv4_as_sockaddr_in(&addr);
+        let rc = unsafe {
+            aconnect::<Recorder>(
+                3,
+                std::ptr::from_ref(&sockaddr).cast(),
+                std::mem::size_of_val(&sockaddr) as c_ares_sys::ares_socklen_t,
+                0,
+                user_data(&recorder),
+            )
+        };
+        assert_eq!(rc, -1);
+        assert_eq!(
+            io::Error::last_os_error().raw_os_error(),
+            Some(errno::EINPROGRESS)
+        );
+        let calls = recorder.calls.lock().unwrap();
+        assert_eq!(*calls, ["connect 3 192.0.2.1:53 SocketConnectFlags(0x0)"]);
+    }
+
+    #[test]
+    fn recvfrom_reports_sender() {
+        let recorder = Recorder::default();
+        let mut buf = [0u8; 16];
+        let mut from: c_types::sockaddr_in6 = unsafe { std::mem::zeroed() };
+        let mut from_len = std::mem::size_of_val(&from) as c_ares_sys::ares_socklen_t;
+        let read = unsafe {
+            arecvfrom::<Recorder>(
+                3,
+                buf.as_mut_ptr().cast(),
+                buf.len(),
+                0,
+                std::ptr::from_mut(&mut from).cast(),
+                &raw mut from_len,
+                user_data(&recorder),
+            )
+        };
+        assert_eq!(read, 3);
+        assert_eq!(&buf[..3], b"abc");
+        let from = unsafe {
+            socket_addr_from_sockaddr(std::ptr::from_ref(&from).cast(), from_len as usize)
+        };
+        assert_eq!(from, Some("192.0.2.1:53".parse().unwrap()));
+    }
+
+    #[test]
+    fn sendto_without_address() {
+        let recorder = Recorder::default();
+        let buf = [0u8; 12];
+        let written = unsafe {
+            asendto::<Recorder>(
+                3,
+                buf.as_ptr().cast(),
+                buf.len(),
+                0,
+                std::ptr::null(),
+                0,
+                user_data(&recorder),
+            )
+        };
+        assert_eq!(written, 12);
+        let calls = recorder.calls.lock().unwrap();
+        assert_eq!(*calls, ["sendto 3 12 None"]);
+    }
+
+    #[test]
+    fn getsockname_round_trip() {
+        let recorder = Recorder::default();
+        let mut addr: c_types::sockaddr_in6 = unsafe { std::mem::zeroed() };
+        let mut addr_len = std::mem::size_of_val(&addr) as c_ares_sys::ares_socklen_t;
+        let rc = unsafe {
+            agetsockname::<Recorder>(
+                3,
+                std::ptr::from_mut(&mut addr).cast(),
+                &raw mut addr_len,
+                user_data(&recorder),
+            )
+        };
+        assert_eq!(rc, 0);
+        let expected = SocketAddrV6::new(Ipv6Addr::LOCALHOST, 5353, 0, 0);
+        let expected_sockaddr = socket_addrv6_as_sockaddr_in6(&expected);
+        assert_eq!(addr_len as usize, std::mem::size_of_val(&expected_sockaddr));
+        let addr = unsafe {
+            socket_addr_from_sockaddr(std::ptr::from_ref(&addr).cast(), addr_len as usize)
+        };
+        assert_eq!(addr, Some(SocketAddr::V6(expected)));
+    }
+
+    #[test]
+    fn setsockopt_defaults_to_unsupported() {
+        let recorder = Recorder::default();
+        let size: c_int = 4096;
+        let rc = unsafe {
+            asetsockopt::<Recorder>(
+                3,
+                c_ares_sys::ares_socket_opt_t::ARES_SOCKET_OPT_SENDBUF_SIZE,
+                std::ptr::from_ref(&size).cast(),
+                std::mem::size_of_val(&size) as c_ares_sys::ares_socklen_t,
+                user_data(&recorder),
+            )
+        };
+        assert_eq!(rc, -1);
+        assert_eq!(
+            io::Error::last_os_error().raw_os_error(),
+            Some(errno::ENOSYS)
+        );
+    }
+
+    #[test]
+    fn socket_option_values() {
+        use c_ares_sys::ares_socket_opt_t::*;
+        let size = 4096_i32.to_ne_bytes();
+        assert_eq!(
+            socket_option(ARES_SOCKET_OPT_RECVBUF_SIZE, &size),
+            Some(SocketOption::RecvBufferSize(4096))
+        );
+        let negative = (-1_i32).to_ne_bytes();
+        assert_eq!(socket_option(ARES_SOCKET_OPT_SENDBUF_SIZE, &negative), None);
+        assert_eq!(socket_option(ARES_SOCKET_OPT_SENDBUF_SIZE, &[1, 2]), None);
+        assert_eq!(
+            socket_option(ARES_SOCKET_OPT_BIND_DEVICE, b"eth0\0\0\0\0"),
+            Some(SocketOption::BindDevice(b"eth0"))
+        );
+        assert_eq!(
+            socket_option(ARES_SOCKET_OPT_TCP_FASTOPEN, &1_i32.to_ne_bytes()),
+            Some(SocketOption::TcpFastOpen(true))
+        );
+    }
+
+    #[test]
+    fn system_setsockopt_applies_buffer_size() {
+        let funcs = SystemSocketFunctions;
+        let socket = funcs
+            .open(AddressFamily::INET, SocketType::Datagram)
+            .unwrap();
+        funcs
+            .setsockopt(socket, SocketOption::RecvBufferSize(65536))
+            .unwrap();
+        let size = SystemSocketFunctions::with_socket(socket, socket2::Socket::recv_buffer_size);
+        assert!(size.unwrap() >= 65536);
+        let result = funcs.setsockopt(socket, SocketOption::TcpFastOpen(true));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Unsupported);
+        funcs.close(socket).unwrap();
+    }
+
+    #[test]
+    fn error_codes() {
+        assert_eq!(
+            error_code(&io::ErrorKind::WouldBlock.into()),
+            errno::EWOULDBLOCK
+        );
+        assert_eq!(
+            error_code(&io::ErrorKind::AddrInUse.into()),
+            errno::EADDRINUSE
+        );
+        assert_eq!(error_code(&io::ErrorKind::Other.into()), errno::EIO);
+        assert_eq!(error_code(&io::Error::from_raw_os_error(42)), 42);
+    }
+
+    #[test]
+    fn channel_uses_socket_functions() {
+        let recorder = Recorder::default();
+        let calls = Arc::clone(&recorder.calls);
+        let mut channel = Channel::new().unwrap();
+        channel
+            .set_socket_functions(recorder)
+            .unwrap()
+            .set_servers(["192.0.2.1"])
+            .unwrap();
+        channel.query_a("example.com", |_result| {});
+        assert_eq!(
+            calls.lock().unwrap().first().map(String::as_str),
+            Some("open INET Datagram")
+        );
+    }
+
+    #[test]
+    fn options_socket_functions_survive_clone() {
+        let recorder = Recorder::default();
+        let calls = Arc::clone(&recorder.calls);
+        let mut options = Options::new();
+        options.set_socket_functions(recorder);
+        let channel = Channel::with_options(options).unwrap();
+        let mut cloned = channel.try_clone().unwrap();
+        drop(channel);
+        cloned.set_servers(["192.0.2.1"]).unwrap();
+        cloned.query_a("example.com", |_result| {});
+        assert_eq!(
+            calls.lock().unwrap().first().map(String::as_str),
+            Some("open INET Datagram")
+        );
+    }
+}
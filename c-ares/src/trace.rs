@@ -0,0 +1,448 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use crate::dns::{DnsParseFlags, DnsRecord};
+use crate::error::Result;
+use crate::socket_functions::{SocketBindFlags, SocketConnectFlags, SocketFunctions, SocketOption};
+use crate::types::{AddressFamily, Socket, SocketType};
+
+pub(crate) type TraceCallback = dyn Fn(&TraceEvent<'_>) + Send + Sync + 'static;
+
+/// Whether a traced message was sent to a server, or received from one.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+pub enum TraceDirection {
+    /// The message was sent to the server.
+    Sent,
+
+    /// The message was received from the server.
+    Received,
+}
+
+/// A DNS message sent to, or received from, a server.
+///
+/// These are reported to the callback installed by
+/// [`Channel::set_trace_callback()`](crate::Channel::set_trace_callback) or
+/// [`Options::set_trace_callback()`](crate::Options::set_trace_callback).
+#[derive(Clone, Debug)]
+pub struct TraceEvent<'a> {
+    direction: TraceDirection,
+    server: SocketAddr,
+    transport: SocketType,
+    timestamp: SystemTime,
+    message: &'a [u8],
+}
+
+impl<'a> TraceEvent<'a> {
+    /// Whether the message was sent or received.
+    pub fn direction(&self) -> TraceDirection {
+        self.direction
+    }
+
+    /// The address of the server that the message was sent to, or received from.
+    pub fn server(&self) -> SocketAddr {
+        self.server
+    }
+
+    /// The transport that carried the message: `Datagram` for UDP, `Stream` for TCP.
+    pub fn transport(&self) -> SocketType {
+        self.transport
+    }
+
+    /// When the message was sent or received.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    /// The id of the query that the message belongs to, as given in its header.
+    pub fn qid(&self) -> u16 {
+        match self.message {
+            [high, low, ..] => u16::from_be_bytes([*high, *low]),
+            _ => 0,
+        }
+    }
+
+    /// The message in wire format.  For TCP, this excludes the two-byte length prefix.
+    pub fn message(&self) -> &'a [u8] {
+        self.message
+    }
+
+    /// Parse the message.
+    pub fn record(&self) -> Result<DnsRecord> {
+        DnsRecord::parse(self.message, DnsParseFlags::empty())
+    }
+}
+
+// What we know about a socket that c-ares has opened.
+struct SocketTrace {
+    transport: SocketType,
+    peer: Option<SocketAddr>,
+
+    // Stream data that does not yet make up a whole message.
+    sent: Vec<u8>,
+    received: Vec<u8>,
+}
+
+// The sockets that are being traced.  These outlive any one `TracingSocketFunctions`, so that
+// replacing the trace callback or the socket functions carries on tracing open sockets.
+#[derive(Default)]
+pub(crate) struct TracedSockets(Mutex<HashMap<Socket, SocketTrace>>);
+
+impl TracedSockets {
+    fn lock(&self) -> MutexGuard<'_, HashMap<Socket, SocketTrace>> {
+        self.0.lock().unwrap()
+    }
+}
+
+// Socket functions that report every message passing through them, and otherwise defer to `inner`.
+pub(crate) struct TracingSocketFunctions {
+    inner: Arc<dyn SocketFunctions>,
+    callback: Arc<TraceCallback>,
+    sockets: Arc<TracedSockets>,
+}
+
+impl TracingSocketFunctions {
+    pub(crate) fn new(
+        inner: Arc<dyn SocketFunctions>,
+        callback: Arc<TraceCallback>,
+        sockets: Arc<TracedSockets>,
+    ) -> Self {
+        Self {
+            inner,
+            callback,
+            sockets,
+        }
+    }
+
+    // Report the messages made up by `data`, which has just been sent or received on `socket`.
+    fn trace(
+        &self,
+        socket: Socket,
+        direction: TraceDirection,
+        data: &[u8],
+        addr: Option<SocketAddr>,
+    ) {
+        let timestamp = SystemTime::now();
+        let (transport, server, messages) = {
+            let mut sockets = self.sockets.lock();
+            let Some(trace) = sockets.get_mut(&socket) else {
+                return;
+            };
+            let Some(server) = trace.peer.or(addr) else {
+                return;
+            };
+            let messages = match trace.transport {
+                SocketType::Datagram => vec![data.to_vec()],
+                SocketType::Stream => {
+                    let buffer = match direction {
+                        TraceDirection::Sent => &mut trace.sent,
+                        TraceDirection::Received => &mut trace.received,
+                    };
+                    buffer.extend_from_slice(data);
+                    take_stream_messages(buffer)
+                }
+            };
+            (trace.transport, server, messages)
+        };
+        for message in &messages {
+            let event = TraceEvent {
+                direction,
+                server,
+                transport,
+                timestamp,
+                message,
+            };
+            (self.callback)(&event);
+        }
+    }
+}
+
+// Splits whole length-prefixed messages off the front of a stream.
+fn take_stream_messages(buffer: &mut Vec<u8>) -> Vec<Vec<u8>> {
+    let mut messages = Vec::new();
+    let mut start = 0;
+    while let Some(&[high, low]) = buffer.get(start..start + 2) {
+        let end = start + 2 + usize::from(u16::from_be_bytes([high, low]));
+        let Some(message) = buffer.get(start + 2..end) else {
+            break;
+        };
+        messages.push(message.to_vec());
+        start = end;
+    }
+    buffer.drain(..start);
+    messages
+}
+
+impl SocketFunctions for TracingSocketFunctions {
+    fn open(&self, family: AddressFamily, socket_type: SocketType) -> io::Result<Socket> {
+        let socket = self.inner.open(family, socket_type)?;
+        let trace = SocketTrace {
+            transport: socket_type,
+            peer: None,
+            sent: Vec::new(),
+            received: Vec::new(),
+        };
+        self.sockets.lock().insert(socket, trace);
+        Ok(socket)
+    }
+
+    fn close(&self, socket: Socket) -> io::Result<()> {
+        self.sockets.lock().remove(&socket);
+        self.inner.close(socket)
+    }
+
+    fn connect(
+        &self,
+        socket: Socket,
+        addr: &SocketAddr,
+        flags: SocketConnectFlags,
+    ) -> io::Result<()> {
+        if let Some(trace) = self.sockets.lock().get_mut(&socket) {
+            trace.peer = Some(*addr);
+        }
+        self.inner.connect(socket, addr, flags)
+    }
+
+    fn recvfrom(&self, socket: Socket, buf: &mut [u8]) -> io::Result<(usize, Option<SocketAddr>)> {
+        let (read, from) = self.inner.recvfrom(socket, buf)?;
+        if read > 0 {
+            self.trace(socket, TraceDirection::Received, &buf[..read], from);
+        }
+        Ok((read, from))
+    }
+
+    fn sendto(&self, socket: Socket, buf: &[u8], addr: Option<&SocketAddr>) -> io::Result<usize> {
+        let written = self.inner.sendto(socket, buf, addr)?;
+        if written > 0 {
+            self.trace(socket, TraceDirection::Sent, &buf[..written], addr.copied());
+        }
+        Ok(written)
+    }
+
+    fn getsockname(&self, socket: Socket) -> io::Result<SocketAddr> {
+        self.inner.getsockname(socket)
+    }
+
+    fn bind(&self, socket: Socket, addr: &SocketAddr, flags: SocketBindFlags) -> io::Result<()> {
+        self.inner.bind(socket, addr, flags)
+    }
+
+    fn setsockopt(&self, socket: Socket, option: SocketOption<'_>) -> io::Result<()> {
+        self.inner.setsockopt(socket, option)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    // Accepts everything that is sent, and replays canned data on receive.
+    #[derive(Default)]
+    struct Loopback {
+        incoming: Mutex<VecDeque<Vec<u8>>>,
+    }
+
+    impl SocketFunctions for Loopback {
+        fn open(&self, _family: AddressFamily, _socket_type: SocketType) -> io::Result<Socket> {
+            Ok(3)
+        }
+
+        fn close(&self, _socket: Socket) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn connect(
+            &self,
+            _socket: Socket,
+            _addr: &SocketAddr,
+            _flags: SocketConnectFlags,
+        ) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn recvfrom(
+            &self,
+            _socket: Socket,
+            buf: &mut [u8],
+        ) -> io::Result<(usize, Option<SocketAddr>)> {
+            let data = self
+                .incoming
+                .lock()
+                .unwrap()
+                .pop_front()
+                .ok_or(io::ErrorKind::WouldBlock)?;
+            buf[..data.len()].copy_from_slice(&data);
+            Ok((data.len(), None))
+        }
+
+        fn sendto(
+            &self,
+            _socket: Socket,
+            buf: &[u8],
+            _addr: Option<&SocketAddr>,
+        ) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn getsockname(&self, _socket: Socket) -> io::Result<SocketAddr> {
+            Err(io::ErrorKind::Unsupported.into())
+        }
+
+        fn bind(
+            &self,
+            _socket: Socket,
+            _addr: &SocketAddr,
+            _flags: SocketBindFlags,
+        ) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    type Seen = Arc<Mutex<Vec<(TraceDirection, SocketAddr, SocketType, Vec<u8>)>>>;
+
+    fn tracing(incoming: &[&[u8]]) -> (TracingSocketFunctions, Seen) {
+        let loopback = Loopback {
+            incoming: Mutex::new(incoming.iter().map(|data| data.to_vec()).collect()),
+        };
+        retracing(Arc::new(loopback), Arc::default())
+    }
+
+    // Trace `inner`, carrying on from whatever is known about `sockets`.
+    fn retracing(
+        inner: Arc<dyn SocketFunctions>,
+        sockets: Arc<TracedSockets>,
+    ) -> (TracingSocketFunctions, Seen) {
+        let seen: Seen = Arc::default();
+        let events = Arc::clone(&seen);
+        let callback = move |event: &TraceEvent<'_>| {
+            events.lock().unwrap().push((
+                event.direction(),
+                event.server(),
+                event.transport(),
+                event.message().to_vec(),
+            ));
+        };
+        let funcs = TracingSocketFunctions::new(inner, Arc::new(callback), sockets);
+        (funcs, seen)
+    }
+
+    fn server() -> SocketAddr {
+        "192.0.2.1:53".parse().unwrap()
+    }
+
+    #[test]
+    fn datagrams() {
+        let (funcs, seen) = tracing(&[b"\x12\x34reply"]);
+        let socket = funcs
+            .open(AddressFamily::INET, SocketType::Datagram)
+            .unwrap();
+        funcs
+            .connect(socket, &server(), SocketConnectFlags::empty())
+            .unwrap();
+        funcs.sendto(socket, b"\x12\x34query", None).unwrap();
+        let mut buf = [0; 64];
+        funcs.recvfrom(socket, &mut buf).unwrap();
+        let seen = seen.lock().unwrap();
+        assert_eq!(
+            *seen,
+            [
+                (
+                    TraceDirection::Sent,
+                    server(),
+                    SocketType::Datagram,
+                    b"\x12\x34query".to_vec()
+                ),
+                (
+                    TraceDirection::Received,
+                    server(),
+                    SocketType::Datagram,
+                    b"\x12\x34reply".to_vec()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn stream_reassembly() {
+        let (funcs, seen) = tracing(&[b"\x00\x03ab", b"c\x00\x02de\x00", b"\x01f"]);
+        let socket = funcs.open(AddressFamily::INET, SocketType::Stream).unwrap();
+        funcs
+            .connect(socket, &server(), SocketConnectFlags::empty())
+            .unwrap();
+        funcs.sendto(socket, b"\x00\x02", None).unwrap();
+        funcs.sendto(socket, b"xy", None).unwrap();
+        let mut buf = [0; 64];
+        while funcs.recvfrom(socket, &mut buf).is_ok() {}
+        let messages: Vec<_> = seen
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(direction, _, transport, message)| {
+                assert_eq!(*transport, SocketType::Stream);
+                (*direction, message.clone())
+            })
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (TraceDirection::Sent, b"xy".to_vec()),
+                (TraceDirection::Received, b"abc".to_vec()),
+                (TraceDirection::Received, b"de".to_vec()),
+                (TraceDirection::Received, b"f".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_sockets_are_ignored() {
+        let (funcs, seen) = tracing(&[]);
+        funcs.sendto(9, b"\x12\x34query", None).unwrap();
+        let socket = funcs.open(AddressFamily::INET, SocketType::Stream).unwrap();
+        funcs.close(socket).unwrap();
+        funcs.sendto(socket, b"\x00\x01x", None).unwrap();
+        assert!(seen.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn qid() {
+        let event = TraceEvent {
+            direction: TraceDirection::Sent,
+            server: server(),
+            transport: SocketType::Datagram,
+            timestamp: SystemTime::now(),
+            message: b"\xab\xcd\x01\x00",
+        };
+        assert_eq!(event.qid(), 0xabcd);
+        let short = TraceEvent {
+            message: b"\xab",
+            ..event
+        };
+        assert_eq!(short.qid(), 0);
+    }
+
+    #[test]
+    fn open_sockets_survive_retracing() {
+        let (first, first_seen) = tracing(&[]);
+        let socket = first
+            .open(AddressFamily::INET, SocketType::Datagram)
+            .unwrap();
+        first
+            .connect(socket, &server(), SocketConnectFlags::empty())
+            .unwrap();
+        let (second, second_seen) = retracing(Arc::clone(&first.inner), Arc::clone(&first.sockets));
+        second.sendto(socket, b"\x12\x34query", None).unwrap();
+        assert!(first_seen.lock().unwrap().is_empty());
+        assert_eq!(
+            *second_seen.lock().unwrap(),
+            [(
+                TraceDirection::Sent,
+                server(),
+                SocketType::Datagram,
+                b"\x12\x34query".to_vec()
+            )]
+        );
+    }
+}
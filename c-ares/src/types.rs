@@ -1,3 +1,5 @@
+use core::ffi::c_int;
+
 /// The platform-specific file descriptor / socket type.  That is, either a `RawFd` or a
 /// `RawSocket`.
 pub type Socket = c_ares_sys::ares_socket_t;
@@ -19,6 +21,37 @@ pub enum AddressFamily {
     UNSPEC = c_types::AF_UNSPEC as isize,
 }
 
+/// Socket types.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+pub enum SocketType {
+    /// A stream (TCP) socket.
+    Stream,
+
+    /// A datagram (UDP) socket.
+    Datagram,
+}
+
+#[cfg(unix)]
+pub(crate) const SOCK_STREAM: c_int = libc::SOCK_STREAM;
+#[cfg(unix)]
+pub(crate) const SOCK_DGRAM: c_int = libc::SOCK_DGRAM;
+
+#[cfg(windows)]
+pub(crate) const SOCK_STREAM: c_int = 1;
+#[cfg(windows)]
+pub(crate) const SOCK_DGRAM: c_int = 2;
+
+impl SocketType {
+    // Convert the `type` argument that c-ares passes to its socket hooks.
+    pub(crate) fn from_raw(socket_type: c_int) -> Option<Self> {
+        match socket_type {
+            SOCK_STREAM => Some(SocketType::Stream),
+            SOCK_DGRAM => Some(SocketType::Datagram),
+            _ => None,
+        }
+    }
+}
+
 // See arpa/nameser.h
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
 #[allow(clippy::upper_case_acronyms)]
@@ -95,6 +128,13 @@ mod tests {
         assert_eq!(bad, SOCKET_BAD);
     }
 
+    #[test]
+    fn socket_type_from_raw() {
+        assert_eq!(SocketType::from_raw(SOCK_STREAM), Some(SocketType::Stream));
+        assert_eq!(SocketType::from_raw(SOCK_DGRAM), Some(SocketType::Datagram));
+        assert_eq!(SocketType::from_raw(-1), None);
+    }
+
     #[test]
     fn address_family_values() {
         assert_ne!(AddressFamily::INET as isize, AddressFamily::INET6 as isize);
@@ -4,7 +4,11 @@ use crate::types::AddressFamily;
 use core::ffi::{c_char, c_int};
 use std::ffi::CStr;
 use std::mem;
+#[cfg(cares1_34)]
+use std::net::SocketAddr;
 use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6};
+#[cfg(cares1_34)]
+use std::ptr;
 use std::str;
 
 // Convert an address family into a more strongly typed AddressFamily.
@@ -181,6 +185,74 @@ pub fn sockaddr_in6_scope_id(sa: &c_types::sockaddr_in6) -> u32 {
     unsafe { sa.Anonymous.sin6_scope_id }
 }
 
+// Read a SocketAddr from a sockaddr of the given length.
+#[cfg(cares1_34)]
+pub unsafe fn socket_addr_from_sockaddr(
+    addr: *const c_types::sockaddr,
+    len: usize,
+) -> Option<SocketAddr> {
+    if addr.is_null() || len < mem::size_of::<c_types::sockaddr>() {
+        return None;
+    }
+    let family = unsafe { (*addr).sa_family } as c_types::ADDRESS_FAMILY;
+    match family {
+        c_types::AF_INET if len >= mem::size_of::<c_types::sockaddr_in>() => {
+            let sa = unsafe { ptr::read_unaligned(addr.cast::<c_types::sockaddr_in>()) };
+            let ip = ipv4_from_in_addr(sa.sin_addr);
+            let port = u16::from_be(sa.sin_port);
+            Some(SocketAddr::V4(SocketAddrV4::new(ip, port)))
+        }
+        c_types::AF_INET6 if len >= mem::size_of::<c_types::sockaddr_in6>() => {
+            let sa = unsafe { ptr::read_unaligned(addr.cast::<c_types::sockaddr_in6>()) };
+            let ip = ipv6_from_in6_addr(sa.sin6_addr);
+            let port = u16::from_be(sa.sin6_port);
+            let scope_id = sockaddr_in6_scope_id(&sa);
+            Some(SocketAddr::V6(SocketAddrV6::new(
+                ip,
+                port,
+                sa.sin6_flowinfo,
+                scope_id,
+            )))
+        }
+        _ => None,
+    }
+}
+
+// Write a SocketAddr into a caller-supplied sockaddr buffer.
+//
+// On input `len` is the size of the buffer; on output it is the size of the address.  As with
+// `getsockname()`, the address is truncated if the buffer is too small.
+#[cfg(cares1_34)]
+pub unsafe fn socket_addr_to_sockaddr(
+    socket_addr: &SocketAddr,
+    addr: *mut c_types::sockaddr,
+    len: *mut c_types::socklen_t,
+) {
+    let sockaddr_in: c_types::sockaddr_in;
+    let sockaddr_in6: c_types::sockaddr_in6;
+    let (src, size): (*const u8, usize) = match socket_addr {
+        SocketAddr::V4(v4) => {
+            sockaddr_in = socket_addrv4_as_sockaddr_in(v4);
+            (
+                ptr::from_ref(&sockaddr_in).cast(),
+                mem::size_of::<c_types::sockaddr_in>(),
+            )
+        }
+        SocketAddr::V6(v6) => {
+            sockaddr_in6 = socket_addrv6_as_sockaddr_in6(v6);
+            (
+                ptr::from_ref(&sockaddr_in6).cast(),
+                mem::size_of::<c_types::sockaddr_in6>(),
+            )
+        }
+    };
+    let available = unsafe { *len } as usize;
+    unsafe {
+        ptr::copy_nonoverlapping(src, addr.cast::<u8>(), size.min(available));
+        *len = size as c_types::socklen_t;
+    }
+}
+
 pub unsafe fn c_string_as_str_unchecked<'a>(c_str: *const c_char) -> &'a str {
     let bytes = unsafe { CStr::from_ptr(c_str) }.to_bytes();
     unsafe { str::from_utf8_unchecked(bytes) }
@@ -238,8 +310,11 @@ pub fn version() -> (&'static str, u32) {
 
 /// Whether the underlying `c-ares` library was built with thread safety enabled or not.
 ///
-/// This is unlikely to be of interest to users of this crate.  Our API assumes that c-ares was not
-/// built with thread safety, and uses Rust's safety features to prevent errors.
+/// `Channel` assumes that c-ares was not built with thread safety, and uses Rust's safety features
+/// to prevent errors.  When c-ares was built with thread safety, [`SharedChannel`] can be used from
+/// many threads at once, without a lock.
+///
+/// [`SharedChannel`]: crate::SharedChannel
 #[cfg(cares1_23)]
 pub fn thread_safety() -> bool {
     let safety = unsafe { c_ares_sys::ares_threadsafety() };
@@ -0,0 +1,22 @@
+//! Integration tests for installing a Rust allocator.
+//!
+//! The allocator can only be installed once per process, so these tests live in a binary of
+//! their own.
+
+use c_ares::{Channel, Error, allocation_stats, init_with_global_allocator};
+
+#[test]
+fn init_with_global_allocator_when_library_unused() {
+    // Not while a channel is using the library...
+    let channel = Channel::new().unwrap();
+    assert_eq!(init_with_global_allocator(), Err(Error::ENOTIMP));
+    drop(channel);
+
+    // ... but once the last channel has gone - and then only once.
+    assert_eq!(init_with_global_allocator(), Ok(()));
+    assert_eq!(init_with_global_allocator(), Err(Error::ENOTIMP));
+
+    let channel = Channel::new().unwrap();
+    assert!(allocation_stats().live_allocations() > 0);
+    drop(channel);
+}
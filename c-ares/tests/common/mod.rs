@@ -3,11 +3,34 @@
 //! These tests build a plain `Channel` (no built-in event thread) and drive
 //! its I/O manually via `process_channel`. Doing so means the tests work
 //! regardless of whether c-ares was compiled with thread-safety support.
+//!
+//! Queries go to a local server answering from fixture records, so the tests
+//! don't need a network.  That server is built on `DnsRecord`, which needs
+//! c-ares 1.28; with an older c-ares, the tests fall back to `8.8.8.8`.
 
 use c_ares::*;
 use std::time::{Duration, Instant};
 
-/// Create a `Channel` configured to talk to `8.8.8.8`.
+/// The DNS server that the tests should use.
+#[cfg(cares1_28)]
+pub fn server() -> String {
+    use c_ares_test_server::TestServer;
+    use std::sync::OnceLock;
+
+    static SERVER: OnceLock<TestServer> = OnceLock::new();
+    SERVER
+        .get_or_init(|| TestServer::with_fixtures().expect("Failed to start test server"))
+        .addr()
+        .to_string()
+}
+
+/// The DNS server that the tests should use.
+#[cfg(not(cares1_28))]
+pub fn server() -> String {
+    "8.8.8.8".to_owned()
+}
+
+/// Create a `Channel` configured to talk to [`server`].
 ///
 /// The channel does not use the c-ares built-in event thread; drive its I/O
 /// with [`process_channel`].
@@ -20,13 +43,21 @@ pub fn channel() -> Channel {
 
     let mut channel = Channel::with_options(options).expect("Failed to create channel");
     channel
-        .set_servers(["8.8.8.8"])
+        .set_servers([server().as_str()])
         .expect("Failed to set servers");
     channel
 }
 
+/// Drive `channel`'s I/O until it has no more queries pending, or `timeout`
+/// elapses.
+#[cfg(cares1_34)]
+pub fn process_channel(channel: &mut Channel, timeout: Duration) {
+    let _ = channel.run_until_idle(Some(Instant::now() + timeout));
+}
+
 /// Drive `channel`'s I/O until it has no more sockets of interest, or
 /// `timeout` elapses.
+#[cfg(not(cares1_34))]
 pub fn process_channel(channel: &mut Channel, timeout: Duration) {
     let start = Instant::now();
     let mut events = polling::Events::new();
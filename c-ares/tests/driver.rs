@@ -0,0 +1,143 @@
+//! Integration tests for the built-in single-threaded driver: `poll_once()` and
+//! `run_until_idle()`.
+
+#![cfg(cares1_34)]
+
+#[allow(dead_code)]
+mod common;
+
+use c_ares::*;
+use common::channel;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+#[test]
+fn poll_once_when_idle() {
+    let mut channel = channel();
+    let start = Instant::now();
+    assert_eq!(channel.poll_once(Duration::from_secs(5)), Ok(false));
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn run_until_idle_when_idle() {
+    let mut channel = channel();
+    assert_eq!(channel.run_until_idle(None), Ok(()));
+}
+
+#[test]
+fn poll_once_until_done() {
+    let mut channel = channel();
+    let (tx, rx) = mpsc::channel();
+    channel.query_a("google.com", move |result| {
+        tx.send(result.map(|results| results.iter().count()))
+            .unwrap();
+    });
+
+    while channel.poll_once(Duration::from_millis(100)).unwrap() {}
+    let count = rx.try_recv().expect("Query did not complete").unwrap();
+    assert!(count > 0, "No A records returned");
+}
+
+#[test]
+fn run_until_idle_completes_queries() {
+    let mut channel = channel();
+    let (tx, rx) = mpsc::channel();
+    for name in ["google.com", "github.com", "example.com"] {
+        let tx = tx.clone();
+        channel.query_a(name, move |result| tx.send(result.is_ok()).unwrap());
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    assert_eq!(channel.run_until_idle(Some(deadline)), Ok(()));
+    assert_eq!(rx.try_iter().filter(|ok| *ok).count(), 3);
+}
+
+#[test]
+fn run_until_idle_times_out() {
+    // A server that never answers.
+    let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut options = Options::new();
+    options.set_timeout(Duration::from_secs(10)).set_tries(1);
+    let mut channel = Channel::with_options(options).unwrap();
+    channel
+        .set_servers([silent.local_addr().unwrap().to_string().as_str()])
+        .unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    channel.query_a("google.com", move |result| tx.send(result.is_ok()).unwrap());
+
+    let deadline = Instant::now() + Duration::from_millis(50);
+    assert_eq!(channel.run_until_idle(Some(deadline)), Err(Error::ETIMEOUT));
+    assert!(Instant::now() >= deadline);
+    assert!(rx.try_recv().is_err(), "Query should still be pending");
+    assert_eq!(channel.queue_active_queries(), 1);
+
+    // Finish the query while its handler can still report the result.
+    channel.cancel();
+    assert_eq!(rx.try_recv(), Ok(false));
+}
+
+// Socket functions whose sockets are not file descriptors, so cannot be waited on.
+struct UnwatchableSockets;
+
+impl SocketFunctions for UnwatchableSockets {
+    fn open(&self, _family: AddressFamily, _socket_type: SocketType) -> io::Result<Socket> {
+        Ok(Socket::MAX - 1)
+    }
+
+    fn close(&self, _socket: Socket) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn connect(
+        &self,
+        _socket: Socket,
+        _addr: &SocketAddr,
+        _flags: SocketConnectFlags,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn recvfrom(
+        &self,
+        _socket: Socket,
+        _buf: &mut [u8],
+    ) -> io::Result<(usize, Option<SocketAddr>)> {
+        Err(io::ErrorKind::WouldBlock.into())
+    }
+
+    fn sendto(&self, _socket: Socket, buf: &[u8], _addr: Option<&SocketAddr>) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn getsockname(&self, _socket: Socket) -> io::Result<SocketAddr> {
+        Ok(([127, 0, 0, 1], 1024).into())
+    }
+
+    fn bind(&self, _socket: Socket, _addr: &SocketAddr, _flags: SocketBindFlags) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn run_until_idle_skips_unwatchable_sockets() {
+    let timeout = Duration::from_millis(50);
+    let mut options = Options::new();
+    options
+        .set_timeout(timeout)
+        .set_max_timeout(timeout)
+        .set_tries(1)
+        .set_socket_functions(UnwatchableSockets);
+    let mut channel = Channel::with_options(options).unwrap();
+    channel.set_servers(["192.0.2.53"]).unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    channel.query_a("google.com", move |result| tx.send(result.err()).unwrap());
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    assert_eq!(channel.run_until_idle(Some(deadline)), Ok(()));
+    assert_eq!(rx.try_recv(), Ok(Some(Error::ETIMEOUT)));
+}
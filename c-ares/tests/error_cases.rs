@@ -8,7 +8,7 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_host_by_address_nonexistent() {
     use std::net::IpAddr;
 
@@ -31,7 +31,7 @@ fn get_host_by_address_nonexistent() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_host_by_name_nonexistent() {
     let mut channel = common::channel();
 
@@ -53,7 +53,7 @@ fn get_host_by_name_nonexistent() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_name_info_nonexistent() {
     use std::net::SocketAddr;
 
@@ -80,7 +80,7 @@ fn get_name_info_nonexistent() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_caa_nonexistent() {
     let mut channel = common::channel();
 
@@ -98,7 +98,7 @@ fn query_caa_nonexistent() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_cancel() {
     let mut channel = common::channel();
 
@@ -124,7 +124,7 @@ fn query_cancel() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_cname_nonexistent() {
     let mut channel = common::channel();
 
@@ -142,7 +142,7 @@ fn query_cname_nonexistent() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_mx_nonexistent() {
     let mut channel = common::channel();
 
@@ -160,7 +160,7 @@ fn query_mx_nonexistent() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_nonexistent_domain() {
     let mut channel = common::channel();
 
@@ -178,7 +178,7 @@ fn query_nonexistent_domain() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_ns_nonexistent() {
     let mut channel = common::channel();
 
@@ -196,7 +196,7 @@ fn query_ns_nonexistent() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_soa_nonexistent() {
     let mut channel = common::channel();
 
@@ -214,7 +214,7 @@ fn query_soa_nonexistent() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_srv_nonexistent() {
     let mut channel = common::channel();
 
@@ -265,7 +265,7 @@ fn query_a_null_byte() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_txt_nonexistent() {
     let mut channel = common::channel();
 
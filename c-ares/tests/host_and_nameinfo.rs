@@ -9,7 +9,7 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_host_by_address_ipv4() {
     use std::net::IpAddr;
 
@@ -31,7 +31,7 @@ fn get_host_by_address_ipv4() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_host_by_address_ipv6() {
     use std::net::IpAddr;
 
@@ -53,7 +53,7 @@ fn get_host_by_address_ipv6() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_host_by_name_ipv4() {
     let mut channel = channel();
 
@@ -79,7 +79,7 @@ fn get_host_by_name_ipv4() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_host_by_name_ipv6() {
     let mut channel = channel();
 
@@ -102,7 +102,7 @@ fn get_host_by_name_ipv6() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_name_info_ipv4() {
     use std::net::SocketAddr;
 
@@ -132,7 +132,7 @@ fn get_name_info_ipv4() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_name_info_ipv6() {
     use std::net::SocketAddr;
 
@@ -155,7 +155,7 @@ fn get_name_info_ipv6() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_addrinfo_ipv4() {
     let mut channel = channel();
 
@@ -182,7 +182,7 @@ fn get_addrinfo_ipv4() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_addrinfo_ipv6() {
     let mut channel = channel();
 
@@ -209,7 +209,7 @@ fn get_addrinfo_ipv6() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_addrinfo_unspec() {
     let mut channel = channel();
 
@@ -229,7 +229,7 @@ fn get_addrinfo_unspec() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_addrinfo_with_service() {
     let mut channel = channel();
 
@@ -261,7 +261,7 @@ fn get_addrinfo_with_service() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_addrinfo_cnames() {
     let mut channel = channel();
 
@@ -295,7 +295,7 @@ fn get_addrinfo_cnames() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_addrinfo_debug_display() {
     let mut channel = channel();
 
@@ -337,7 +337,7 @@ fn get_addrinfo_debug_display() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_addrinfo_display_with_cnames() {
     let mut channel = channel();
 
@@ -372,7 +372,7 @@ fn get_addrinfo_display_with_cnames() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn get_addrinfo_nonexistent() {
     let mut channel = channel();
 
@@ -9,7 +9,7 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_a_record() {
     let mut channel = channel();
 
@@ -43,7 +43,7 @@ fn query_a_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_aaaa_record() {
     let mut channel = channel();
 
@@ -73,7 +73,7 @@ fn query_aaaa_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_caa_record() {
     let mut channel = channel();
 
@@ -104,7 +104,7 @@ fn query_caa_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_cname_record() {
     let mut channel = channel();
 
@@ -125,7 +125,7 @@ fn query_cname_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_mx_record() {
     let mut channel = channel();
 
@@ -155,7 +155,7 @@ fn query_mx_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_naptr_record() {
     let mut channel = channel();
 
@@ -186,7 +186,7 @@ fn query_naptr_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_ns_record() {
     let mut channel = channel();
 
@@ -207,7 +207,7 @@ fn query_ns_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_ptr_record() {
     let mut channel = channel();
 
@@ -228,7 +228,7 @@ fn query_ptr_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_soa_record() {
     let mut channel = channel();
 
@@ -254,7 +254,7 @@ fn query_soa_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_srv_record() {
     let mut channel = channel();
 
@@ -288,7 +288,7 @@ fn query_srv_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_txt_record() {
     let mut channel = channel();
 
@@ -318,7 +318,7 @@ fn query_txt_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn query_uri_record() {
     let mut channel = channel();
 
@@ -346,7 +346,7 @@ fn query_uri_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn raw_query() {
     let mut channel = channel();
 
@@ -364,3 +364,32 @@ fn raw_query() {
 
     assert!(completed.load(Ordering::SeqCst), "Query did not complete");
 }
+
+// A query for google.com's A records, with id 0x1234 and recursion desired.
+const RAW_QUERY: &[u8] = b"\x12\x34\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00\
+    \x06google\x03com\x00\x00\x01\x00\x01";
+
+#[test]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
+fn send_raw_query() {
+    let mut channel = channel();
+
+    let completed = Arc::new(AtomicBool::new(false));
+    let completed_clone = completed.clone();
+
+    channel.send_raw(RAW_QUERY, move |result| {
+        completed_clone.store(true, Ordering::SeqCst);
+        let response = result.expect("Query failed");
+        // The response has the query's id, even though c-ares sent the query under another.
+        assert_eq!(response[..2], RAW_QUERY[..2]);
+        // QR is set and the rcode is NOERROR, with the one question and some answers.
+        assert_eq!(response[2] & 0x80, 0x80);
+        assert_eq!(response[3] & 0x0f, 0);
+        assert_eq!(response[4..6], [0, 1]);
+        assert_ne!(response[6..8], [0, 0]);
+    });
+
+    process_channel(&mut channel, Duration::from_secs(3));
+
+    assert!(completed.load(Ordering::SeqCst), "Query did not complete");
+}
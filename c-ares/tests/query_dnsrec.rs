@@ -12,7 +12,6 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
 #[test]
-#[ignore = "requires network"]
 fn query_dnsrec_a_record() {
     let mut channel = channel();
 
@@ -53,7 +52,6 @@ fn query_dnsrec_a_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
 fn query_dnsrec_aaaa_record() {
     let mut channel = channel();
 
@@ -87,7 +85,6 @@ fn query_dnsrec_aaaa_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
 fn query_dnsrec_mx_record() {
     let mut channel = channel();
 
@@ -117,7 +114,6 @@ fn query_dnsrec_mx_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
 fn query_dnsrec_txt_record() {
     let mut channel = channel();
 
@@ -155,7 +151,6 @@ fn query_dnsrec_txt_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
 fn send_dnsrec_a_record() {
     let mut channel = channel();
 
@@ -192,7 +187,6 @@ fn send_dnsrec_a_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
 fn search_dnsrec_a_record() {
     let mut channel = channel();
 
@@ -224,7 +218,6 @@ fn search_dnsrec_a_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
 fn write_and_reparse_roundtrip() {
     let mut channel = channel();
 
@@ -259,7 +252,6 @@ fn write_and_reparse_roundtrip() {
 }
 
 #[test]
-#[ignore = "requires network"]
 fn query_dnsrec_soa_record() {
     let mut channel = channel();
 
@@ -311,7 +303,6 @@ fn query_dnsrec_soa_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
 fn query_dnsrec_ns_record() {
     let mut channel = channel();
 
@@ -340,7 +331,6 @@ fn query_dnsrec_ns_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
 fn query_dnsrec_cname_record() {
     let mut channel = channel();
 
@@ -385,7 +375,6 @@ fn query_dnsrec_cname_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
 fn query_dnsrec_nonexistent_domain() {
     let mut channel = channel();
 
@@ -409,7 +398,6 @@ fn query_dnsrec_nonexistent_domain() {
 }
 
 #[test]
-#[ignore = "requires network"]
 fn send_dnsrec_nonexistent_domain() {
     let mut channel = channel();
 
@@ -444,7 +432,6 @@ fn send_dnsrec_nonexistent_domain() {
 }
 
 #[test]
-#[ignore = "requires network"]
 fn query_dnsrec_cancel() {
     let mut channel = channel();
 
@@ -469,28 +456,26 @@ fn query_dnsrec_cancel() {
 }
 
 #[test]
-#[ignore = "requires network"]
 fn query_dnsrec_returns_query_id() {
     let mut channel = channel();
 
     let completed = Arc::new(AtomicBool::new(false));
     let completed_clone = completed.clone();
 
-    let qid = channel
+    let handle = channel
         .query_dnsrec("google.com", DnsCls::IN, DnsRecordType::A, move |result| {
             completed_clone.store(true, Ordering::SeqCst);
             result.expect("Query failed");
         })
         .expect("query_dnsrec failed");
 
-    let _ = qid;
+    assert!(handle.qid().is_some());
 
     process_channel(&mut channel, Duration::from_secs(3));
     assert!(completed.load(Ordering::SeqCst), "Query did not complete");
 }
 
 #[test]
-#[ignore = "requires network"]
 fn send_dnsrec_returns_query_id() {
     let mut channel = channel();
 
@@ -503,21 +488,20 @@ fn send_dnsrec_returns_query_id() {
     let completed = Arc::new(AtomicBool::new(false));
     let completed_clone = completed.clone();
 
-    let qid = channel
+    let handle = channel
         .send_dnsrec(&query, move |result| {
             completed_clone.store(true, Ordering::SeqCst);
             result.expect("send_dnsrec query failed");
         })
         .expect("send_dnsrec failed");
 
-    let _ = qid;
+    assert!(handle.qid().is_some());
 
     process_channel(&mut channel, Duration::from_secs(3));
     assert!(completed.load(Ordering::SeqCst), "Query did not complete");
 }
 
 #[test]
-#[ignore = "requires network"]
 fn query_dnsrec_duplicate_response() {
     let mut channel = channel();
 
@@ -549,7 +533,6 @@ fn query_dnsrec_duplicate_response() {
 }
 
 #[test]
-#[ignore = "requires network"]
 fn query_dnsrec_response_has_additional_section() {
     let mut channel = channel();
 
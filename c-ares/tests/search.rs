@@ -8,7 +8,7 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn raw_search() {
     let mut options = Options::new();
     options
@@ -19,7 +19,7 @@ fn raw_search() {
         .unwrap();
     let mut channel = Channel::with_options(options).expect("Failed to create channel");
     channel
-        .set_servers(["8.8.8.8"])
+        .set_servers([common::server().as_str()])
         .expect("Failed to set servers");
 
     let completed = Arc::new(AtomicBool::new(false));
@@ -38,7 +38,7 @@ fn raw_search() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_a_record() {
     let mut options = Options::new();
     options
@@ -49,7 +49,7 @@ fn search_a_record() {
         .unwrap();
     let mut channel = Channel::with_options(options).expect("Failed to create channel");
     channel
-        .set_servers(["8.8.8.8"])
+        .set_servers([common::server().as_str()])
         .expect("Failed to set servers");
 
     let completed = Arc::new(AtomicBool::new(false));
@@ -67,7 +67,7 @@ fn search_a_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_aaaa_record() {
     let mut channel = common::channel();
 
@@ -85,7 +85,7 @@ fn search_aaaa_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_caa_record() {
     let mut channel = common::channel();
 
@@ -103,7 +103,7 @@ fn search_caa_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_cname_record() {
     let mut channel = common::channel();
 
@@ -121,7 +121,7 @@ fn search_cname_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_mx_record() {
     let mut channel = common::channel();
 
@@ -139,7 +139,7 @@ fn search_mx_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_naptr_record() {
     let mut channel = common::channel();
 
@@ -157,7 +157,7 @@ fn search_naptr_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_ns_record() {
     let mut channel = common::channel();
 
@@ -175,7 +175,7 @@ fn search_ns_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_ptr_record() {
     let mut channel = common::channel();
 
@@ -193,7 +193,7 @@ fn search_ptr_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_soa_record() {
     let mut channel = common::channel();
 
@@ -211,7 +211,7 @@ fn search_soa_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_srv_record() {
     let mut channel = common::channel();
 
@@ -229,7 +229,7 @@ fn search_srv_record() {
 }
 
 #[test]
-#[ignore = "requires network"]
+#[cfg_attr(not(cares1_28), ignore = "requires network")]
 fn search_txt_record() {
     let mut channel = common::channel();
 
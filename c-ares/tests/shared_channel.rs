@@ -0,0 +1,102 @@
+//! Integration tests for `SharedChannel`: queries made from many threads without a lock.
+
+#![cfg(cares1_34)]
+
+#[allow(dead_code)]
+mod common;
+
+use c_ares::*;
+use common::channel;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, mpsc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Share the test channel, or return `None` if c-ares is not thread-safe.
+fn shared_channel() -> Option<Arc<SharedChannel>> {
+    SharedChannel::new(channel()).ok().map(Arc::new)
+}
+
+#[test]
+fn queries_from_many_threads() {
+    let Some(channel) = shared_channel() else {
+        return;
+    };
+    let (tx, rx) = mpsc::channel();
+    let threads: Vec<_> = ["google.com", "github.com", "example.com"]
+        .into_iter()
+        .map(|name| {
+            let channel = Arc::clone(&channel);
+            let tx = tx.clone();
+            thread::spawn(move || {
+                channel.query_a(name, move |result| tx.send(result.is_ok()).unwrap());
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    assert_eq!(channel.run_until_idle(Some(deadline)), Ok(()));
+    assert_eq!(rx.try_iter().filter(|ok| *ok).count(), 3);
+}
+
+#[test]
+fn queries_while_another_thread_drives() {
+    let Some(channel) = shared_channel() else {
+        return;
+    };
+    let (tx, rx) = mpsc::channel();
+    channel.query_a("google.com", move |result| tx.send(result.is_ok()).unwrap());
+
+    let driver = {
+        let channel = Arc::clone(&channel);
+        thread::spawn(move || {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            channel.run_until_idle(Some(deadline))
+        })
+    };
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(true));
+    assert_eq!(driver.join().unwrap(), Ok(()));
+}
+
+#[test]
+fn replaced_callbacks_are_not_called() {
+    let Some(channel) = shared_channel() else {
+        return;
+    };
+    let first = Arc::new(AtomicUsize::new(0));
+    let second = Arc::new(AtomicUsize::new(0));
+    {
+        let first = Arc::clone(&first);
+        channel.set_socket_configure_callback(move |_, _| {
+            first.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        });
+    }
+    {
+        let second = Arc::clone(&second);
+        channel.set_socket_configure_callback(move |_, _| {
+            second.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        });
+    }
+
+    channel.query_a("google.com", |_| {});
+    let deadline = Instant::now() + Duration::from_secs(5);
+    assert_eq!(channel.run_until_idle(Some(deadline)), Ok(()));
+    assert_eq!(first.load(Ordering::SeqCst), 0);
+    assert!(second.load(Ordering::SeqCst) > 0);
+}
+
+#[test]
+fn into_inner_keeps_configuration() {
+    let Some(channel) = shared_channel() else {
+        return;
+    };
+    let channel = Arc::into_inner(channel).unwrap();
+    channel.set_servers(["127.0.0.1:5353"]).unwrap();
+    let channel = channel.into_inner();
+    assert_eq!(channel.servers(), ["127.0.0.1:5353"]);
+}
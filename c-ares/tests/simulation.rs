@@ -0,0 +1,232 @@
+//! Integration tests for timeouts, retries and failover, over a simulated network.
+//!
+//! Times are read from the simulation's virtual clock, which can fall behind c-ares's own schedule
+//! when the test machine is slow - but never runs ahead of it.  So the assertions are on the order
+//! of events, and on how long c-ares waited at most, with a little slack for rounding.
+//!
+//! The maximum timeout is set to the timeout, so that c-ares uses the timeout as given rather than
+//! stretching it from its own measurements of the servers.  That keeps the whole suite down to a
+//! fraction of a second of real time.
+
+#![cfg(cares1_34)]
+
+use c_ares::*;
+use c_ares_test_server::{Fault, Simulation};
+use std::collections::HashSet;
+use std::sync::mpsc;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_millis(20);
+const SLACK: Duration = Duration::from_millis(2);
+
+fn options(tries: u32) -> Options {
+    let mut options = Options::new();
+    options
+        .set_timeout(TIMEOUT)
+        .set_max_timeout(TIMEOUT)
+        .set_tries(tries);
+    options
+}
+
+// Looks up the A records for `name`, and runs the simulation until the query completes.
+fn query(sim: &Simulation, channel: &mut Channel, name: &str) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    channel.query_a(name, move |result| {
+        tx.send(result.map(drop)).unwrap();
+    });
+    sim.run(channel);
+    rx.try_recv().expect("Query did not complete")
+}
+
+// The times between consecutive attempts, which are made in order.
+fn gaps(sim: &Simulation) -> Vec<Duration> {
+    let attempts = sim.attempts();
+    attempts
+        .windows(2)
+        .map(|pair| {
+            assert!(pair[0].at() <= pair[1].at(), "attempts out of order");
+            pair[1].at() - pair[0].at()
+        })
+        .collect()
+}
+
+#[test]
+fn retries_until_tries_run_out() {
+    let sim = Simulation::with_fixtures();
+    let server = sim.add_server();
+    sim.set_fault(server, Fault::Drop);
+    let mut channel = sim.channel(options(3)).unwrap();
+
+    let result = query(&sim, &mut channel, "google.com");
+    assert_eq!(result, Err(Error::ETIMEOUT));
+
+    let attempts = sim.attempts();
+    assert_eq!(attempts.len(), 3);
+    assert!(attempts.iter().all(|attempt| attempt.server() == server));
+    assert_eq!(attempts[0].at(), Duration::ZERO);
+    for gap in gaps(&sim) {
+        assert!(gap <= TIMEOUT + SLACK, "retried after {gap:?}");
+    }
+}
+
+#[test]
+fn answers_after_lost_packets() {
+    let sim = Simulation::with_fixtures();
+    let server = sim.add_server();
+    sim.script(server, [Fault::Drop, Fault::Drop]);
+    let mut channel = sim.channel(options(3)).unwrap();
+
+    assert_eq!(query(&sim, &mut channel, "google.com"), Ok(()));
+    let faults: Vec<_> = sim.attempts().iter().map(|a| a.fault()).collect();
+    assert_eq!(faults, [Some(Fault::Drop), Some(Fault::Drop), None]);
+}
+
+#[test]
+fn fails_over_after_timeout() {
+    let sim = Simulation::with_fixtures();
+    let first = sim.add_server();
+    let second = sim.add_server();
+    sim.set_fault(first, Fault::Drop);
+    let mut channel = sim.channel(options(2)).unwrap();
+
+    assert_eq!(query(&sim, &mut channel, "google.com"), Ok(()));
+    let servers: Vec<_> = sim.attempts().iter().map(|a| a.server()).collect();
+    assert_eq!(servers, [first, second]);
+    assert!(gaps(&sim)[0] <= TIMEOUT + SLACK);
+}
+
+#[test]
+fn fails_over_immediately_on_servfail() {
+    let sim = Simulation::with_fixtures();
+    let first = sim.add_server();
+    let second = sim.add_server();
+    sim.set_fault(first, Fault::ServFail);
+    let mut channel = sim.channel(options(2)).unwrap();
+
+    assert_eq!(query(&sim, &mut channel, "google.com"), Ok(()));
+    let attempts = sim.attempts();
+    assert_eq!(attempts.len(), 2);
+    assert_eq!(attempts[0].server(), first);
+    assert_eq!(attempts[1].server(), second);
+    assert_eq!(attempts[1].at(), attempts[0].at());
+}
+
+#[test]
+fn max_timeout_caps_retries() {
+    let max_timeout = Duration::from_millis(30);
+    let sim = Simulation::with_fixtures();
+    let server = sim.add_server();
+    sim.set_fault(server, Fault::Drop);
+    let mut options = options(4);
+    options.set_max_timeout(max_timeout);
+    let mut channel = sim.channel(options).unwrap();
+
+    assert_eq!(
+        query(&sim, &mut channel, "google.com"),
+        Err(Error::ETIMEOUT)
+    );
+    assert_eq!(sim.attempts().len(), 4);
+    for gap in gaps(&sim) {
+        assert!(gap <= max_timeout + SLACK, "retried after {gap:?}");
+    }
+}
+
+#[test]
+fn retries_failed_server() {
+    let sim = Simulation::with_fixtures();
+    let first = sim.add_server();
+    let second = sim.add_server();
+    sim.script(first, [Fault::ServFail]);
+    let mut failover = ServerFailoverOptions::new();
+    failover.set_retry_chance(1).set_retry_delay(Duration::ZERO);
+    let mut options = options(2);
+    options.set_server_failover_options(&failover);
+    let mut channel = sim.channel(options).unwrap();
+
+    assert_eq!(query(&sim, &mut channel, "google.com"), Ok(()));
+    assert_eq!(query(&sim, &mut channel, "github.com"), Ok(()));
+    // The second query goes to the healthy server, and a copy goes to the failed server as a
+    // probe.
+    let attempts: Vec<_> = sim
+        .attempts()
+        .iter()
+        .map(|a| (a.server(), a.name().to_owned()))
+        .collect();
+    assert_eq!(
+        attempts,
+        [
+            (first, "google.com".to_owned()),
+            (second, "google.com".to_owned()),
+            (second, "github.com".to_owned()),
+            (first, "github.com".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn avoids_failed_server() {
+    let sim = Simulation::with_fixtures();
+    let first = sim.add_server();
+    let second = sim.add_server();
+    sim.script(first, [Fault::ServFail]);
+    let mut failover = ServerFailoverOptions::new();
+    failover.set_retry_chance(0);
+    let mut options = options(2);
+    options.set_server_failover_options(&failover);
+    let mut channel = sim.channel(options).unwrap();
+
+    assert_eq!(query(&sim, &mut channel, "google.com"), Ok(()));
+    assert_eq!(query(&sim, &mut channel, "github.com"), Ok(()));
+    let servers: Vec<_> = sim.attempts().iter().map(|a| a.server()).collect();
+    assert_eq!(servers, [first, second, second]);
+}
+
+#[test]
+fn udp_max_queries_opens_new_sockets() {
+    let sim = Simulation::with_fixtures();
+    sim.add_server();
+    let mut options = options(1);
+    options.set_udp_max_queries(Some(2));
+    let mut channel = sim.channel(options).unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    for name in ["google.com", "github.com", "example.com"] {
+        let tx = tx.clone();
+        channel.query_a(name, move |result| tx.send(result.map(drop)).unwrap());
+    }
+    sim.run(&mut channel);
+    assert_eq!(rx.try_iter().filter(Result::is_ok).count(), 3);
+
+    let attempts = sim.attempts();
+    assert_eq!(attempts.len(), 3);
+    let connections: HashSet<_> = attempts.iter().map(|a| a.connection()).collect();
+    assert_eq!(connections.len(), 2);
+}
+
+#[test]
+fn truncated_answer_retries_over_tcp() {
+    let sim = Simulation::with_fixtures();
+    let server = sim.add_server();
+    sim.script(server, [Fault::Truncate]);
+    let mut channel = sim.channel(options(1)).unwrap();
+
+    assert_eq!(query(&sim, &mut channel, "google.com"), Ok(()));
+    let transports: Vec<_> = sim.attempts().iter().map(|a| a.transport()).collect();
+    assert_eq!(transports, [SocketType::Datagram, SocketType::Stream]);
+}
+
+#[test]
+fn waits_for_slow_answer() {
+    let delay = Duration::from_millis(10);
+    let sim = Simulation::with_fixtures();
+    let server = sim.add_server();
+    sim.set_fault(server, Fault::Delay(delay));
+    // Leave the timeout well clear of the delay, so that a slow test machine cannot time out first.
+    let mut options = options(1);
+    options.set_timeout(delay * 10).set_max_timeout(delay * 10);
+    let mut channel = sim.channel(options).unwrap();
+
+    assert_eq!(query(&sim, &mut channel, "google.com"), Ok(()));
+    assert_eq!(sim.attempts().len(), 1);
+    assert_eq!(sim.now(), delay);
+}
@@ -0,0 +1,65 @@
+//! Integration tests for reading master files with `DnsRecord::add_zone_file()`, against the zones
+//! in `tests/zones`.
+
+#![cfg(cares1_28)]
+
+use c_ares::*;
+use std::path::PathBuf;
+
+fn zone(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/zones")
+        .join(name)
+}
+
+fn answers(rec: &DnsRecord) -> Vec<String> {
+    rec.rrs(DnsSection::Answer)
+        .map(|rr| rr.to_string())
+        .collect()
+}
+
+fn new_rec() -> DnsRecord {
+    DnsRecord::new(0, DnsFlags::QR, DnsOpcode::Query, DnsRcode::NoError).unwrap()
+}
+
+#[test]
+fn includes_are_relative_to_the_including_file() {
+    let mut rec = new_rec();
+    rec.add_zone_file(DnsSection::Answer, zone("main.zone"), "example.com.")
+        .unwrap();
+    assert_eq!(
+        answers(&rec),
+        [
+            "a.hosts.example.com.\t300\tIN\tA\t192.0.2.1",
+            "c.hosts.example.com.\t300\tIN\tA\t192.0.2.4",
+            "b.other.\t300\tIN\tA\t192.0.2.2",
+            "after.example.com.\t300\tIN\tA\t192.0.2.3",
+        ]
+    );
+}
+
+#[test]
+fn recursive_include_fails() {
+    let mut rec = new_rec();
+    let result = rec.add_zone_file(DnsSection::Answer, zone("loop.zone"), ".");
+    assert_eq!(result.err(), Some(Error::EBADSTR));
+    assert_eq!(rec.rr_count(DnsSection::Answer), 0);
+}
+
+#[test]
+fn missing_file_fails() {
+    let mut rec = new_rec();
+    let result = rec.add_zone_file(DnsSection::Answer, zone("absent.zone"), ".");
+    assert_eq!(result.err(), Some(Error::EFILE));
+}
+
+#[test]
+fn missing_include_fails() {
+    let mut rec = new_rec();
+    let result = rec.add_zone_str(
+        DnsSection::Answer,
+        "$INCLUDE c-ares-no-such-file.zone\n",
+        ".",
+    );
+    assert_eq!(result.err(), Some(Error::EFILE));
+}